@@ -17,6 +17,14 @@ pub struct PullRequest {
     pub url: Url,
 }
 
+/// The combined CI status a hosting provider reports for a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitStatus {
+    Pending,
+    Success,
+    Failure,
+}
+
 #[derive(Clone)]
 pub struct GitRemote {
     pub host: Arc<dyn GitHostingProvider + Send + Sync + 'static>,
@@ -50,6 +58,16 @@ impl GitRemote {
             .ok()
             .flatten()
     }
+
+    pub async fn commit_status(
+        &self,
+        commit: SharedString,
+        client: Arc<dyn HttpClient>,
+    ) -> Result<Option<CommitStatus>> {
+        self.host
+            .commit_status(&self.owner, &self.repo, commit, client)
+            .await
+    }
 }
 
 pub struct BuildCommitPermalinkParams<'a> {
@@ -134,6 +152,18 @@ pub trait GitHostingProvider {
     ) -> Result<Option<Url>> {
         Ok(None)
     }
+
+    /// Returns the combined CI status of the given commit, or `None` if the provider doesn't
+    /// report statuses or doesn't know the commit (e.g. because it hasn't been pushed).
+    async fn commit_status(
+        &self,
+        _repo_owner: &str,
+        _repo: &str,
+        _commit: SharedString,
+        _http_client: Arc<dyn HttpClient>,
+    ) -> Result<Option<CommitStatus>> {
+        Ok(None)
+    }
 }
 
 #[derive(Default, Deref, DerefMut)]
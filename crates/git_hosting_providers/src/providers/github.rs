@@ -8,11 +8,12 @@ use gpui::SharedString;
 use http_client::{AsyncBody, HttpClient, HttpRequestExt, Request};
 use regex::Regex;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use url::Url;
 
 use git::{
-    BuildCommitPermalinkParams, BuildPermalinkParams, GitHostingProvider, ParsedGitRemote,
-    PullRequest, RemoteUrl,
+    BuildCommitPermalinkParams, BuildPermalinkParams, CommitStatus, GitHostingProvider,
+    ParsedGitRemote, PullRequest, RemoteUrl,
 };
 
 use crate::get_host_from_git_remote_url;
@@ -61,6 +62,72 @@ struct User {
     pub avatar_url: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct CombinedStatus {
+    state: String,
+    total_count: usize,
+}
+
+/// GitHub Actions and other GitHub Apps report through check runs, which the combined status
+/// doesn't include.
+#[derive(Debug, Deserialize)]
+struct CheckRuns {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRun {
+    status: String,
+    conclusion: Option<String>,
+}
+
+impl CombinedStatus {
+    fn commit_status(&self) -> Option<CommitStatus> {
+        // GitHub reports "pending" for commits without any statuses.
+        if self.total_count == 0 {
+            return None;
+        }
+        match self.state.as_str() {
+            "success" => Some(CommitStatus::Success),
+            "pending" => Some(CommitStatus::Pending),
+            "failure" | "error" => Some(CommitStatus::Failure),
+            _ => None,
+        }
+    }
+}
+
+impl CheckRun {
+    fn commit_status(&self) -> Option<CommitStatus> {
+        if self.status != "completed" {
+            return Some(CommitStatus::Pending);
+        }
+        match self.conclusion.as_deref()? {
+            "success" | "neutral" | "skipped" => Some(CommitStatus::Success),
+            "failure" | "timed_out" | "cancelled" | "action_required" | "startup_failure" => {
+                Some(CommitStatus::Failure)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Any failure fails the commit, and any pending status or run keeps it pending.
+fn combine_commit_statuses(
+    statuses: impl IntoIterator<Item = CommitStatus>,
+) -> Option<CommitStatus> {
+    statuses
+        .into_iter()
+        .fold(None, |combined, status| match (combined, status) {
+            (Some(CommitStatus::Failure), _) | (_, CommitStatus::Failure) => {
+                Some(CommitStatus::Failure)
+            }
+            (Some(CommitStatus::Pending), _) | (_, CommitStatus::Pending) => {
+                Some(CommitStatus::Pending)
+            }
+            _ => Some(CommitStatus::Success),
+        })
+}
+
 #[derive(Debug)]
 pub struct Github {
     name: String,
@@ -140,6 +207,68 @@ impl Github {
             .map(|commit| commit.author)
             .context("failed to deserialize GitHub commit details")
     }
+
+    async fn fetch_github_commit_status(
+        &self,
+        repo_owner: &str,
+        repo: &str,
+        commit: &str,
+        client: &Arc<dyn HttpClient>,
+    ) -> Result<Option<CombinedStatus>> {
+        self.fetch_github_commit_checks(repo_owner, repo, commit, "status", client)
+            .await
+    }
+
+    async fn fetch_github_check_runs(
+        &self,
+        repo_owner: &str,
+        repo: &str,
+        commit: &str,
+        client: &Arc<dyn HttpClient>,
+    ) -> Result<Option<CheckRuns>> {
+        self.fetch_github_commit_checks(repo_owner, repo, commit, "check-runs", client)
+            .await
+    }
+
+    async fn fetch_github_commit_checks<T: DeserializeOwned>(
+        &self,
+        repo_owner: &str,
+        repo: &str,
+        commit: &str,
+        endpoint: &str,
+        client: &Arc<dyn HttpClient>,
+    ) -> Result<Option<T>> {
+        let Some(host) = self.base_url.host_str() else {
+            bail!("failed to get host from github base url");
+        };
+        let url =
+            format!("https://api.{host}/repos/{repo_owner}/{repo}/commits/{commit}/{endpoint}");
+
+        let mut request = Request::get(&url)
+            .header("Content-Type", "application/json")
+            .follow_redirects(http_client::RedirectPolicy::FollowAll);
+
+        if let Ok(github_token) = std::env::var("GITHUB_TOKEN") {
+            request = request.header("Authorization", format!("Bearer {}", github_token));
+        }
+
+        let mut response = client
+            .send(request.body(AsyncBody::default())?)
+            .await
+            .with_context(|| format!("error fetching GitHub commit {endpoint} at {:?}", url))?;
+
+        // Commits that were never pushed are reported as missing.
+        if response.status().is_client_error() {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        response.body_mut().read_to_end(&mut body).await?;
+
+        serde_json::from_slice::<T>(&body)
+            .map(Some)
+            .with_context(|| format!("failed to deserialize GitHub commit {endpoint}"))
+    }
 }
 
 #[async_trait]
@@ -255,6 +384,30 @@ impl GitHostingProvider for Github {
             .transpose()?;
         Ok(avatar_url)
     }
+
+    async fn commit_status(
+        &self,
+        repo_owner: &str,
+        repo: &str,
+        commit: SharedString,
+        http_client: Arc<dyn HttpClient>,
+    ) -> Result<Option<CommitStatus>> {
+        let (combined_status, check_runs) = futures::future::try_join(
+            self.fetch_github_commit_status(repo_owner, repo, &commit, &http_client),
+            self.fetch_github_check_runs(repo_owner, repo, &commit, &http_client),
+        )
+        .await?;
+        let statuses = combined_status
+            .and_then(|status| status.commit_status())
+            .into_iter()
+            .chain(
+                check_runs
+                    .into_iter()
+                    .flat_map(|check_runs| check_runs.check_runs)
+                    .filter_map(|check_run| check_run.commit_status()),
+            );
+        Ok(combine_commit_statuses(statuses))
+    }
 }
 
 #[cfg(test)]
@@ -526,4 +679,37 @@ mod tests {
         let expected_url = "https://github.com/zed-industries/nonexistent/blob/3ef1539900037dd3601be7149b2b39ed6d0ce3db/app/blog/%5Bslug%5D/page.tsx#L8";
         assert_eq!(permalink.to_string(), expected_url.to_string())
     }
+
+    #[test]
+    fn test_commit_status_includes_check_runs() {
+        let combined_status: CombinedStatus =
+            serde_json::from_str(r#"{"state": "pending", "total_count": 0}"#).unwrap();
+        let check_runs: CheckRuns = serde_json::from_str(
+            r#"{"total_count": 2, "check_runs": [
+                {"status": "completed", "conclusion": "success"},
+                {"status": "in_progress", "conclusion": null}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(combined_status.commit_status(), None);
+        let statuses = check_runs
+            .check_runs
+            .iter()
+            .filter_map(|check_run| check_run.commit_status())
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [CommitStatus::Success, CommitStatus::Pending]);
+        assert_eq!(
+            combine_commit_statuses(statuses.iter().copied()),
+            Some(CommitStatus::Pending)
+        );
+        assert_eq!(
+            combine_commit_statuses([CommitStatus::Pending, CommitStatus::Failure]),
+            Some(CommitStatus::Failure)
+        );
+        assert_eq!(
+            combine_commit_statuses([CommitStatus::Success]),
+            Some(CommitStatus::Success)
+        );
+        assert_eq!(combine_commit_statuses([]), None);
+    }
 }
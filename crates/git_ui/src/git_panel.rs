@@ -182,6 +182,9 @@ pub fn register(workspace: &mut Workspace) {
     workspace.register_action(|workspace, _: &ToggleFocus, window, cx| {
         workspace.toggle_panel_focus::<GitPanel>(window, cx);
     });
+    workspace.register_action(|workspace, _: &zed_actions::jj::ViewAsGit, window, cx| {
+        workspace.focus_panel::<GitPanel>(window, cx);
+    });
     workspace.register_action(|workspace, _: &ExpandCommitEditor, window, cx| {
         CommitModal::toggle(workspace, None, window, cx)
     });
@@ -1179,6 +1182,9 @@ impl GitPanel {
         let Some(active_repository) = self.active_repository.clone() else {
             return;
         };
+        if self.prevent_jj_managed_staging(cx) {
+            return;
+        }
         cx.spawn({
             async move |this, cx| {
                 let result = this
@@ -1274,6 +1280,9 @@ impl GitPanel {
         let Some(active_repository) = self.active_repository.clone() else {
             return;
         };
+        if self.prevent_jj_managed_staging(cx) {
+            return;
+        }
         cx.spawn({
             async move |this, cx| {
                 let result = this
@@ -3242,6 +3251,13 @@ impl GitPanel {
             _ => format!("{} Changes", self.entry_count),
         };
 
+        let is_jj_managed = self.is_jj_managed(cx);
+        let tooltip = if is_jj_managed {
+            "jj manages this working copy, so git staging is disabled"
+        } else {
+            tooltip
+        };
+
         Some(
             self.panel_header_container(window, cx)
                 .px_2()
@@ -3263,6 +3279,7 @@ impl GitPanel {
                 .child(
                     h_flex()
                         .gap_1()
+                        .children(self.render_view_as_jj_button(cx))
                         .child(self.render_overflow_menu("overflow_menu"))
                         .child(
                             panel_filled_button(text)
@@ -3271,7 +3288,7 @@ impl GitPanel {
                                     action.as_ref(),
                                     &self.focus_handle,
                                 ))
-                                .disabled(self.entry_count == 0)
+                                .disabled(self.entry_count == 0 || is_jj_managed)
                                 .on_click(move |_, _, cx| {
                                     let action = action.boxed_clone();
                                     cx.defer(move |cx| {
@@ -3283,6 +3300,68 @@ impl GitPanel {
         )
     }
 
+    /// Whether the active repository is colocated with a jj repository. jj then owns the
+    /// working copy: it snapshots the files on disk and rewrites git's index as it sees fit,
+    /// so staging there would be silently undone.
+    #[cfg_attr(not(feature = "jj-ui"), allow(unused_variables))]
+    fn is_jj_managed(&self, cx: &App) -> bool {
+        #[cfg(feature = "jj-ui")]
+        if let Some(repository) = &self.active_repository
+            && let Some(jj_store) = self.project.read(cx).jj_store()
+        {
+            let work_directory = &repository.read(cx).work_directory_abs_path;
+            return jj_store
+                .read(cx)
+                .repository_for_work_directory(work_directory)
+                .is_some();
+        }
+        false
+    }
+
+    /// Refuses to stage or unstage in a repository jj manages, explaining why, rather than
+    /// let jj undo it.
+    fn prevent_jj_managed_staging(&self, cx: &mut Context<Self>) -> bool {
+        if !self.is_jj_managed(cx) {
+            return false;
+        }
+        let toast = StatusToast::new(
+            "jj manages this working copy, so git staging is disabled. Use the jj panel instead.",
+            cx,
+            |this, _| {
+                this.icon(ToastIcon::new(IconName::Info).color(Color::Muted))
+                    .dismiss_button(true)
+            },
+        );
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace.toggle_status_toast(toast, cx);
+            })
+            .ok();
+        true
+    }
+
+    /// In a repository colocated with jj, offers switching to the jj panel on it.
+    fn render_view_as_jj_button(&self, cx: &App) -> Option<AnyElement> {
+        if !self.is_jj_managed(cx) {
+            return None;
+        }
+        Some(
+            panel_button("View as jj")
+                .color(Color::Muted)
+                .tooltip(Tooltip::for_action_title_in(
+                    "Switch to the jj Panel",
+                    &zed_actions::jj::ViewAsJj,
+                    &self.focus_handle,
+                ))
+                .on_click(|_, _, cx| {
+                    cx.defer(|cx| {
+                        cx.dispatch_action(&zed_actions::jj::ViewAsJj);
+                    })
+                })
+                .into_any_element(),
+        )
+    }
+
     pub(crate) fn render_remote_button(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
         let branch = self.active_repository.as_ref()?.read(cx).branch.clone();
         if !self.can_push_and_pull(cx) {
@@ -3669,7 +3748,7 @@ impl GitPanel {
         };
 
         let checkbox = Checkbox::new("stage-file", is_staging_or_staged.into())
-            .disabled(!self.has_write_access(cx))
+            .disabled(!self.has_write_access(cx) || self.is_jj_managed(cx))
             .fill()
             .elevation(ElevationIndex::Surface)
             .on_click({
@@ -4075,7 +4154,7 @@ impl GitPanel {
                     .cursor_pointer()
                     .child(
                         Checkbox::new(checkbox_id, is_staged)
-                            .disabled(!has_write_access)
+                            .disabled(!has_write_access || self.is_jj_managed(cx))
                             .fill()
                             .elevation(ElevationIndex::Surface)
                             .on_click_ext({
@@ -0,0 +1,42 @@
+use jj_lib::repo::StoreFactories;
+use jj_lib::working_copy::WorkingCopyFactory;
+use jj_lib::workspace::{WorkingCopyFactories, default_working_copy_factories};
+
+/// The commit, operation and index stores and the working-copy implementations
+/// [`JjWorkspace`](crate::JjWorkspace) can load repositories with.
+///
+/// Starts with the backends built into jj. Organizations running jj with a custom backend
+/// register theirs here, so their repositories load instead of failing on the unknown type
+/// recorded under `.jj/repo/store/type`.
+pub struct JjBackends {
+    pub(crate) store_factories: StoreFactories,
+    pub(crate) working_copy_factories: WorkingCopyFactories,
+}
+
+impl Default for JjBackends {
+    fn default() -> Self {
+        Self {
+            store_factories: StoreFactories::default(),
+            working_copy_factories: default_working_copy_factories(),
+        }
+    }
+}
+
+impl JjBackends {
+    /// Adds the given store backends, replacing built-in ones registered under the same names.
+    pub fn add_store_factories(&mut self, store_factories: StoreFactories) {
+        self.store_factories.merge(store_factories);
+    }
+
+    pub fn add_working_copy_factory(
+        &mut self,
+        name: impl Into<String>,
+        factory: Box<dyn WorkingCopyFactory>,
+    ) {
+        self.working_copy_factories.insert(name.into(), factory);
+    }
+
+    pub fn has_working_copy_backend(&self, name: &str) -> bool {
+        self.working_copy_factories.contains_key(name)
+    }
+}
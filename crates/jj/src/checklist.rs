@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+/// A task-list line in a change description, such as `- [x] update the docs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecklistItem {
+    pub text: String,
+    pub is_checked: bool,
+    /// Where the character between the brackets sits in the description.
+    mark_range: Range<usize>,
+}
+
+/// Finds the task-list items in `description`, in the order they appear.
+pub fn checklist_items(description: &str) -> Vec<ChecklistItem> {
+    let mut items = Vec::new();
+    let mut line_start = 0;
+    for line in description.split_inclusive('\n') {
+        if let Some(item) = parse_item(line.trim_end_matches(['\n', '\r']), line_start) {
+            items.push(item);
+        }
+        line_start += line.len();
+    }
+    items
+}
+
+/// How many of the description's task-list items are checked, out of how many, or `None` if
+/// it has no task list.
+pub fn checklist_progress(description: &str) -> Option<(usize, usize)> {
+    let items = checklist_items(description);
+    if items.is_empty() {
+        return None;
+    }
+    let checked = items.iter().filter(|item| item.is_checked).count();
+    Some((checked, items.len()))
+}
+
+/// Returns `description` with its `index`th task-list item checked or unchecked, leaving the
+/// rest of the text untouched.
+pub fn toggle_checklist_item(description: &str, index: usize) -> Option<String> {
+    let item = checklist_items(description).into_iter().nth(index)?;
+    let mark = if item.is_checked { " " } else { "x" };
+    let mut toggled = description.to_string();
+    toggled.replace_range(item.mark_range, mark);
+    Some(toggled)
+}
+
+fn parse_item(line: &str, line_start: usize) -> Option<ChecklistItem> {
+    let content = line.trim_start();
+    let indent = line.len() - content.len();
+    let rest = content
+        .strip_prefix("- ")
+        .or_else(|| content.strip_prefix("* "))
+        .or_else(|| content.strip_prefix("+ "))?;
+    let is_checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &rest[3..];
+    if !text.is_empty() && !text.starts_with(' ') {
+        return None;
+    }
+    let mark_start = line_start + indent + 3;
+    Some(ChecklistItem {
+        text: text.trim().to_string(),
+        is_checked,
+        mark_range: mark_start..mark_start + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_checklist_item() {
+        let description = "Add bookmarks\n\n- [x] list them\n  * [ ] show them\n- [link](url)\n";
+        assert_eq!(checklist_progress(description), Some((1, 2)));
+        assert_eq!(
+            checklist_items(description)
+                .iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>(),
+            ["list them", "show them"]
+        );
+        assert_eq!(
+            toggle_checklist_item(description, 1).as_deref(),
+            Some("Add bookmarks\n\n- [x] list them\n  * [x] show them\n- [link](url)\n")
+        );
+        assert_eq!(
+            toggle_checklist_item(description, 0).as_deref(),
+            Some("Add bookmarks\n\n- [ ] list them\n  * [ ] show them\n- [link](url)\n")
+        );
+        assert_eq!(toggle_checklist_item(description, 2), None);
+        assert_eq!(checklist_progress("no tasks here"), None);
+    }
+}
@@ -1,7 +1,47 @@
+//! Zed's interface to Jujutsu repositories, wrapping `jj-lib`.
+//!
+//! Other crates use the items re-exported here rather than `jj-lib` itself, so that its
+//! breaking changes stay contained to this crate. [`JjWorkspace`] runs queries and operations,
+//! each operation snapshotting the working copy first and recording one jj operation; queries
+//! return plain data such as [`CommitSummary`].
+//!
+//! ```no_run
+//! use jj::{JjWorkspace, short_change_hash};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let workspace = JjWorkspace::load("/path/to/repo")?;
+//! for commit in workspace.evaluate_revset("::@", 20)? {
+//!     println!("{} {}", short_change_hash(&commit.change_id), commit.description);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod backends;
+mod checklist;
+mod parent_tree_cache;
+mod patch;
+mod profile;
+mod query;
+mod template;
 mod tracker;
 mod workspace;
 
+pub use backends::JjBackends;
+pub use checklist::{ChecklistItem, checklist_items, checklist_progress, toggle_checklist_item};
 pub use jj_lib::backend::{ChangeId, CommitId};
-pub use jj_lib::repo_path::RepoPathBuf;
+pub use jj_lib::object_id::ObjectId;
+pub use jj_lib::repo::StoreFactories;
+pub use jj_lib::repo_path::{RepoPath, RepoPathBuf};
+pub use jj_lib::working_copy::WorkingCopyFactory;
+pub use parent_tree_cache::ParentTreeCache;
+pub use profile::RepositoryProfile;
+pub use query::{CommitField, CommitRecord};
 pub use tracker::{JjRepositoryEntry, JjTracker, UpdatedJjRepositoriesSet, UpdatedJjRepository};
-pub use workspace::{CommitSummary, JjWorkspace, short_change_hash, short_commit_hash};
+pub use workspace::{
+    AnnotatedLine, BookmarkSummary, CommitDateRange, CommitRef, CommitSummary, ConflictSides,
+    DiffStats, DivergentChangeError, FetchSummary, FileStatus, FileStatusKind, GitHead, GitRemote,
+    GraphEdge, GraphEdgeKind, JjWorkspace, OperationSummary, PushSummary, RebaseMode,
+    RepositoryAtOperation, UnsupportedFormatError, WorkingCopyProblem, WorkingCopyState,
+    describe_changed_paths, short_change_hash, short_commit_hash,
+};
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use jj_lib::backend::{CommitId, FileId};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::repo_path::RepoPath;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use util::ResultExt as _;
+
+/// Keeps the cache file small even for long sessions spent on the same change.
+const MAX_ENTRIES: usize = 4096;
+
+/// Materializing a change's files inserts many entries in a burst, so the file is rewritten at
+/// most this often. Entries not yet written are flushed when the cache is dropped; losing them
+/// only costs materializing those files again.
+const WRITE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// On-disk cache recording where the parent-tree version of a path is stored, so that
+/// reopening buffers on the same change reads the file directly instead of resolving (and
+/// possibly merging) the parent trees again.
+///
+/// Entries are keyed by the working copy's parent commits and the path, and the whole cache
+/// is dropped whenever the repository's operation head moves. Files are content-addressed, so
+/// the recorded [`FileId`] always reads back the contents that were materialized.
+pub struct ParentTreeCache {
+    path: PathBuf,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheState {
+    operation_id: String,
+    entries: HashMap<String, CachedFile>,
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    last_write: Option<Instant>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedFile {
+    file_id: String,
+}
+
+impl ParentTreeCache {
+    pub fn load(path: PathBuf) -> Self {
+        let state = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|err| {
+                warn!(
+                    target: "jj::parent_tree_cache",
+                    "discarding unreadable cache {}: {err}",
+                    path.display()
+                );
+                CacheState::default()
+            }),
+            Err(err) => {
+                if err.kind() != ErrorKind::NotFound {
+                    warn!(
+                        target: "jj::parent_tree_cache",
+                        "failed to read cache {}: {err}",
+                        path.display()
+                    );
+                }
+                CacheState::default()
+            }
+        };
+        Self {
+            path,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub(crate) fn get(
+        &self,
+        operation_id: &OperationId,
+        parent_ids: &[CommitId],
+        path: &RepoPath,
+    ) -> Option<FileId> {
+        let mut state = self.lock();
+        state.invalidate_if_stale(operation_id);
+        let cached = state.entries.get(&entry_key(parent_ids, path))?;
+        FileId::try_from_hex(&cached.file_id)
+    }
+
+    pub(crate) fn insert(
+        &self,
+        operation_id: &OperationId,
+        parent_ids: &[CommitId],
+        path: &RepoPath,
+        file_id: &FileId,
+    ) -> Result<()> {
+        let contents = {
+            let mut state = self.lock();
+            state.invalidate_if_stale(operation_id);
+            if state.entries.len() >= MAX_ENTRIES {
+                state.entries.clear();
+            }
+            state.entries.insert(
+                entry_key(parent_ids, path),
+                CachedFile {
+                    file_id: file_id.hex(),
+                },
+            );
+            state.dirty = true;
+            if state
+                .last_write
+                .is_some_and(|last_write| last_write.elapsed() < WRITE_INTERVAL)
+            {
+                return Ok(());
+            }
+            state.take_contents()?
+        };
+        self.write(contents)
+    }
+
+    /// Writes entries inserted since the last write.
+    pub fn flush(&self) -> Result<()> {
+        let contents = {
+            let mut state = self.lock();
+            if !state.dirty {
+                return Ok(());
+            }
+            state.take_contents()?
+        };
+        self.write(contents)
+    }
+
+    fn write(&self, contents: Vec<u8>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, contents)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for ParentTreeCache {
+    fn drop(&mut self) {
+        self.flush().log_err();
+    }
+}
+
+impl CacheState {
+    fn take_contents(&mut self) -> Result<Vec<u8>> {
+        self.dirty = false;
+        self.last_write = Some(Instant::now());
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    fn invalidate_if_stale(&mut self, operation_id: &OperationId) {
+        let operation_id = operation_id.hex();
+        if self.operation_id != operation_id {
+            self.operation_id = operation_id;
+            self.entries.clear();
+        }
+    }
+}
+
+fn entry_key(parent_ids: &[CommitId], path: &RepoPath) -> String {
+    let parents = parent_ids
+        .iter()
+        .map(|id| id.hex())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{parents}:{}", path.as_internal_file_string())
+}
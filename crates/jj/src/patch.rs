@@ -0,0 +1,270 @@
+use anyhow::{Result, anyhow};
+use imara_diff::{Algorithm, UnifiedDiffBuilder, diff, intern::InternedInput};
+use std::ops::Range;
+use time::{OffsetDateTime, UtcOffset, format_description::well_known::Rfc2822};
+
+/// Metadata written into the header of a git-format patch.
+pub(crate) struct PatchHeader<'a> {
+    pub commit_id: &'a str,
+    pub author_name: &'a str,
+    pub author_email: &'a str,
+    pub timestamp_millis: i64,
+    pub tz_offset_minutes: i32,
+    pub description: &'a str,
+}
+
+/// A single file's section of a multi-file patch.
+pub(crate) struct FilePatch<'a> {
+    pub path: String,
+    pub deleted: bool,
+    /// The `---`/`+++` headers and hunks, in the form `diffy` parses.
+    pub hunks: &'a str,
+}
+
+pub(crate) struct ParsedPatch<'a> {
+    pub subject: Option<String>,
+    pub files: Vec<FilePatch<'a>>,
+}
+
+/// Renders the difference between two versions of a file as a git-style unified diff.
+/// `None` means the file doesn't exist on that side.
+pub(crate) fn render_file_diff(path: &str, old: Option<&[u8]>, new: Option<&[u8]>) -> String {
+    let mut output = format!("diff --git a/{path} b/{path}\n");
+    match (old, new) {
+        (None, None) => return String::new(),
+        (None, Some(_)) => {
+            output.push_str("new file mode 100644\n");
+            output.push_str(&format!("--- /dev/null\n+++ b/{path}\n"));
+        }
+        (Some(_), None) => {
+            output.push_str("deleted file mode 100644\n");
+            output.push_str(&format!("--- a/{path}\n+++ /dev/null\n"));
+        }
+        (Some(_), Some(_)) => {
+            output.push_str(&format!("--- a/{path}\n+++ b/{path}\n"));
+        }
+    }
+
+    let old_text = std::str::from_utf8(old.unwrap_or_default());
+    let new_text = std::str::from_utf8(new.unwrap_or_default());
+    match (old_text, new_text) {
+        (Ok(old_text), Ok(new_text)) => {
+            let input = InternedInput::new(old_text, new_text);
+            output.push_str(&diff(
+                Algorithm::Histogram,
+                &input,
+                UnifiedDiffBuilder::new(&input),
+            ));
+        }
+        _ => output.push_str("Binary files differ\n"),
+    }
+    output
+}
+
+/// Counts the lines added to and removed from a file, like `git diff --numstat`. `None` means
+/// the file doesn't exist on that side, and binary files count no lines.
+pub(crate) fn count_changed_lines(old: Option<&[u8]>, new: Option<&[u8]>) -> (usize, usize) {
+    let old_text = std::str::from_utf8(old.unwrap_or_default());
+    let new_text = std::str::from_utf8(new.unwrap_or_default());
+    let (Ok(old_text), Ok(new_text)) = (old_text, new_text) else {
+        return (0, 0);
+    };
+    let input = InternedInput::new(old_text, new_text);
+    let mut insertions = 0;
+    let mut deletions = 0;
+    diff(
+        Algorithm::Histogram,
+        &input,
+        |removed: Range<u32>, added: Range<u32>| {
+            deletions += (removed.end - removed.start) as usize;
+            insertions += (added.end - added.start) as usize;
+        },
+    );
+    (insertions, deletions)
+}
+
+/// Wraps a diff in the mail headers `git am` expects.
+pub(crate) fn render_git_patch(header: &PatchHeader, diff: &str) -> Result<String> {
+    let date =
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(header.timestamp_millis) * 1_000_000)?
+            .to_offset(UtcOffset::from_whole_seconds(
+                header.tz_offset_minutes * 60,
+            )?)
+            .format(&Rfc2822)?;
+    let mut lines = header.description.trim().lines();
+    let subject = lines.next().unwrap_or_default();
+    let body = lines.collect::<Vec<_>>().join("\n");
+
+    let mut output = format!(
+        "From {} Mon Sep 17 00:00:00 2001\nFrom: {} <{}>\nDate: {date}\nSubject: [PATCH] {subject}\n\n",
+        header.commit_id, header.author_name, header.author_email
+    );
+    let body = body.trim();
+    if !body.is_empty() {
+        output.push_str(body);
+        output.push('\n');
+    }
+    output.push_str("---\n\n");
+    output.push_str(diff);
+    Ok(output)
+}
+
+/// Splits a patch produced by `git diff`, `git format-patch` or [`render_file_diff`] into
+/// per-file sections. Text without `diff --git` headers is treated as a single-file diff.
+pub(crate) fn parse_patch(text: &str) -> Result<ParsedPatch<'_>> {
+    let subject = text
+        .lines()
+        .take_while(|line| !line.starts_with("diff --git "))
+        .find_map(|line| line.strip_prefix("Subject: "))
+        .map(|subject| subject.trim_start_matches("[PATCH] ").trim().to_string())
+        .filter(|subject| !subject.is_empty());
+
+    let mut sections = Vec::new();
+    let mut section_start = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.starts_with("diff --git ") {
+            if let Some(start) = section_start {
+                sections.push(&text[start..offset]);
+            }
+            section_start = Some(offset);
+        }
+        offset += line.len();
+    }
+    match section_start {
+        Some(start) => sections.push(&text[start..]),
+        None => sections.push(text),
+    }
+
+    let files = sections
+        .into_iter()
+        .map(parse_file_section)
+        .collect::<Result<Vec<_>>>()?;
+    if files.is_empty() {
+        return Err(anyhow!("patch contains no file changes"));
+    }
+    Ok(ParsedPatch { subject, files })
+}
+
+fn parse_file_section(section: &str) -> Result<FilePatch<'_>> {
+    let hunks_start = section
+        .find("\n--- ")
+        .map(|index| index + 1)
+        .or_else(|| section.starts_with("--- ").then_some(0));
+    let Some(hunks_start) = hunks_start else {
+        if section.contains("Binary files") {
+            return Err(anyhow!("binary patches are not supported"));
+        }
+        return Err(anyhow!("patch section has no file headers"));
+    };
+    let hunks = &section[hunks_start..];
+    let mut header_lines = hunks.lines();
+    let old_path = header_lines
+        .next()
+        .and_then(|line| line.strip_prefix("--- "))
+        .map(header_path);
+    let new_path = header_lines
+        .next()
+        .and_then(|line| line.strip_prefix("+++ "))
+        .map(header_path)
+        .ok_or_else(|| anyhow!("patch section is missing its +++ header"))?;
+
+    let deleted = new_path.is_none();
+    let path = new_path
+        .or(old_path.flatten())
+        .ok_or_else(|| anyhow!("patch section has no file path"))?;
+    Ok(FilePatch {
+        path,
+        deleted,
+        hunks,
+    })
+}
+
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or(header).trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_file_diff() {
+        let modified = render_file_diff("a.txt", Some(b"one\ntwo\n"), Some(b"one\nthree\n"));
+        assert!(modified.starts_with("diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ "));
+        assert!(modified.contains("\n-two\n+three\n"));
+
+        let added = render_file_diff("new.txt", None, Some(b"hello\n"));
+        assert!(added.starts_with(
+            "diff --git a/new.txt b/new.txt\nnew file mode 100644\n--- /dev/null\n+++ b/new.txt\n"
+        ));
+        assert!(added.ends_with("\n+hello\n"));
+
+        assert_eq!(
+            render_file_diff("image.png", Some(&[0xff, 0xfe]), None),
+            "diff --git a/image.png b/image.png\ndeleted file mode 100644\n--- a/image.png\n+++ /dev/null\nBinary files differ\n"
+        );
+        assert_eq!(render_file_diff("gone.txt", None, None), "");
+    }
+
+    #[test]
+    fn test_count_changed_lines() {
+        assert_eq!(
+            count_changed_lines(
+                Some(b"one\ntwo\nthree\n"),
+                Some(b"one\n2\n3\nthree\nfour\n")
+            ),
+            (3, 1)
+        );
+        assert_eq!(count_changed_lines(None, Some(b"a\nb\n")), (2, 0));
+        assert_eq!(count_changed_lines(Some(b"gone\n"), None), (0, 1));
+        assert_eq!(
+            count_changed_lines(Some(&[0xff, 0xfe]), Some(b"text\n")),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let diff = [
+            render_file_diff("src/a.rs", Some(b"one\n"), Some(b"two\n")),
+            render_file_diff("b.txt", Some(b"gone\n"), None),
+        ]
+        .concat();
+        let patch = render_git_patch(
+            &PatchHeader {
+                commit_id: "abc123",
+                author_name: "Jane",
+                author_email: "jane@example.com",
+                timestamp_millis: 0,
+                tz_offset_minutes: 0,
+                description: "Fix things\n\nLonger explanation.\n",
+            },
+            &diff,
+        )
+        .unwrap();
+        assert!(patch.starts_with("From abc123 Mon Sep 17 00:00:00 2001\n"));
+        assert!(patch.contains("Subject: [PATCH] Fix things\n\nLonger explanation.\n---\n"));
+
+        let parsed = parse_patch(&patch).unwrap();
+        assert_eq!(parsed.subject.as_deref(), Some("Fix things"));
+        assert_eq!(parsed.files.len(), 2);
+        assert_eq!(parsed.files[0].path, "src/a.rs");
+        assert!(!parsed.files[0].deleted);
+        assert!(parsed.files[0].hunks.starts_with("--- a/src/a.rs\n"));
+        assert_eq!(parsed.files[1].path, "b.txt");
+        assert!(parsed.files[1].deleted);
+
+        let parsed = parse_patch("--- old.txt\n+++ new.txt\n@@ -1 +1 @@\n-a\n+b\n").unwrap();
+        assert_eq!(parsed.subject, None);
+        assert_eq!(parsed.files[0].path, "new.txt");
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SLOW_LOAD: Duration = Duration::from_secs(1);
+const SLOW_SNAPSHOT: Duration = Duration::from_millis(500);
+const SLOW_LOG_QUERY: Duration = Duration::from_millis(500);
+const SLOW_MATERIALIZATION: Duration = Duration::from_millis(200);
+
+/// Timings of the jj operations Zed runs most often, for diagnosing slow repositories.
+#[derive(Debug, Clone)]
+pub struct RepositoryProfile {
+    pub workspace_root: PathBuf,
+    pub load: Duration,
+    /// How long snapshotting the working copy took, or `None` if the workspace is read-only.
+    pub snapshot: Option<Duration>,
+    pub log_query: Duration,
+    pub log_commit_count: usize,
+    /// The file whose parent-tree version was materialized, and how long it took.
+    pub materialization: Option<(String, Duration)>,
+    pub uses_watchman: bool,
+}
+
+impl RepositoryProfile {
+    pub fn suggestions(&self) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        if self
+            .snapshot
+            .is_some_and(|snapshot| snapshot >= SLOW_SNAPSHOT)
+            && !self.uses_watchman
+        {
+            suggestions.push(
+                "Snapshotting the working copy is slow. Enable watchman with \
+                 `jj config set --user core.fsmonitor watchman`."
+                    .to_string(),
+            );
+        }
+        if self.log_query >= SLOW_LOG_QUERY {
+            suggestions.push(format!(
+                "Loading {} commits is slow. Reduce the log limit, or abandon old heads so \
+                 fewer commits are visible.",
+                self.log_commit_count
+            ));
+        }
+        if self.load >= SLOW_LOAD {
+            suggestions.push(
+                "Loading the workspace is slow. Running `jj util gc` can shrink the operation \
+                 log and index."
+                    .to_string(),
+            );
+        }
+        if let Some((path, duration)) = &self.materialization
+            && *duration >= SLOW_MATERIALIZATION
+        {
+            suggestions.push(format!(
+                "Reading {path} from the parent tree is slow. Large or conflicted files make \
+                 diff bases expensive to compute."
+            ));
+        }
+        suggestions
+    }
+
+    /// Renders the profile as Markdown, ready to paste into a bug report.
+    pub fn report(&self) -> String {
+        let mut report = String::from("# jj repository profile\n\n");
+        let rows = [
+            ("Workspace load", Some(self.load)),
+            ("Working-copy snapshot", self.snapshot),
+            ("Log query", Some(self.log_query)),
+            (
+                "Sample materialization",
+                self.materialization.as_ref().map(|(_, duration)| *duration),
+            ),
+        ];
+        writeln!(report, "Repository: `{}`\n", self.workspace_root.display()).ok();
+        for (label, duration) in rows {
+            match duration {
+                Some(duration) => writeln!(report, "- {label}: {} ms", duration.as_millis()),
+                None => writeln!(report, "- {label}: skipped"),
+            }
+            .ok();
+        }
+        writeln!(report, "- Commits loaded: {}", self.log_commit_count).ok();
+        if let Some((path, _)) = &self.materialization {
+            writeln!(report, "- Materialized file: `{path}`").ok();
+        }
+        writeln!(
+            report,
+            "- Filesystem monitor: {}",
+            if self.uses_watchman {
+                "watchman"
+            } else {
+                "none"
+            }
+        )
+        .ok();
+
+        let suggestions = self.suggestions();
+        report.push_str("\n## Suggestions\n\n");
+        if suggestions.is_empty() {
+            report.push_str("Nothing stands out.\n");
+        }
+        for suggestion in suggestions {
+            writeln!(report, "- {suggestion}").ok();
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        let mut profile = RepositoryProfile {
+            workspace_root: PathBuf::from("/repo"),
+            load: Duration::from_millis(10),
+            snapshot: Some(Duration::from_millis(20)),
+            log_query: Duration::from_millis(30),
+            log_commit_count: 50,
+            materialization: Some(("src/main.rs".to_string(), Duration::from_millis(1))),
+            uses_watchman: false,
+        };
+        assert!(profile.suggestions().is_empty());
+        assert!(profile.report().contains("Nothing stands out."));
+
+        profile.snapshot = Some(Duration::from_secs(2));
+        let suggestions = profile.suggestions();
+        assert_eq!(suggestions.len(), 1);
+        assert!(suggestions[0].contains("watchman"));
+
+        profile.uses_watchman = true;
+        profile.log_query = Duration::from_secs(2);
+        let suggestions = profile.suggestions();
+        assert_eq!(suggestions.len(), 1);
+        assert!(suggestions[0].contains("50 commits"));
+    }
+}
@@ -0,0 +1,154 @@
+use jj_lib::backend::CommitId;
+use jj_lib::commit::Commit;
+use jj_lib::object_id::ObjectId as _;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// A property of a commit that [`crate::JjWorkspace::query_commits`] can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitField {
+    ChangeId,
+    CommitId,
+    ParentIds,
+    Description,
+    Author,
+    AuthorEmail,
+    /// When the commit was committed, as an RFC 3339 string.
+    Timestamp,
+    /// Local bookmarks pointing at the commit.
+    Bookmarks,
+    /// Whether the commit is the workspace's working-copy commit.
+    IsWorkingCopy,
+    /// Whether the commit changes nothing compared to its parents.
+    IsEmpty,
+}
+
+impl CommitField {
+    pub const ALL: [Self; 10] = [
+        Self::ChangeId,
+        Self::CommitId,
+        Self::ParentIds,
+        Self::Description,
+        Self::Author,
+        Self::AuthorEmail,
+        Self::Timestamp,
+        Self::Bookmarks,
+        Self::IsWorkingCopy,
+        Self::IsEmpty,
+    ];
+}
+
+/// One commit matched by a query, holding only the requested fields so that the JSON it
+/// serializes to has exactly those keys. Ids are full hex strings, change ids in jj's
+/// `k`-`z` alphabet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmarks: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_working_copy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_empty: Option<bool>,
+}
+
+/// What a record needs besides the commit itself.
+pub(crate) struct RecordContext<'a> {
+    pub bookmarks_by_commit: &'a HashMap<CommitId, Vec<String>>,
+    pub working_copy_id: Option<&'a CommitId>,
+}
+
+impl CommitRecord {
+    /// `is_empty` comes from the caller, which only works it out when [`CommitField::IsEmpty`]
+    /// is requested since that means diffing against the parents.
+    pub(crate) fn new(
+        commit: &Commit,
+        fields: &[CommitField],
+        context: &RecordContext,
+        is_empty: Option<bool>,
+    ) -> Self {
+        let mut record = Self::default();
+        for field in fields {
+            match field {
+                CommitField::ChangeId => record.change_id = Some(commit.change_id().to_string()),
+                CommitField::CommitId => record.commit_id = Some(commit.id().hex()),
+                CommitField::ParentIds => {
+                    record.parent_ids =
+                        Some(commit.parent_ids().iter().map(|id| id.hex()).collect())
+                }
+                CommitField::Description => {
+                    record.description = Some(commit.description().to_string())
+                }
+                CommitField::Author => record.author = Some(commit.author().name.clone()),
+                CommitField::AuthorEmail => {
+                    record.author_email = Some(commit.author().email.clone())
+                }
+                CommitField::Timestamp => {
+                    let nanos = commit.committer().timestamp.timestamp.0 as i128 * 1_000_000;
+                    record.timestamp = OffsetDateTime::from_unix_timestamp_nanos(nanos)
+                        .ok()
+                        .and_then(|time| time.format(&Rfc3339).ok());
+                }
+                CommitField::Bookmarks => {
+                    record.bookmarks = Some(
+                        context
+                            .bookmarks_by_commit
+                            .get(commit.id())
+                            .cloned()
+                            .unwrap_or_default(),
+                    )
+                }
+                CommitField::IsWorkingCopy => {
+                    record.is_working_copy = Some(context.working_copy_id == Some(commit.id()))
+                }
+                CommitField::IsEmpty => record.is_empty = is_empty,
+            }
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_record_serializes_requested_fields() {
+        let fields: Vec<CommitField> =
+            serde_json::from_str(r#"["change_id", "bookmarks", "is_working_copy"]"#).unwrap();
+        assert_eq!(
+            fields,
+            [
+                CommitField::ChangeId,
+                CommitField::Bookmarks,
+                CommitField::IsWorkingCopy
+            ]
+        );
+        let record = CommitRecord {
+            change_id: Some("zzzzzzzz".into()),
+            bookmarks: Some(vec!["main".into()]),
+            is_working_copy: Some(false),
+            ..CommitRecord::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"change_id":"zzzzzzzz","bookmarks":["main"],"is_working_copy":false}"#
+        );
+    }
+}
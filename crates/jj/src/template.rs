@@ -0,0 +1,607 @@
+//! A small subset of jj's template language, enough for log templates that lay out a commit's
+//! ids, author and description. The full language lives in jj-cli, which this crate doesn't
+//! depend on, so templates reaching beyond this subset are reported as unsupported rather than
+//! rendered differently from `jj log`.
+//!
+//! Supported are double-quoted strings, integers, `++`, the user's `template-aliases`, and:
+//!
+//! - Keywords: `change_id`, `commit_id`, `description`, `author`, `committer`, `bookmarks`,
+//!   `current_working_copy`, `empty`, `conflict` and `root`.
+//! - Functions: `if(condition, then[, otherwise])`, `concat(...)`, `separate(separator, ...)` and
+//!   `label(label, content)`, whose label is ignored.
+//! - Methods: `.short([length])` and `.shortest([length])` on ids; `.name()`, `.email()` and
+//!   `.timestamp()` on signatures; `.ago()` and `.format(format)` on timestamps; `.first_line()`
+//!   on text; and `.join(separator)` on `bookmarks`.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, FixedOffset, TimeZone as _, Utc};
+use jj_lib::backend::Timestamp;
+use std::collections::HashMap;
+
+/// Deep enough for aliases built from other aliases, shallow enough to stop recursive ones.
+const MAX_ALIAS_DEPTH: usize = 32;
+
+/// What a template can ask about the commit it renders.
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateCommit {
+    pub change_id: String,
+    /// Length of the shortest prefix that tells the change id apart in the repository.
+    pub change_id_shortest: usize,
+    pub commit_id: String,
+    pub commit_id_shortest: usize,
+    pub description: String,
+    pub author: TemplateSignature,
+    pub committer: TemplateSignature,
+    pub bookmarks: Vec<String>,
+    pub is_working_copy: bool,
+    pub is_empty: bool,
+    pub has_conflict: bool,
+    pub is_root: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateSignature {
+    pub name: String,
+    pub email: String,
+    pub timestamp: Timestamp,
+}
+
+/// The user's `template-aliases`, keyed by name. Function aliases keep their parameter names.
+#[derive(Debug, Default)]
+pub(crate) struct TemplateAliases {
+    aliases: HashMap<String, (Vec<String>, String)>,
+}
+
+impl TemplateAliases {
+    /// Adds an alias declared as `name` or `name(param, ...)` in the config.
+    pub(crate) fn insert(&mut self, declaration: &str, body: String) -> Result<()> {
+        let declaration = declaration.trim();
+        let (name, params) = match declaration.split_once('(') {
+            Some((name, params)) => {
+                let params = params
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("invalid template alias {declaration:?}"))?;
+                let params = params
+                    .split(',')
+                    .map(|param| param.trim().to_string())
+                    .filter(|param| !param.is_empty())
+                    .collect();
+                (name.trim(), params)
+            }
+            None => (declaration, Vec::new()),
+        };
+        self.aliases.insert(name.to_string(), (params, body));
+        Ok(())
+    }
+}
+
+/// Renders `template` for `commit`, expanding `aliases` along the way.
+pub(crate) fn render(
+    template: &str,
+    commit: &TemplateCommit,
+    aliases: &TemplateAliases,
+) -> Result<String> {
+    let expression = parse(template)?;
+    let evaluator = Evaluator { commit, aliases };
+    Ok(evaluator
+        .evaluate(&expression, &HashMap::new(), 0)?
+        .into_text())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    String(String),
+    Integer(i64),
+    Identifier(String),
+    Call(String, Vec<Expression>),
+    Method(Box<Expression>, String, Vec<Expression>),
+    Concat(Vec<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    String(String),
+    Integer(i64),
+    Identifier(String),
+    OpenParen,
+    CloseParen,
+    Comma,
+    Dot,
+    Concat,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(character) = chars.next() {
+        let token = match character {
+            _ if character.is_whitespace() => continue,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            ',' => Token::Comma,
+            '.' => Token::Dot,
+            '+' if chars.next_if_eq(&'+').is_some() => Token::Concat,
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => string.push(match chars.next() {
+                            Some('n') => '\n',
+                            Some(escaped @ ('"' | '\\')) => escaped,
+                            other => {
+                                return Err(anyhow!("invalid escape in template: \\{other:?}"));
+                            }
+                        }),
+                        Some(next) => string.push(next),
+                        None => return Err(anyhow!("unterminated string in template")),
+                    }
+                }
+                Token::String(string)
+            }
+            _ if character.is_ascii_digit() => {
+                let mut digits = character.to_string();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                Token::Integer(digits.parse()?)
+            }
+            _ if character.is_alphabetic() || character == '_' => {
+                let mut identifier = character.to_string();
+                while let Some(next) = chars.next_if(|next| next.is_alphanumeric() || *next == '_')
+                {
+                    identifier.push(next);
+                }
+                Token::Identifier(identifier)
+            }
+            _ => return Err(anyhow!("unsupported character {character:?} in template")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse(text: &str) -> Result<Expression> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Ok(Expression::String(String::new()));
+    }
+    let expression = parser.parse_concat()?;
+    match parser.peek() {
+        None => Ok(expression),
+        Some(token) => Err(anyhow!("unexpected {token:?} in template")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "expected {token:?} in template, found {:?}",
+                self.peek()
+            ))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Expression> {
+        let mut parts = vec![self.parse_term()?];
+        while self.eat(&Token::Concat) {
+            parts.push(self.parse_term()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expression::Concat(parts)
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Expression> {
+        let mut expression = self.parse_primary()?;
+        while self.eat(&Token::Dot) {
+            let Some(Token::Identifier(method)) = self.next() else {
+                return Err(anyhow!("expected a method name after '.' in template"));
+            };
+            self.expect(&Token::OpenParen)?;
+            let arguments = self.parse_arguments()?;
+            expression = Expression::Method(Box::new(expression), method, arguments);
+        }
+        Ok(expression)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression> {
+        match self.next() {
+            Some(Token::String(string)) => Ok(Expression::String(string)),
+            Some(Token::Integer(integer)) => Ok(Expression::Integer(integer)),
+            Some(Token::OpenParen) => {
+                let expression = self.parse_concat()?;
+                self.expect(&Token::CloseParen)?;
+                Ok(expression)
+            }
+            Some(Token::Identifier(name)) => {
+                if self.eat(&Token::OpenParen) {
+                    Ok(Expression::Call(name, self.parse_arguments()?))
+                } else {
+                    Ok(Expression::Identifier(name))
+                }
+            }
+            token => Err(anyhow!("unexpected {token:?} in template")),
+        }
+    }
+
+    /// Parses the arguments after an opening parenthesis, up to and including the closing one.
+    fn parse_arguments(&mut self) -> Result<Vec<Expression>> {
+        let mut arguments = Vec::new();
+        if self.eat(&Token::CloseParen) {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.parse_concat()?);
+            if self.eat(&Token::CloseParen) {
+                return Ok(arguments);
+            }
+            self.expect(&Token::Comma)?;
+            // Like jj, allow a trailing comma.
+            if self.eat(&Token::CloseParen) {
+                return Ok(arguments);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Text(String),
+    Boolean(bool),
+    Integer(i64),
+    Id { full: String, shortest: usize },
+    Signature(TemplateSignature),
+    Timestamp(Timestamp),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn into_text(self) -> String {
+        match self {
+            Self::Text(text) => text,
+            Self::Boolean(boolean) => boolean.to_string(),
+            Self::Integer(integer) => integer.to_string(),
+            Self::Id { full, .. } => full,
+            Self::Signature(signature) => format!("{} <{}>", signature.name, signature.email),
+            Self::Timestamp(timestamp) => format_timestamp(&timestamp, "%Y-%m-%d %H:%M:%S.%3f %:z"),
+            Self::List(items) => items.join(" "),
+        }
+    }
+
+    /// Like jj, conditions accept text and lists as well, which are true when non-empty.
+    fn is_true(&self) -> Result<bool> {
+        match self {
+            Self::Boolean(boolean) => Ok(*boolean),
+            Self::Text(text) => Ok(!text.is_empty()),
+            Self::List(items) => Ok(!items.is_empty()),
+            other => Err(anyhow!(
+                "{other:?} can't be used as a condition in a template"
+            )),
+        }
+    }
+}
+
+struct Evaluator<'a> {
+    commit: &'a TemplateCommit,
+    aliases: &'a TemplateAliases,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(
+        &self,
+        expression: &Expression,
+        locals: &HashMap<String, Value>,
+        depth: usize,
+    ) -> Result<Value> {
+        if depth > MAX_ALIAS_DEPTH {
+            return Err(anyhow!("template aliases expand too deeply"));
+        }
+        let evaluate = |expression: &Expression| self.evaluate(expression, locals, depth);
+        match expression {
+            Expression::String(string) => Ok(Value::Text(string.clone())),
+            Expression::Integer(integer) => Ok(Value::Integer(*integer)),
+            Expression::Identifier(name) => {
+                if let Some(value) = locals.get(name) {
+                    return Ok(value.clone());
+                }
+                if let Some(value) = self.keyword(name) {
+                    return Ok(value);
+                }
+                self.expand_alias(name, &[], locals, depth)
+            }
+            Expression::Call(name, arguments) => self.call(name, arguments, locals, depth),
+            Expression::Method(receiver, name, arguments) => {
+                let receiver = evaluate(receiver)?;
+                let arguments = arguments.iter().map(evaluate).collect::<Result<Vec<_>>>()?;
+                call_method(receiver, name, arguments)
+            }
+            Expression::Concat(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&evaluate(part)?.into_text());
+                }
+                Ok(Value::Text(text))
+            }
+        }
+    }
+
+    fn keyword(&self, name: &str) -> Option<Value> {
+        let commit = self.commit;
+        Some(match name {
+            "change_id" => Value::Id {
+                full: commit.change_id.clone(),
+                shortest: commit.change_id_shortest,
+            },
+            "commit_id" => Value::Id {
+                full: commit.commit_id.clone(),
+                shortest: commit.commit_id_shortest,
+            },
+            "description" => Value::Text(commit.description.clone()),
+            "author" => Value::Signature(commit.author.clone()),
+            "committer" => Value::Signature(commit.committer.clone()),
+            "bookmarks" => Value::List(commit.bookmarks.clone()),
+            "current_working_copy" => Value::Boolean(commit.is_working_copy),
+            "empty" => Value::Boolean(commit.is_empty),
+            "conflict" => Value::Boolean(commit.has_conflict),
+            "root" => Value::Boolean(commit.is_root),
+            _ => return None,
+        })
+    }
+
+    fn expand_alias(
+        &self,
+        name: &str,
+        arguments: &[Expression],
+        locals: &HashMap<String, Value>,
+        depth: usize,
+    ) -> Result<Value> {
+        let Some((params, body)) = self.aliases.aliases.get(name) else {
+            return Err(anyhow!("template uses {name}, which isn't supported"));
+        };
+        if params.len() != arguments.len() {
+            return Err(anyhow!(
+                "template alias {name} takes {} arguments, got {}",
+                params.len(),
+                arguments.len()
+            ));
+        }
+        let mut alias_locals = HashMap::new();
+        for (param, argument) in params.iter().zip(arguments) {
+            alias_locals.insert(param.clone(), self.evaluate(argument, locals, depth)?);
+        }
+        self.evaluate(&parse(body)?, &alias_locals, depth + 1)
+    }
+
+    fn call(
+        &self,
+        name: &str,
+        arguments: &[Expression],
+        locals: &HashMap<String, Value>,
+        depth: usize,
+    ) -> Result<Value> {
+        let evaluate = |expression: &Expression| self.evaluate(expression, locals, depth);
+        let text =
+            |expression: &Expression| Ok::<_, anyhow::Error>(evaluate(expression)?.into_text());
+        // User aliases shadow the built-in functions, as they do in jj.
+        if self.aliases.aliases.contains_key(name) {
+            return self.expand_alias(name, arguments, locals, depth);
+        }
+        match (name, arguments) {
+            ("if", [condition, then]) => Ok(if evaluate(condition)?.is_true()? {
+                evaluate(then)?
+            } else {
+                Value::Text(String::new())
+            }),
+            ("if", [condition, then, otherwise]) => {
+                if evaluate(condition)?.is_true()? {
+                    evaluate(then)
+                } else {
+                    evaluate(otherwise)
+                }
+            }
+            ("concat", parts) => Ok(Value::Text(
+                parts.iter().map(text).collect::<Result<String>>()?,
+            )),
+            ("separate", [separator, parts @ ..]) => {
+                let separator = text(separator)?;
+                let parts = parts
+                    .iter()
+                    .map(text)
+                    .filter(|part| !matches!(part, Ok(part) if part.is_empty()))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Text(parts.join(&separator)))
+            }
+            // Colors are left to the panel.
+            ("label", [_, content]) => evaluate(content),
+            _ => Err(anyhow!(
+                "template uses {name}() with {} arguments, which isn't supported",
+                arguments.len()
+            )),
+        }
+    }
+}
+
+fn call_method(receiver: Value, name: &str, arguments: Vec<Value>) -> Result<Value> {
+    let length_argument = |default: usize| match arguments.first() {
+        Some(Value::Integer(length)) => Ok((*length).max(0) as usize),
+        Some(other) => Err(anyhow!("{name}() expects a number, got {other:?}")),
+        None => Ok(default),
+    };
+    let text_argument = || match arguments.first() {
+        Some(value) => Ok(value.clone().into_text()),
+        None => Err(anyhow!("{name}() expects an argument")),
+    };
+    match (receiver, name) {
+        (Value::Id { full, .. }, "short") => Ok(Value::Text(
+            full.chars().take(length_argument(12)?).collect(),
+        )),
+        (Value::Id { full, shortest }, "shortest") => {
+            let length = shortest.max(length_argument(0)?);
+            Ok(Value::Text(full.chars().take(length).collect()))
+        }
+        (Value::Signature(signature), "name") => Ok(Value::Text(signature.name)),
+        (Value::Signature(signature), "email") => Ok(Value::Text(signature.email)),
+        (Value::Signature(signature), "timestamp") => Ok(Value::Timestamp(signature.timestamp)),
+        (Value::Timestamp(timestamp), "ago") => Ok(Value::Text(time_ago(
+            timestamp.timestamp.0,
+            Utc::now().timestamp_millis(),
+        ))),
+        (Value::Timestamp(timestamp), "format") => {
+            Ok(Value::Text(format_timestamp(&timestamp, &text_argument()?)))
+        }
+        (Value::Text(text), "first_line") => Ok(Value::Text(
+            text.lines().next().unwrap_or_default().to_string(),
+        )),
+        (Value::List(items), "join") => Ok(Value::Text(items.join(&text_argument()?))),
+        (receiver, name) => Err(anyhow!(
+            "template calls {name}() on {receiver:?}, which isn't supported"
+        )),
+    }
+}
+
+fn timestamp_in_its_zone(timestamp: &Timestamp) -> Option<DateTime<FixedOffset>> {
+    let offset = FixedOffset::east_opt(timestamp.tz_offset * 60)?;
+    offset.timestamp_millis_opt(timestamp.timestamp.0).single()
+}
+
+fn format_timestamp(timestamp: &Timestamp, format: &str) -> String {
+    match timestamp_in_its_zone(timestamp) {
+        Some(time) => time.format(format).to_string(),
+        None => "<out-of-range date>".to_string(),
+    }
+}
+
+/// Words a duration the way `timestamp.ago()` does in jj, in its largest whole unit.
+fn time_ago(then_millis: i64, now_millis: i64) -> String {
+    const UNITS: [(&str, i64); 7] = [
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+        ("second", 1),
+    ];
+    let seconds = (now_millis - then_millis) / 1000;
+    let (direction, seconds) = if seconds < 0 {
+        ("in the future", -seconds)
+    } else {
+        ("ago", seconds)
+    };
+    for (unit, unit_seconds) in UNITS {
+        let count = seconds / unit_seconds;
+        if count >= 1 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{count} {unit}{plural} {direction}");
+        }
+    }
+    "now".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jj_lib::backend::MillisSinceEpoch;
+
+    fn commit() -> TemplateCommit {
+        let signature = TemplateSignature {
+            name: "Ada Lovelace".into(),
+            email: "ada@example.com".into(),
+            timestamp: Timestamp {
+                timestamp: MillisSinceEpoch(1_700_000_000_000),
+                tz_offset: 60,
+            },
+        };
+        TemplateCommit {
+            change_id: "kmtwsmvnzxwqorpzrrlyrlqqwmvmrnzl".into(),
+            change_id_shortest: 2,
+            commit_id: "0123456789abcdef0123456789abcdef01234567".into(),
+            commit_id_shortest: 4,
+            description: "Fix the parser\n\nIt dropped the last token.\n".into(),
+            author: signature.clone(),
+            committer: signature,
+            bookmarks: vec!["main".into(), "parser".into()],
+            is_working_copy: true,
+            is_empty: false,
+            has_conflict: false,
+            is_root: false,
+        }
+    }
+
+    #[test]
+    fn test_render_log_template() {
+        let mut aliases = TemplateAliases::default();
+        aliases
+            .insert("wc_marker", r#"if(current_working_copy, "@", "○")"#.into())
+            .unwrap();
+        aliases
+            .insert("quoted(text)", r#""'" ++ text ++ "'""#.into())
+            .unwrap();
+        let template = r#"
+            separate(" ",
+                wc_marker,
+                change_id.shortest(8),
+                author.email(),
+                author.timestamp().format("%Y-%m-%d %H:%M"),
+                bookmarks.join(","),
+                if(empty, label("empty", "(empty)")),
+                commit_id.short(),
+            ) ++ "\n" ++ quoted(description.first_line())
+        "#;
+        assert_eq!(
+            render(template, &commit(), &aliases).unwrap(),
+            "@ kmtwsmvn ada@example.com 2023-11-14 23:13 main,parser 0123456789ab\n\
+             'Fix the parser'"
+        );
+    }
+
+    #[test]
+    fn test_render_reports_unsupported_template() {
+        let aliases = TemplateAliases::default();
+        assert!(render("builtin_log_compact", &commit(), &aliases).is_err());
+        assert!(render("parents.map(|c| c.commit_id())", &commit(), &aliases).is_err());
+        assert!(render("truncate_end(10, description)", &commit(), &aliases).is_err());
+        assert!(render("!empty", &commit(), &aliases).is_err());
+    }
+
+    #[test]
+    fn test_time_ago() {
+        let hour = 60 * 60 * 1000;
+        assert_eq!(time_ago(0, 3 * hour), "3 hours ago");
+        assert_eq!(time_ago(0, hour), "1 hour ago");
+        assert_eq!(time_ago(0, 500), "now");
+    }
+}
@@ -1,72 +1,686 @@
+use crate::backends::JjBackends;
+use crate::parent_tree_cache::ParentTreeCache;
+use crate::patch;
+use crate::profile::RepositoryProfile;
+use crate::query::{CommitField, CommitRecord, RecordContext};
+use crate::template::{self, TemplateAliases, TemplateCommit, TemplateSignature};
 use anyhow::{Result, anyhow};
-use jj_lib::backend::{ChangeId, CommitId};
+use futures::StreamExt as _;
+use jj_lib::annotate::FileAnnotator;
+use jj_lib::backend::{ChangeId, CommitId, CopyId, MillisSinceEpoch, TreeValue};
 use jj_lib::commit::Commit;
-use jj_lib::config::StackedConfig;
+use jj_lib::config::{ConfigGetResultExt as _, ConfigSource, StackedConfig};
 use jj_lib::conflicts::{ConflictMarkerStyle, MaterializedTreeValue, materialize_tree_value};
-use jj_lib::fileset::FilesetExpression;
+use jj_lib::fileset::{self, FilesetDiagnostics, FilesetExpression};
+use jj_lib::fsmonitor::FsmonitorSettings;
+use jj_lib::git::{
+    self as jj_git, GitBranchPushTargets, GitFetch, GitSettings, REMOTE_NAME_FOR_LOCAL_GIT_REPO,
+    RemoteCallbacks,
+};
 use jj_lib::gitignore::GitIgnoreFile;
-use jj_lib::matchers::Matcher;
-use jj_lib::ref_name::WorkspaceNameBuf;
-use jj_lib::repo::{ReadonlyRepo, Repo as _, RepoLoader, StoreFactories};
-use jj_lib::repo_path::RepoPath;
+use jj_lib::graph::{self as jj_graph, GraphEdgeType};
+use jj_lib::hex_util::HexPrefix;
+use jj_lib::index::PrefixResolution;
+use jj_lib::matchers::{EverythingMatcher, FilesMatcher, Matcher, UnionMatcher};
+use jj_lib::merge::{Merge, MergedTreeValue};
+use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::{OperationId, RefTarget, RemoteRef};
+use jj_lib::op_walk;
+use jj_lib::operation::Operation;
+use jj_lib::ref_name::{
+    RefName, RefNameBuf, RemoteName, RemoteNameBuf, WorkspaceName, WorkspaceNameBuf,
+};
+use jj_lib::refs::{BookmarkPushAction, LocalAndRemoteRef, classify_bookmark_push_action};
+use jj_lib::repo::{ReadonlyRepo, Repo as _, RepoLoader, StoreLoadError};
+use jj_lib::repo_path::{RepoPath, RepoPathBuf, RepoPathUiConverter};
+use jj_lib::revset::{
+    self, DefaultSymbolResolver, ResolvedRevsetExpression, RevsetAliasesMap, RevsetDiagnostics,
+    RevsetExpression, RevsetExtensions, RevsetFilterPredicate, RevsetParseContext,
+    RevsetWorkspaceContext,
+};
+use jj_lib::rewrite::{merge_commit_trees, rebase_commit};
 use jj_lib::settings::UserSettings;
+use jj_lib::str_util::StringPattern;
+use jj_lib::time_util::{DatePattern, DatePatternContext};
 use jj_lib::transaction::Transaction;
-use jj_lib::working_copy::{CheckoutOptions, SnapshotOptions, WorkingCopyFreshness};
-use jj_lib::workspace::{self, DefaultWorkspaceLoaderFactory, WorkspaceLoaderFactory};
+use jj_lib::working_copy::{CheckoutOptions, SnapshotOptions, SnapshotStats, WorkingCopyFreshness};
+use jj_lib::workspace::{
+    self, DefaultWorkspaceLoaderFactory, WorkspaceLoader, WorkspaceLoaderFactory,
+};
 use log::{debug, warn};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{ErrorKind, Read as _};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::slice;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use time::macros::format_description;
+use util::ResultExt as _;
 
 /// Thin wrapper around `jj_lib`'s workspace APIs for UI consumers.
 pub struct JjWorkspace {
     repo_loader: RepoLoader,
     workspace_name: WorkspaceNameBuf,
     workspace_root: PathBuf,
+    backends: Arc<JjBackends>,
+    parent_tree_cache: Option<Arc<ParentTreeCache>>,
+    read_only: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct CommitSummary {
     pub commit_id: CommitId,
+    pub parent_ids: Vec<CommitId>,
     pub change_id: ChangeId,
     pub author: String,
+    pub author_email: String,
+    /// When the change was first authored, in milliseconds since the epoch. Rewriting the
+    /// commit keeps it, unlike `timestamp`.
+    pub author_timestamp: i64,
+    pub committer: String,
+    pub committer_email: String,
     pub description: String,
+    /// When the commit was last written, in milliseconds since the epoch.
     pub timestamp: i64,
+    pub is_signed: bool,
+    /// Whether the commit is reachable from a bookmark on a git remote.
+    pub is_pushed: bool,
+    /// The closest bookmark or tag pointing at this commit or one of its descendants.
+    pub nearest_ref: Option<String>,
+    /// The lines `jj log` would draw from this commit down to others in the same listing, one
+    /// per parent. Empty for commits that weren't listed as part of a log.
+    pub graph_edges: Vec<GraphEdge>,
+    /// Whether other visible commits share this commit's change id, as when the change was
+    /// rewritten concurrently from two workspaces.
+    pub is_divergent: bool,
+    /// Whether the commit was abandoned or rewritten, so that it only shows up in the
+    /// operation log and evolution log rather than in `jj log`.
+    pub is_hidden: bool,
+    /// Whether the commit changes nothing compared to its parents.
+    pub is_empty: bool,
+    pub has_conflict: bool,
+    /// The bookmarks and tags pointing at the commit, as `jj log` labels it. Empty for commits
+    /// that weren't listed as part of a log.
+    pub refs: Vec<CommitRef>,
+}
+
+/// Returned, wrapped in an [`anyhow::Error`], by operations given a change id that maps to more
+/// than one visible commit. Callers can downcast to it to let the user pick one of
+/// `commit_ids`, e.g. with [`JjWorkspace::keep_divergent_commit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DivergentChangeError {
+    pub change_id: ChangeId,
+    pub commit_ids: Vec<CommitId>,
+}
+
+impl std::fmt::Display for DivergentChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "change {} is divergent: it has {} visible commits ({})",
+            short_change_hash(&self.change_id),
+            self.commit_ids.len(),
+            self.commit_ids
+                .iter()
+                .map(short_commit_hash)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for DivergentChangeError {}
+
+/// Returned, wrapped in an [`anyhow::Error`], when a repository's metadata is in a format the
+/// bundled `jj-lib` can't read, usually because a newer jj upgraded it. Retrying won't help, so
+/// callers should stop using the repository rather than report each failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedFormatError {
+    pub detail: String,
+}
+
+impl UnsupportedFormatError {
+    /// Finds the errors `jj-lib` returns for unknown store types or undecodable operation and
+    /// working-copy metadata anywhere in `error`'s chain.
+    pub fn detect(error: &anyhow::Error) -> Option<Self> {
+        error.chain().find_map(|cause| {
+            if let Some(unsupported) = cause.downcast_ref::<UnsupportedFormatError>() {
+                return Some(unsupported.clone());
+            }
+            if let Some(StoreLoadError::UnsupportedType { store, store_type }) =
+                cause.downcast_ref::<StoreLoadError>()
+            {
+                return Some(Self {
+                    detail: format!("unknown {store} store type {store_type:?}"),
+                });
+            }
+            // jj-lib boxes prost's decode errors, whose version doesn't match ours, so the
+            // message is the only stable thing to match on.
+            let message = cause.to_string();
+            message
+                .contains("decode Protobuf")
+                .then(|| Self { detail: message })
+        })
+    }
+}
+
+impl std::fmt::Display for UnsupportedFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "repository was created by a newer jj ({}); update Zed or downgrade jj",
+            self.detail
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedFormatError {}
+
+/// How a commit in a log connects to one of its ancestors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphEdgeKind {
+    /// The target is the commit's parent.
+    Direct,
+    /// The commits between this one and the target were left out of the log.
+    Indirect,
+    /// The parent and all its ancestors were left out of the log, so the target is the parent
+    /// itself even though it isn't listed.
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphEdge {
+    pub target: CommitId,
+    pub kind: GraphEdgeKind,
+}
+
+impl GraphEdge {
+    fn from_lib(edge: jj_graph::GraphEdge<CommitId>) -> Self {
+        let kind = match edge.edge_type {
+            GraphEdgeType::Direct => GraphEdgeKind::Direct,
+            GraphEdgeType::Indirect => GraphEdgeKind::Indirect,
+            GraphEdgeType::Missing => GraphEdgeKind::Missing,
+        };
+        Self {
+            target: edge.target,
+            kind,
+        }
+    }
+}
+
+/// Bounds on when commits were committed, in milliseconds since the epoch, for narrowing the log
+/// like `committer_date()` revsets do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitDateRange {
+    /// Only commits committed at or after this time.
+    pub after: Option<i64>,
+    /// Only commits committed before this time.
+    pub before: Option<i64>,
+}
+
+impl CommitDateRange {
+    pub fn is_unbounded(&self) -> bool {
+        self.after.is_none() && self.before.is_none()
+    }
+}
+
+/// A bookmark or tag pointing at a commit. Remote bookmarks that point where their tracking
+/// local bookmark does are left out, since `jj log` only labels the local one then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRef {
+    pub name: String,
+    /// The remote of a remote bookmark, or `None` for local bookmarks and tags.
+    pub remote: Option<String>,
+    pub is_tag: bool,
+    /// Whether the ref also points at other commits.
+    pub is_conflicted: bool,
+}
+
+impl CommitRef {
+    /// The ref as `jj log` writes it, like `main@origin`, or `main??` when it's conflicted.
+    pub fn label(&self) -> String {
+        let mut label = match &self.remote {
+            Some(remote) => format!("{}@{remote}", self.name),
+            None => self.name.clone(),
+        };
+        if self.is_conflicted {
+            label.push_str("??");
+        }
+        label
+    }
+}
+
+/// A local bookmark or a bookmark on a git remote, like an entry of `jj bookmark list --all`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkSummary {
+    pub name: String,
+    /// The remote the bookmark was last fetched from, or `None` for a local bookmark.
+    pub remote: Option<String>,
+    /// The commits the bookmark points at. There are several when it's conflicted.
+    pub target_ids: Vec<CommitId>,
+    /// Whether the local bookmark of the same name follows this remote bookmark. Always false
+    /// for local bookmarks.
+    pub is_tracked: bool,
+}
+
+impl BookmarkSummary {
+    pub fn is_conflicted(&self) -> bool {
+        self.target_ids.len() > 1
+    }
+}
+
+/// How the working-copy change alters a path relative to its parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatusKind {
+    Added,
+    Modified,
+    Removed,
+    Conflicted,
+}
+
+impl FileStatusKind {
+    fn from_values(before: &MergedTreeValue, after: &MergedTreeValue) -> Self {
+        if !after.is_resolved() {
+            Self::Conflicted
+        } else if after.is_absent() {
+            Self::Removed
+        } else if before.is_absent() {
+            Self::Added
+        } else {
+            Self::Modified
+        }
+    }
+}
+
+/// A path the working-copy change touches, like a line of `jj status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    pub path: RepoPathBuf,
+    pub kind: FileStatusKind,
+}
+
+/// How much a change alters, like the summary line of `jj diff --stat`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// The contents on each side of a two-sided conflict and the base they diverged from, as a
+/// merge tool shows them. A side that deletes the file is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSides {
+    pub left: String,
+    pub base: String,
+    pub right: String,
+}
+
+/// The change that last touched a line of a file, like a line of `jj file annotate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedLine {
+    pub change_id: ChangeId,
+    pub commit_id: CommitId,
+    pub author: String,
+    pub timestamp: i64,
+}
+
+/// Where git's HEAD is in a colocated repository, which is what git tools treat as checked out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHead {
+    pub commit_id: CommitId,
+    /// The branch HEAD is attached to, or else the branches pointing at its commit. jj leaves
+    /// HEAD detached, so git tools only see a branch checked out when one points there.
+    pub branches: Vec<String>,
+    /// Whether HEAD is at the working-copy change's parent, `@-`, where jj keeps it.
+    pub is_working_copy_parent: bool,
+}
+
+/// A remote of the repository's backing git repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRemote {
+    pub name: String,
+    /// The fetch URL, absent when git's config names the remote without one.
+    pub url: Option<String>,
+}
+
+/// What `jj git fetch` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchSummary {
+    /// Remote bookmarks that appeared, moved or went away, written `name@remote`.
+    pub updated_bookmarks: Vec<String>,
+    /// Local commits abandoned because the remote no longer has them.
+    pub abandoned_commits: usize,
+}
+
+impl FetchSummary {
+    pub fn describe(&self) -> String {
+        let mut description = match self.updated_bookmarks.as_slice() {
+            [] => "Nothing new".to_string(),
+            [bookmark] => format!("Updated {bookmark}"),
+            bookmarks => format!("Updated {} bookmarks", bookmarks.len()),
+        };
+        if self.abandoned_commits > 0 {
+            description.push_str(&format!(
+                ", abandoned {} commit{}",
+                self.abandoned_commits,
+                if self.abandoned_commits == 1 { "" } else { "s" }
+            ));
+        }
+        description
+    }
+}
+
+/// What `jj git push` did with one bookmark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushSummary {
+    pub bookmark: String,
+    /// Whether the remote changed. It doesn't when the bookmark was already up to date there.
+    pub pushed: bool,
+}
+
+/// The log and bookmarks as they were right after an operation, like `jj log` and
+/// `jj bookmark list` run with `--at-operation`.
+#[derive(Debug, Clone)]
+pub struct RepositoryAtOperation {
+    pub commits: Vec<CommitSummary>,
+    pub bookmarks: Vec<BookmarkSummary>,
+    /// The workspace's working-copy change at the time.
+    pub current_change_id: Option<ChangeId>,
+}
+
+/// An entry of the repository's operation log.
+#[derive(Debug, Clone)]
+pub struct OperationSummary {
+    /// The operation id as hex.
+    pub id: String,
+    /// When the operation finished, in milliseconds since the epoch.
+    pub timestamp: i64,
+    pub description: String,
+    /// Who ran the operation and on which machine, which tells operations synced from
+    /// elsewhere or run by others on a shared machine apart from one's own.
+    pub username: String,
+    pub hostname: String,
+}
+
+impl OperationSummary {
+    fn from_operation(operation: &Operation) -> Self {
+        let metadata = operation.metadata();
+        Self {
+            id: operation.id().hex(),
+            timestamp: metadata.time.end.timestamp.0,
+            description: metadata.description.clone(),
+            username: metadata.username.clone(),
+            hostname: metadata.hostname.clone(),
+        }
+    }
+}
+
+/// Leftovers of an interrupted jj process (Zed's or the CLI's) that keep the working copy
+/// from being used until they're cleaned up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkingCopyProblem {
+    /// The working copy wasn't updated after an operation rewrote its commit, as when a
+    /// checkout is interrupted. Fixed by `jj workspace update-stale`.
+    Stale,
+    /// A working-copy lock that nothing has touched for several minutes.
+    StaleLock(PathBuf),
+}
+
+/// The operation a workspace's files were last checked out or snapshotted at, next to the
+/// repository's operation head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkingCopyState {
+    /// The working copy's recorded operation id as hex.
+    pub operation_id: String,
+    pub head_operation_id: String,
+    /// Whether an operation since then rewrote the working-copy commit without updating the
+    /// files, so that snapshotting fails until the working copy is updated.
+    pub is_stale: bool,
+}
+
+impl WorkingCopyState {
+    pub fn is_at_head(&self) -> bool {
+        self.operation_id == self.head_operation_id
+    }
+}
+
+/// Which commits [`JjWorkspace::rebase`] moves, after `jj rebase`'s `-r`, `-s` and `-b` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebaseMode {
+    /// Only the change itself. Its children move onto its old parents.
+    Revision,
+    /// The change and all of its descendants.
+    Source,
+    /// Every ancestor and descendant of the change that isn't already on the destination.
+    Branch,
+}
+
+impl RebaseMode {
+    fn subject(self) -> &'static str {
+        match self {
+            Self::Revision => "change",
+            Self::Source => "descendants of change",
+            Self::Branch => "branch of change",
+        }
+    }
+}
+
+/// How many commits to search for the candidates listed when a change id prefix is ambiguous.
+const AMBIGUOUS_PREFIX_SCAN_LIMIT: usize = 10_000;
+const MAX_AMBIGUOUS_CANDIDATES: usize = 5;
+
+/// How many operations back [`JjWorkspace::hidden_heads`] looks for abandoned work.
+const HIDDEN_HEADS_OPERATION_LIMIT: usize = 500;
+
+/// How old an untouched working-copy lock has to be before it's assumed to be left over from
+/// a crash rather than held by a running process.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(5 * 60);
+
+impl CommitSummary {
+    fn from_commit(commit: &Commit) -> Self {
+        Self {
+            commit_id: commit.id().clone(),
+            parent_ids: commit.parent_ids().to_vec(),
+            change_id: commit.change_id().clone(),
+            author: commit.author().name.clone(),
+            author_email: commit.author().email.clone(),
+            author_timestamp: commit.author().timestamp.timestamp.0,
+            committer: commit.committer().name.clone(),
+            committer_email: commit.committer().email.clone(),
+            description: commit.description().to_string(),
+            timestamp: commit.committer().timestamp.timestamp.0,
+            is_signed: commit.is_signed(),
+            is_pushed: false,
+            nearest_ref: None,
+            graph_edges: Vec::new(),
+            is_divergent: false,
+            is_hidden: false,
+            is_empty: false,
+            has_conflict: false,
+            refs: Vec::new(),
+        }
+    }
+
+    /// Like [`Self::from_commit`], also telling whether `commit` is divergent, hidden, empty
+    /// or conflicted in `repo`.
+    fn from_commit_in(repo: &Arc<ReadonlyRepo>, commit: &Commit) -> Result<Self> {
+        let visible_ids = repo
+            .resolve_change_id(commit.change_id())
+            .unwrap_or_default();
+        Ok(Self {
+            is_divergent: visible_ids.len() > 1,
+            is_hidden: !visible_ids.contains(commit.id()),
+            is_empty: commit.is_empty(repo.as_ref())?,
+            has_conflict: commit.tree()?.has_conflict(),
+            ..Self::from_commit(commit)
+        })
+    }
 }
 
 impl JjWorkspace {
     pub fn load(workspace_root: impl AsRef<Path>) -> Result<Self> {
-        let workspace_root = workspace_root.as_ref();
-        let loader = DefaultWorkspaceLoaderFactory.create(workspace_root)?;
-        let config = StackedConfig::with_defaults();
-        let settings = UserSettings::from_config(config)?;
-        let workspace = loader.load(
-            &settings,
-            &StoreFactories::default(),
-            &workspace::default_working_copy_factories(),
-        )?;
+        Self::load_with_backends(workspace_root, Arc::new(JjBackends::default()))
+    }
 
+    /// Like [`Self::load`], for repositories whose stores or working copy may be managed by
+    /// backends other than jj's built-in ones. Each backend is the one the repository records,
+    /// looked up by name in `backends`.
+    pub fn load_with_backends(
+        workspace_root: impl AsRef<Path>,
+        backends: Arc<JjBackends>,
+    ) -> Result<Self> {
+        let loader = DefaultWorkspaceLoaderFactory.create(workspace_root.as_ref())?;
+        let workspace = Self::load_with(loader.as_ref(), &backends)?;
         Ok(Self {
             repo_loader: workspace.repo_loader().clone(),
             workspace_name: workspace.workspace_name().to_owned(),
             workspace_root: workspace.workspace_root().to_path_buf(),
+            backends,
+            parent_tree_cache: None,
+            read_only: false,
+        })
+    }
+
+    /// Creates a new repository at `workspace_root` using jj's native backend, like
+    /// `jj init`, and loads it.
+    pub fn init(workspace_root: impl AsRef<Path>) -> Result<Self> {
+        let settings = UserSettings::from_config(StackedConfig::with_defaults())?;
+        workspace::Workspace::init_simple(&settings, workspace_root.as_ref())?;
+        Self::load(workspace_root)
+    }
+
+    /// Times loading the workspace, snapshotting, querying the log and materializing a file
+    /// from the parent tree, the way Zed does them. A read-only workspace skips the snapshot.
+    pub async fn profile(&self, log_limit: usize) -> Result<RepositoryProfile> {
+        let started = Instant::now();
+        let (_, repo) = self.load_workspace_and_repo()?;
+        let load = started.elapsed();
+
+        let snapshot = if self.read_only {
+            None
+        } else {
+            let started = Instant::now();
+            self.snapshot_working_copy()?;
+            Some(started.elapsed())
+        };
+
+        let started = Instant::now();
+        let log_commit_count = self.recent_commits(log_limit)?.len();
+        let log_query = started.elapsed();
+
+        let sample_path = Self::workspace_commit(&repo, &self.workspace_name)?
+            .tree()?
+            .entries()
+            .next()
+            .map(|(path, _)| path);
+        let materialization = match sample_path {
+            Some(path) => {
+                let started = Instant::now();
+                self.parent_tree_text(&path).await?;
+                Some((
+                    path.as_internal_file_string().to_string(),
+                    started.elapsed(),
+                ))
+            }
+            None => None,
+        };
+
+        let uses_watchman = matches!(
+            self.settings().fsmonitor_settings()?,
+            FsmonitorSettings::Watchman(_)
+        );
+        Ok(RepositoryProfile {
+            workspace_root: self.workspace_root.clone(),
+            load,
+            snapshot,
+            log_query,
+            log_commit_count,
+            materialization,
+            uses_watchman,
         })
     }
 
+    pub fn with_parent_tree_cache(mut self, cache: Arc<ParentTreeCache>) -> Self {
+        self.parent_tree_cache = Some(cache);
+        self
+    }
+
+    /// Makes every method that would write to the repository or the working copy fail
+    /// instead, for checkouts owned by automation that must not gain operations. Queries skip
+    /// the snapshot they would otherwise start with, so they see the working copy as jj last
+    /// recorded it.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow!(
+                "{} is opened read-only",
+                self.workspace_root.display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn start_transaction(&self, repo: &Arc<ReadonlyRepo>) -> Result<Transaction> {
+        self.ensure_writable()?;
+        Ok(repo.start_transaction())
+    }
+
+    /// jj's defaults, then the user's config, then the repository's `config.toml`. Operations
+    /// load the workspace afresh, so edits to settings like `snapshot.auto-track` apply from the
+    /// next one on.
+    fn load_settings(loader: &dyn WorkspaceLoader) -> Result<UserSettings> {
+        let mut config = StackedConfig::with_defaults();
+        if let Some(user_config_path) = user_config_path() {
+            config.load_file(ConfigSource::User, user_config_path)?;
+        }
+        let repo_config_path = loader.repo_path().join("config.toml");
+        if repo_config_path.is_file() {
+            config.load_file(ConfigSource::Repo, repo_config_path)?;
+        }
+        Ok(UserSettings::from_config(config)?)
+    }
+
     fn load_workspace(&self) -> Result<workspace::Workspace> {
         let loader = DefaultWorkspaceLoaderFactory.create(&self.workspace_root)?;
-        let config = StackedConfig::with_defaults();
-        let settings = UserSettings::from_config(config)?;
-        Ok(loader.load(
-            &settings,
-            &StoreFactories::default(),
-            &workspace::default_working_copy_factories(),
-        )?)
+        Self::load_with(loader.as_ref(), &self.backends)
+    }
+
+    fn load_with(
+        loader: &dyn WorkspaceLoader,
+        backends: &JjBackends,
+    ) -> Result<workspace::Workspace> {
+        let backend = loader.get_working_copy_type()?;
+        if !backends.has_working_copy_backend(&backend) {
+            return Err(anyhow!("unsupported working copy backend {backend:?}"));
+        }
+        let settings = Self::load_settings(loader)?;
+        loader
+            .load(
+                &settings,
+                &backends.store_factories,
+                &backends.working_copy_factories,
+            )
+            .map_err(|error| with_unsupported_format(error.into()))
     }
 
     fn load_workspace_and_repo(&self) -> Result<(workspace::Workspace, Arc<ReadonlyRepo>)> {
         let workspace = self.load_workspace()?;
-        let repo = workspace.repo_loader().load_at_head()?;
+        let repo = workspace
+            .repo_loader()
+            .load_at_head()
+            .map_err(|error| with_unsupported_format(error.into()))?;
         Ok((workspace, repo))
     }
 
@@ -74,13 +688,34 @@ impl JjWorkspace {
         let Some(commit_ids) = repo.resolve_change_id(change_id) else {
             return Err(anyhow!("change {} not found", short_change_hash(change_id)));
         };
-        let commit_id = commit_ids.first().ok_or_else(|| {
-            anyhow!(
+        match commit_ids.as_slice() {
+            [] => Err(anyhow!(
                 "change {} has no associated commits",
                 short_change_hash(change_id)
-            )
-        })?;
-        Ok(repo.store().get_commit(commit_id)?)
+            )),
+            [commit_id] => Ok(repo.store().get_commit(commit_id)?),
+            _ => Err(DivergentChangeError {
+                change_id: change_id.clone(),
+                commit_ids,
+            }
+            .into()),
+        }
+    }
+
+    fn workspace_commit(
+        repo: &Arc<ReadonlyRepo>,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Commit> {
+        let wc_commit_id = repo
+            .view()
+            .get_wc_commit_id(workspace_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "workspace '{}' missing working copy commit",
+                    workspace_name.as_str()
+                )
+            })?;
+        Ok(repo.store().get_commit(wc_commit_id)?)
     }
 
     fn apply_transaction(
@@ -90,6 +725,18 @@ impl JjWorkspace {
         description: impl Into<String>,
     ) -> Result<()> {
         tx.repo_mut().rebase_descendants()?;
+        if self.is_colocated() {
+            Self::export_refs_to_git(&mut tx)?;
+            if let Some(wc_commit_id) = tx
+                .repo()
+                .view()
+                .get_wc_commit_id(workspace.workspace_name())
+                .cloned()
+            {
+                let wc_commit = tx.repo().store().get_commit(&wc_commit_id)?;
+                jj_git::reset_head(tx.repo_mut(), &wc_commit)?;
+            }
+        }
         let old_repo = tx.base_repo().clone();
         let new_repo = tx.commit(description)?;
 
@@ -119,7 +766,7 @@ impl JjWorkspace {
             old_tree.as_ref(),
             &new_wc_commit,
             &CheckoutOptions {
-                conflict_marker_style: ConflictMarkerStyle::default(),
+                conflict_marker_style: self.conflict_marker_style()?,
             },
         )?;
         Ok(())
@@ -129,7 +776,7 @@ impl JjWorkspace {
         self.snapshot_working_copy()?;
         let (mut workspace, repo) = self.load_workspace_and_repo()?;
         let commit = Self::resolve_change_commit(&repo, change_id)?;
-        let mut tx = repo.start_transaction();
+        let mut tx = self.start_transaction(&repo)?;
         tx.repo_mut()
             .edit(workspace.workspace_name().to_owned(), &commit)?;
         self.apply_transaction(
@@ -143,7 +790,7 @@ impl JjWorkspace {
         self.snapshot_working_copy()?;
         let (mut workspace, repo) = self.load_workspace_and_repo()?;
         let commit = Self::resolve_change_commit(&repo, change_id)?;
-        let mut tx = repo.start_transaction();
+        let mut tx = self.start_transaction(&repo)?;
         {
             let builder = tx.repo_mut().rewrite_commit(&commit);
             let builder = builder.set_description(new_description.to_string());
@@ -156,73 +803,1615 @@ impl JjWorkspace {
         )
     }
 
-    pub async fn parent_tree_text(&self, path: &RepoPath) -> Result<Option<String>> {
-        debug!(
-            target: "jj::workspace",
-            "parent_tree_text requested: workspace={} path={}",
-            self.workspace_name.as_str(),
-            path.as_internal_file_string()
-        );
-        let repo = self.repo_loader.load_at_head()?;
-        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
-            warn!(
-                target: "jj::workspace",
-                "missing working copy commit: workspace={} path={}",
-                self.workspace_name.as_str(),
-                path.as_internal_file_string()
-            );
-            return Ok(None);
-        };
-        debug!(
-            target: "jj::workspace",
-            "materializing parent tree: workspace={} path={} commit={:?}",
-            self.workspace_name.as_str(),
-            path.as_internal_file_string(),
-            wc_commit_id
-        );
-        let wc_commit = repo.store().get_commit(wc_commit_id)?;
-        let parent_tree = wc_commit.parent_tree(repo.as_ref())?;
-        let merged_value = parent_tree.path_value(path)?;
-        let materialized = materialize_tree_value(repo.store(), path, merged_value).await?;
-        let bytes = match materialized {
-            MaterializedTreeValue::File(mut file) => Some(file.read_all(path)?),
-            MaterializedTreeValue::AccessDenied(err) => {
-                return Err(anyhow!("access to {path:?} denied: {err}"));
-            }
-            _ => None,
-        };
+    /// Abandons `change_id` and rebases its descendants onto its parents, like `jj abandon`. If
+    /// it was the working-copy change, a new empty one takes its place.
+    pub fn abandon_change(&self, change_id: &ChangeId) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("the root change cannot be abandoned"));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut().record_abandoned_commit(&commit);
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("abandon change {}", short_change_hash(change_id)),
+        )
+    }
 
-        let text = bytes.and_then(|data| String::from_utf8(data).ok());
-        debug!(
-            target: "jj::workspace",
-            "parent_tree_text resolved: workspace={} path={} bytes={}",
-            self.workspace_name.as_str(),
-            path.as_internal_file_string(),
-            text.as_ref().map(|t| t.len()).unwrap_or(0)
-        );
-        Ok(text)
+    /// Resolves a divergent change by abandoning every visible commit of `change_id` other
+    /// than `commit_id`, like `jj abandon` on the unwanted ones.
+    pub fn keep_divergent_commit(&self, change_id: &ChangeId, commit_id: &CommitId) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit_ids = repo.resolve_change_id(change_id).unwrap_or_default();
+        if !commit_ids.contains(commit_id) {
+            return Err(anyhow!(
+                "commit {} is not a visible commit of change {}",
+                short_commit_hash(commit_id),
+                short_change_hash(change_id)
+            ));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        for other_id in commit_ids.iter().filter(|other_id| *other_id != commit_id) {
+            let other = repo.store().get_commit(other_id)?;
+            tx.repo_mut().record_abandoned_commit(&other);
+        }
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!(
+                "keep commit {} of divergent change {}",
+                short_commit_hash(commit_id),
+                short_change_hash(change_id)
+            ),
+        )
     }
 
-    pub fn current_change_id(&self) -> Result<Option<ChangeId>> {
-        let repo = self.repo_loader.load_at_head()?;
-        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
-            return Ok(None);
-        };
-        let commit = repo.store().get_commit(wc_commit_id)?;
-        Ok(Some(commit.change_id().clone()))
+    /// Copies `change_id` onto the same parents with a new change id, like `jj duplicate`.
+    /// Returns the copy's change id.
+    pub fn duplicate_change(&self, change_id: &ChangeId) -> Result<ChangeId> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("the root change cannot be duplicated"));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        let duplicate = tx
+            .repo_mut()
+            .new_commit(commit.parent_ids().to_vec(), commit.tree_id().clone())
+            .set_description(commit.description())
+            .set_author(commit.author().clone())
+            .write()?;
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("duplicate change {}", short_change_hash(change_id)),
+        )?;
+        Ok(duplicate.change_id().clone())
     }
 
-    fn snapshot_working_copy(&self) -> Result<()> {
-        let mut workspace = self.load_workspace()?;
-        let mut repo = workspace.repo_loader().load_at_head()?;
-        let workspace_name = workspace.workspace_name().to_owned();
-        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&workspace_name) else {
-            return Ok(());
+    /// Points the local bookmark `name` at `change_id`, creating the bookmark if it doesn't
+    /// exist, like `jj bookmark set --allow-backwards`.
+    pub fn set_bookmark(&self, name: &str, change_id: &ChangeId) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(anyhow!("invalid bookmark name {name:?}"));
+        }
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("a bookmark cannot point at the root change"));
+        }
+        let name = RefNameBuf::from(name);
+        let verb = if repo.view().get_local_bookmark(&name).is_absent() {
+            "create"
+        } else {
+            "point"
         };
-        let mut wc_commit = repo.store().get_commit(wc_commit_id)?;
-        let auto_track_matcher = self.snapshot_auto_tracking_matcher()?;
-        let options = self.snapshot_options(&*auto_track_matcher)?;
-        let mut locked_ws = workspace.start_working_copy_mutation()?;
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .set_local_bookmark_target(&name, RefTarget::normal(commit.id().clone()));
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!(
+                "{verb} bookmark {} to change {}",
+                name.as_str(),
+                short_change_hash(change_id)
+            ),
+        )
+    }
+
+    /// Deletes the local bookmark `name`, like `jj bookmark delete`. Remote bookmarks it tracks
+    /// are kept, so the next push deletes them too.
+    pub fn delete_bookmark(&self, name: &str) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let name = RefNameBuf::from(name);
+        if repo.view().get_local_bookmark(&name).is_absent() {
+            return Err(anyhow!("no bookmark named {}", name.as_str()));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .set_local_bookmark_target(&name, RefTarget::absent());
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("delete bookmark {}", name.as_str()),
+        )
+    }
+
+    /// Forgets the bookmark `name`, like `jj bookmark forget`: the local bookmark goes away
+    /// without a deletion for the next push, and remote bookmarks of that name stop being
+    /// tracked so that fetching doesn't bring it back.
+    pub fn forget_bookmark(&self, name: &str) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let name = RefNameBuf::from(name);
+        let view = repo.view();
+        let remotes = view
+            .all_remote_bookmarks()
+            .filter(|(symbol, remote_ref)| symbol.name == &*name && remote_ref.target.is_present())
+            .map(|(symbol, _)| symbol.remote.to_owned())
+            .collect::<Vec<_>>();
+        if view.get_local_bookmark(&name).is_absent() && remotes.is_empty() {
+            return Err(anyhow!("no bookmark named {}", name.as_str()));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        let repo_mut = tx.repo_mut();
+        repo_mut.set_local_bookmark_target(&name, RefTarget::absent());
+        for remote in &remotes {
+            let symbol = name.to_remote_symbol(remote);
+            // The colocated git repository's own ref would re-create the bookmark on import.
+            if remote.as_str() == REMOTE_NAME_FOR_LOCAL_GIT_REPO.as_str() {
+                repo_mut.set_remote_bookmark(symbol, RemoteRef::absent());
+            } else {
+                repo_mut.untrack_remote_bookmark(symbol);
+            }
+        }
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("forget bookmark {}", name.as_str()),
+        )
+    }
+
+    /// Starts tracking `name@remote`, like `jj bookmark track`: the local bookmark is merged
+    /// with it, follows it on fetch, and is pushed to it.
+    pub fn track_remote_bookmark(&self, name: &str, remote: &str) -> Result<()> {
+        self.set_remote_bookmark_tracking(name, remote, true)
+    }
+
+    /// Stops tracking `name@remote`, like `jj bookmark untrack`, leaving the local bookmark
+    /// where it is.
+    pub fn untrack_remote_bookmark(&self, name: &str, remote: &str) -> Result<()> {
+        self.set_remote_bookmark_tracking(name, remote, false)
+    }
+
+    /// Whether the backing git repo shares the workspace's working copy, as after
+    /// `jj git init --colocate`. Git tools then work on the same files, so its refs are kept in
+    /// sync with jj's: imported after snapshots and exported by every change made here, which
+    /// also moves git's HEAD to the working-copy change's parent like the jj CLI does.
+    pub fn is_colocated(&self) -> bool {
+        let Ok(git_backend) = jj_git::get_git_backend(self.repo_loader.store()) else {
+            return false;
+        };
+        let Some(git_workdir) = git_backend.git_workdir() else {
+            return false;
+        };
+        match (
+            git_workdir.canonicalize(),
+            self.workspace_root.canonicalize(),
+        ) {
+            (Ok(git_workdir), Ok(workspace_root)) => git_workdir == workspace_root,
+            _ => false,
+        }
+    }
+
+    /// Imports bookmarks and tags that git tools changed, like `jj git import`. Resolves to
+    /// whether anything changed, recording an operation if so.
+    pub fn import_git_refs(&self) -> Result<bool> {
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let git_settings = GitSettings::from_settings(self.settings())?;
+        let mut tx = self.start_transaction(&repo)?;
+        let stats = jj_git::import_refs(tx.repo_mut(), &git_settings)?;
+        if !tx.repo().has_changes() {
+            return Ok(false);
+        }
+        debug!(
+            target: "jj::workspace",
+            "imported {} changed git refs, abandoning {} commits",
+            stats.changed_remote_bookmarks.len() + stats.changed_remote_tags.len(),
+            stats.abandoned_commits.len()
+        );
+        self.apply_transaction(&mut workspace, tx, "import git refs")?;
+        Ok(true)
+    }
+
+    /// Writes jj's bookmarks to the backing git repo's refs, like `jj git export`. Changes
+    /// made through this workspace in a colocated repository do this on their own.
+    pub fn export_git_refs(&self) -> Result<()> {
+        let (_, repo) = self.load_workspace_and_repo()?;
+        let mut tx = self.start_transaction(&repo)?;
+        Self::export_refs_to_git(&mut tx)?;
+        if tx.repo().has_changes() {
+            tx.commit("export git refs")?;
+        }
+        Ok(())
+    }
+
+    fn export_refs_to_git(tx: &mut Transaction) -> Result<()> {
+        let stats = jj_git::export_refs(tx.repo_mut())?;
+        for (symbol, reason) in &stats.failed_bookmarks {
+            warn!(
+                target: "jj::workspace",
+                "failed to export bookmark {symbol:?} to git: {reason}"
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads git's HEAD in a colocated repository, or `None` elsewhere or before the first
+    /// commit.
+    pub fn git_head(&self) -> Result<Option<GitHead>> {
+        if !self.is_colocated() {
+            return Ok(None);
+        }
+        let repo = self.repo_loader.load_at_head()?;
+        let git_repo_path = jj_git::get_git_backend(repo.store())?.git_repo_path();
+        let git_repo = git2::Repository::open(git_repo_path)?;
+        let Ok(head) = git_repo.head() else {
+            return Ok(None);
+        };
+        let head_oid = head.peel_to_commit()?.id();
+        let branches = if head.is_branch() {
+            head.shorthand().map(str::to_string).into_iter().collect()
+        } else {
+            let mut branches = Vec::new();
+            for branch in git_repo.branches(Some(git2::BranchType::Local))? {
+                let (branch, _) = branch?;
+                if branch.get().target() == Some(head_oid)
+                    && let Some(name) = branch.name()?
+                {
+                    branches.push(name.to_string());
+                }
+            }
+            branches
+        };
+        let commit_id = CommitId::from_bytes(head_oid.as_bytes());
+        let is_working_copy_parent = match repo.view().get_wc_commit_id(&self.workspace_name) {
+            Some(wc_commit_id) => repo
+                .store()
+                .get_commit(wc_commit_id)?
+                .parent_ids()
+                .contains(&commit_id),
+            None => false,
+        };
+        Ok(Some(GitHead {
+            commit_id,
+            branches,
+            is_working_copy_parent,
+        }))
+    }
+
+    /// Lists the remotes of the backing git repo, like `jj git remote list`.
+    pub fn git_remotes(&self) -> Result<Vec<GitRemote>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let git_repo_path = jj_git::get_git_backend(repo.store())?.git_repo_path();
+        let git_repo = git2::Repository::open(git_repo_path)?;
+        let names = git_repo.remotes()?;
+        names
+            .iter()
+            .flatten()
+            .map(|name| {
+                let remote = git_repo.find_remote(name)?;
+                Ok(GitRemote {
+                    name: name.to_string(),
+                    url: remote.url().map(str::to_string),
+                })
+            })
+            .collect()
+    }
+
+    /// Adds a remote to the backing git repo, like `jj git remote add`.
+    pub fn add_git_remote(&self, name: &str, url: &str) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let remote = RemoteNameBuf::from(name);
+        let mut tx = self.start_transaction(&repo)?;
+        jj_git::add_remote(tx.repo_mut(), &remote, url)?;
+        self.apply_transaction(&mut workspace, tx, format!("add git remote {name}"))?;
+        Ok(())
+    }
+
+    /// Fetches every bookmark and tag of the git remote `remote`, like `jj git fetch --remote`,
+    /// and imports them. `on_progress` is called with the fraction of the transfer done so far.
+    pub fn git_fetch(
+        &self,
+        remote: &str,
+        on_progress: &mut dyn FnMut(f32),
+    ) -> Result<FetchSummary> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let git_settings = GitSettings::from_settings(self.settings())?;
+        let remote = RemoteNameBuf::from(remote);
+        let mut tx = self.start_transaction(&repo)?;
+        let mut fetch = GitFetch::new(tx.repo_mut(), &git_settings)?;
+        let mut report_progress = |progress: &jj_git::Progress| on_progress(progress.overall);
+        let mut callbacks = RemoteCallbacks::default();
+        callbacks.progress = Some(&mut report_progress);
+        fetch.fetch(&remote, &[StringPattern::everything()], callbacks, None)?;
+        let stats = fetch.import_refs()?;
+        let summary = FetchSummary {
+            updated_bookmarks: stats
+                .changed_remote_bookmarks
+                .iter()
+                .map(|(symbol, _)| format!("{}@{}", symbol.name.as_str(), symbol.remote.as_str()))
+                .collect(),
+            abandoned_commits: stats.abandoned_commits.len(),
+        };
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("fetch from git remote {}", remote.as_str()),
+        )?;
+        Ok(summary)
+    }
+
+    /// Pushes the local bookmark `bookmark` to the git remote `remote`, like
+    /// `jj git push --bookmark`, creating, moving or deleting it there to match.
+    pub fn git_push(&self, bookmark: &str, remote: &str) -> Result<PushSummary> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let name = RefNameBuf::from(bookmark);
+        let remote = RemoteNameBuf::from(remote);
+        let mut tx = self.start_transaction(&repo)?;
+        let pushed = self.push_bookmark(&mut tx, &name, &remote)?;
+        if pushed {
+            self.apply_transaction(
+                &mut workspace,
+                tx,
+                format!(
+                    "push bookmark {} to git remote {}",
+                    name.as_str(),
+                    remote.as_str()
+                ),
+            )?;
+        }
+        Ok(PushSummary {
+            bookmark: bookmark.to_string(),
+            pushed,
+        })
+    }
+
+    /// Pushes `change_id` under a bookmark named after it, like `jj git push --change`: the
+    /// bookmark, `push-` and the short change id unless `git.push-bookmark-prefix` says
+    /// otherwise, is created or moved to the change first.
+    pub fn git_push_change(&self, change_id: &ChangeId, remote: &str) -> Result<PushSummary> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("the root change cannot be pushed"));
+        }
+        let prefix = repo
+            .settings()
+            .get_string("git.push-bookmark-prefix")
+            .optional()?
+            .unwrap_or_else(|| "push-".to_string());
+        let change = short_change_hash(change_id);
+        let name = RefNameBuf::from(format!("{prefix}{change}"));
+        let remote = RemoteNameBuf::from(remote);
+        let target = RefTarget::normal(commit.id().clone());
+        let bookmark_moved = *repo.view().get_local_bookmark(&name) != target;
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut().set_local_bookmark_target(&name, target);
+        let pushed = self.push_bookmark(&mut tx, &name, &remote)?;
+        if pushed {
+            self.apply_transaction(
+                &mut workspace,
+                tx,
+                format!("push change {change} to git remote {}", remote.as_str()),
+            )?;
+        } else if bookmark_moved {
+            self.apply_transaction(
+                &mut workspace,
+                tx,
+                format!("point bookmark {} at change {change}", name.as_str()),
+            )?;
+        }
+        Ok(PushSummary {
+            bookmark: name.as_str().to_string(),
+            pushed,
+        })
+    }
+
+    fn push_bookmark(
+        &self,
+        tx: &mut Transaction,
+        name: &RefName,
+        remote: &RemoteName,
+    ) -> Result<bool> {
+        if remote.as_str() == REMOTE_NAME_FOR_LOCAL_GIT_REPO.as_str() {
+            return Err(anyhow!(
+                "bookmarks are exported to the colocated git repository, not pushed"
+            ));
+        }
+        let label = format!("{}@{}", name.as_str(), remote.as_str());
+        let view = tx.repo().view();
+        let targets = LocalAndRemoteRef {
+            local_target: view.get_local_bookmark(name),
+            remote_ref: view.get_remote_bookmark(name.to_remote_symbol(remote)),
+        };
+        let update = match classify_bookmark_push_action(targets) {
+            BookmarkPushAction::Update(update) => update,
+            BookmarkPushAction::AlreadyMatches => return Ok(false),
+            BookmarkPushAction::LocalConflicted => {
+                return Err(anyhow!("bookmark {} is conflicted", name.as_str()));
+            }
+            BookmarkPushAction::RemoteConflicted => {
+                return Err(anyhow!(
+                    "remote bookmark {label} is conflicted; fetch first"
+                ));
+            }
+            BookmarkPushAction::RemoteUntracked => {
+                return Err(anyhow!(
+                    "remote bookmark {label} exists but isn't tracked; track it first"
+                ));
+            }
+        };
+        if let Some(new_target) = &update.new_target {
+            Self::check_pushable(tx, new_target, remote)?;
+        }
+
+        let git_settings = GitSettings::from_settings(self.settings())?;
+        let targets = GitBranchPushTargets {
+            branch_updates: vec![(name.to_owned(), update)],
+        };
+        let stats = jj_git::push_branches(
+            tx.repo_mut(),
+            &git_settings,
+            remote,
+            &targets,
+            RemoteCallbacks::default(),
+        )?;
+        if !stats.all_ok() {
+            return Err(anyhow!("the remote rejected the push of {label}"));
+        }
+        Ok(true)
+    }
+
+    /// Refuses to push commits the jj CLI refuses to: ones without a description or with
+    /// conflicts, among those the remote doesn't have yet.
+    fn check_pushable(tx: &Transaction, new_target: &CommitId, remote: &RemoteName) -> Result<()> {
+        let repo = tx.repo();
+        let remote_heads = repo
+            .view()
+            .all_remote_bookmarks()
+            .filter(|(symbol, _)| symbol.remote == remote)
+            .flat_map(|(_, remote_ref)| remote_ref.target.added_ids().cloned())
+            .collect::<Vec<_>>();
+        let new_commits = RevsetExpression::commit(new_target.clone())
+            .ancestors()
+            .minus(&RevsetExpression::commits(remote_heads).ancestors())
+            .minus(&RevsetExpression::root());
+        for commit_id in new_commits.evaluate(repo)?.iter() {
+            let commit = repo.store().get_commit(&commit_id?)?;
+            let change = short_change_hash(commit.change_id());
+            if commit.description().trim().is_empty() {
+                return Err(anyhow!("change {change} has no description"));
+            }
+            if commit.tree()?.has_conflict() {
+                return Err(anyhow!("change {change} has conflicts"));
+            }
+        }
+        Ok(())
+    }
+
+    fn set_remote_bookmark_tracking(&self, name: &str, remote: &str, track: bool) -> Result<()> {
+        let remote = RemoteNameBuf::from(remote);
+        if remote.as_str() == REMOTE_NAME_FOR_LOCAL_GIT_REPO.as_str() {
+            return Err(anyhow!(
+                "bookmarks of the colocated git repository are always tracked"
+            ));
+        }
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let name = RefNameBuf::from(name);
+        let label = format!("{}@{}", name.as_str(), remote.as_str());
+        let symbol = name.to_remote_symbol(&remote);
+        let remote_ref = repo.view().get_remote_bookmark(symbol);
+        if remote_ref.is_absent() {
+            return Err(anyhow!("no remote bookmark {label}"));
+        }
+        if remote_ref.is_tracked() == track {
+            let state = if track { "already" } else { "not" };
+            return Err(anyhow!("remote bookmark {label} is {state} tracked"));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        let description = if track {
+            tx.repo_mut().track_remote_bookmark(symbol);
+            format!("track remote bookmark {label}")
+        } else {
+            tx.repo_mut().untrack_remote_bookmark(symbol);
+            format!("untrack remote bookmark {label}")
+        };
+        self.apply_transaction(&mut workspace, tx, description)
+    }
+
+    /// Folds `change_id` into its parent, like `jj squash -r`, and moves the change's descendants
+    /// onto the parent. With `paths`, only those files move and the rest of the change stays; the
+    /// change is abandoned, and the descriptions combined, only once nothing is left in it.
+    pub fn squash_change(&self, change_id: &ChangeId, paths: Option<&[RepoPathBuf]>) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let [parent_id] = commit.parent_ids() else {
+            return Err(anyhow!(
+                "change {} has {} parents; only changes with a single parent can be squashed",
+                short_change_hash(change_id),
+                commit.parent_ids().len()
+            ));
+        };
+        if parent_id == repo.store().root_commit_id() {
+            return Err(anyhow!("changes cannot be squashed into the root change"));
+        }
+        let parent = repo.store().get_commit(parent_id)?;
+        let new_parent_tree_id = match paths {
+            None => commit.tree_id().clone(),
+            Some(paths) => {
+                let tree = commit.tree()?;
+                let mut tree_builder = MergedTreeBuilder::new(parent.tree_id().clone());
+                for path in paths {
+                    tree_builder.set_or_remove(path.clone(), tree.path_value(path)?);
+                }
+                tree_builder.write_tree(repo.store())?
+            }
+        };
+        let squashes_everything = new_parent_tree_id == *commit.tree_id();
+        let description = if squashes_everything {
+            squashed_description(parent.description(), commit.description())
+        } else {
+            parent.description().to_string()
+        };
+
+        let mut tx = self.start_transaction(&repo)?;
+        let new_parent = tx
+            .repo_mut()
+            .rewrite_commit(&parent)
+            .set_tree_id(new_parent_tree_id)
+            .set_description(description)
+            .write()?;
+        if squashes_everything {
+            tx.repo_mut().record_abandoned_commit(&commit);
+        } else {
+            tx.repo_mut()
+                .rewrite_commit(&commit)
+                .set_parents(vec![new_parent.id().clone()])
+                .write()?;
+        }
+        let operation = match paths {
+            Some(paths) if !squashes_everything => format!(
+                "squash {} files of change {} into {}",
+                paths.len(),
+                short_change_hash(change_id),
+                short_change_hash(parent.change_id())
+            ),
+            _ => format!(
+                "squash change {} into {}",
+                short_change_hash(change_id),
+                short_change_hash(parent.change_id())
+            ),
+        };
+        self.apply_transaction(&mut workspace, tx, operation)
+    }
+
+    /// Sets `paths` in the working-copy change back to how they are in `source_change_id`, like
+    /// `jj restore --from`. Without a source, the working-copy change's modifications to them are
+    /// discarded so they match its parent again.
+    pub fn restore_paths(
+        &self,
+        paths: &[RepoPathBuf],
+        source_change_id: Option<&ChangeId>,
+    ) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::workspace_commit(&repo, workspace.workspace_name())?;
+        let source_tree = match source_change_id {
+            Some(change_id) => Self::resolve_change_commit(&repo, change_id)?.tree()?,
+            None => commit.parent_tree(repo.as_ref())?,
+        };
+        let mut tree_builder = MergedTreeBuilder::new(commit.tree_id().clone());
+        for path in paths {
+            tree_builder.set_or_remove(path.clone(), source_tree.path_value(path)?);
+        }
+        let restored_tree_id = tree_builder.write_tree(repo.store())?;
+        if restored_tree_id == *commit.tree_id() {
+            return Ok(());
+        }
+
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .rewrite_commit(&commit)
+            .set_tree_id(restored_tree_id)
+            .write()?;
+        let mut operation = match paths {
+            [path] => format!("restore {}", path.as_internal_file_string()),
+            _ => format!("restore {} paths", paths.len()),
+        };
+        if let Some(change_id) = source_change_id {
+            operation.push_str(&format!(" from {}", short_change_hash(change_id)));
+        }
+        self.apply_transaction(&mut workspace, tx, operation)
+    }
+
+    /// Moves the modifications of `paths` out of `change_id` into a new child change, like
+    /// `jj split`. The original change keeps its description and the rest of its modifications,
+    /// and its descendants, including the working copy, move onto the new change. Returns the
+    /// new change's id.
+    pub fn split_change(&self, change_id: &ChangeId, paths: &[RepoPathBuf]) -> Result<ChangeId> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("the root change cannot be split"));
+        }
+        let parent_tree = commit.parent_tree(repo.as_ref())?;
+        let mut tree_builder = MergedTreeBuilder::new(commit.tree_id().clone());
+        for path in paths {
+            tree_builder.set_or_remove(path.clone(), parent_tree.path_value(path)?);
+        }
+        let remaining_tree_id = tree_builder.write_tree(repo.store())?;
+        if remaining_tree_id == *commit.tree_id() || remaining_tree_id == parent_tree.id() {
+            return Err(anyhow!(
+                "the selected files must cover some, but not all, of change {}",
+                short_change_hash(change_id)
+            ));
+        }
+
+        let mut tx = self.start_transaction(&repo)?;
+        let remaining = tx
+            .repo_mut()
+            .rewrite_commit(&commit)
+            .set_tree_id(remaining_tree_id)
+            .write()?;
+        let split_off = tx
+            .repo_mut()
+            .new_commit(vec![remaining.id().clone()], commit.tree_id().clone())
+            .write()?;
+        // Descendants belong on top of both halves, not just the first one.
+        tx.repo_mut()
+            .set_rewritten_commit(commit.id().clone(), split_off.id().clone());
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!(
+                "split {} files out of change {}",
+                paths.len(),
+                short_change_hash(change_id)
+            ),
+        )?;
+        Ok(split_off.change_id().clone())
+    }
+
+    /// Moves `change_id` onto `destination_change_id`, like `jj rebase`, with `mode` picking
+    /// which commits move along with it.
+    pub fn rebase(
+        &self,
+        change_id: &ChangeId,
+        destination_change_id: &ChangeId,
+        mode: RebaseMode,
+    ) -> Result<()> {
+        if change_id == destination_change_id {
+            return Err(anyhow!("a change cannot be rebased onto itself"));
+        }
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if commit.id() == repo.store().root_commit_id() {
+            return Err(anyhow!("the root change cannot be rebased"));
+        }
+        let destination = Self::resolve_change_commit(&repo, destination_change_id)?;
+        let destination_ids = vec![destination.id().clone()];
+        // `jj rebase -s` and `-b` refuse this too: the first would create a cycle and the
+        // second would find nothing to move.
+        if mode != RebaseMode::Revision {
+            let descendant_ids = RevsetExpression::commit(commit.id().clone())
+                .descendants()
+                .evaluate(repo.as_ref())?
+                .iter()
+                .collect::<Result<Vec<_>, _>>()?;
+            if descendant_ids.contains(destination.id()) {
+                return Err(anyhow!(
+                    "change {} cannot be rebased onto its own descendant",
+                    short_change_hash(change_id)
+                ));
+            }
+        }
+
+        let mut tx = self.start_transaction(&repo)?;
+        match mode {
+            RebaseMode::Revision => {
+                let child_ids = RevsetExpression::commit(commit.id().clone())
+                    .children()
+                    .evaluate(repo.as_ref())?
+                    .iter()
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Children are rewritten first so that `rebase_descendants` doesn't carry them
+                // along with the moved change.
+                for child_id in child_ids {
+                    let child = repo.store().get_commit(&child_id)?;
+                    let mut new_parent_ids = Vec::new();
+                    for parent_id in child.parent_ids() {
+                        let replacements = if parent_id == commit.id() {
+                            commit.parent_ids()
+                        } else {
+                            slice::from_ref(parent_id)
+                        };
+                        for replacement in replacements {
+                            if !new_parent_ids.contains(replacement) {
+                                new_parent_ids.push(replacement.clone());
+                            }
+                        }
+                    }
+                    rebase_commit(tx.repo_mut(), child, new_parent_ids)?;
+                }
+                rebase_commit(tx.repo_mut(), commit, destination_ids)?;
+            }
+            RebaseMode::Source => {
+                rebase_commit(tx.repo_mut(), commit, destination_ids)?;
+            }
+            RebaseMode::Branch => {
+                let root_ids = RevsetExpression::commit(destination.id().clone())
+                    .range(&RevsetExpression::commit(commit.id().clone()))
+                    .roots()
+                    .evaluate(repo.as_ref())?
+                    .iter()
+                    .collect::<Result<Vec<_>, _>>()?;
+                for root_id in root_ids {
+                    let root = repo.store().get_commit(&root_id)?;
+                    if root.parent_ids() != destination_ids.as_slice() {
+                        rebase_commit(tx.repo_mut(), root, destination_ids.clone())?;
+                    }
+                }
+            }
+        }
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!(
+                "rebase {} {} onto {}",
+                mode.subject(),
+                short_change_hash(change_id),
+                short_change_hash(destination_change_id)
+            ),
+        )
+    }
+
+    /// Returns the paths modified by `change_id` relative to its parents.
+    pub async fn changed_paths(&self, change_id: &ChangeId) -> Result<Vec<RepoPathBuf>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let parent_tree = commit.parent_tree(repo.as_ref())?;
+        let tree = commit.tree()?;
+        let mut diff = parent_tree.diff_stream(&tree, &EverythingMatcher);
+        let mut paths = Vec::new();
+        while let Some(entry) = diff.next().await {
+            entry.values?;
+            paths.push(entry.path);
+        }
+        Ok(paths)
+    }
+
+    /// Counts the files and lines `change_id` changes relative to its parents, without
+    /// rendering the diff. Binary files count toward the files but not the lines.
+    pub async fn diff_stats(&self, change_id: &ChangeId) -> Result<DiffStats> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let parent_tree = commit.parent_tree(repo.as_ref())?;
+        let tree = commit.tree()?;
+        let mut diff = parent_tree.diff_stream(&tree, &EverythingMatcher);
+        let mut stats = DiffStats::default();
+        while let Some(entry) = diff.next().await {
+            let (before, after) = entry.values?;
+            let path = entry.path.as_ref();
+            let before = Self::read_file_value(&repo, path, before).await?;
+            let after = Self::read_file_value(&repo, path, after).await?;
+            let (insertions, deletions) =
+                patch::count_changed_lines(before.as_deref(), after.as_deref());
+            stats.files_changed += 1;
+            stats.insertions += insertions;
+            stats.deletions += deletions;
+        }
+        Ok(stats)
+    }
+
+    /// Renders the change's diff against its parents as a git-style patch.
+    pub async fn diff_text(&self, change_id: &ChangeId) -> Result<String> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let parent_tree = commit.parent_tree(repo.as_ref())?;
+        Self::render_tree_diff(&repo, &parent_tree, &commit.tree()?).await
+    }
+
+    /// Renders the change's diff against one of its parents rather than against the merge
+    /// of all of them.
+    pub async fn diff_against_parent(
+        &self,
+        change_id: &ChangeId,
+        parent_id: &CommitId,
+    ) -> Result<String> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        if !commit.parent_ids().contains(parent_id) {
+            return Err(anyhow!(
+                "commit {} is not a parent of change {}",
+                short_commit_hash(parent_id),
+                short_change_hash(change_id)
+            ));
+        }
+        let parent = repo.store().get_commit(parent_id)?;
+        Self::render_tree_diff(&repo, &parent.tree()?, &commit.tree()?).await
+    }
+
+    async fn render_tree_diff(
+        repo: &Arc<ReadonlyRepo>,
+        from_tree: &MergedTree,
+        to_tree: &MergedTree,
+    ) -> Result<String> {
+        let mut diff = from_tree.diff_stream(to_tree, &EverythingMatcher);
+        let mut text = String::new();
+        while let Some(entry) = diff.next().await {
+            let (before, after) = entry.values?;
+            let path = entry.path.as_ref();
+            let before = Self::read_file_value(repo, path, before).await?;
+            let after = Self::read_file_value(repo, path, after).await?;
+            text.push_str(&patch::render_file_diff(
+                path.as_internal_file_string(),
+                before.as_deref(),
+                after.as_deref(),
+            ));
+        }
+        Ok(text)
+    }
+
+    /// Starts a new empty change on top of `parent_change_id` and makes it the working-copy
+    /// change, like `jj new`.
+    pub fn new_change(&self, parent_change_id: &ChangeId, description: &str) -> Result<ChangeId> {
+        self.new_change_with_parents(slice::from_ref(parent_change_id), description)
+    }
+
+    /// Starts a new empty change on top of all of `parent_change_ids`, like `jj new A B`. With
+    /// more than one parent the new change is a merge whose tree combines the parents' trees.
+    pub fn new_change_with_parents(
+        &self,
+        parent_change_ids: &[ChangeId],
+        description: &str,
+    ) -> Result<ChangeId> {
+        if parent_change_ids.is_empty() {
+            return Err(anyhow!("a new change needs at least one parent"));
+        }
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let parents = parent_change_ids
+            .iter()
+            .map(|change_id| Self::resolve_change_commit(&repo, change_id))
+            .collect::<Result<Vec<_>>>()?;
+        let tree = merge_commit_trees(repo.as_ref(), &parents)?;
+        let parent_ids = parents.iter().map(|parent| parent.id().clone()).collect();
+        let mut tx = self.start_transaction(&repo)?;
+        let new_commit = tx
+            .repo_mut()
+            .new_commit(parent_ids, tree.id())
+            .set_description(description)
+            .write()?;
+        tx.repo_mut()
+            .edit(workspace.workspace_name().to_owned(), &new_commit)?;
+        let parent_hashes = parent_change_ids
+            .iter()
+            .map(short_change_hash)
+            .collect::<Vec<_>>();
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("new empty change on {}", parent_hashes.join(", ")),
+        )?;
+        Ok(new_commit.change_id().clone())
+    }
+
+    /// Moves the working-copy change aside as a "WIP: parked" change and starts a new empty
+    /// change on its parents. Returns the parked change's id so it can be unparked later.
+    pub fn park_working_copy(&self) -> Result<ChangeId> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let workspace_name = workspace.workspace_name().to_owned();
+        let wc_commit = Self::workspace_commit(&repo, &workspace_name)?;
+        let parent_tree = wc_commit.parent_tree(repo.as_ref())?;
+        if parent_tree.id() == *wc_commit.tree_id() {
+            return Err(anyhow!("the working copy has no changes to park"));
+        }
+
+        let timestamp = OffsetDateTime::now_utc().format(format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second] UTC"
+        ))?;
+        let mut description = format!("WIP: parked {timestamp}");
+        if !wc_commit.description().trim().is_empty() {
+            description.push_str("\n\n");
+            description.push_str(wc_commit.description());
+        }
+
+        let mut tx = self.start_transaction(&repo)?;
+        let parked = tx
+            .repo_mut()
+            .rewrite_commit(&wc_commit)
+            .set_description(description)
+            .write()?;
+        let new_commit = tx
+            .repo_mut()
+            .new_commit(wc_commit.parent_ids().to_vec(), parent_tree.id())
+            .write()?;
+        tx.repo_mut().edit(workspace_name, &new_commit)?;
+        self.apply_transaction(&mut workspace, tx, "park working copy")?;
+        Ok(parked.change_id().clone())
+    }
+
+    /// Squashes a change created by [`Self::park_working_copy`] back into the working copy.
+    pub fn unpark(&self, parked_change_id: &ChangeId) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let workspace_name = workspace.workspace_name().to_owned();
+        let wc_commit = Self::workspace_commit(&repo, &workspace_name)?;
+        let parked = Self::resolve_change_commit(&repo, parked_change_id)?;
+        if parked.id() == wc_commit.id() {
+            return Err(anyhow!("the parked change is already the working copy"));
+        }
+
+        let parked_parent_tree = parked.parent_tree(repo.as_ref())?;
+        let new_tree = wc_commit
+            .tree()?
+            .merge(&parked_parent_tree, &parked.tree()?)?;
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .rewrite_commit(&wc_commit)
+            .set_tree_id(new_tree.id())
+            .write()?;
+        tx.repo_mut().record_abandoned_commit(&parked);
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("unpark change {}", short_change_hash(parked_change_id)),
+        )
+    }
+
+    /// Exports the change as a git-format patch in colocated repositories, and as a plain
+    /// unified diff otherwise.
+    pub async fn export_patch(&self, change_id: &ChangeId) -> Result<String> {
+        let diff = self.diff_text(change_id).await?;
+        if !self.is_colocated() {
+            return Ok(diff);
+        }
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let author = commit.author();
+        patch::render_git_patch(
+            &patch::PatchHeader {
+                commit_id: &commit.id().hex(),
+                author_name: &author.name,
+                author_email: &author.email,
+                timestamp_millis: author.timestamp.timestamp.0,
+                tz_offset_minutes: author.timestamp.tz_offset,
+                description: commit.description(),
+            },
+            &diff,
+        )
+    }
+
+    /// Applies a patch on top of the working copy as a new change, which becomes the
+    /// working copy. Returns the new change's id.
+    pub async fn import_patch(&self, patch_text: &str) -> Result<ChangeId> {
+        let parsed = patch::parse_patch(patch_text)?;
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let workspace_name = workspace.workspace_name().to_owned();
+        let wc_commit = Self::workspace_commit(&repo, &workspace_name)?;
+        let base_tree = wc_commit.tree()?;
+        let mut tree_builder = MergedTreeBuilder::new(wc_commit.tree_id().clone());
+        for file_patch in &parsed.files {
+            let path = RepoPathBuf::from_internal_string(file_patch.path.as_str())?;
+            let base_value = base_tree.path_value(&path)?;
+            let executable = matches!(
+                base_value.as_normal(),
+                Some(TreeValue::File {
+                    executable: true,
+                    ..
+                })
+            );
+            let base_text = match Self::read_file_value(&repo, &path, base_value).await? {
+                Some(bytes) => String::from_utf8(bytes)
+                    .map_err(|_| anyhow!("cannot patch binary file {}", file_patch.path))?,
+                None => String::new(),
+            };
+            let hunks = diffy::Patch::from_str(file_patch.hunks)?;
+            let new_text = diffy::apply(&base_text, &hunks)
+                .map_err(|err| anyhow!("failed to apply patch to {}: {err}", file_patch.path))?;
+            if file_patch.deleted {
+                tree_builder.set_or_remove(path, Merge::absent());
+            } else {
+                let file_id = repo
+                    .store()
+                    .write_file(&path, &mut new_text.as_bytes())
+                    .await?;
+                tree_builder.set_or_remove(
+                    path,
+                    Merge::normal(TreeValue::File {
+                        id: file_id,
+                        executable,
+                        copy_id: CopyId::placeholder(),
+                    }),
+                );
+            }
+        }
+        let tree_id = tree_builder.write_tree(repo.store())?;
+
+        let mut tx = self.start_transaction(&repo)?;
+        let commit = tx
+            .repo_mut()
+            .new_commit(vec![wc_commit.id().clone()], tree_id)
+            .set_description(parsed.subject.unwrap_or_default())
+            .write()?;
+        tx.repo_mut().edit(workspace_name, &commit)?;
+        self.apply_transaction(&mut workspace, tx, "import patch")?;
+        Ok(commit.change_id().clone())
+    }
+
+    async fn read_file_value(
+        repo: &Arc<ReadonlyRepo>,
+        path: &RepoPath,
+        value: MergedTreeValue,
+    ) -> Result<Option<Vec<u8>>> {
+        match materialize_tree_value(repo.store(), path, value).await? {
+            MaterializedTreeValue::File(mut file) => Ok(Some(file.read_all(path)?)),
+            MaterializedTreeValue::AccessDenied(err) => {
+                Err(anyhow!("access to {path:?} denied: {err}"))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn parent_tree_text(&self, path: &RepoPath) -> Result<Option<String>> {
+        debug!(
+            target: "jj::workspace",
+            "parent_tree_text requested: workspace={} path={}",
+            self.workspace_name.as_str(),
+            path.as_internal_file_string()
+        );
+        let repo = self.repo_loader.load_at_head()?;
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
+            warn!(
+                target: "jj::workspace",
+                "missing working copy commit: workspace={} path={}",
+                self.workspace_name.as_str(),
+                path.as_internal_file_string()
+            );
+            return Ok(None);
+        };
+        debug!(
+            target: "jj::workspace",
+            "materializing parent tree: workspace={} path={} commit={:?}",
+            self.workspace_name.as_str(),
+            path.as_internal_file_string(),
+            wc_commit_id
+        );
+        let wc_commit = repo.store().get_commit(wc_commit_id)?;
+        if let Some(text) = self.cached_parent_tree_text(&repo, &wc_commit, path).await {
+            return Ok(Some(text));
+        }
+        let parent_tree = wc_commit.parent_tree(repo.as_ref())?;
+        let merged_value = parent_tree.path_value(path)?;
+        let materialized = materialize_tree_value(repo.store(), path, merged_value).await?;
+        let bytes = match materialized {
+            MaterializedTreeValue::File(mut file) => {
+                let bytes = file.read_all(path)?;
+                if let Some(cache) = &self.parent_tree_cache {
+                    cache
+                        .insert(repo.op_id(), wc_commit.parent_ids(), path, &file.id)
+                        .log_err();
+                }
+                Some(bytes)
+            }
+            MaterializedTreeValue::AccessDenied(err) => {
+                return Err(anyhow!("access to {path:?} denied: {err}"));
+            }
+            _ => None,
+        };
+
+        let text = bytes.and_then(|data| String::from_utf8(data).ok());
+        debug!(
+            target: "jj::workspace",
+            "parent_tree_text resolved: workspace={} path={} bytes={}",
+            self.workspace_name.as_str(),
+            path.as_internal_file_string(),
+            text.as_ref().map(|t| t.len()).unwrap_or(0)
+        );
+        Ok(text)
+    }
+
+    async fn cached_parent_tree_text(
+        &self,
+        repo: &Arc<ReadonlyRepo>,
+        wc_commit: &Commit,
+        path: &RepoPath,
+    ) -> Option<String> {
+        let cache = self.parent_tree_cache.as_ref()?;
+        let file_id = cache.get(repo.op_id(), wc_commit.parent_ids(), path)?;
+        let mut reader = repo.store().read_file(path, &file_id).await.log_err()?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).log_err()?;
+        debug!(
+            target: "jj::workspace",
+            "parent_tree_text served from cache: workspace={} path={}",
+            self.workspace_name.as_str(),
+            path.as_internal_file_string()
+        );
+        String::from_utf8(bytes).ok()
+    }
+
+    /// Records the files on disk into the working-copy change, which every other jj command
+    /// does first, so that changes to what's tracked show up without running one.
+    pub fn snapshot(&self) -> Result<()> {
+        self.snapshot_working_copy()
+    }
+
+    /// Lists the files on disk that snapshots leave out although no ignore file hides them,
+    /// because `snapshot.auto-track` doesn't match them or they're too large, like the
+    /// "Untracked paths" of `jj status`.
+    pub fn untracked_paths(&self) -> Result<Vec<RepoPathBuf>> {
+        let auto_track_matcher = self.snapshot_auto_tracking_matcher(self.settings())?;
+        let stats = self.snapshot_working_copy_with(&*auto_track_matcher, None)?;
+        Ok(stats.untracked_paths.into_keys().collect())
+    }
+
+    /// Starts tracking `paths` in the working-copy change, like `jj file track`.
+    pub fn track_paths(&self, paths: &[RepoPathBuf]) -> Result<()> {
+        let auto_track_matcher = self.snapshot_auto_tracking_matcher(self.settings())?;
+        let paths_matcher = FilesMatcher::new(paths);
+        let matcher = UnionMatcher::new(&*auto_track_matcher, &paths_matcher);
+        self.snapshot_working_copy_with(&matcher, Some("track paths"))?;
+        Ok(())
+    }
+
+    /// Returns the hex id of the repository's current operation head.
+    pub fn head_operation_id(&self) -> Result<String> {
+        let repo = self.repo_loader.load_at_head()?;
+        Ok(repo.op_id().hex())
+    }
+
+    /// Summarizes the repository's current operation head.
+    pub fn head_operation(&self) -> Result<OperationSummary> {
+        let repo = self.repo_loader.load_at_head()?;
+        Ok(OperationSummary::from_operation(repo.operation()))
+    }
+
+    /// Detects what an interrupted jj process may have left behind in this workspace.
+    pub fn working_copy_problems(&self) -> Result<Vec<WorkingCopyProblem>> {
+        let mut problems = Vec::new();
+        if let Some(lock_path) = self.stale_lock_path()? {
+            // Loading the working copy below would wait on the lock, so report it on its own.
+            problems.push(WorkingCopyProblem::StaleLock(lock_path));
+            return Ok(problems);
+        }
+
+        if self.working_copy_state()?.is_stale {
+            problems.push(WorkingCopyProblem::Stale);
+        }
+        Ok(problems)
+    }
+
+    /// Compares the operation the working copy was last updated at with the operation head,
+    /// without snapshotting.
+    pub fn working_copy_state(&self) -> Result<WorkingCopyState> {
+        let workspace = self.load_workspace()?;
+        let repo = self.repo_loader.load_at_head()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        let working_copy = workspace.working_copy();
+        let is_at_head = working_copy.operation_id() == repo.op_id();
+        Ok(WorkingCopyState {
+            operation_id: working_copy.operation_id().hex(),
+            head_operation_id: repo.op_id().hex(),
+            is_stale: !is_at_head && working_copy.tree_id()? != wc_commit.tree_id(),
+        })
+    }
+
+    /// Removes a stale working-copy lock, then checks out the working-copy commit if the
+    /// working copy is stale, like `jj workspace update-stale`.
+    pub fn recover_working_copy(&self) -> Result<()> {
+        self.ensure_writable()?;
+        if let Some(lock_path) = self.stale_lock_path()? {
+            warn!(
+                target: "jj::workspace",
+                "removing stale working copy lock {}",
+                lock_path.display()
+            );
+            std::fs::remove_file(&lock_path)?;
+        }
+        if !self
+            .working_copy_problems()?
+            .contains(&WorkingCopyProblem::Stale)
+        {
+            return Ok(());
+        }
+
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        workspace.check_out(
+            repo.op_id().clone(),
+            None,
+            &wc_commit,
+            &CheckoutOptions {
+                conflict_marker_style: self.conflict_marker_style()?,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn stale_lock_path(&self) -> Result<Option<PathBuf>> {
+        let lock_path = self
+            .workspace_root
+            .join(".jj")
+            .join("working_copy")
+            .join("working_copy.lock");
+        let modified = match std::fs::metadata(&lock_path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let age = modified.elapsed().unwrap_or_default();
+        Ok((age >= STALE_LOCK_AGE).then_some(lock_path))
+    }
+
+    /// Returns up to `limit` operations, starting from the operation head and walking back
+    /// through its ancestors.
+    pub fn operation_log(&self, limit: usize) -> Result<Vec<OperationSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        op_walk::walk_ancestors(slice::from_ref(repo.operation()))
+            .take(limit)
+            .map(|operation| Ok(OperationSummary::from_operation(&operation?)))
+            .collect()
+    }
+
+    /// Reverts the most recent operation, like `jj undo`, and updates the working copy.
+    /// Working-copy changes made since then are kept.
+    pub fn undo(&self) -> Result<()> {
+        let undone_operation = self.repo_loader.load_at_head()?.operation().clone();
+        let parent = undone_operation
+            .parents()
+            .next()
+            .ok_or_else(|| anyhow!("there is no operation to undo"))??;
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let undone_repo = self.repo_loader.load_at(&undone_operation)?;
+        let parent_repo = self.repo_loader.load_at(&parent)?;
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut().merge(&undone_repo, &parent_repo)?;
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("undo operation {}", undone_operation.id().hex()),
+        )
+    }
+
+    /// Restores the repository to how it was just before the given operation, undoing that
+    /// operation and every one after it.
+    pub fn restore_before_operation(&self, operation_id: &str) -> Result<()> {
+        let operation_id = OperationId::try_from_hex(operation_id)
+            .ok_or_else(|| anyhow!("invalid operation id {operation_id}"))?;
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let operation = self.repo_loader.load_operation(&operation_id)?;
+        let parent = operation
+            .parents()
+            .next()
+            .ok_or_else(|| anyhow!("operation {} has no parent", operation_id.hex()))??;
+        let target_repo = self.repo_loader.load_at(&parent)?;
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .set_view(target_repo.view().store_view().clone());
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("restore to operation {}", parent.id().hex()),
+        )
+    }
+
+    /// Resolves a change id typed by the user, which may be any unique prefix of the id as the
+    /// UI shows it.
+    pub fn resolve_change_id_prefix(&self, prefix: &str) -> Result<ChangeId> {
+        let prefix = prefix.trim();
+        let hex_prefix = HexPrefix::try_from_reverse_hex(prefix)
+            .ok_or_else(|| anyhow!("{prefix:?} is not a change id"))?;
+        let repo = self.repo_loader.load_at_head()?;
+        match repo.resolve_change_id_prefix(&hex_prefix) {
+            PrefixResolution::SingleMatch(commit_ids) => {
+                let commit_id = commit_ids
+                    .first()
+                    .ok_or_else(|| anyhow!("no change matches {prefix:?}"))?;
+                Ok(repo.store().get_commit(commit_id)?.change_id().clone())
+            }
+            PrefixResolution::NoMatch => Err(anyhow!("no change matches {prefix:?}")),
+            PrefixResolution::AmbiguousMatch => {
+                let mut candidates = Self::visible_commits(&repo, AMBIGUOUS_PREFIX_SCAN_LIMIT)?
+                    .iter()
+                    .map(|commit| commit.change_id().to_string())
+                    .filter(|change_id| change_id.starts_with(prefix))
+                    .collect::<Vec<_>>();
+                candidates.sort();
+                candidates.dedup();
+                let shown = candidates
+                    .iter()
+                    .take(MAX_AMBIGUOUS_CANDIDATES)
+                    .map(|change_id| &change_id[..change_id.len().min(12)])
+                    .collect::<Vec<_>>();
+                let mut message = format!("ambiguous change id prefix {prefix:?}");
+                if !shown.is_empty() {
+                    message.push_str(&format!(", candidates: {}", shown.join(", ")));
+                    if candidates.len() > shown.len() {
+                        message.push_str(", …");
+                    }
+                }
+                Err(anyhow!(message))
+            }
+        }
+    }
+
+    pub fn current_change_id(&self) -> Result<Option<ChangeId>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
+            return Ok(None);
+        };
+        let commit = repo.store().get_commit(wc_commit_id)?;
+        Ok(Some(commit.change_id().clone()))
+    }
+
+    /// Returns the working copy commit, i.e. the change whose parent tree is used as the
+    /// diff base for buffers in this workspace.
+    pub fn working_copy_commit(&self) -> Result<Option<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
+            return Ok(None);
+        };
+        let commit = repo.store().get_commit(wc_commit_id)?;
+        Ok(Some(CommitSummary::from_commit_in(&repo, &commit)?))
+    }
+
+    /// Returns the working-copy change and its conflicted paths, if it has any conflicts.
+    pub fn working_copy_conflicts(&self) -> Result<Option<(ChangeId, Vec<RepoPathBuf>)>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
+            return Ok(None);
+        };
+        let commit = repo.store().get_commit(wc_commit_id)?;
+        let paths = Self::conflicted_paths_in(&commit)?;
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((commit.change_id().clone(), paths)))
+    }
+
+    /// Lists the paths with unresolved conflicts in `change_id`, like `jj resolve --list -r`.
+    pub fn conflicted_paths(&self, change_id: &ChangeId) -> Result<Vec<RepoPathBuf>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        Self::conflicted_paths_in(&commit)
+    }
+
+    fn conflicted_paths_in(commit: &Commit) -> Result<Vec<RepoPathBuf>> {
+        Ok(commit.tree()?.conflicts().map(|(path, _)| path).collect())
+    }
+
+    /// Snapshots the working copy and finds the change that last touched each line of `path`
+    /// in `@`, like `jj file annotate`. Returns one entry per line.
+    pub fn annotate(&self, path: &RepoPath) -> Result<Vec<AnnotatedLine>> {
+        self.snapshot_working_copy()?;
+        let repo = self.repo_loader.load_at_head()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        let mut annotator = FileAnnotator::from_commit(&wc_commit, path)?;
+        annotator.compute(repo.as_ref(), &RevsetExpression::all())?;
+        let annotation = annotator.to_annotation();
+        let mut commits = HashMap::new();
+        annotation
+            .lines()
+            .map(|(origin, _)| {
+                // Lines whose origin is outside the searched commits are attributed to the
+                // oldest commit searched, which still tells where the line came from.
+                let (Ok(commit_id) | Err(commit_id)) = origin;
+                if !commits.contains_key(commit_id) {
+                    let commit = repo.store().get_commit(commit_id)?;
+                    commits.insert(commit_id.clone(), commit);
+                }
+                let commit = &commits[commit_id];
+                Ok(AnnotatedLine {
+                    change_id: commit.change_id().clone(),
+                    commit_id: commit_id.clone(),
+                    author: commit.author().name.clone(),
+                    timestamp: commit.author().timestamp.timestamp.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the sides of the conflict at `path` in the working-copy change, like `jj resolve`
+    /// hands them to a merge tool. Conflicts with more than two sides have no base to show, so
+    /// they're an error.
+    pub async fn conflict_sides(&self, path: &RepoPath) -> Result<ConflictSides> {
+        let repo = self.repo_loader.load_at_head()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        let conflict = wc_commit.tree()?.path_value(path)?;
+        if conflict.is_resolved() {
+            return Err(anyhow!(
+                "{} has no conflict to resolve",
+                path.as_internal_file_string()
+            ));
+        }
+        if conflict.num_sides() != 2 {
+            return Err(anyhow!(
+                "{} has a {}-sided conflict",
+                path.as_internal_file_string(),
+                conflict.num_sides()
+            ));
+        }
+        Ok(ConflictSides {
+            left: Self::read_conflict_term(&repo, path, conflict.get_add(0)).await?,
+            base: Self::read_conflict_term(&repo, path, conflict.get_remove(0)).await?,
+            right: Self::read_conflict_term(&repo, path, conflict.get_add(1)).await?,
+        })
+    }
+
+    async fn read_conflict_term(
+        repo: &Arc<ReadonlyRepo>,
+        path: &RepoPath,
+        term: Option<&Option<TreeValue>>,
+    ) -> Result<String> {
+        let term = term.cloned().flatten();
+        if !matches!(term, None | Some(TreeValue::File { .. })) {
+            return Err(anyhow!(
+                "{} isn't a regular file on every side of the conflict",
+                path.as_internal_file_string()
+            ));
+        }
+        let contents = Self::read_file_value(repo, path, Merge::resolved(term))
+            .await?
+            .unwrap_or_default();
+        String::from_utf8(contents)
+            .map_err(|_| anyhow!("{} isn't a text file", path.as_internal_file_string()))
+    }
+
+    /// Records `resolved_contents` as the resolution of the conflict at `path` in the
+    /// working-copy change, like finishing `jj resolve`, and writes it to the file on disk.
+    pub async fn resolve_conflict(&self, path: &RepoPath, resolved_contents: &str) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        let conflict = wc_commit.tree()?.path_value(path)?;
+        if conflict.is_resolved() {
+            return Err(anyhow!(
+                "{} has no conflict to resolve",
+                path.as_internal_file_string()
+            ));
+        }
+        let executable = conflict.adds().flatten().any(|value| {
+            matches!(
+                value,
+                TreeValue::File {
+                    executable: true,
+                    ..
+                }
+            )
+        });
+        let file_id = repo
+            .store()
+            .write_file(path, &mut resolved_contents.as_bytes())
+            .await?;
+        let mut tree_builder = MergedTreeBuilder::new(wc_commit.tree_id().clone());
+        tree_builder.set_or_remove(
+            path.to_owned(),
+            Merge::normal(TreeValue::File {
+                id: file_id,
+                executable,
+                copy_id: CopyId::placeholder(),
+            }),
+        );
+        let tree_id = tree_builder.write_tree(repo.store())?;
+
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut()
+            .rewrite_commit(&wc_commit)
+            .set_tree_id(tree_id)
+            .write()?;
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("resolve conflict in {}", path.as_internal_file_string()),
+        )
+    }
+
+    /// Snapshots the working copy and lists the paths `@` changes relative to `@-`, like
+    /// `jj status`. Conflicted paths are listed even when the change leaves them untouched,
+    /// since they're conflicted because of the parents.
+    pub async fn status(&self) -> Result<Vec<FileStatus>> {
+        self.snapshot_working_copy()?;
+        let repo = self.repo_loader.load_at_head()?;
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&self.workspace_name) else {
+            return Ok(Vec::new());
+        };
+        let commit = repo.store().get_commit(wc_commit_id)?;
+        let parent_tree = commit.parent_tree(repo.as_ref())?;
+        let tree = commit.tree()?;
+        let mut diff = parent_tree.diff_stream(&tree, &EverythingMatcher);
+        let mut statuses = Vec::new();
+        while let Some(entry) = diff.next().await {
+            let (before, after) = entry.values?;
+            statuses.push(FileStatus {
+                path: entry.path,
+                kind: FileStatusKind::from_values(&before, &after),
+            });
+        }
+        for (path, _) in tree.conflicts() {
+            if !statuses.iter().any(|status| status.path == path) {
+                statuses.push(FileStatus {
+                    path,
+                    kind: FileStatusKind::Conflicted,
+                });
+            }
+        }
+        statuses.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(statuses)
+    }
+
+    /// Returns the visible commit currently associated with `change_id`, if any. Of a divergent
+    /// change's commits, it returns the first, marked [`CommitSummary::is_divergent`].
+    pub fn commit_for_change(&self, change_id: &ChangeId) -> Result<Option<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit_ids = repo.resolve_change_id(change_id).unwrap_or_default();
+        let Some(commit_id) = commit_ids.first() else {
+            return Ok(None);
+        };
+        let commit = repo.store().get_commit(commit_id)?;
+        Ok(Some(CommitSummary::from_commit_in(&repo, &commit)?))
+    }
+
+    /// Returns the commits whose change now maps to a different commit, i.e. which have been
+    /// rewritten (or abandoned) since they were loaded.
+    pub fn rewritten_commits(&self, commits: &[(CommitId, ChangeId)]) -> Result<Vec<CommitId>> {
+        let repo = self.repo_loader.load_at_head()?;
+        Ok(commits
+            .iter()
+            .filter(|(commit_id, change_id)| {
+                !repo
+                    .resolve_change_id(change_id)
+                    .is_some_and(|commit_ids| commit_ids.contains(commit_id))
+            })
+            .map(|(commit_id, _)| commit_id.clone())
+            .collect())
+    }
+
+    /// Snapshots the working copy like every jj command does first, and in a colocated
+    /// repository then picks up what git tools did to its refs. Importing comes second so that
+    /// the files on disk are recorded before anything it abandons could move the working copy.
+    fn snapshot_working_copy(&self) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        let auto_track_matcher = self.snapshot_auto_tracking_matcher(self.settings())?;
+        self.snapshot_working_copy_with(&*auto_track_matcher, None)?;
+        if self.is_colocated() {
+            self.import_git_refs()?;
+        }
+        Ok(())
+    }
+
+    /// Snapshots the working copy, starting to track the new files `start_tracking_matcher`
+    /// matches. With an `operation` description the change is recorded as that command rather
+    /// than as an automatic snapshot.
+    fn snapshot_working_copy_with(
+        &self,
+        start_tracking_matcher: &dyn Matcher,
+        operation: Option<&str>,
+    ) -> Result<SnapshotStats> {
+        self.ensure_writable()?;
+        let mut workspace = self.load_workspace()?;
+        let mut repo = workspace.repo_loader().load_at_head()?;
+        let workspace_name = workspace.workspace_name().to_owned();
+        let Some(wc_commit_id) = repo.view().get_wc_commit_id(&workspace_name) else {
+            return Ok(SnapshotStats::default());
+        };
+        let mut wc_commit = repo.store().get_commit(wc_commit_id)?;
+        let options = self.snapshot_options(start_tracking_matcher)?;
+        let mut locked_ws = workspace.start_working_copy_mutation()?;
         match WorkingCopyFreshness::check_stale(locked_ws.locked_wc(), &wc_commit, &repo)
             .map_err(|err| anyhow!(err))?
         {
@@ -230,7 +2419,7 @@ impl JjWorkspace {
             WorkingCopyFreshness::Updated(wc_operation) => {
                 repo = repo.reload_at(&wc_operation)?;
                 let Some(id) = repo.view().get_wc_commit_id(&workspace_name) else {
-                    return Ok(());
+                    return Ok(SnapshotStats::default());
                 };
                 wc_commit = repo.store().get_commit(id)?;
             }
@@ -239,104 +2428,858 @@ impl JjWorkspace {
                     "working copy is stale; run `jj workspace update-stale` before switching revisions"
                 ));
             }
-            WorkingCopyFreshness::SiblingOperation => {
-                return Err(anyhow!(
-                    "working copy operation diverged; run `jj workspace update-stale`"
-                ));
+            WorkingCopyFreshness::SiblingOperation => {
+                return Err(anyhow!(
+                    "working copy operation diverged; run `jj workspace update-stale`"
+                ));
+            }
+        }
+
+        let (new_tree_id, stats) = locked_ws.locked_wc().snapshot(&options)?;
+        let mut op_id = repo.op_id().clone();
+        if new_tree_id != *wc_commit.tree_id() {
+            let mut tx = self.start_transaction(&repo)?;
+            tx.set_is_snapshot(operation.is_none());
+            let repo_mut = tx.repo_mut();
+            let new_commit = repo_mut
+                .rewrite_commit(&wc_commit)
+                .set_tree_id(new_tree_id)
+                .write()?;
+            repo_mut.set_wc_commit(workspace_name.clone(), new_commit.id().clone())?;
+            let rebased = repo_mut.rebase_descendants()?;
+            if rebased > 0 {
+                debug!(
+                    target: "jj::workspace",
+                    "snapshot rebased {rebased} descendant commits"
+                );
+            }
+            let new_repo = tx.commit(operation.unwrap_or("snapshot working copy"))?;
+            op_id = new_repo.op_id().clone();
+        }
+        locked_ws.finish(op_id)?;
+        Ok(stats)
+    }
+
+    /// Which new files snapshots start tracking, from `snapshot.auto-track` like the jj CLI.
+    fn snapshot_auto_tracking_matcher(&self, settings: &UserSettings) -> Result<Box<dyn Matcher>> {
+        let Some(pattern) = settings.get_string("snapshot.auto-track").optional()? else {
+            return Ok(FilesetExpression::all().to_matcher());
+        };
+        let path_converter = RepoPathUiConverter::Fs {
+            cwd: self.workspace_root.clone(),
+            base: self.workspace_root.clone(),
+        };
+        let expression = fileset::parse(&mut FilesetDiagnostics::new(), &pattern, &path_converter)
+            .map_err(|err| anyhow!("invalid snapshot.auto-track {pattern:?}: {err}"))?;
+        Ok(expression.to_matcher())
+    }
+
+    fn snapshot_options<'a>(
+        &self,
+        start_tracking_matcher: &'a dyn Matcher,
+    ) -> Result<SnapshotOptions<'a>> {
+        let fsmonitor_settings = self.settings().fsmonitor_settings()?;
+        let max_new_file_size = u64::MAX;
+        Ok(SnapshotOptions {
+            base_ignores: GitIgnoreFile::empty(),
+            fsmonitor_settings,
+            progress: None,
+            start_tracking_matcher,
+            max_new_file_size,
+            conflict_marker_style: self.conflict_marker_style()?,
+        })
+    }
+
+    /// How conflicts are written into files on checkout and parsed back on snapshot, as the
+    /// user's `ui.conflict-marker-style` asks, so that Zed and the `jj` CLI agree on it.
+    fn conflict_marker_style(&self) -> Result<ConflictMarkerStyle> {
+        Ok(self
+            .settings()
+            .get("ui.conflict-marker-style")
+            .optional()?
+            .unwrap_or_default())
+    }
+
+    fn settings(&self) -> &UserSettings {
+        self.repo_loader.settings()
+    }
+
+    /// Lists the `limit` most recent visible commits the way `jj log -r 'all()'` orders them,
+    /// with the graph edges between them.
+    pub fn recent_commits(&self, limit: usize) -> Result<Vec<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        Self::log_commits(&repo, &RevsetExpression::all(), limit)
+    }
+
+    /// Like [`Self::recent_commits`], but only the commits committed within `range`. They're
+    /// found with `committer_date()` revsets, so the range can reach further back than the
+    /// `limit` most recent commits. Refs outside the range don't label the commits in it.
+    pub fn recent_commits_in_date_range(
+        &self,
+        limit: usize,
+        range: CommitDateRange,
+    ) -> Result<Vec<CommitSummary>> {
+        if range.is_unbounded() {
+            return self.recent_commits(limit);
+        }
+        let repo = self.repo_loader.load_at_head()?;
+        let mut expression: Rc<ResolvedRevsetExpression> = RevsetExpression::all();
+        let date_patterns = [
+            range
+                .after
+                .map(|after| DatePattern::AtOrAfter(MillisSinceEpoch(after))),
+            range
+                .before
+                .map(|before| DatePattern::Before(MillisSinceEpoch(before))),
+        ];
+        for pattern in date_patterns.into_iter().flatten() {
+            expression = expression.intersection(&RevsetExpression::filter(
+                RevsetFilterPredicate::CommitterDate(pattern),
+            ));
+        }
+        Self::log_commits(&repo, &expression, limit)
+    }
+
+    /// Summarizes at most `limit` versions of `change_id`, from the current one back through
+    /// the versions it was rewritten from, like `jj evolog`. Every version has the same change
+    /// id, so they're told apart by their commit ids.
+    pub fn evolution_log(&self, change_id: &ChangeId, limit: usize) -> Result<Vec<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let mut visited = HashSet::from([commit.id().clone()]);
+        let mut pending = VecDeque::from([commit]);
+        let mut versions = Vec::new();
+        while versions.len() < limit {
+            let Some(commit) = pending.pop_front() else {
+                break;
+            };
+            for predecessor in commit.predecessors() {
+                let predecessor = predecessor?;
+                if visited.insert(predecessor.id().clone()) {
+                    pending.push_back(predecessor);
+                }
+            }
+            versions.push(CommitSummary::from_commit_in(&repo, &commit)?);
+        }
+        Ok(versions)
+    }
+
+    /// Snapshots the working copy and summarizes at most `limit` ancestors of `@` that modified
+    /// `path` or anything under it, newest first, like `jj log -r '::@' <path>`.
+    pub fn file_history(&self, path: &RepoPath, limit: usize) -> Result<Vec<CommitSummary>> {
+        self.snapshot_working_copy()?;
+        let repo = self.repo_loader.load_at_head()?;
+        let wc_commit = Self::workspace_commit(&repo, &self.workspace_name)?;
+        let expression = RevsetExpression::commit(wc_commit.id().clone())
+            .ancestors()
+            .intersection(&RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::prefix_path(path.to_owned()),
+            )));
+        Self::log_commits(&repo, &expression, limit)
+    }
+
+    /// Evaluates `revset`, written as on the `jj` command line, and summarizes at most `limit`
+    /// matching commits, newest first.
+    pub fn evaluate_revset(&self, revset: &str, limit: usize) -> Result<Vec<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let expression = self.parse_revset(&repo, revset)?;
+        Self::log_commits(&repo, &expression, limit)
+    }
+
+    /// Like [`Self::evaluate_revset`], but reports `fields` of the matching commits as plain
+    /// records.
+    pub fn query_commits(
+        &self,
+        revset: &str,
+        fields: &[CommitField],
+        limit: usize,
+    ) -> Result<Vec<CommitRecord>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let expression = self.parse_revset(&repo, revset)?;
+
+        let mut bookmarks_by_commit = HashMap::<CommitId, Vec<String>>::new();
+        if fields.contains(&CommitField::Bookmarks) {
+            for (name, target) in repo.view().local_bookmarks() {
+                for commit_id in target.added_ids() {
+                    bookmarks_by_commit
+                        .entry(commit_id.clone())
+                        .or_default()
+                        .push(name.as_str().to_string());
+                }
+            }
+        }
+        let context = RecordContext {
+            bookmarks_by_commit: &bookmarks_by_commit,
+            working_copy_id: repo.view().get_wc_commit_id(&self.workspace_name),
+        };
+        let wants_is_empty = fields.contains(&CommitField::IsEmpty);
+        expression
+            .evaluate(repo.as_ref())?
+            .iter()
+            .take(limit)
+            .map(|commit_id| {
+                let commit = repo.store().get_commit(&commit_id?)?;
+                let is_empty = if wants_is_empty {
+                    Some(commit.is_empty(repo.as_ref())?)
+                } else {
+                    None
+                };
+                Ok(CommitRecord::new(&commit, fields, &context, is_empty))
+            })
+            .collect()
+    }
+
+    /// The user's `templates.log`, unless it names one of jj's built-in templates, which only
+    /// the jj CLI can render.
+    pub fn log_template(&self) -> Result<Option<String>> {
+        let template = self.settings().get_string("templates.log").optional()?;
+        Ok(template.filter(|template| !template.trim_start().starts_with("builtin_")))
+    }
+
+    /// Renders the current commit of `change_id` with a jj commit template such as
+    /// [`Self::log_template`], expanding the user's `template-aliases`. Only a small subset of
+    /// the template language is supported: ids, signatures, the description, bookmarks and a
+    /// few flags, combined with `if`, `separate` and `++`. Templates reaching beyond it are an
+    /// error.
+    pub fn render_commit(&self, change_id: &ChangeId, template: &str) -> Result<String> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commit = Self::resolve_change_commit(&repo, change_id)?;
+        let mut aliases = TemplateAliases::default();
+        for declaration in self.settings().table_keys("template-aliases") {
+            let body = self
+                .settings()
+                .get_string(["template-aliases", declaration])?;
+            aliases.insert(declaration, body)?;
+        }
+        let bookmarks = repo
+            .view()
+            .local_bookmarks()
+            .filter(|(_, target)| target.added_ids().any(|id| id == commit.id()))
+            .map(|(name, _)| name.as_str().to_string())
+            .collect();
+        let signature = |signature: &jj_lib::backend::Signature| TemplateSignature {
+            name: signature.name.clone(),
+            email: signature.email.clone(),
+            timestamp: signature.timestamp.clone(),
+        };
+        let template_commit = TemplateCommit {
+            change_id: commit.change_id().to_string(),
+            change_id_shortest: repo.shortest_unique_change_id_prefix_len(commit.change_id()),
+            commit_id: commit.id().hex(),
+            commit_id_shortest: repo
+                .index()
+                .shortest_unique_commit_id_prefix_len(commit.id()),
+            description: commit.description().to_string(),
+            author: signature(commit.author()),
+            committer: signature(commit.committer()),
+            bookmarks,
+            is_working_copy: repo.view().get_wc_commit_id(&self.workspace_name)
+                == Some(commit.id()),
+            is_empty: commit.is_empty(repo.as_ref())?,
+            has_conflict: commit.tree()?.has_conflict(),
+            is_root: commit.id() == repo.store().root_commit_id(),
+        };
+        template::render(template, &template_commit, &aliases)
+    }
+
+    fn parse_revset(
+        &self,
+        repo: &Arc<ReadonlyRepo>,
+        text: &str,
+    ) -> Result<Rc<ResolvedRevsetExpression>> {
+        let aliases_map = self.revset_aliases()?;
+        let extensions = RevsetExtensions::default();
+        let path_converter = RepoPathUiConverter::Fs {
+            cwd: self.workspace_root.clone(),
+            base: self.workspace_root.clone(),
+        };
+        let context = RevsetParseContext {
+            aliases_map: &aliases_map,
+            local_variables: HashMap::new(),
+            user_email: self.settings().user_email(),
+            date_pattern_context: DatePatternContext::from(chrono::Local::now()),
+            extensions: &extensions,
+            workspace: Some(RevsetWorkspaceContext {
+                path_converter: &path_converter,
+                workspace_name: &self.workspace_name,
+            }),
+        };
+        let mut diagnostics = RevsetDiagnostics::new();
+        let expression = revset::parse(&mut diagnostics, text, &context)
+            .map_err(|err| anyhow!("invalid revset {text:?}: {err}"))?;
+        let symbol_resolver =
+            DefaultSymbolResolver::new(repo.as_ref(), extensions.symbol_resolvers());
+        Ok(expression.resolve_user_expression(repo.as_ref(), &symbol_resolver)?)
+    }
+
+    /// The aliases the jj CLI defines, like `trunk()` and `mutable()`, overridden by the user's
+    /// `revset-aliases`.
+    fn revset_aliases(&self) -> Result<RevsetAliasesMap> {
+        let mut aliases_map = RevsetAliasesMap::new();
+        for (declaration, definition) in DEFAULT_REVSET_ALIASES {
+            aliases_map.insert(declaration, *definition)?;
+        }
+        for declaration in self.settings().table_keys("revset-aliases") {
+            let definition = self
+                .settings()
+                .get_string(["revset-aliases", declaration])?;
+            aliases_map
+                .insert(declaration, definition)
+                .map_err(|err| anyhow!("invalid revset alias {declaration:?}: {err}"))?;
+        }
+        Ok(aliases_map)
+    }
+
+    /// Summarizes the first `limit` commits `expression` evaluates to, along with the graph
+    /// edges between them. Edges can point past the last of them, at commits the limit cut off.
+    fn log_commits(
+        repo: &Arc<ReadonlyRepo>,
+        expression: &Rc<ResolvedRevsetExpression>,
+        limit: usize,
+    ) -> Result<Vec<CommitSummary>> {
+        let mut commits = Vec::new();
+        let mut graph_edges = Vec::new();
+        for node in expression.evaluate(repo.as_ref())?.iter_graph().take(limit) {
+            let (commit_id, edges) = node?;
+            commits.push(repo.store().get_commit(&commit_id)?);
+            graph_edges.push(edges.into_iter().map(GraphEdge::from_lib).collect());
+        }
+        Ok(Self::summarize_commits(repo, &commits)?
+            .into_iter()
+            .zip(graph_edges)
+            .map(|(summary, graph_edges)| CommitSummary {
+                graph_edges,
+                ..summary
+            })
+            .collect())
+    }
+
+    fn summarize_commits(
+        repo: &Arc<ReadonlyRepo>,
+        commits: &[Commit],
+    ) -> Result<Vec<CommitSummary>> {
+        let mut nearest_refs = Self::nearest_descendant_refs(repo, commits)?;
+        let mut refs = Self::refs_by_commit(repo);
+        let pushed_commits = Self::pushed_commits(repo, commits)?;
+        commits
+            .iter()
+            .map(|commit| {
+                Ok(CommitSummary {
+                    nearest_ref: nearest_refs.remove(commit.id()),
+                    refs: refs.remove(commit.id()).unwrap_or_default(),
+                    is_pushed: pushed_commits.contains(commit.id()),
+                    ..CommitSummary::from_commit_in(repo, commit)?
+                })
+            })
+            .collect()
+    }
+
+    fn refs_by_commit(repo: &Arc<ReadonlyRepo>) -> HashMap<CommitId, Vec<CommitRef>> {
+        let mut refs: HashMap<CommitId, Vec<CommitRef>> = HashMap::new();
+        let mut add = |target: &RefTarget, name: &str, remote: Option<&str>, is_tag: bool| {
+            for commit_id in target.added_ids() {
+                refs.entry(commit_id.clone()).or_default().push(CommitRef {
+                    name: name.to_string(),
+                    remote: remote.map(str::to_string),
+                    is_tag,
+                    is_conflicted: target.has_conflict(),
+                });
+            }
+        };
+        for (name, targets) in repo.view().bookmarks() {
+            add(targets.local_target, name.as_str(), None, false);
+            for (remote, remote_ref) in targets.remote_refs {
+                let is_synced =
+                    remote_ref.is_tracked() && remote_ref.target == *targets.local_target;
+                if remote == REMOTE_NAME_FOR_LOCAL_GIT_REPO || is_synced {
+                    continue;
+                }
+                add(
+                    &remote_ref.target,
+                    name.as_str(),
+                    Some(remote.as_str()),
+                    false,
+                );
+            }
+        }
+        for (name, target) in repo.view().tags() {
+            add(target, name.as_str(), None, true);
+        }
+        refs
+    }
+
+    /// Lists every bookmark by name, each local bookmark before its remote counterparts. The
+    /// pseudo-remote jj uses for the colocated git repository's own refs is left out.
+    pub fn bookmarks(&self) -> Result<Vec<BookmarkSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        Ok(Self::bookmarks_in(&repo))
+    }
+
+    fn bookmarks_in(repo: &Arc<ReadonlyRepo>) -> Vec<BookmarkSummary> {
+        let mut bookmarks = Vec::new();
+        for (name, targets) in repo.view().bookmarks() {
+            if targets.local_target.is_present() {
+                bookmarks.push(BookmarkSummary {
+                    name: name.as_str().to_string(),
+                    remote: None,
+                    target_ids: targets.local_target.added_ids().cloned().collect(),
+                    is_tracked: false,
+                });
+            }
+            for (remote, remote_ref) in targets.remote_refs {
+                if remote == REMOTE_NAME_FOR_LOCAL_GIT_REPO || !remote_ref.target.is_present() {
+                    continue;
+                }
+                bookmarks.push(BookmarkSummary {
+                    name: name.as_str().to_string(),
+                    remote: Some(remote.as_str().to_string()),
+                    target_ids: remote_ref.target.added_ids().cloned().collect(),
+                    is_tracked: remote_ref.is_tracked(),
+                });
+            }
+        }
+        bookmarks
+    }
+
+    /// Loads the repository as it was right after `operation_id`, with up to `limit` commits
+    /// of its log, without snapshotting or otherwise changing anything.
+    pub fn at_operation(&self, operation_id: &str, limit: usize) -> Result<RepositoryAtOperation> {
+        let operation_id = OperationId::try_from_hex(operation_id)
+            .ok_or_else(|| anyhow!("invalid operation id {operation_id}"))?;
+        let operation = self.repo_loader.load_operation(&operation_id)?;
+        let repo = self.repo_loader.load_at(&operation)?;
+        Ok(RepositoryAtOperation {
+            commits: Self::log_commits(&repo, &RevsetExpression::all(), limit)?,
+            bookmarks: Self::bookmarks_in(&repo),
+            current_change_id: Self::workspace_commit(&repo, &self.workspace_name)
+                .ok()
+                .map(|commit| commit.change_id().clone()),
+        })
+    }
+
+    /// Summarizes up to `limit` commits that were heads at one of the recent operations and
+    /// whose change has no visible commit left, i.e. abandoned work, most recently hidden first.
+    /// Empty commits without a description are left out, like the ones jj abandons on its own
+    /// when the working copy moves.
+    pub fn hidden_heads(&self, limit: usize) -> Result<Vec<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let mut seen_commits = HashSet::new();
+        let mut seen_changes = HashSet::new();
+        let mut commits = Vec::new();
+        'operations: for operation in op_walk::walk_ancestors(slice::from_ref(repo.operation()))
+            .take(HIDDEN_HEADS_OPERATION_LIMIT)
+        {
+            let view = operation?.view()?;
+            let mut heads = view
+                .heads()
+                .iter()
+                .filter(|commit_id| seen_commits.insert((*commit_id).clone()))
+                .cloned()
+                .collect::<Vec<_>>();
+            heads.sort();
+            for commit_id in heads {
+                if commits.len() >= limit {
+                    break 'operations;
+                }
+                let commit = repo.store().get_commit(&commit_id)?;
+                let is_abandoned = repo
+                    .resolve_change_id(commit.change_id())
+                    .is_none_or(|commit_ids| commit_ids.is_empty());
+                if is_abandoned
+                    && seen_changes.insert(commit.change_id().clone())
+                    && !commit.is_discardable(repo.as_ref())?
+                {
+                    commits.push(commit);
+                }
+            }
+        }
+        Self::summarize_commits(&repo, &commits)
+    }
+
+    /// Makes a hidden commit visible again, bringing back abandoned work. Fails if its change
+    /// already has a visible commit, since that would make the change divergent.
+    pub fn restore_hidden_commit(&self, commit_id: &CommitId) -> Result<()> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commit = repo.store().get_commit(commit_id)?;
+        if repo
+            .resolve_change_id(commit.change_id())
+            .is_some_and(|commit_ids| !commit_ids.is_empty())
+        {
+            return Err(anyhow!(
+                "change {} already has a visible commit",
+                short_change_hash(commit.change_id())
+            ));
+        }
+        let mut tx = self.start_transaction(&repo)?;
+        tx.repo_mut().add_head(&commit)?;
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("restore hidden commit {}", short_commit_hash(commit_id)),
+        )
+    }
+
+    /// Returns the members of `commits` that are ancestors of a remote bookmark, ignoring
+    /// the pseudo-remote jj uses to track the colocated git repository's own refs. The
+    /// ancestry comes from the index, so commits outside `commits` don't cut the walk short.
+    fn pushed_commits(repo: &Arc<ReadonlyRepo>, commits: &[Commit]) -> Result<HashSet<CommitId>> {
+        let remote_ids = repo
+            .view()
+            .all_remote_bookmarks()
+            .filter(|(symbol, _)| symbol.remote != REMOTE_NAME_FOR_LOCAL_GIT_REPO)
+            .flat_map(|(_, remote_ref)| remote_ref.target.added_ids().cloned())
+            .collect::<Vec<_>>();
+        if remote_ids.is_empty() || commits.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let commit_ids = commits.iter().map(|commit| commit.id().clone()).collect();
+        let pushed: Rc<ResolvedRevsetExpression> = RevsetExpression::commits(remote_ids)
+            .ancestors()
+            .intersection(&RevsetExpression::commits(commit_ids));
+        Ok(pushed
+            .evaluate(repo.as_ref())?
+            .iter()
+            .collect::<Result<HashSet<_>, _>>()?)
+    }
+
+    /// Walks from every bookmark and tag towards the roots, breadth first, so each commit
+    /// is labelled with the ref reachable through the fewest descendants. Bookmarks win
+    /// ties with tags. The walk covers `commits::refs` as the index computes it, so refs on
+    /// descendants outside `commits`, as in a filtered log, still count.
+    fn nearest_descendant_refs(
+        repo: &Arc<ReadonlyRepo>,
+        commits: &[Commit],
+    ) -> Result<HashMap<CommitId, String>> {
+        let view = repo.view();
+        let mut queue = VecDeque::new();
+        for (name, target) in view.local_bookmarks() {
+            for commit_id in target.added_ids() {
+                queue.push_back((commit_id.clone(), name.as_str().to_string()));
+            }
+        }
+        for (name, target) in view.tags() {
+            for commit_id in target.added_ids() {
+                queue.push_back((commit_id.clone(), name.as_str().to_string()));
             }
         }
+        if queue.is_empty() || commits.is_empty() {
+            return Ok(HashMap::new());
+        }
 
-        let (new_tree_id, _stats) = locked_ws.locked_wc().snapshot(&options)?;
-        let mut op_id = repo.op_id().clone();
-        if new_tree_id != *wc_commit.tree_id() {
-            let mut tx = repo.start_transaction();
-            tx.set_is_snapshot(true);
-            let repo_mut = tx.repo_mut();
-            let new_commit = repo_mut
-                .rewrite_commit(&wc_commit)
-                .set_tree_id(new_tree_id)
-                .write()?;
-            repo_mut.set_wc_commit(workspace_name.clone(), new_commit.id().clone())?;
-            let rebased = repo_mut.rebase_descendants()?;
-            if rebased > 0 {
-                debug!(
-                    target: "jj::workspace",
-                    "snapshot rebased {rebased} descendant commits"
-                );
+        let ref_ids = queue
+            .iter()
+            .map(|(commit_id, _)| commit_id.clone())
+            .collect();
+        let commit_ids = commits.iter().map(|commit| commit.id().clone()).collect();
+        let between: Rc<ResolvedRevsetExpression> =
+            RevsetExpression::commits(commit_ids).dag_range_to(&RevsetExpression::commits(ref_ids));
+        let between = between
+            .evaluate(repo.as_ref())?
+            .iter()
+            .collect::<Result<HashSet<_>, _>>()?;
+
+        let mut nearest = HashMap::new();
+        while let Some((commit_id, name)) = queue.pop_front() {
+            if nearest.contains_key(&commit_id) || !between.contains(&commit_id) {
+                continue;
             }
-            let new_repo = tx.commit("snapshot working copy")?;
-            op_id = new_repo.op_id().clone();
+            let commit = repo.store().get_commit(&commit_id)?;
+            for parent_id in commit.parent_ids() {
+                queue.push_back((parent_id.clone(), name.clone()));
+            }
+            nearest.insert(commit_id, name);
         }
-        locked_ws.finish(op_id)?;
-        Ok(())
+        Ok(nearest)
     }
 
-    fn snapshot_auto_tracking_matcher(&self) -> Result<Box<dyn Matcher>> {
-        let expression = FilesetExpression::all();
-        Ok(expression.to_matcher())
+    /// Returns the commits among the `limit` most recent ones that are based on an ancestor
+    /// which has since been rewritten, along with their descendants.
+    pub fn restack_candidates(&self, limit: usize) -> Result<Vec<CommitSummary>> {
+        let repo = self.repo_loader.load_at_head()?;
+        let commits = Self::visible_commits(&repo, limit)?;
+        let successors = Self::rewritten_commits(&repo, &commits)?;
+        let stale = Self::stale_commits(&commits, &successors);
+        Ok(commits
+            .iter()
+            .filter(|commit| stale.contains(commit.id()))
+            .map(CommitSummary::from_commit)
+            .collect())
     }
 
-    fn snapshot_options<'a>(
-        &self,
-        start_tracking_matcher: &'a dyn Matcher,
-    ) -> Result<SnapshotOptions<'a>> {
-        let fsmonitor_settings = self.settings().fsmonitor_settings()?;
-        let max_new_file_size = u64::MAX;
-        Ok(SnapshotOptions {
-            base_ignores: GitIgnoreFile::empty(),
-            fsmonitor_settings,
-            progress: None,
-            start_tracking_matcher,
-            max_new_file_size,
-            conflict_marker_style: ConflictMarkerStyle::default(),
-        })
-    }
+    /// Rebases descendants of rewritten ancestors onto the ancestors' latest versions.
+    pub fn restack(&self, limit: usize) -> Result<usize> {
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let commits = Self::visible_commits(&repo, limit)?;
+        let successors = Self::rewritten_commits(&repo, &commits)?;
+        let stale = Self::stale_commits(&commits, &successors);
+        if stale.is_empty() {
+            return Ok(0);
+        }
 
-    fn settings(&self) -> &UserSettings {
-        self.repo_loader.settings()
+        let mut tx = self.start_transaction(&repo)?;
+        for (old_id, new_id) in &successors {
+            tx.repo_mut()
+                .set_rewritten_commit(old_id.clone(), new_id.clone());
+        }
+        let rebased = tx.repo_mut().rebase_descendants()?;
+        self.apply_transaction(&mut workspace, tx, format!("restack {rebased} commits"))?;
+        Ok(rebased)
     }
 
-    pub fn recent_commits(&self, limit: usize) -> Result<Vec<CommitSummary>> {
+    /// Returns up to `limit` changes not in trunk whose diffs trunk already contains: they're
+    /// empty, or would become empty if rebased onto trunk. Only mutable changes that no remote
+    /// bookmark points at or descends from are considered, since others may still be someone
+    /// else's work. The working-copy change and merges are left out too, since abandoning those
+    /// loses more than a landed diff.
+    pub fn landed_changes(&self, limit: usize) -> Result<Vec<CommitSummary>> {
         let repo = self.repo_loader.load_at_head()?;
-        let store = repo.store();
-        let mut heads: Vec<_> = repo.view().heads().iter().cloned().collect();
-        heads.sort();
-        let mut stack = Vec::new();
-        for head in heads {
-            let commit = store.get_commit(&head)?;
-            stack.push(commit);
+        let trunk = self.trunk_commit(&repo)?;
+        let trunk_tree = trunk.tree()?;
+        let wc_commit_id = repo.view().get_wc_commit_id(&self.workspace_name);
+        let candidates = self.parse_revset(
+            &repo,
+            "(trunk()..visible_heads()) & mutable() & ~::remote_bookmarks()",
+        )?;
+        let mut landed = Vec::new();
+        for commit_id in candidates.evaluate(repo.as_ref())?.iter() {
+            if landed.len() == limit {
+                break;
+            }
+            let commit_id = commit_id?;
+            if Some(&commit_id) == wc_commit_id {
+                continue;
+            }
+            let commit = repo.store().get_commit(&commit_id)?;
+            if commit.parent_ids().len() != 1 {
+                continue;
+            }
+            let rebased_tree =
+                trunk_tree.merge(&commit.parent_tree(repo.as_ref())?, &commit.tree()?)?;
+            if rebased_tree.id() == trunk_tree.id() {
+                landed.push(commit);
+            }
         }
+        Self::summarize_commits(&repo, &landed)
+    }
 
-        let mut visited = HashSet::new();
-        let mut summaries = Vec::new();
-
-        while let Some(commit) = stack.pop() {
-            if !visited.insert(commit.id().clone()) {
-                continue;
+    /// Abandons every change in `change_ids` in a single operation, rebasing their descendants
+    /// like [`Self::abandon_change`] does. Returns how many were abandoned.
+    pub fn abandon_changes(&self, change_ids: &[ChangeId]) -> Result<usize> {
+        if change_ids.is_empty() {
+            return Ok(0);
+        }
+        self.snapshot_working_copy()?;
+        let (mut workspace, repo) = self.load_workspace_and_repo()?;
+        let mut tx = self.start_transaction(&repo)?;
+        for change_id in change_ids {
+            let commit = Self::resolve_change_commit(&repo, change_id)?;
+            if commit.id() == repo.store().root_commit_id() {
+                return Err(anyhow!("the root change cannot be abandoned"));
             }
+            tx.repo_mut().record_abandoned_commit(&commit);
+        }
+        self.apply_transaction(
+            &mut workspace,
+            tx,
+            format!("abandon {} changes", change_ids.len()),
+        )?;
+        Ok(change_ids.len())
+    }
 
-            let timestamp = commit.committer().timestamp.timestamp;
-            summaries.push(CommitSummary {
-                commit_id: commit.id().clone(),
-                change_id: commit.change_id().clone(),
-                author: commit.author().name.clone(),
-                description: commit.description().to_string(),
-                timestamp: timestamp.0,
-            });
+    /// The commit the `trunk()` revset alias resolves to, which is the root commit when no
+    /// trunk bookmark exists.
+    fn trunk_commit(&self, repo: &Arc<ReadonlyRepo>) -> Result<Commit> {
+        let trunk_id = self
+            .parse_revset(repo, "trunk()")?
+            .evaluate(repo.as_ref())?
+            .iter()
+            .next()
+            .transpose()?
+            .filter(|commit_id| commit_id != repo.store().root_commit_id())
+            .ok_or_else(|| anyhow!("trunk() doesn't point at any bookmark"))?;
+        Ok(repo.store().get_commit(&trunk_id)?)
+    }
 
-            if summaries.len() >= limit {
-                break;
+    /// Maps each commit to its latest rewritten version, as recorded by the predecessors of
+    /// the given commits. Predecessors that are still visible, as when a change diverged,
+    /// weren't replaced, so they're left out and their descendants stay where they are.
+    fn rewritten_commits(
+        repo: &Arc<ReadonlyRepo>,
+        commits: &[Commit],
+    ) -> Result<HashMap<CommitId, CommitId>> {
+        let mut successors = HashMap::new();
+        for commit in commits {
+            for predecessor_id in commit.predecessor_ids() {
+                if predecessor_id == commit.id() {
+                    continue;
+                }
+                let predecessor = repo.store().get_commit(predecessor_id)?;
+                let is_visible = repo
+                    .resolve_change_id(predecessor.change_id())
+                    .is_some_and(|commit_ids| commit_ids.contains(predecessor_id));
+                if !is_visible {
+                    successors.insert(predecessor_id.clone(), commit.id().clone());
+                }
+            }
+        }
+
+        // Only keep mappings to commits which have not themselves been rewritten.
+        let mut resolved = HashMap::new();
+        for (old_id, mut new_id) in &successors {
+            let mut hops = 0;
+            while let Some(next_id) = successors.get(new_id) {
+                new_id = next_id;
+                hops += 1;
+                if hops > successors.len() {
+                    break;
+                }
             }
+            resolved.insert(old_id.clone(), new_id.clone());
+        }
+        Ok(resolved)
+    }
 
-            let mut parents: Vec<_> = commit.parent_ids().iter().cloned().collect();
-            parents.reverse();
-            for parent_id in parents {
-                let parent = store.get_commit(&parent_id)?;
-                stack.push(parent);
+    fn stale_commits(
+        commits: &[Commit],
+        successors: &HashMap<CommitId, CommitId>,
+    ) -> HashSet<CommitId> {
+        let mut stale = HashSet::new();
+        // Children come before their parents, so propagate staleness until it settles.
+        loop {
+            let mut changed = false;
+            for commit in commits {
+                if stale.contains(commit.id()) || successors.contains_key(commit.id()) {
+                    continue;
+                }
+                if commit.parent_ids().iter().any(|parent_id| {
+                    successors.contains_key(parent_id) || stale.contains(parent_id)
+                }) {
+                    stale.insert(commit.id().clone());
+                    changed = true;
+                }
+            }
+            if !changed {
+                return stale;
             }
         }
+    }
+
+    /// The `limit` most recent visible commits, children before their parents, in the order
+    /// `jj log` lists them. Evaluating the revset walks the index, so only the commits
+    /// returned are loaded from the store.
+    fn visible_commits(repo: &Arc<ReadonlyRepo>, limit: usize) -> Result<Vec<Commit>> {
+        RevsetExpression::all()
+            .evaluate(repo.as_ref())?
+            .iter()
+            .take(limit)
+            .map(|commit_id| Ok(repo.store().get_commit(&commit_id?)?))
+            .collect()
+    }
+}
+
+/// The revset aliases jj-cli ships in its default config, which jj-lib doesn't know about.
+const DEFAULT_REVSET_ALIASES: &[(&str, &str)] = &[
+    (
+        "trunk()",
+        r#"latest(
+            remote_bookmarks(exact:"main", exact:"origin") |
+            remote_bookmarks(exact:"master", exact:"origin") |
+            remote_bookmarks(exact:"trunk", exact:"origin") |
+            remote_bookmarks(exact:"main", exact:"upstream") |
+            remote_bookmarks(exact:"master", exact:"upstream") |
+            remote_bookmarks(exact:"trunk", exact:"upstream") |
+            root()
+        )"#,
+    ),
+    (
+        "builtin_immutable_heads()",
+        "trunk() | tags() | untracked_remote_bookmarks()",
+    ),
+    ("immutable_heads()", "builtin_immutable_heads()"),
+    ("immutable()", "::(immutable_heads() | root())"),
+    ("mutable()", "~immutable()"),
+];
+
+/// Where the jj CLI reads the user's config from: `$JJ_CONFIG` if set, else `~/.jjconfig.toml`
+/// or `jj/config.toml` in the platform's config directory, whichever exists.
+fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("JJ_CONFIG") {
+        return Some(PathBuf::from(path)).filter(|path| path.is_file());
+    }
+    [
+        dirs::home_dir().map(|home| home.join(".jjconfig.toml")),
+        dirs::config_dir().map(|config| config.join("jj").join("config.toml")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|path| path.is_file())
+}
+
+/// Replaces `error` with an [`UnsupportedFormatError`] when it's one, so that its message tells
+/// the user what to do instead of naming the file jj-lib failed on.
+fn with_unsupported_format(error: anyhow::Error) -> anyhow::Error {
+    match UnsupportedFormatError::detect(&error) {
+        Some(unsupported) => unsupported.into(),
+        None => error,
+    }
+}
+
+/// Directory and file names that carry no meaning in a change description.
+const DESCRIPTION_NOISE_COMPONENTS: &[&str] = &["crates", "src", "lib", "mod"];
+
+/// Derives a short description such as "update editor/display_map" from the internal
+/// (slash-separated) paths modified by a change.
+pub fn describe_changed_paths<'a>(paths: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut file_count = 0;
+    let topics: Vec<Vec<&str>> = paths
+        .into_iter()
+        .inspect(|_| file_count += 1)
+        .map(|path| {
+            let mut components: Vec<&str> = path.split('/').collect();
+            if let Some(file_name) = components.last_mut()
+                && let Some((stem, _)) = file_name.rsplit_once('.')
+                && !stem.is_empty()
+            {
+                *file_name = stem;
+            }
+            components.retain(|component| !DESCRIPTION_NOISE_COMPONENTS.contains(component));
+            components
+        })
+        .filter(|components| !components.is_empty())
+        .collect();
+
+    let first = topics.first()?;
+    let common_len = topics.iter().skip(1).fold(first.len(), |len, topic| {
+        first
+            .iter()
+            .zip(topic)
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    if common_len > 0 {
+        return Some(format!("update {}", first[..common_len].join("/")));
+    }
 
-        Ok(summaries)
+    let mut roots: Vec<&str> = topics.iter().map(|topic| topic[0]).collect();
+    roots.sort_unstable();
+    roots.dedup();
+    if roots.len() <= 3 {
+        Some(format!("update {}", roots.join(", ")))
+    } else {
+        Some(format!("update {file_count} files"))
+    }
+}
+
+/// Combines descriptions the way `jj squash` does without an editor: an empty description
+/// yields to the other one, and two non-empty descriptions are joined by a blank line.
+fn squashed_description(parent: &str, child: &str) -> String {
+    match (parent.trim(), child.trim()) {
+        (parent, "") => parent.to_string(),
+        ("", child) => child.to_string(),
+        (parent, child) => format!("{parent}\n\n{child}"),
     }
 }
 
@@ -347,3 +3290,869 @@ pub fn short_change_hash(change_id: &ChangeId) -> String {
 pub fn short_commit_hash(commit_id: &CommitId) -> String {
     format!("{commit_id:.12}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jj_lib::op_store::RemoteRefState;
+    use jj_lib::repo::StoreFactories;
+    use rand::prelude::*;
+    use std::env;
+
+    const LOG_LIMIT: usize = 1000;
+
+    #[test]
+    fn test_describe_changed_paths() {
+        assert_eq!(describe_changed_paths([]), None);
+        assert_eq!(
+            describe_changed_paths(["crates/editor/src/display_map.rs"]).as_deref(),
+            Some("update editor/display_map")
+        );
+        assert_eq!(
+            describe_changed_paths([
+                "crates/editor/src/display_map.rs",
+                "crates/editor/src/element.rs",
+            ])
+            .as_deref(),
+            Some("update editor")
+        );
+        assert_eq!(
+            describe_changed_paths(["crates/jj/src/lib.rs", "Cargo.toml"]).as_deref(),
+            Some("update Cargo, jj")
+        );
+        assert_eq!(
+            describe_changed_paths(["a/x.rs", "b/x.rs", "c/x.rs", "d/x.rs"]).as_deref(),
+            Some("update 4 files")
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_working_copy_backend() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".jj/working_copy/type"),
+            "virtual_file_system",
+        )
+        .unwrap();
+        let error = JjWorkspace::load(temp_dir.path()).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "unsupported working copy backend \"virtual_file_system\""
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_newer_op_store_format() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".jj/repo/op_store/type"),
+            "simple_op_store_v2",
+        )
+        .unwrap();
+        let error = JjWorkspace::load(temp_dir.path()).err().unwrap();
+        let unsupported = error.downcast_ref::<UnsupportedFormatError>().unwrap();
+        assert_eq!(
+            unsupported.detail,
+            "unknown operation store type \"simple_op_store_v2\""
+        );
+        assert_eq!(
+            UnsupportedFormatError::detect(&error).as_ref(),
+            Some(unsupported)
+        );
+        assert!(UnsupportedFormatError::detect(&anyhow!("change abc not found")).is_none());
+    }
+
+    #[test]
+    fn test_load_with_registered_store_backend() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join(".jj/repo/store/type"), "company_store").unwrap();
+        assert!(JjWorkspace::load(temp_dir.path()).is_err());
+
+        let mut store_factories = StoreFactories::empty();
+        store_factories.add_backend(
+            "company_store",
+            Box::new(|_settings, store_path| {
+                Ok(Box::new(jj_lib::simple_backend::SimpleBackend::load(
+                    store_path,
+                )))
+            }),
+        );
+        let mut backends = JjBackends::default();
+        backends.add_store_factories(store_factories);
+        let workspace =
+            JjWorkspace::load_with_backends(temp_dir.path(), Arc::new(backends)).unwrap();
+        assert!(!workspace.recent_commits(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_only_workspace_records_no_operations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        JjWorkspace::init(temp_dir.path()).unwrap();
+        let workspace = JjWorkspace::load(temp_dir.path())
+            .unwrap()
+            .with_read_only(true);
+        let operation_id = workspace.head_operation_id().unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "contents").unwrap();
+
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        assert!(workspace.new_change(&change_id, "child").is_err());
+        assert!(workspace.snapshot().is_ok());
+        assert!(workspace.recent_commits(10).is_ok());
+        let profile = futures::executor::block_on(workspace.profile(10)).unwrap();
+        assert!(profile.snapshot.is_none());
+        assert_eq!(workspace.head_operation_id().unwrap(), operation_id);
+    }
+
+    #[test]
+    fn test_annotate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let file_path = temp_dir.path().join("file.txt");
+        std::fs::write(&file_path, "first\n").unwrap();
+        let first = workspace.current_change_id().unwrap().unwrap();
+        let second = workspace.new_change(&first, "second").unwrap();
+        std::fs::write(&file_path, "first\nsecond\n").unwrap();
+
+        let repo_path = RepoPathBuf::from_internal_string("file.txt").unwrap();
+        let change_ids = workspace
+            .annotate(&repo_path)
+            .unwrap()
+            .into_iter()
+            .map(|line| line.change_id)
+            .collect::<Vec<_>>();
+        assert_eq!(change_ids, vec![first, second]);
+    }
+
+    #[test]
+    fn test_file_history() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "first\n").unwrap();
+        let first = workspace.current_change_id().unwrap().unwrap();
+        let other = workspace.new_change(&first, "other").unwrap();
+        std::fs::write(temp_dir.path().join("other.txt"), "other\n").unwrap();
+        let second = workspace.new_change(&other, "second").unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "second\n").unwrap();
+
+        let repo_path = RepoPathBuf::from_internal_string("file.txt").unwrap();
+        let change_ids = workspace
+            .file_history(&repo_path, 10)
+            .unwrap()
+            .into_iter()
+            .map(|commit| commit.change_id)
+            .collect::<Vec<_>>();
+        assert_eq!(change_ids, vec![second, first]);
+    }
+
+    #[test]
+    fn test_evolution_log() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "first").unwrap();
+        workspace.rename_change(&change_id, "second").unwrap();
+
+        let descriptions = workspace
+            .evolution_log(&change_id, 10)
+            .unwrap()
+            .into_iter()
+            .map(|version| version.description)
+            .collect::<Vec<_>>();
+        assert_eq!(descriptions, vec!["second", "first", ""]);
+        assert_eq!(workspace.evolution_log(&change_id, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_divergent_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        let (_, repo) = workspace.load_workspace_and_repo().unwrap();
+        let commit = JjWorkspace::resolve_change_commit(&repo, &change_id).unwrap();
+        let mut tx = repo.start_transaction();
+        let copy = tx
+            .repo_mut()
+            .new_commit(commit.parent_ids().to_vec(), commit.tree_id().clone())
+            .set_change_id(change_id.clone())
+            .set_description("copy")
+            .write()
+            .unwrap();
+        tx.commit("make change divergent").unwrap();
+
+        let error = workspace.rename_change(&change_id, "renamed").unwrap_err();
+        let divergent = error.downcast_ref::<DivergentChangeError>().unwrap();
+        assert_eq!(divergent.commit_ids.len(), 2);
+        assert!(
+            workspace
+                .commit_for_change(&change_id)
+                .unwrap()
+                .unwrap()
+                .is_divergent
+        );
+
+        workspace
+            .keep_divergent_commit(&change_id, copy.id())
+            .unwrap();
+        let kept = workspace.commit_for_change(&change_id).unwrap().unwrap();
+        assert_eq!(&kept.commit_id, copy.id());
+        assert!(!kept.is_divergent);
+        workspace.rename_change(&change_id, "renamed").unwrap();
+    }
+
+    #[test]
+    fn test_working_copy_state() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "described").unwrap();
+        let state = workspace.working_copy_state().unwrap();
+        assert!(state.is_at_head());
+        assert!(!state.is_stale);
+
+        std::fs::write(temp_dir.path().join("file.txt"), "contents").unwrap();
+        workspace.snapshot().unwrap();
+        let (_, repo) = workspace.load_workspace_and_repo().unwrap();
+        let commit = JjWorkspace::resolve_change_commit(&repo, &change_id).unwrap();
+        let parent = repo.store().get_commit(&commit.parent_ids()[0]).unwrap();
+        let mut tx = repo.start_transaction();
+        tx.repo_mut()
+            .rewrite_commit(&commit)
+            .set_tree_id(parent.tree_id().clone())
+            .write()
+            .unwrap();
+        tx.repo_mut().rebase_descendants().unwrap();
+        tx.commit("rewrite working copy elsewhere").unwrap();
+
+        let state = workspace.working_copy_state().unwrap();
+        assert!(!state.is_at_head());
+        assert!(state.is_stale);
+        workspace.recover_working_copy().unwrap();
+        assert!(!workspace.working_copy_state().unwrap().is_stale);
+    }
+
+    #[test]
+    fn test_hidden_heads() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "contents").unwrap();
+        workspace
+            .rename_change(&change_id, "abandoned work")
+            .unwrap();
+        workspace.abandon_change(&change_id).unwrap();
+
+        let hidden = workspace.hidden_heads(10).unwrap();
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].change_id, change_id);
+        assert_eq!(hidden[0].description, "abandoned work");
+        assert!(hidden[0].is_hidden);
+        assert!(workspace.commit_for_change(&change_id).unwrap().is_none());
+
+        workspace
+            .restore_hidden_commit(&hidden[0].commit_id)
+            .unwrap();
+        let restored = workspace.commit_for_change(&change_id).unwrap().unwrap();
+        assert!(!restored.is_hidden);
+        assert!(workspace.hidden_heads(10).unwrap().is_empty());
+        assert!(
+            workspace
+                .restore_hidden_commit(&hidden[0].commit_id)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_at_operation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "before").unwrap();
+        let operation_id = workspace.head_operation_id().unwrap();
+        workspace.set_bookmark("main", &change_id).unwrap();
+        workspace.rename_change(&change_id, "after").unwrap();
+
+        let head_operation_id = workspace.head_operation_id().unwrap();
+        let past = workspace.at_operation(&operation_id, 10).unwrap();
+        assert!(past.bookmarks.is_empty());
+        assert_eq!(past.current_change_id, Some(change_id.clone()));
+        let description = past
+            .commits
+            .iter()
+            .find(|commit| commit.change_id == change_id)
+            .map(|commit| commit.description.as_str());
+        assert_eq!(description, Some("before"));
+        assert_eq!(workspace.head_operation_id().unwrap(), head_operation_id);
+    }
+
+    #[test]
+    fn test_resolve_conflict() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let file_path = temp_dir.path().join("file.txt");
+        let repo_path = RepoPathBuf::from_internal_string("file.txt").unwrap();
+        std::fs::write(&file_path, "base\n").unwrap();
+        let base = workspace.current_change_id().unwrap().unwrap();
+        let left = workspace.new_change(&base, "left").unwrap();
+        std::fs::write(&file_path, "left\n").unwrap();
+        let right = workspace.new_change(&base, "right").unwrap();
+        std::fs::write(&file_path, "right\n").unwrap();
+        let merge = workspace
+            .new_change_with_parents(&[left, right], "merge")
+            .unwrap();
+        assert_eq!(
+            workspace.conflicted_paths(&merge).unwrap(),
+            vec![repo_path.clone()]
+        );
+        assert_eq!(
+            futures::executor::block_on(workspace.conflict_sides(&repo_path)).unwrap(),
+            ConflictSides {
+                left: "left\n".to_string(),
+                base: "base\n".to_string(),
+                right: "right\n".to_string(),
+            }
+        );
+
+        futures::executor::block_on(workspace.resolve_conflict(&repo_path, "merged\n")).unwrap();
+        assert!(workspace.conflicted_paths(&merge).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "merged\n");
+        assert!(
+            futures::executor::block_on(workspace.resolve_conflict(&repo_path, "again\n")).is_err()
+        );
+    }
+
+    #[test]
+    fn test_empty_and_conflicted_commits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let file_path = temp_dir.path().join("file.txt");
+        std::fs::write(&file_path, "base\n").unwrap();
+        let base = workspace.current_change_id().unwrap().unwrap();
+        let left = workspace.new_change(&base, "left").unwrap();
+        std::fs::write(&file_path, "left\n").unwrap();
+        let right = workspace.new_change(&base, "right").unwrap();
+        std::fs::write(&file_path, "right\n").unwrap();
+        let merge = workspace
+            .new_change_with_parents(&[left.clone(), right], "merge")
+            .unwrap();
+
+        let left_commit = workspace.commit_for_change(&left).unwrap().unwrap();
+        assert!(!left_commit.is_empty);
+        assert!(!left_commit.has_conflict);
+        let merge_commit = workspace.working_copy_commit().unwrap().unwrap();
+        assert_eq!(merge_commit.change_id, merge);
+        assert!(merge_commit.is_empty);
+        assert!(merge_commit.has_conflict);
+    }
+
+    #[test]
+    fn test_commit_refs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "content\n").unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.set_bookmark("main", &change_id).unwrap();
+        let commit_id = workspace
+            .commit_for_change(&change_id)
+            .unwrap()
+            .unwrap()
+            .commit_id;
+        let repo = workspace.repo_loader.load_at_head().unwrap();
+        let mut tx = repo.start_transaction();
+        tx.repo_mut().set_tag_target(
+            &RefNameBuf::from("v1.0"),
+            RefTarget::normal(commit_id.clone()),
+        );
+        tx.commit("tag v1.0").unwrap();
+
+        let commits = workspace.recent_commits(10).unwrap();
+        let commit = commits
+            .iter()
+            .find(|commit| commit.commit_id == commit_id)
+            .unwrap();
+        let labels = commit.refs.iter().map(CommitRef::label).collect::<Vec<_>>();
+        assert_eq!(labels, ["main", "v1.0"]);
+        assert!(commit.refs[1].is_tag);
+    }
+
+    #[test]
+    fn test_nearest_ref_outside_filtered_log() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "base\n").unwrap();
+        let base = workspace.current_change_id().unwrap().unwrap();
+        let feature = workspace.new_change(&base, "feature").unwrap();
+        workspace.set_bookmark("feature", &feature).unwrap();
+
+        let commits = workspace.evaluate_revset("root()+", 10).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].nearest_ref.as_deref(), Some("feature"));
+    }
+
+    #[test]
+    fn test_rebase_source_and_branch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "a\n").unwrap();
+        let base = workspace.current_change_id().unwrap().unwrap();
+        let middle = workspace.new_change(&base, "middle").unwrap();
+        let tip = workspace.new_change(&middle, "tip").unwrap();
+        let sibling = workspace.new_change(&base, "sibling").unwrap();
+        let commit =
+            |change_id: &ChangeId| workspace.commit_for_change(change_id).unwrap().unwrap();
+
+        assert!(workspace.rebase(&base, &tip, RebaseMode::Source).is_err());
+        assert!(workspace.rebase(&base, &tip, RebaseMode::Branch).is_err());
+
+        workspace
+            .rebase(&middle, &sibling, RebaseMode::Source)
+            .unwrap();
+        assert_eq!(commit(&middle).parent_ids, [commit(&sibling).commit_id]);
+        assert_eq!(commit(&tip).parent_ids, [commit(&middle).commit_id]);
+
+        workspace
+            .rebase(&middle, &base, RebaseMode::Source)
+            .unwrap();
+        assert_eq!(commit(&middle).parent_ids, [commit(&base).commit_id]);
+        // From the tip, the branch not already on the sibling starts at the middle change.
+        workspace
+            .rebase(&tip, &sibling, RebaseMode::Branch)
+            .unwrap();
+        assert_eq!(commit(&middle).parent_ids, [commit(&sibling).commit_id]);
+        assert_eq!(commit(&tip).parent_ids, [commit(&middle).commit_id]);
+        assert_eq!(commit(&sibling).parent_ids, [commit(&base).commit_id]);
+    }
+
+    #[test]
+    fn test_revset_aliases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(
+            temp_dir.path().join(".jj/repo/config.toml"),
+            "[revset-aliases]\n'current()' = '@'\n",
+        )
+        .unwrap();
+        let workspace = JjWorkspace::load(temp_dir.path()).unwrap();
+        let current = workspace.working_copy_commit().unwrap().unwrap();
+
+        let trunk = workspace.evaluate_revset("trunk()", 10).unwrap();
+        assert_eq!(trunk.len(), 1);
+        assert!(trunk[0].parent_ids.is_empty());
+        let mutable = workspace.evaluate_revset("mutable()", 10).unwrap();
+        assert_eq!(mutable.len(), 1);
+        assert_eq!(mutable[0].commit_id, current.commit_id);
+        let aliased = workspace.evaluate_revset("current()", 10).unwrap();
+        assert_eq!(aliased[0].commit_id, current.commit_id);
+    }
+
+    #[test]
+    fn test_landed_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let file_path = temp_dir.path().join("file.txt");
+        let root_change_id = workspace
+            .repo_loader
+            .store()
+            .root_commit()
+            .change_id()
+            .clone();
+        std::fs::write(&file_path, "landed\n").unwrap();
+        let trunk = workspace.current_change_id().unwrap().unwrap();
+        // Both changes repeat trunk's diff, but someone else's bookmark points at the second.
+        let landed = workspace.new_change(&root_change_id, "landed").unwrap();
+        std::fs::write(&file_path, "landed\n").unwrap();
+        let shared = workspace.new_change(&root_change_id, "shared").unwrap();
+        std::fs::write(&file_path, "landed\n").unwrap();
+        workspace.new_change(&trunk, "").unwrap();
+
+        let repo = workspace.repo_loader.load_at_head().unwrap();
+        let mut tx = repo.start_transaction();
+        for (name, change_id) in [("main", &trunk), ("feature", &shared)] {
+            let commit = JjWorkspace::resolve_change_commit(&repo, change_id).unwrap();
+            tx.repo_mut().set_remote_bookmark(
+                RefName::new(name).to_remote_symbol(RemoteName::new("origin")),
+                RemoteRef {
+                    target: RefTarget::normal(commit.id().clone()),
+                    state: RemoteRefState::Tracked,
+                },
+            );
+        }
+        tx.commit("add remote bookmarks").unwrap();
+
+        let changes = workspace.landed_changes(10).unwrap();
+        let change_ids = changes
+            .iter()
+            .map(|commit| commit.change_id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(change_ids, [landed]);
+    }
+
+    fn file_text(workspace: &JjWorkspace, change_id: &ChangeId, path: &str) -> Option<String> {
+        let repo = workspace.repo_loader.load_at_head().unwrap();
+        let commit = JjWorkspace::resolve_change_commit(&repo, change_id).unwrap();
+        let path = RepoPathBuf::from_internal_string(path).unwrap();
+        let value = commit.tree().unwrap().path_value(&path).unwrap();
+        futures::executor::block_on(JjWorkspace::read_file_value(&repo, &path, value))
+            .unwrap()
+            .map(|bytes| String::from_utf8(bytes).unwrap())
+    }
+
+    fn repo_paths(paths: &[&str]) -> Vec<RepoPathBuf> {
+        paths
+            .iter()
+            .map(|path| RepoPathBuf::from_internal_string(*path).unwrap())
+            .collect()
+    }
+
+    fn init_git_workspace(workspace_root: &Path) -> JjWorkspace {
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        workspace::Workspace::init_internal_git(&settings, workspace_root).unwrap();
+        JjWorkspace::load(workspace_root).unwrap()
+    }
+
+    #[test]
+    fn test_split_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("first.txt"), "first\n").unwrap();
+        std::fs::write(temp_dir.path().join("second.txt"), "second\n").unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "both files").unwrap();
+
+        assert!(
+            workspace
+                .split_change(&change_id, &repo_paths(&["first.txt", "second.txt"]))
+                .is_err()
+        );
+        let split_off = workspace
+            .split_change(&change_id, &repo_paths(&["first.txt"]))
+            .unwrap();
+
+        assert_eq!(file_text(&workspace, &change_id, "first.txt"), None);
+        assert_eq!(
+            file_text(&workspace, &change_id, "second.txt").as_deref(),
+            Some("second\n")
+        );
+        assert_eq!(
+            file_text(&workspace, &split_off, "first.txt").as_deref(),
+            Some("first\n")
+        );
+        let original = workspace.commit_for_change(&change_id).unwrap().unwrap();
+        let split_off_commit = workspace.commit_for_change(&split_off).unwrap().unwrap();
+        assert_eq!(original.description, "both files");
+        assert_eq!(split_off_commit.parent_ids, [original.commit_id]);
+        assert_eq!(workspace.current_change_id().unwrap(), Some(split_off));
+    }
+
+    #[test]
+    fn test_squash_change_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("base.txt"), "base\n").unwrap();
+        let parent = workspace.current_change_id().unwrap().unwrap();
+        let child = workspace.new_change(&parent, "child").unwrap();
+        std::fs::write(temp_dir.path().join("first.txt"), "first\n").unwrap();
+        std::fs::write(temp_dir.path().join("second.txt"), "second\n").unwrap();
+
+        workspace
+            .squash_change(&child, Some(&repo_paths(&["first.txt"])))
+            .unwrap();
+
+        assert_eq!(
+            file_text(&workspace, &parent, "first.txt").as_deref(),
+            Some("first\n")
+        );
+        assert_eq!(file_text(&workspace, &parent, "second.txt"), None);
+        assert_eq!(
+            file_text(&workspace, &child, "second.txt").as_deref(),
+            Some("second\n")
+        );
+        let parent_commit = workspace.commit_for_change(&parent).unwrap().unwrap();
+        let child_commit = workspace.commit_for_change(&child).unwrap().unwrap();
+        assert_eq!(child_commit.parent_ids, [parent_commit.commit_id]);
+        assert_eq!(child_commit.description, "child");
+        assert_eq!(workspace.current_change_id().unwrap(), Some(child));
+    }
+
+    #[test]
+    fn test_duplicate_change() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "content\n").unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "original").unwrap();
+
+        let duplicate = workspace.duplicate_change(&change_id).unwrap();
+
+        assert_ne!(duplicate, change_id);
+        assert_eq!(
+            file_text(&workspace, &duplicate, "file.txt").as_deref(),
+            Some("content\n")
+        );
+        let original_commit = workspace.commit_for_change(&change_id).unwrap().unwrap();
+        let duplicate_commit = workspace.commit_for_change(&duplicate).unwrap().unwrap();
+        assert_eq!(duplicate_commit.parent_ids, original_commit.parent_ids);
+        assert_eq!(duplicate_commit.description, "original");
+        assert_eq!(workspace.current_change_id().unwrap(), Some(change_id));
+    }
+
+    #[test]
+    fn test_park_and_unpark_working_copy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("base.txt"), "base\n").unwrap();
+        let base = workspace.current_change_id().unwrap().unwrap();
+        workspace.new_change(&base, "").unwrap();
+        assert!(workspace.park_working_copy().is_err());
+        std::fs::write(temp_dir.path().join("draft.txt"), "draft\n").unwrap();
+
+        let parked = workspace.park_working_copy().unwrap();
+        let current = workspace.current_change_id().unwrap().unwrap();
+        assert_ne!(current, parked);
+        assert_eq!(file_text(&workspace, &current, "draft.txt"), None);
+        assert!(!temp_dir.path().join("draft.txt").exists());
+        assert_eq!(
+            file_text(&workspace, &parked, "draft.txt").as_deref(),
+            Some("draft\n")
+        );
+        let base_commit = workspace.commit_for_change(&base).unwrap().unwrap();
+        let parked_commit = workspace.commit_for_change(&parked).unwrap().unwrap();
+        let current_commit = workspace.commit_for_change(&current).unwrap().unwrap();
+        assert!(parked_commit.description.starts_with("WIP: parked "));
+        assert_eq!(parked_commit.parent_ids, [base_commit.commit_id.clone()]);
+        assert_eq!(current_commit.parent_ids, [base_commit.commit_id]);
+
+        workspace.unpark(&parked).unwrap();
+        assert_eq!(
+            workspace.current_change_id().unwrap(),
+            Some(current.clone())
+        );
+        assert_eq!(
+            file_text(&workspace, &current, "draft.txt").as_deref(),
+            Some("draft\n")
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("draft.txt")).unwrap(),
+            "draft\n"
+        );
+    }
+
+    #[test]
+    fn test_set_bookmark() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "content\n").unwrap();
+        let first = workspace.current_change_id().unwrap().unwrap();
+        let second = workspace.new_change(&first, "").unwrap();
+        let labels = |change_id: &ChangeId| {
+            let commit = workspace.commit_for_change(change_id).unwrap().unwrap();
+            commit.refs.iter().map(CommitRef::label).collect::<Vec<_>>()
+        };
+
+        assert!(workspace.set_bookmark("two words", &first).is_err());
+        assert!(workspace.set_bookmark(" ", &first).is_err());
+
+        workspace.set_bookmark("main", &second).unwrap();
+        assert_eq!(labels(&second), ["main"]);
+        // Moving a bookmark backwards is allowed, like `jj bookmark set --allow-backwards`.
+        workspace.set_bookmark(" main ", &first).unwrap();
+        assert_eq!(labels(&first), ["main"]);
+        assert!(labels(&second).is_empty());
+    }
+
+    #[test]
+    fn test_git_push_and_fetch() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote_repo = git2::Repository::init_bare(remote_dir.path()).unwrap();
+        let remote_url = remote_dir.path().to_str().unwrap();
+
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = init_git_workspace(source_dir.path());
+        source.add_git_remote("origin", remote_url).unwrap();
+        assert_eq!(
+            source
+                .git_remotes()
+                .unwrap()
+                .iter()
+                .map(|remote| remote.name.as_str())
+                .collect::<Vec<_>>(),
+            ["origin"]
+        );
+        std::fs::write(source_dir.path().join("file.txt"), "content\n").unwrap();
+        let change_id = source.current_change_id().unwrap().unwrap();
+        source.set_bookmark("main", &change_id).unwrap();
+        // Commits without a description aren't pushed.
+        assert!(source.git_push("main", "origin").is_err());
+        source.rename_change(&change_id, "add file").unwrap();
+        source.set_bookmark("main", &change_id).unwrap();
+
+        let summary = source.git_push("main", "origin").unwrap();
+        assert!(summary.pushed);
+        let commit_id = source
+            .commit_for_change(&change_id)
+            .unwrap()
+            .unwrap()
+            .commit_id;
+        let remote_main = remote_repo.find_reference("refs/heads/main").unwrap();
+        assert_eq!(remote_main.target().unwrap().to_string(), commit_id.hex());
+        assert!(!source.git_push("main", "origin").unwrap().pushed);
+
+        let destination_dir = tempfile::tempdir().unwrap();
+        let destination = init_git_workspace(destination_dir.path());
+        destination.add_git_remote("origin", remote_url).unwrap();
+        let summary = destination.git_fetch("origin", &mut |_| {}).unwrap();
+        assert_eq!(summary.updated_bookmarks, ["main@origin"]);
+        let fetched = destination.evaluate_revset("main@origin", 1).unwrap();
+        assert_eq!(
+            fetched
+                .iter()
+                .map(|commit| (commit.commit_id.clone(), commit.description.as_str()))
+                .collect::<Vec<_>>(),
+            [(commit_id, "add file")]
+        );
+        let summary = destination.git_fetch("origin", &mut |_| {}).unwrap();
+        assert!(summary.updated_bookmarks.is_empty());
+
+        let tip = source.new_change(&change_id, "tip").unwrap();
+        source.set_bookmark("main", &tip).unwrap();
+        assert!(source.git_push("main", "origin").unwrap().pushed);
+        let parents = source.evaluate_revset("main@origin-", 1).unwrap();
+        assert_eq!(parents.len(), 1);
+        assert!(parents[0].is_pushed);
+    }
+
+    #[test]
+    fn test_git_push_change_keeps_bookmark() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(remote_dir.path()).unwrap();
+        let workspace_dir = tempfile::tempdir().unwrap();
+        let workspace = init_git_workspace(workspace_dir.path());
+        workspace
+            .add_git_remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+        std::fs::write(workspace_dir.path().join("file.txt"), "content\n").unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.rename_change(&change_id, "add file").unwrap();
+
+        let summary = workspace.git_push_change(&change_id, "origin").unwrap();
+        assert!(summary.pushed);
+        workspace.delete_bookmark(&summary.bookmark).unwrap();
+
+        // The remote already matches, but the local bookmark is still recreated.
+        let summary = workspace.git_push_change(&change_id, "origin").unwrap();
+        assert!(!summary.pushed);
+        assert!(
+            workspace
+                .bookmarks()
+                .unwrap()
+                .iter()
+                .any(|bookmark| bookmark.name == summary.bookmark && bookmark.remote.is_none())
+        );
+    }
+
+    #[test]
+    fn test_squashed_description() {
+        assert_eq!(squashed_description("", ""), "");
+        assert_eq!(squashed_description("parent\n", "  "), "parent");
+        assert_eq!(squashed_description("", "child"), "child");
+        assert_eq!(
+            squashed_description("parent\n", "child\n"),
+            "parent\n\nchild"
+        );
+    }
+
+    /// Interleaves Zed's operations with commits made by a concurrently running `jj` process
+    /// that only occasionally catches up with the repository, then checks that every change
+    /// survives the op-log merges exactly once.
+    #[gpui::test(iterations = 20)]
+    fn test_random_concurrent_operations(mut rng: StdRng) {
+        let operations = env::var("OPERATIONS")
+            .map(|i| i.parse().expect("invalid `OPERATIONS` variable"))
+            .unwrap_or(10);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let jj_workspace = JjWorkspace::init(temp_dir.path()).unwrap();
+        let mut external_repo = jj_workspace.repo_loader.load_at_head().unwrap();
+        let mut expected_descriptions = Vec::new();
+
+        for index in 0..operations {
+            match rng.random_range(0..100) {
+                0..40 => {
+                    let commits = jj_workspace.recent_commits(LOG_LIMIT).unwrap();
+                    let parent = commits.choose(&mut rng).unwrap();
+                    let description = format!("zed {index}");
+                    log::info!(
+                        "zed: new change {description:?} on {}",
+                        short_change_hash(&parent.change_id)
+                    );
+                    jj_workspace
+                        .new_change(&parent.change_id, &description)
+                        .unwrap();
+                    expected_descriptions.push(description);
+                }
+                40..55 if !expected_descriptions.is_empty() => {
+                    let description = expected_descriptions
+                        .swap_remove(rng.random_range(0..expected_descriptions.len()));
+                    let commit = jj_workspace
+                        .recent_commits(LOG_LIMIT)
+                        .unwrap()
+                        .into_iter()
+                        .find(|commit| commit.description == description)
+                        .unwrap();
+                    log::info!("zed: abandon {description:?}");
+                    jj_workspace.abandon_change(&commit.change_id).unwrap();
+                }
+                55..70 => {
+                    log::info!("external: reload at head");
+                    external_repo = jj_workspace.repo_loader.load_at_head().unwrap();
+                }
+                _ => {
+                    let mut heads = external_repo.view().heads().iter().collect::<Vec<_>>();
+                    heads.sort();
+                    let parent = external_repo
+                        .store()
+                        .get_commit(heads.choose(&mut rng).unwrap())
+                        .unwrap();
+                    let description = format!("external {index}");
+                    log::info!(
+                        "external: new change {description:?} on {}",
+                        short_change_hash(parent.change_id())
+                    );
+                    let mut tx = external_repo.start_transaction();
+                    tx.repo_mut()
+                        .new_commit(vec![parent.id().clone()], parent.tree_id().clone())
+                        .set_description(&description)
+                        .write()
+                        .unwrap();
+                    external_repo = tx.commit(format!("new change {description}")).unwrap();
+                    expected_descriptions.push(description);
+                }
+            }
+        }
+
+        let commits = jj_workspace.recent_commits(LOG_LIMIT).unwrap();
+        let mut descriptions = commits
+            .iter()
+            .map(|commit| commit.description.clone())
+            .filter(|description| !description.is_empty())
+            .collect::<Vec<_>>();
+        descriptions.sort();
+        expected_descriptions.sort();
+        assert_eq!(descriptions, expected_descriptions);
+
+        let reloaded_commit_ids = JjWorkspace::load(temp_dir.path())
+            .unwrap()
+            .recent_commits(LOG_LIMIT)
+            .unwrap()
+            .into_iter()
+            .map(|commit| commit.commit_id)
+            .collect::<Vec<_>>();
+        let commit_ids = commits
+            .into_iter()
+            .map(|commit| commit.commit_id)
+            .collect::<Vec<_>>();
+        assert_eq!(reloaded_commit_ids, commit_ids);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::JjPanel;
+use editor::Editor;
+use feature_flags::{FeatureFlagAppExt as _, JjUiFeatureFlag};
+use gpui::{Context, Entity, EventEmitter, Subscription, Task, WeakEntity, Window};
+use jj::short_change_hash;
+use language::Buffer;
+use log::warn;
+use project::{JjBufferChange, Project, project_settings::ProjectSettings};
+use settings::Settings as _;
+use ui::{ButtonStyle, Tooltip, prelude::*};
+use workspace::{ItemHandle, ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView, Workspace};
+
+/// Toolbar chip showing which change the active buffer's diff base belongs to.
+pub struct JjChangeIndicator {
+    workspace: WeakEntity<Workspace>,
+    project: Entity<Project>,
+    buffer: Option<Entity<Buffer>>,
+    change: Option<JjBufferChange>,
+    _refresh_task: Option<Task<()>>,
+    _store_subscription: Option<Subscription>,
+}
+
+impl JjChangeIndicator {
+    pub fn new(workspace: &Workspace, cx: &mut Context<Self>) -> Self {
+        let project = workspace.project().clone();
+        let store_subscription = project
+            .read(cx)
+            .jj_store()
+            .cloned()
+            .map(|store| cx.observe(&store, |indicator, _, cx| indicator.refresh(cx)));
+        Self {
+            workspace: workspace.weak_handle(),
+            project,
+            buffer: None,
+            change: None,
+            _refresh_task: None,
+            _store_subscription: store_subscription,
+        }
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        let Some(buffer) = self.buffer.clone() else {
+            self.change = None;
+            self._refresh_task = None;
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) = store.read(cx).change_for_buffer(&buffer, cx) else {
+            self.change = None;
+            cx.notify();
+            return;
+        };
+        self._refresh_task = Some(cx.spawn(async move |indicator, cx| {
+            let change = match task.await {
+                Ok(change) => change,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to load change for buffer: {err:?}");
+                    None
+                }
+            };
+            indicator
+                .update(cx, |indicator, cx| {
+                    indicator.change = change;
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    fn reveal_in_panel(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(change) = self.change.clone() else {
+            return;
+        };
+        self.workspace
+            .update(cx, |workspace, cx| {
+                if let Some(panel) = workspace.focus_panel::<JjPanel>(window, cx) {
+                    panel.update(cx, |panel, cx| {
+                        panel.reveal_change(
+                            change.repository_id,
+                            change.commit.change_id.clone(),
+                            window,
+                            cx,
+                        );
+                    });
+                }
+            })
+            .ok();
+    }
+}
+
+impl EventEmitter<ToolbarItemEvent> for JjChangeIndicator {}
+
+impl ToolbarItemView for JjChangeIndicator {
+    fn set_active_pane_item(
+        &mut self,
+        active_pane_item: Option<&dyn ItemHandle>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> ToolbarItemLocation {
+        self.buffer = None;
+        self.change = None;
+        if cx.has_flag::<JjUiFeatureFlag>()
+            && ProjectSettings::get_global(cx).jj.show_change_indicator
+            && let Some(editor) = active_pane_item.and_then(|item| item.act_as::<Editor>(cx))
+        {
+            self.buffer = editor.read(cx).buffer().read(cx).as_singleton();
+        }
+
+        let is_tracked = self.buffer.as_ref().is_some_and(|buffer| {
+            self.project
+                .read(cx)
+                .jj_store()
+                .is_some_and(|store| store.read(cx).is_buffer_tracked(buffer, cx))
+        });
+        if !is_tracked {
+            self.buffer = None;
+        }
+        self.refresh(cx);
+
+        if is_tracked {
+            ToolbarItemLocation::PrimaryLeft
+        } else {
+            ToolbarItemLocation::Hidden
+        }
+    }
+}
+
+impl Render for JjChangeIndicator {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some(change) = self.change.as_ref() else {
+            return div().into_any_element();
+        };
+        let summary = change
+            .commit
+            .description
+            .lines()
+            .next()
+            .filter(|line| !line.trim().is_empty())
+            .unwrap_or("(no description set)");
+        let label = format!(
+            "in change {}: {summary}",
+            short_change_hash(&change.commit.change_id)
+        );
+
+        Button::new("jj-change-indicator", label)
+            .style(ButtonStyle::Subtle)
+            .label_size(LabelSize::Small)
+            .icon(IconName::GitBranch)
+            .icon_size(IconSize::Small)
+            .icon_color(Color::Muted)
+            .icon_position(IconPosition::Start)
+            .tooltip(Tooltip::text("Show change in the Jujutsu panel"))
+            .on_click(cx.listener(|indicator, _, window, cx| {
+                indicator.reveal_in_panel(window, cx);
+            }))
+            .into_any_element()
+    }
+}
@@ -0,0 +1,172 @@
+use jj::{ChangeId, CommitId, short_change_hash, short_commit_hash};
+use project::JjCommitSummary;
+use ui::{Tooltip, prelude::*};
+
+/// The lines under a commit's title in the jj log: the user's `templates.log` output when the
+/// panel could render it, or else the commit and change ids, the author and the nearest ref.
+#[derive(IntoElement, RegisterComponent)]
+pub struct CommitDetail {
+    commit: JjCommitSummary,
+    rendered: Option<SharedString>,
+}
+
+impl CommitDetail {
+    pub fn new(commit: JjCommitSummary) -> Self {
+        Self {
+            commit,
+            rendered: None,
+        }
+    }
+
+    pub fn rendered(mut self, rendered: Option<SharedString>) -> Self {
+        self.rendered = rendered;
+        self
+    }
+}
+
+impl RenderOnce for CommitDetail {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        if let Some(rendered) = self.rendered {
+            return v_flex()
+                .gap(rems(0.1))
+                .children(rendered.lines().map(|line| {
+                    Label::new(line.to_string())
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted)
+                        .buffer_font(cx)
+                }))
+                .into_any_element();
+        }
+        let commit = self.commit;
+        h_flex()
+            .gap(rems(0.5))
+            .child(
+                Label::new(format!("commit {}", short_commit_hash(&commit.commit_id)))
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(format!("change {}", short_change_hash(&commit.change_id)))
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            )
+            .child(
+                div()
+                    .id(SharedString::from(format!(
+                        "jj-author-{}",
+                        short_commit_hash(&commit.commit_id)
+                    )))
+                    .child(
+                        Label::new(commit.author.clone())
+                            .size(LabelSize::XSmall)
+                            .color(Color::Placeholder),
+                    )
+                    .tooltip(Tooltip::text(identity_summary(&commit))),
+            )
+            .when(is_committed_by_someone_else(&commit), |row| {
+                row.child(
+                    Label::new(format!("committed by {}", commit.committer))
+                        .size(LabelSize::XSmall)
+                        .color(Color::Placeholder),
+                )
+            })
+            .when_some(commit.nearest_ref, |row, name| {
+                row.child(
+                    Label::new(format!("in: {name}"))
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
+            .into_any_element()
+    }
+}
+
+/// Whether the commit was last written by someone other than its author, as when another
+/// person rebased or amended it.
+fn is_committed_by_someone_else(commit: &JjCommitSummary) -> bool {
+    if commit.author_email.is_empty() || commit.committer_email.is_empty() {
+        commit.committer != commit.author
+    } else {
+        commit.committer_email != commit.author_email
+    }
+}
+
+fn identity_summary(commit: &JjCommitSummary) -> String {
+    let identity = |name: &str, email: &str| {
+        if email.is_empty() {
+            name.to_string()
+        } else {
+            format!("{name} <{email}>")
+        }
+    };
+    format!(
+        "Author: {}\nCommitter: {}",
+        identity(&commit.author, &commit.author_email),
+        identity(&commit.committer, &commit.committer_email)
+    )
+}
+
+/// A commit to show in component previews.
+pub(crate) fn preview_commit(description: &str, is_current: bool) -> JjCommitSummary {
+    JjCommitSummary {
+        commit_id: CommitId::new(vec![0x4e, 0x2a, 0x91, 0x07, 0xc3, 0x5d]),
+        parent_ids: vec![CommitId::new(vec![0x1b, 0x80, 0x6f, 0x3c, 0x22, 0xe4])],
+        change_id: ChangeId::new(vec![0x9d, 0x31, 0x7a, 0xc0, 0x55, 0x18]),
+        description: description.to_string().into(),
+        author: "Jane Doe".into(),
+        author_email: "jane@example.com".into(),
+        author_timestamp: 1_759_990_000_000,
+        committer: "Jane Doe".into(),
+        committer_email: "jane@example.com".into(),
+        timestamp: 1_760_000_000_000,
+        is_signed: false,
+        is_pushed: true,
+        nearest_ref: Some("main".into()),
+        is_current,
+        graph_edges: Vec::new(),
+        is_divergent: false,
+        is_hidden: false,
+        is_empty: false,
+        has_conflict: false,
+        refs: Vec::new(),
+    }
+}
+
+// View this component preview using `workspace: open component-preview`
+impl Component for CommitDetail {
+    fn scope() -> ComponentScope {
+        ComponentScope::VersionControl
+    }
+
+    fn preview(_window: &mut Window, _cx: &mut App) -> Option<AnyElement> {
+        let commit = preview_commit("Fix the flaky watcher test", false);
+        Some(
+            v_flex()
+                .gap_6()
+                .child(example_group(vec![
+                    single_example(
+                        "Ids and author",
+                        CommitDetail::new(commit.clone()).into_any_element(),
+                    ),
+                    single_example(
+                        "Committed by someone else",
+                        CommitDetail::new(JjCommitSummary {
+                            committer: "Sam Roe".into(),
+                            committer_email: "sam@example.com".into(),
+                            ..commit.clone()
+                        })
+                        .into_any_element(),
+                    ),
+                    single_example(
+                        "templates.log",
+                        CommitDetail::new(commit)
+                            .rendered(Some(
+                                "nkywpvts jane@example.com 2 hours ago\n4e2a9107".into(),
+                            ))
+                            .into_any_element(),
+                    ),
+                ]))
+                .into_any_element(),
+        )
+    }
+}
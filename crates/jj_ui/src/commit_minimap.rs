@@ -0,0 +1,111 @@
+use gpui::{Pixels, ScrollHandle};
+use jj::CommitId;
+use project::JjCommitSummary;
+use ui::prelude::*;
+
+const ROW_HEIGHT: Pixels = px(3.);
+const LANE_WIDTH: Pixels = px(4.);
+
+/// Compact overview of the commit log's graph shape, with the rows currently scrolled into
+/// view highlighted. Clicking a row scrolls the log to it.
+#[derive(IntoElement)]
+pub(crate) struct CommitMinimap {
+    lanes: Vec<usize>,
+    current_row: Option<usize>,
+    scroll_handle: ScrollHandle,
+}
+
+impl CommitMinimap {
+    pub(crate) fn new(commits: &[JjCommitSummary], scroll_handle: ScrollHandle) -> Self {
+        Self {
+            lanes: graph_lanes(
+                commits
+                    .iter()
+                    .map(|commit| (&commit.commit_id, commit.parent_ids.as_slice())),
+            ),
+            current_row: commits.iter().position(|commit| commit.is_current),
+            scroll_handle,
+        }
+    }
+}
+
+impl RenderOnce for CommitMinimap {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let lane_count = self.lanes.iter().max().map_or(1, |lane| lane + 1);
+        let visible_rows = self.scroll_handle.top_item()..=self.scroll_handle.bottom_item();
+        let colors = cx.theme().colors();
+        let viewport_color = colors.element_selected;
+        let dot_color = colors.text_muted;
+        let current_color = colors.text_accent;
+
+        v_flex()
+            .flex_none()
+            .w(LANE_WIDTH * lane_count as f32 + px(2.))
+            .py(px(2.))
+            .rounded(px(2.))
+            .bg(colors.surface_background)
+            .children(self.lanes.into_iter().enumerate().map(|(row, lane)| {
+                let scroll_handle = self.scroll_handle.clone();
+                let color = if self.current_row == Some(row) {
+                    current_color
+                } else {
+                    dot_color
+                };
+                div()
+                    .id(row)
+                    .relative()
+                    .w_full()
+                    .h(ROW_HEIGHT)
+                    .cursor_pointer()
+                    .when(visible_rows.contains(&row), |this| this.bg(viewport_color))
+                    .child(
+                        div()
+                            .absolute()
+                            .top_0()
+                            .left(LANE_WIDTH * lane as f32 + px(1.))
+                            .size(px(2.))
+                            .bg(color),
+                    )
+                    .on_click(move |_, window, _| {
+                        scroll_handle.scroll_to_item(row);
+                        window.refresh();
+                    })
+            }))
+    }
+}
+
+/// Assigns each row of a log (children before parents) the column its node is drawn in,
+/// reusing a column once the commit it was waiting for has been placed.
+fn graph_lanes<'a>(rows: impl IntoIterator<Item = (&'a CommitId, &'a [CommitId])>) -> Vec<usize> {
+    let mut expected: Vec<Option<&CommitId>> = Vec::new();
+    let mut lanes = Vec::new();
+    for (commit_id, parent_ids) in rows {
+        let lane = expected
+            .iter()
+            .position(|id| *id == Some(commit_id))
+            .or_else(|| expected.iter().position(Option::is_none))
+            .unwrap_or_else(|| {
+                expected.push(None);
+                expected.len() - 1
+            });
+        for id in &mut expected {
+            if *id == Some(commit_id) {
+                *id = None;
+            }
+        }
+
+        let mut parent_ids = parent_ids.iter();
+        expected[lane] = parent_ids.next();
+        for parent_id in parent_ids {
+            if expected.contains(&Some(parent_id)) {
+                continue;
+            }
+            match expected.iter().position(Option::is_none) {
+                Some(free_lane) => expected[free_lane] = Some(parent_id),
+                None => expected.push(Some(parent_id)),
+            }
+        }
+        lanes.push(lane);
+    }
+    lanes
+}
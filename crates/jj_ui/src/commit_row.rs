@@ -0,0 +1,411 @@
+use crate::commit_detail::{CommitDetail, preview_commit};
+use git::CommitStatus;
+use gpui::{AnyElement, MouseButton, MouseDownEvent, MouseUpEvent};
+use jj::{CommitRef, checklist_progress, short_commit_hash};
+use project::JjCommitSummary;
+use smallvec::SmallVec;
+use ui::{Indicator, Tooltip, prelude::*};
+
+/// A commit in the jj log: its title with badges, its [`CommitDetail`], and whatever the panel
+/// adds below, like the merge parents or the checklist of the selected change.
+#[derive(IntoElement, RegisterComponent)]
+pub struct CommitRow {
+    commit: JjCommitSummary,
+    timestamp: SharedString,
+    rendered: Option<SharedString>,
+    ci_status: Option<CommitStatus>,
+    is_stale: bool,
+    color_by_author: bool,
+    selected: bool,
+    is_merge_parent: bool,
+    disabled: bool,
+    children: SmallVec<[AnyElement; 2]>,
+    on_click: Option<Box<dyn Fn(&MouseUpEvent, &mut Window, &mut App) + 'static>>,
+    on_secondary_mouse_down: Option<Box<dyn Fn(&MouseDownEvent, &mut Window, &mut App) + 'static>>,
+}
+
+impl CommitRow {
+    pub fn new(commit: JjCommitSummary, timestamp: impl Into<SharedString>) -> Self {
+        Self {
+            commit,
+            timestamp: timestamp.into(),
+            rendered: None,
+            ci_status: None,
+            is_stale: false,
+            color_by_author: false,
+            selected: false,
+            is_merge_parent: false,
+            disabled: false,
+            children: SmallVec::new(),
+            on_click: None,
+            on_secondary_mouse_down: None,
+        }
+    }
+
+    /// The commit's `templates.log` output, shown instead of its ids and author.
+    pub fn rendered(mut self, rendered: Option<SharedString>) -> Self {
+        self.rendered = rendered;
+        self
+    }
+
+    pub fn ci_status(mut self, ci_status: Option<CommitStatus>) -> Self {
+        self.ci_status = ci_status;
+        self
+    }
+
+    /// Marks a commit that was rewritten since the log was loaded.
+    pub fn stale(mut self, is_stale: bool) -> Self {
+        self.is_stale = is_stale;
+        self
+    }
+
+    pub fn color_by_author(mut self, color_by_author: bool) -> Self {
+        self.color_by_author = color_by_author;
+        self
+    }
+
+    pub fn merge_parent(mut self, is_merge_parent: bool) -> Self {
+        self.is_merge_parent = is_merge_parent;
+        self
+    }
+
+    pub fn on_click(
+        mut self,
+        handler: impl Fn(&MouseUpEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_click = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_secondary_mouse_down(
+        mut self,
+        handler: impl Fn(&MouseDownEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_secondary_mouse_down = Some(Box::new(handler));
+        self
+    }
+
+    fn render_title(&self) -> Div {
+        let commit = &self.commit;
+        h_flex()
+            .gap(rems(0.25))
+            .items_center()
+            .when(commit.is_current, |title| {
+                title.child(Label::new("•").color(Color::Accent).size(LabelSize::Small))
+            })
+            .child(Label::new(commit.description.clone()).size(LabelSize::Default))
+            .children(commit.refs.iter().map(|commit_ref| {
+                let color = if commit_ref.is_conflicted {
+                    Color::Error
+                } else if commit_ref.is_tag {
+                    Color::Info
+                } else if commit_ref.remote.is_some() {
+                    Color::Muted
+                } else {
+                    Color::Accent
+                };
+                Label::new(commit_ref.label())
+                    .size(LabelSize::XSmall)
+                    .color(color)
+            }))
+            .when(commit.is_empty, |title| {
+                title.child(
+                    Label::new("(empty)")
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
+            .when(commit.has_conflict, |title| {
+                title.child(
+                    Label::new("conflict")
+                        .size(LabelSize::XSmall)
+                        .color(Color::Error),
+                )
+            })
+            .when_some(self.ci_status, |title, status| {
+                let (color, tooltip) = match status {
+                    CommitStatus::Success => (Color::Success, "CI passed"),
+                    CommitStatus::Pending => (Color::Warning, "CI running"),
+                    CommitStatus::Failure => (Color::Error, "CI failed"),
+                };
+                title.child(
+                    div()
+                        .id(SharedString::from(format!(
+                            "jj-ci-{}",
+                            short_commit_hash(&commit.commit_id)
+                        )))
+                        .child(Indicator::dot().color(color))
+                        .tooltip(Tooltip::text(tooltip)),
+                )
+            })
+            .when_some(
+                checklist_progress(&commit.description),
+                |title, (checked, total)| {
+                    title.child(
+                        Label::new(format!("{checked}/{total}"))
+                            .size(LabelSize::XSmall)
+                            .color(if checked == total {
+                                Color::Success
+                            } else {
+                                Color::Muted
+                            }),
+                    )
+                },
+            )
+            .when(commit.is_signed, |title| {
+                title.child(
+                    Label::new("signed")
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
+            .when(commit.is_divergent, |title| {
+                title.child(
+                    div()
+                        .id(SharedString::from(format!(
+                            "jj-divergent-{}",
+                            short_commit_hash(&commit.commit_id)
+                        )))
+                        .child(
+                            Label::new("divergent")
+                                .size(LabelSize::XSmall)
+                                .color(Color::Warning),
+                        )
+                        .tooltip(Tooltip::text("Other visible commits share this change id")),
+                )
+            })
+            .when(commit.is_hidden, |title| {
+                title.child(
+                    Label::new("hidden")
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
+            .when(self.is_stale, |title| {
+                title.child(
+                    Label::new("rewritten")
+                        .size(LabelSize::XSmall)
+                        .color(Color::Warning),
+                )
+            })
+    }
+}
+
+impl ParentElement for CommitRow {
+    fn extend(&mut self, elements: impl IntoIterator<Item = AnyElement>) {
+        self.children.extend(elements)
+    }
+}
+
+impl Toggleable for CommitRow {
+    fn toggle_state(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+}
+
+impl Disableable for CommitRow {
+    fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+}
+
+impl RenderOnce for CommitRow {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        let colors = cx.theme().colors();
+        let body = v_flex()
+            .gap(rems(0.1))
+            .child(
+                h_flex().justify_between().child(self.render_title()).child(
+                    Label::new(self.timestamp.clone())
+                        .color(Color::Muted)
+                        .size(LabelSize::XSmall),
+                ),
+            )
+            .child(CommitDetail::new(self.commit.clone()).rendered(self.rendered))
+            .children(self.children);
+
+        let mut row = div().rounded(px(4.0)).p(px(4.0)).child(body);
+
+        if self.color_by_author {
+            let identity = if self.commit.author_email.is_empty() {
+                &self.commit.author
+            } else {
+                &self.commit.author_email
+            };
+            let author_color = cx
+                .theme()
+                .players()
+                .color_for_participant(author_color_index(identity));
+            row = row.bg(author_color.cursor.opacity(0.08));
+        }
+
+        if self.commit.is_current {
+            row = row
+                .border_1()
+                .border_color(colors.border_focused)
+                .bg(colors.surface_background);
+        }
+
+        // The working-copy change is usually empty right after `jj new`, and stays prominent.
+        if self.commit.is_empty && !self.commit.is_current {
+            row = row.opacity(0.7);
+        }
+
+        if self.is_stale {
+            row = row.opacity(0.6);
+        }
+
+        if self.selected {
+            row = row.bg(colors.element_selected);
+        }
+
+        if self.is_merge_parent {
+            row = row.border_1().border_color(colors.border_selected);
+        }
+
+        if self.disabled {
+            return row.opacity(0.75);
+        }
+
+        let hover_background = colors.surface_background;
+        row = row
+            .cursor_pointer()
+            .hover(|row| row.bg(hover_background))
+            .on_mouse_down(MouseButton::Left, |_, window, _| {
+                window.prevent_default();
+            });
+        if let Some(on_click) = self.on_click {
+            row = row.on_mouse_up(MouseButton::Left, on_click);
+        }
+        if let Some(on_secondary_mouse_down) = self.on_secondary_mouse_down {
+            row = row.on_mouse_down(MouseButton::Right, on_secondary_mouse_down);
+        }
+        row
+    }
+}
+
+/// FNV-1a, so an author keeps their color across sessions and releases.
+fn author_color_index(identity: &str) -> u32 {
+    identity.bytes().fold(0x811c9dc5, |hash: u32, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    })
+}
+
+// View this component preview using `workspace: open component-preview`
+impl Component for CommitRow {
+    fn scope() -> ComponentScope {
+        ComponentScope::VersionControl
+    }
+
+    fn preview(_window: &mut Window, _cx: &mut App) -> Option<AnyElement> {
+        let timestamp = "2025-10-09T08:53:20Z";
+        let commit = preview_commit("Fix the flaky watcher test", false);
+        let current = preview_commit("Add a status API\n\n- [x] jj crate\n- [ ] panel", true);
+        Some(
+            v_flex()
+                .gap_6()
+                .child(example_group(vec![
+                    single_example(
+                        "Default",
+                        CommitRow::new(commit.clone(), timestamp).into_any_element(),
+                    ),
+                    single_example(
+                        "Current change",
+                        CommitRow::new(current, timestamp).into_any_element(),
+                    ),
+                    single_example(
+                        "Selected",
+                        CommitRow::new(commit.clone(), timestamp)
+                            .toggle_state(true)
+                            .into_any_element(),
+                    ),
+                ]))
+                .child(example_group(vec![
+                    single_example(
+                        "CI failed",
+                        CommitRow::new(commit.clone(), timestamp)
+                            .ci_status(Some(CommitStatus::Failure))
+                            .into_any_element(),
+                    ),
+                    single_example(
+                        "Empty",
+                        CommitRow::new(
+                            JjCommitSummary {
+                                is_empty: true,
+                                ..commit.clone()
+                            },
+                            timestamp,
+                        )
+                        .into_any_element(),
+                    ),
+                    single_example(
+                        "With bookmarks and a tag",
+                        CommitRow::new(
+                            JjCommitSummary {
+                                refs: vec![
+                                    CommitRef {
+                                        name: "main".into(),
+                                        remote: None,
+                                        is_tag: false,
+                                        is_conflicted: false,
+                                    },
+                                    CommitRef {
+                                        name: "main".into(),
+                                        remote: Some("origin".into()),
+                                        is_tag: false,
+                                        is_conflicted: false,
+                                    },
+                                    CommitRef {
+                                        name: "v0.3.0".into(),
+                                        remote: None,
+                                        is_tag: true,
+                                        is_conflicted: false,
+                                    },
+                                ],
+                                ..commit.clone()
+                            },
+                            timestamp,
+                        )
+                        .into_any_element(),
+                    ),
+                    single_example(
+                        "Conflicted",
+                        CommitRow::new(
+                            JjCommitSummary {
+                                has_conflict: true,
+                                ..commit.clone()
+                            },
+                            timestamp,
+                        )
+                        .into_any_element(),
+                    ),
+                    single_example(
+                        "Rewritten",
+                        CommitRow::new(commit.clone(), timestamp)
+                            .stale(true)
+                            .into_any_element(),
+                    ),
+                    single_example(
+                        "Divergent",
+                        CommitRow::new(
+                            JjCommitSummary {
+                                is_divergent: true,
+                                ..commit.clone()
+                            },
+                            timestamp,
+                        )
+                        .into_any_element(),
+                    ),
+                    single_example(
+                        "Colored by author",
+                        CommitRow::new(commit, timestamp)
+                            .color_by_author(true)
+                            .into_any_element(),
+                    ),
+                ]))
+                .into_any_element(),
+        )
+    }
+}
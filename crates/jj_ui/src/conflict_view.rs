@@ -0,0 +1,112 @@
+use anyhow::{Context as _, Result};
+use editor::{Editor, MultiBuffer};
+use gpui::{AppContext as _, Context, Entity, Window};
+use language::Language;
+use log::warn;
+use std::sync::Arc;
+use workspace::{SplitDirection, Toast, Workspace, notifications::NotificationId};
+
+struct ConflictViewToast;
+
+/// Lays out the active file's conflict like a merge tool: its left side, base and right side in
+/// read-only panes above the file, which stays the result to edit. Once the file has no
+/// conflict markers left, jj records it as resolved on its next snapshot.
+pub(crate) fn open_conflict_view(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Result<()> {
+    let editor = workspace
+        .active_item_as::<Editor>(cx)
+        .context("no active editor")?;
+    let buffer = editor
+        .read(cx)
+        .buffer()
+        .read(cx)
+        .as_singleton()
+        .context("active editor has no single buffer")?;
+    let store = workspace
+        .project()
+        .read(cx)
+        .jj_store()
+        .cloned()
+        .context("project has no jj store")?;
+    let task = store
+        .read(cx)
+        .conflict_sides(&buffer, cx)
+        .context("file is not in a jj repository")?;
+    let file_name = buffer
+        .read(cx)
+        .file()
+        .map(|file| file.file_name(cx).to_string())
+        .context("buffer has no file")?;
+    let language = buffer.read(cx).language().cloned();
+    let result_pane = workspace.active_pane().clone();
+    cx.spawn_in(window, async move |workspace, cx| {
+        let sides = match task.await {
+            Ok(sides) => sides,
+            Err(err) => {
+                warn!(target: "jj_ui", "failed to read the sides of the conflict: {err:?}");
+                workspace
+                    .update(cx, |workspace, cx| {
+                        workspace.show_toast(
+                            Toast::new(
+                                NotificationId::unique::<ConflictViewToast>(),
+                                format!("Can't open {file_name} in a three-way view: {err}"),
+                            ),
+                            cx,
+                        );
+                    })
+                    .ok();
+                return;
+            }
+        };
+        workspace
+            .update_in(cx, |workspace, window, cx| {
+                let left_pane = workspace.split_pane(result_pane, SplitDirection::Up, window, cx);
+                let base_pane =
+                    workspace.split_pane(left_pane.clone(), SplitDirection::Right, window, cx);
+                let right_pane =
+                    workspace.split_pane(base_pane.clone(), SplitDirection::Right, window, cx);
+                for (pane, text, side) in [
+                    (left_pane, sides.left, "left"),
+                    (base_pane, sides.base, "base"),
+                    (right_pane, sides.right, "right"),
+                ] {
+                    let side_editor = read_only_editor(
+                        workspace,
+                        &text,
+                        format!("{file_name} ({side})"),
+                        language.clone(),
+                        window,
+                        cx,
+                    );
+                    workspace.add_item(pane, Box::new(side_editor), None, false, false, window, cx);
+                }
+                window.focus(&editor.focus_handle(cx));
+            })
+            .ok();
+    })
+    .detach();
+    Ok(())
+}
+
+fn read_only_editor(
+    workspace: &mut Workspace,
+    text: &str,
+    title: String,
+    language: Option<Arc<Language>>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Entity<Editor> {
+    let project = workspace.project().clone();
+    let buffer = project.update(cx, |project, cx| {
+        project.create_local_buffer(text, language, false, cx)
+    });
+    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+    cx.new(|cx| {
+        let mut editor = Editor::for_multibuffer(buffer, Some(project), window, cx);
+        editor.set_read_only(true);
+        editor
+    })
+}
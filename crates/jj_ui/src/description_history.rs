@@ -0,0 +1,41 @@
+use db::kvp::KEY_VALUE_STORE;
+use gpui::App;
+use std::path::Path;
+use util::ResultExt as _;
+
+const KEY_PREFIX: &str = "jj_description_history";
+const MAX_DESCRIPTIONS: usize = 20;
+
+/// Reads the descriptions recently given to changes in the repository, most recent first.
+pub(crate) fn load_descriptions(repo_root: &Path) -> Vec<String> {
+    KEY_VALUE_STORE
+        .read_kvp(&history_key(repo_root))
+        .log_err()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).log_err())
+        .unwrap_or_default()
+}
+
+/// Moves `description` to the front of the repository's history, dropping the oldest entries
+/// once there are too many.
+pub(crate) fn record_description(repo_root: &Path, description: String, cx: &App) {
+    let repo_root = repo_root.to_path_buf();
+    cx.background_spawn(async move {
+        let mut descriptions = load_descriptions(&repo_root);
+        descriptions.retain(|existing| existing != &description);
+        descriptions.insert(0, description);
+        descriptions.truncate(MAX_DESCRIPTIONS);
+        let Some(json) = serde_json::to_string(&descriptions).log_err() else {
+            return;
+        };
+        KEY_VALUE_STORE
+            .write_kvp(history_key(&repo_root), json)
+            .await
+            .log_err();
+    })
+    .detach();
+}
+
+fn history_key(repo_root: &Path) -> String {
+    format!("{KEY_PREFIX}:{}", repo_root.display())
+}
@@ -0,0 +1,181 @@
+use crate::{COMMIT_LOG_LIMIT, JjPanel};
+use fs::RealFs;
+use gpui::{Entity, TestAppContext, VisualTestContext};
+use jj::{ChangeId, CommitDateRange, CommitId, JjWorkspace, short_change_hash};
+use project::{JjStore, Project, ProjectEntryId};
+use rand::prelude::*;
+use serde_json::json;
+use settings::SettingsStore;
+use std::{env, path::Path, sync::Arc};
+use util::test::TempTree;
+use workspace::Workspace;
+
+fn init_test(cx: &mut TestAppContext) {
+    cx.update(|cx| {
+        let settings_store = SettingsStore::test(cx);
+        cx.set_global(settings_store);
+        theme::init(theme::LoadThemes::JustBase, cx);
+        editor::init(cx);
+    });
+}
+
+/// Interleaves operations the panel sends through the project's [`JjStore`] with ones another
+/// jj process makes on the same repository, then checks that the store and the panel list the
+/// same log as a freshly loaded workspace.
+#[gpui::test(iterations = 10)]
+async fn test_random_store_operations(cx: &mut TestAppContext, mut rng: StdRng) {
+    let operations = env::var("OPERATIONS")
+        .map(|i| i.parse().expect("invalid `OPERATIONS` variable"))
+        .unwrap_or(10);
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let root = TempTree::new(json!({
+        "project": {
+            "file.txt": "base\n",
+        },
+    }));
+    let work_directory = root.path().join("project");
+    let external = JjWorkspace::init(&work_directory).unwrap();
+
+    let project = Project::test(
+        Arc::new(RealFs::new(None, cx.executor())),
+        [work_directory.as_path()],
+        cx,
+    )
+    .await;
+    let tree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
+    tree.flush_fs_events(cx).await;
+    cx.executor().run_until_parked();
+    let store = project.read_with(cx, |project, _| project.jj_store().cloned().unwrap());
+    let repository_id = store.read_with(cx, |store, _| store.repositories().first().unwrap().id);
+
+    let mut expected_descriptions = Vec::new();
+    for index in 0..operations {
+        match rng.random_range(0..100) {
+            0..45 => {
+                let commits = store_log(&store, repository_id, cx).await;
+                let parent = commits.choose(&mut rng).unwrap();
+                let description = format!("store {index}");
+                log::info!(
+                    "store: new change {description:?} on {}",
+                    short_change_hash(&parent.0)
+                );
+                store
+                    .update(cx, |store, cx| {
+                        store.new_change(
+                            repository_id,
+                            Some(parent.0.clone()),
+                            description.clone(),
+                            cx,
+                        )
+                    })
+                    .unwrap()
+                    .await
+                    .unwrap();
+                expected_descriptions.push(description);
+            }
+            45..65 if !expected_descriptions.is_empty() => {
+                let description = expected_descriptions
+                    .swap_remove(rng.random_range(0..expected_descriptions.len()));
+                let change_id = store_log(&store, repository_id, cx)
+                    .await
+                    .into_iter()
+                    .find(|(_, _, commit_description)| *commit_description == description)
+                    .unwrap()
+                    .0;
+                log::info!("store: abandon {description:?}");
+                store
+                    .update(cx, |store, cx| {
+                        store.abandon_change(repository_id, change_id, cx)
+                    })
+                    .unwrap()
+                    .await
+                    .unwrap();
+            }
+            _ => {
+                let commits = external.recent_commits(COMMIT_LOG_LIMIT).unwrap();
+                let parent = commits.choose(&mut rng).unwrap();
+                let description = format!("external {index}");
+                log::info!(
+                    "external: new change {description:?} on {}",
+                    short_change_hash(&parent.change_id)
+                );
+                external
+                    .new_change(&parent.change_id, &description)
+                    .unwrap();
+                expected_descriptions.push(description);
+            }
+        }
+        cx.executor().run_until_parked();
+    }
+
+    let commits = store_log(&store, repository_id, cx).await;
+    let mut descriptions = commits
+        .iter()
+        .map(|(_, _, description)| description.clone())
+        .filter(|description| !description.is_empty())
+        .collect::<Vec<_>>();
+    descriptions.sort();
+    expected_descriptions.sort();
+    assert_eq!(descriptions, expected_descriptions);
+
+    let expected_commit_ids = reloaded_commit_ids(&work_directory);
+    let store_commit_ids = commits
+        .into_iter()
+        .map(|(_, commit_id, _)| commit_id)
+        .collect::<Vec<_>>();
+    assert_eq!(store_commit_ids, expected_commit_ids);
+
+    let workspace = cx.add_window(|window, cx| Workspace::test_new(project.clone(), window, cx));
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let panel = workspace.update(cx, JjPanel::new).unwrap();
+    panel.update_in(cx, |panel, window, cx| panel.request_refresh(window, cx));
+    cx.executor().run_until_parked();
+    let panel_commit_ids = panel.read_with(cx, |panel, _| {
+        panel
+            .commits
+            .iter()
+            .map(|commit| commit.commit_id.clone())
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(panel_commit_ids, expected_commit_ids);
+}
+
+async fn store_log(
+    store: &Entity<JjStore>,
+    repository_id: ProjectEntryId,
+    cx: &mut TestAppContext,
+) -> Vec<(ChangeId, CommitId, String)> {
+    store
+        .read_with(cx, |store, cx| {
+            store.recent_commits(
+                Some(repository_id),
+                COMMIT_LOG_LIMIT,
+                CommitDateRange::default(),
+                cx,
+            )
+        })
+        .unwrap()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|commit| {
+            (
+                commit.change_id,
+                commit.commit_id,
+                commit.description.to_string(),
+            )
+        })
+        .collect()
+}
+
+fn reloaded_commit_ids(work_directory: &Path) -> Vec<CommitId> {
+    JjWorkspace::load(work_directory)
+        .unwrap()
+        .recent_commits(COMMIT_LOG_LIMIT)
+        .unwrap()
+        .into_iter()
+        .map(|commit| commit.commit_id)
+        .collect()
+}
@@ -1,28 +1,160 @@
 use anyhow::{Context as _, Result, anyhow};
+use buffer_diff::{BufferDiff, DiffHunkStatusKind};
 use command_palette_hooks::CommandPaletteFilter;
-use editor::Editor;
+use editor::{Editor, EditorEvent, EditorMode, MultiBuffer, hover_markdown_style};
 use feature_flags::{FeatureFlagAppExt as _, JjUiFeatureFlag};
+use git::{CommitStatus, GitHostingProviderRegistry, GitRemote, parse_git_remote_url};
 use gpui::{
-    Action, App, AsyncWindowContext, ClickEvent, Context, Corner, DismissEvent, Entity,
-    EventEmitter, FocusHandle, Focusable, KeyDownEvent, Modifiers, MouseButton, MouseDownEvent,
-    Pixels, Point, SharedString, Subscription, Task, WeakEntity, Window, actions, anchored,
-    deferred, px, rems,
+    Action, App, AsyncWindowContext, ClickEvent, ClipboardItem, Context, Corner, DismissEvent,
+    Entity, EventEmitter, FocusHandle, Focusable, KeyDownEvent, Modifiers, MouseButton,
+    MouseDownEvent, MouseUpEvent, PathPromptOptions, Pixels, Point, PromptLevel, ScrollHandle,
+    SharedString, Subscription, Task, WeakEntity, Window, actions, anchored, deferred, px, rems,
+};
+use jj::{
+    BookmarkSummary, ChangeId, CommitId, DivergentChangeError, FileStatus, GitHead, GitRemote,
+    PushSummary, RepoPathBuf, UnsupportedFormatError, WorkingCopyProblem, WorkingCopyState,
+    checklist_items, short_change_hash, short_commit_hash, toggle_checklist_item,
+};
+use language::{Buffer, language_settings::SoftWrap};
+use log::{Level, debug, info, log, warn};
+use markdown::{Markdown, MarkdownElement};
+use project::{
+    JjCommitSummary, JjOperationRequest, JjRepositorySummary, JjSessionOperation, JjStore,
+    JjStoreEvent, Project, ProjectEntryId, git_store::Repository,
+    project_settings::ProjectSettings,
+};
+use settings::Settings as _;
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use jj::{short_change_hash, short_commit_hash};
-use log::{info, warn};
-use project::{JjCommitSummary, JjRepositorySummary, Project, ProjectEntryId};
-use std::time::Duration;
 use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use ui::{
-    AnyElement, ButtonStyle, ContextMenu, Modal, ModalFooter, ModalHeader, Section, prelude::*,
+    AnyElement, ButtonStyle, Checkbox, ContextMenu, Indicator, Modal, ModalFooter, ModalHeader,
+    PopoverMenu, Section, SpinnerLabel, Tooltip, prelude::*,
 };
-use ui_input::InputField;
 use workspace::{
-    ModalView, Workspace,
+    ModalView, OpenOptions, Toast, Workspace,
     dock::{DockPosition, Panel, PanelEvent},
+    notifications::{NotificationId, simple_message_notification::MessageNotification},
+};
+
+mod change_indicator;
+mod commit_detail;
+mod commit_minimap;
+mod commit_row;
+mod conflict_view;
+mod description_history;
+#[cfg(test)]
+mod jj_panel_tests;
+mod log_date_filter;
+mod log_export;
+mod recent_change_summary;
+mod repo_selector;
+mod revset_filter_modal;
+mod set_bookmark_modal;
+mod working_copy_section;
+
+pub use change_indicator::JjChangeIndicator;
+use commit_minimap::CommitMinimap;
+use commit_row::CommitRow;
+use log_date_filter::{
+    CustomDateRangeModal, LogDateFilter, day_header_label, local_day, local_today,
 };
+use log_export::{LogExportFormat, export_log};
+pub use recent_change_summary::load_change_summaries;
+use repo_selector::RepoSelector;
+use revset_filter_modal::RevsetFilterModal;
+use set_bookmark_modal::SetBookmarkModal;
+use working_copy_section::WorkingCopySection;
+
+actions!(
+    jj_ui,
+    [
+        ToggleFocus,
+        /// Opens the active file in a new editor with its jj diff expanded.
+        OpenDiff,
+        /// Reviews the working-copy change in the active file with every hunk expanded.
+        ReviewCurrentChange,
+        /// Like `OpenDiff`, but logs every hunk of the active editor's diff for debugging.
+        DumpDiffHunks,
+        /// Discards the working-copy change's edits to the active file, restoring it from the
+        /// parent revision.
+        DiscardFileChanges,
+        /// Shows the left side, base and right side of the active file's conflict in panes
+        /// above it, instead of reading them between conflict markers.
+        OpenConflictView,
+        /// Starts a new empty change on top of the working-copy change.
+        NewChange,
+        /// Times common jj operations in the repository and opens a report with suggestions.
+        ProfileRepository,
+        /// Moves the working-copy changes into a parked change and starts a fresh one.
+        ParkWorkingCopy,
+        /// Squashes the parked change back into the working copy.
+        UnparkWorkingCopy,
+        /// Makes the change selected in the panel the working copy, like `jj edit`.
+        EditSelectedChange,
+        /// Edits the description of the change selected in the panel.
+        RenameSelectedChange,
+        /// Abandons the change selected in the panel.
+        AbandonSelectedChange,
+        /// Squashes the change selected in the panel into its parent.
+        SquashSelectedChange,
+        /// Shows or hides the panel's footer listing the keys for the selected change.
+        ToggleKeyLegend
+    ]
+);
+
+/// The panel's own single-key bindings. They live here rather than in the bundled keymaps,
+/// which can't name actions of this optional crate.
+pub fn default_key_bindings() -> Vec<gpui::KeyBinding> {
+    const CONTEXT: Option<&str> = Some("JjPanel");
+    vec![
+        gpui::KeyBinding::new("e", EditSelectedChange, CONTEXT),
+        gpui::KeyBinding::new("r", RenameSelectedChange, CONTEXT),
+        gpui::KeyBinding::new("a", AbandonSelectedChange, CONTEXT),
+        gpui::KeyBinding::new("s", SquashSelectedChange, CONTEXT),
+        gpui::KeyBinding::new("escape", menu::Cancel, CONTEXT),
+    ]
+}
+
+const COMMIT_LOG_LIMIT: usize = 50;
+const HIDDEN_COMMITS_LIMIT: usize = 20;
+/// Per-invocation cap on hunks `OpenDiff` logs individually, so generated files with thousands
+/// of hunks don't stall the UI thread.
+const MAX_LOGGED_HUNKS: usize = 20;
+/// The remote `jj git fetch` and `jj git push` prefer when none is picked in the panel.
+const DEFAULT_GIT_REMOTE: &str = "origin";
+const FETCH_PROGRESS_REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+/// How long to wait before asking again about a commit whose CI is still running, doubled
+/// after every check that finds it pending.
+const CI_STATUS_INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+const CI_STATUS_MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+struct JjOperationToast;
+
+/// The last answer the hosting provider gave about a commit's CI. `None` means the commit has
+/// no CI configured, which is cached like a finished status.
+struct CiStatusCheck {
+    status: Option<CommitStatus>,
+    checked_at: Instant,
+    pending_checks: u32,
+}
 
-actions!(jj_ui, [ToggleFocus, OpenDiff]);
+impl CiStatusCheck {
+    fn needs_refresh(&self, now: Instant) -> bool {
+        if self.status != Some(CommitStatus::Pending) {
+            return false;
+        }
+        let backoff = CI_STATUS_INITIAL_BACKOFF
+            .saturating_mul(1 << self.pending_checks.saturating_sub(1).min(8))
+            .min(CI_STATUS_MAX_BACKOFF);
+        now.duration_since(self.checked_at) >= backoff
+    }
+}
 
 #[derive(Clone)]
 struct CommitMenuTarget {
@@ -30,6 +162,13 @@ struct CommitMenuTarget {
     commit: JjCommitSummary,
 }
 
+/// The log and bookmarks as they were right after an earlier operation.
+struct PastOperation {
+    operation: JjSessionOperation,
+    commits: Vec<JjCommitSummary>,
+    bookmarks: Vec<BookmarkSummary>,
+}
+
 pub fn init(cx: &mut App) {
     info!(target: "jj_ui", "starting to init.");
     if !cx.has_flag::<JjUiFeatureFlag>() {
@@ -40,18 +179,156 @@ pub fn init(cx: &mut App) {
     });
     info!(target: "jj_ui", "jj_ui inited.");
 
-    cx.observe_new(|workspace: &mut Workspace, _, _| {
+    cx.observe_new(|workspace: &mut Workspace, _, cx| {
+        if let Some(store) = workspace.project().read(cx).jj_store().cloned() {
+            cx.subscribe(
+                &store,
+                |workspace, store, event: &JjStoreEvent, cx| match event {
+                    JjStoreEvent::WorkingCopyConflicted {
+                        repository_id,
+                        change_id,
+                        paths,
+                    } => {
+                        let repo_root = store.read(cx).repository_root(*repository_id);
+                        show_conflict_notification(
+                            workspace,
+                            *repository_id,
+                            change_id,
+                            repo_root,
+                            paths,
+                            cx,
+                        );
+                    }
+                    JjStoreEvent::OperationRequested {
+                        repository_id,
+                        requester,
+                        request,
+                    } => {
+                        let requester_name = requester
+                            .as_ref()
+                            .and_then(|peer_id| {
+                                let project = workspace.project().read(cx);
+                                let collaborator = project
+                                    .collaborators()
+                                    .values()
+                                    .find(|collaborator| &collaborator.peer_id == peer_id)?;
+                                workspace
+                                    .user_store()
+                                    .read(cx)
+                                    .get_cached_user(collaborator.user_id)
+                            })
+                            .map(|user| user.github_login.to_string())
+                            .unwrap_or_else(|| "A guest".to_string());
+                        show_operation_request_notification(
+                            workspace,
+                            store,
+                            *repository_id,
+                            requester_name,
+                            request.clone(),
+                            cx,
+                        );
+                    }
+                    JjStoreEvent::UnsupportedFormat {
+                        repository_id,
+                        error,
+                    } => {
+                        let repo_root = store.read(cx).repository_root(*repository_id);
+                        show_unsupported_format_notification(
+                            workspace,
+                            *repository_id,
+                            repo_root,
+                            error,
+                            cx,
+                        );
+                    }
+                    JjStoreEvent::RecoveryNeeded {
+                        repository_id,
+                        problems,
+                    } => {
+                        let repo_root = store.read(cx).repository_root(*repository_id);
+                        show_recovery_notification(
+                            workspace,
+                            store,
+                            *repository_id,
+                            repo_root,
+                            problems,
+                            cx,
+                        );
+                    }
+                },
+            )
+            .detach();
+        }
+        cx.on_release(|workspace, cx| {
+            recent_change_summary::record_change_summaries(workspace, cx);
+        })
+        .detach();
+
         workspace.register_action(|workspace, _: &ToggleFocus, window, cx| {
             info!(target: "jj_ui", "ToggleFocus action invoked");
             let opened = workspace.toggle_panel_focus::<JjPanel>(window, cx);
             info!(target: "jj_ui", "toggle_panel_focus result: opened={}", opened);
         });
+        workspace.register_action(|workspace, _: &zed_actions::jj::ViewAsJj, window, cx| {
+            let work_directory = workspace
+                .project()
+                .read(cx)
+                .active_repository(cx)
+                .map(|repository| repository.read(cx).work_directory_abs_path.clone());
+            if let Some(panel) = workspace.focus_panel::<JjPanel>(window, cx)
+                && let Some(work_directory) = work_directory
+            {
+                panel.update(cx, |panel, cx| {
+                    panel.select_repository_at(&work_directory, window, cx);
+                });
+            }
+        });
         workspace.register_action(|workspace, _: &OpenDiff, window, cx| {
             info!(target: "jj_ui", "OpenDiff action invoked");
-            if let Err(err) = open_unstaged_diff_for_active_editor(workspace, window, cx) {
+            if let Err(err) = open_unstaged_diff_for_active_editor(workspace, false, window, cx) {
                 info!(target: "jj_ui", "OpenDiff failed: {err:?}");
             }
         });
+        workspace.register_action(|workspace, _: &ReviewCurrentChange, window, cx| {
+            if let Err(err) = open_unstaged_diff_for_active_editor(workspace, false, window, cx) {
+                warn!(target: "jj_ui", "ReviewCurrentChange failed: {err:?}");
+            }
+        });
+        workspace.register_action(|workspace, _: &DumpDiffHunks, window, cx| {
+            if let Err(err) = open_unstaged_diff_for_active_editor(workspace, true, window, cx) {
+                info!(target: "jj_ui", "DumpDiffHunks failed: {err:?}");
+            }
+        });
+        workspace.register_action(|workspace, _: &DiscardFileChanges, window, cx| {
+            if let Err(err) = discard_active_file_changes(workspace, window, cx) {
+                warn!(target: "jj_ui", "DiscardFileChanges failed: {err:?}");
+            }
+        });
+        workspace.register_action(|workspace, _: &OpenConflictView, window, cx| {
+            if let Err(err) = conflict_view::open_conflict_view(workspace, window, cx) {
+                warn!(target: "jj_ui", "OpenConflictView failed: {err:?}");
+            }
+        });
+        workspace.register_action(|workspace, _: &NewChange, window, cx| {
+            with_target_repository(workspace, window, cx, |panel, repo_id, window, cx| {
+                panel.new_change(repo_id, None, window, cx);
+            });
+        });
+        workspace.register_action(|workspace, _: &ProfileRepository, window, cx| {
+            with_target_repository(workspace, window, cx, |panel, repo_id, window, cx| {
+                panel.profile_repository(repo_id, window, cx);
+            });
+        });
+        workspace.register_action(|workspace, _: &ParkWorkingCopy, window, cx| {
+            with_target_repository(workspace, window, cx, |panel, repo_id, window, cx| {
+                panel.park_working_copy(repo_id, window, cx);
+            });
+        });
+        workspace.register_action(|workspace, _: &UnparkWorkingCopy, window, cx| {
+            with_target_repository(workspace, window, cx, |panel, repo_id, window, cx| {
+                panel.unpark_working_copy(repo_id, window, cx);
+            });
+        });
     })
     .detach();
 }
@@ -61,15 +338,71 @@ pub struct JjPanel {
     project: Entity<Project>,
     focus_handle: FocusHandle,
     commits: Vec<JjCommitSummary>,
+    restack_candidates: Vec<JjCommitSummary>,
+    bookmarks: Vec<BookmarkSummary>,
+    /// Files of the working copy that `snapshot.auto-track` leaves out.
+    untracked_paths: Vec<RepoPathBuf>,
+    working_copy_status: Vec<FileStatus>,
+    working_copy_state: Option<WorkingCopyState>,
+    git_remotes: Vec<GitRemote>,
+    /// The remote picked for fetching and pushing, if any. See [`Self::git_remote`].
+    selected_remote: Option<SharedString>,
+    /// Where git's HEAD is, when the selected repository is colocated with git.
+    git_head: Option<GitHead>,
+    stale_commits: HashSet<CommitId>,
+    ci_statuses: HashMap<CommitId, CiStatusCheck>,
+    /// The commits as the user's `templates.log` renders them, shown instead of the panel's
+    /// own ids and author line.
+    rendered_commits: HashMap<ChangeId, SharedString>,
     is_loading: bool,
     show_loading_indicator: bool,
     loading_indicator_task: Option<Task<()>>,
     error: Option<SharedString>,
     _task: Option<Task<()>>,
+    _restack_task: Option<Task<()>>,
+    _bookmarks_task: Option<Task<()>>,
+    _untracked_paths_task: Option<Task<()>>,
+    _working_copy_status_task: Option<Task<()>>,
+    _working_copy_state_task: Option<Task<()>>,
+    _git_remotes_task: Option<Task<()>>,
+    _git_head_task: Option<Task<()>>,
+    _freshness_task: Option<Task<()>>,
+    _ci_status_task: Option<Task<()>>,
+    _rendered_commits_task: Option<Task<()>>,
+    /// Redraws the panel while a fetch runs, since the store doesn't notify about its progress.
+    _fetch_progress_task: Option<Task<()>>,
     repositories: Vec<JjRepositorySummary>,
     selected_repo: Option<ProjectEntryId>,
+    selected_change: Option<ChangeId>,
+    date_filter: LogDateFilter,
+    show_key_legend: bool,
+    is_zoomed: bool,
+    /// A revset picking the commits to show instead, ignoring the date filter while set.
+    revset_filter: Option<SharedString>,
+    /// Changes picked with a secondary click to become the parents of a new merge change.
+    merge_parents: Vec<ChangeId>,
+    scroll_handle: ScrollHandle,
     _store_subscription: Option<Subscription>,
     context_menu: Option<(Entity<ContextMenu>, Point<Pixels>, Subscription)>,
+    /// The selection to go back to as each menu or modal opened from the panel closes, innermost
+    /// last. Focus only returns to the panel once the outermost one is gone.
+    focus_stack: Vec<Option<ChangeId>>,
+    _modal_subscription: Option<Subscription>,
+    /// Shown in place of the log until dismissed, to see what an operation did before undoing
+    /// it. Nothing can be changed while it's shown.
+    past_operation: Option<PastOperation>,
+    _past_operation_task: Option<Task<()>>,
+    /// Abandoned commits listed below the log, while `show_hidden_commits` is set.
+    hidden_commits: Vec<JjCommitSummary>,
+    show_hidden_commits: bool,
+    _hidden_commits_task: Option<Task<()>>,
+    /// Whether the panel is open in its dock. Nothing is loaded while it's closed, so that
+    /// projects containing jj repositories don't load them until they're used.
+    is_active: bool,
+    /// Whether the store changed while the panel was closed, so opening it should refresh.
+    needs_refresh: bool,
+    /// The divergent change an operation last failed on, with the commits to pick from.
+    divergent_change: Option<(ProjectEntryId, DivergentChangeError)>,
 }
 
 impl JjPanel {
@@ -88,17 +421,55 @@ impl JjPanel {
                 project,
                 focus_handle,
                 commits: Vec::new(),
+                restack_candidates: Vec::new(),
+                bookmarks: Vec::new(),
+                untracked_paths: Vec::new(),
+                working_copy_status: Vec::new(),
+                working_copy_state: None,
+                git_remotes: Vec::new(),
+                selected_remote: None,
+                git_head: None,
+                stale_commits: HashSet::default(),
+                ci_statuses: HashMap::default(),
+                rendered_commits: HashMap::default(),
                 is_loading: true,
                 show_loading_indicator: false,
                 loading_indicator_task: None,
                 error: None,
                 _task: None,
+                _restack_task: None,
+                _bookmarks_task: None,
+                _untracked_paths_task: None,
+                _working_copy_status_task: None,
+                _working_copy_state_task: None,
+                _git_remotes_task: None,
+                _git_head_task: None,
+                _freshness_task: None,
+                _ci_status_task: None,
+                _rendered_commits_task: None,
+                _fetch_progress_task: None,
                 repositories: Vec::new(),
                 selected_repo: None,
+                selected_change: None,
+                date_filter: LogDateFilter::default(),
+                show_key_legend: true,
+                is_zoomed: false,
+                revset_filter: None,
+                merge_parents: Vec::new(),
+                scroll_handle: ScrollHandle::new(),
                 _store_subscription: None,
                 context_menu: None,
+                focus_stack: Vec::new(),
+                _modal_subscription: None,
+                past_operation: None,
+                _past_operation_task: None,
+                hidden_commits: Vec::new(),
+                show_hidden_commits: false,
+                _hidden_commits_task: None,
+                is_active: false,
+                needs_refresh: true,
+                divergent_change: None,
             };
-            panel.request_refresh(window, cx);
             panel.ensure_store_subscription(window, cx);
             panel
         })
@@ -127,13 +498,19 @@ impl JjPanel {
                     self.repositories = repos.clone();
                     updated = true;
                 }
+                let default_repo = self
+                    .repositories
+                    .iter()
+                    .find(|repo| repo.covers_entire_project)
+                    .or(self.repositories.first())
+                    .map(|repo| repo.id);
                 if let Some(selected) = self.selected_repo {
                     if !self.repositories.iter().any(|repo| repo.id == selected) {
-                        self.selected_repo = self.repositories.first().map(|repo| repo.id);
+                        self.selected_repo = default_repo;
                         updated = true;
                     }
                 } else if !self.repositories.is_empty() {
-                    self.selected_repo = self.repositories.first().map(|repo| repo.id);
+                    self.selected_repo = default_repo;
                     updated = true;
                 }
             }
@@ -163,25 +540,50 @@ impl JjPanel {
             cx.notify();
             return;
         }
+        if let Some(repo_id) = self.selected_repo {
+            jj_store.update(cx, |store, cx| store.load_repository(repo_id, cx));
+        }
         self.is_loading = true;
         self.show_loading_indicator = false;
         self.error = None;
         self.start_loading_indicator_timer(window, cx);
         cx.notify();
         let selected_repo = self.selected_repo;
-        if let Some(task) =
-            jj_store.update(cx, |store, cx| store.recent_commits(selected_repo, 50, cx))
-        {
+        self.refresh_restack_candidates(&jj_store, window, cx);
+        self.refresh_bookmarks(&jj_store, window, cx);
+        self.refresh_untracked_paths(&jj_store, window, cx);
+        self.refresh_working_copy_status(&jj_store, window, cx);
+        self.refresh_hidden_commits(&jj_store, window, cx);
+        self.refresh_git_remotes(&jj_store, window, cx);
+        self.refresh_git_head(&jj_store, window, cx);
+        let date_range = self.date_filter.date_range(OffsetDateTime::now_utc());
+        let task = match (&self.revset_filter, selected_repo) {
+            (Some(revset), Some(repo_id)) => {
+                jj_store
+                    .read(cx)
+                    .revset_commits(repo_id, revset.to_string(), COMMIT_LOG_LIMIT, cx)
+            }
+            _ => jj_store
+                .read(cx)
+                .recent_commits(selected_repo, COMMIT_LOG_LIMIT, date_range, cx),
+        };
+        if let Some(task) = task {
             let panel = cx.weak_entity();
             self._task = Some(cx.spawn_in(window, async move |_, cx| match task.await {
                 Ok(commits) => {
                     if let Some(panel) = panel.upgrade() {
                         let _ = panel.update(cx, |panel, cx| {
+                            panel.merge_parents.retain(|change_id| {
+                                commits.iter().any(|commit| &commit.change_id == change_id)
+                            });
                             panel.commits = commits;
+                            panel.stale_commits.clear();
                             panel.is_loading = false;
                             panel.show_loading_indicator = false;
                             panel.loading_indicator_task = None;
                             panel.error = None;
+                            panel.refresh_ci_statuses(cx);
+                            panel.refresh_rendered_commits(cx);
                             cx.notify();
                         });
                     }
@@ -206,188 +608,3137 @@ impl JjPanel {
         }
     }
 
-    fn start_loading_indicator_timer(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        self.cancel_loading_indicator();
-        let panel = cx.entity().downgrade();
-        let timer = cx.background_executor().timer(Duration::from_millis(500));
-        self.loading_indicator_task = Some(cx.spawn_in(window, async move |_, cx| {
-            timer.await;
-            if let Some(panel) = panel.upgrade() {
-                let _ = panel.update(cx, |panel, cx| {
-                    if panel.is_loading {
-                        panel.show_loading_indicator = true;
-                        cx.notify();
-                    }
-                });
-            }
+    fn refresh_restack_candidates(
+        &mut self,
+        jj_store: &Entity<JjStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_id) = self.selected_repo else {
+            self.restack_candidates.clear();
+            return;
+        };
+        let Some(task) = jj_store
+            .read(cx)
+            .restack_candidates(repo_id, COMMIT_LOG_LIMIT, cx)
+        else {
+            self.restack_candidates.clear();
+            return;
+        };
+        self._restack_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let candidates = match task.await {
+                Ok(candidates) => candidates,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to detect out-of-date changes: {err:?}");
+                    Vec::new()
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.restack_candidates = candidates;
+                    cx.notify();
+                })
+                .ok();
         }));
     }
 
-    fn cancel_loading_indicator(&mut self) {
-        if self.loading_indicator_task.take().is_some() {
-            self.show_loading_indicator = false;
-        }
-    }
-
-    fn ensure_store_subscription(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        if let Some(store) = self.project.read(cx).jj_store().cloned() {
-            if self._store_subscription.is_none() {
-                let subscription = cx.observe_in(&store, window, |panel, _, window, cx| {
-                    panel.handle_store_updated(window, cx);
-                });
-                self._store_subscription = Some(subscription);
-            }
-        } else {
-            self._store_subscription.take();
-        }
-    }
-
-    fn handle_store_updated(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        self.request_refresh(window, cx);
-    }
-
-    fn focus_in(_this: &mut Self, _: &mut Window, cx: &mut Context<Self>) {
-        info!(target: "jj_ui", "JJ panel focused");
-        cx.emit(PanelEvent::Activate);
-    }
-
-    fn format_timestamp(timestamp: i64) -> String {
-        let nanos = (timestamp as i128) * 1_000_000;
-        OffsetDateTime::from_unix_timestamp_nanos(nanos)
-            .ok()
-            .and_then(|time| time.format(&Rfc3339).ok())
-            .unwrap_or_else(|| "unknown time".to_string())
-    }
-
-    fn refresh_action(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
-        info!(
-            target: "jj_ui",
-            "refresh pressed (selected_repo={:?})",
-            self.selected_repo
-        );
-        self.request_refresh(window, cx);
+    fn refresh_bookmarks(
+        &mut self,
+        jj_store: &Entity<JjStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).bookmarks(repo_id, cx))
+        else {
+            self.bookmarks.clear();
+            return;
+        };
+        self._bookmarks_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let bookmarks = match task.await {
+                Ok(bookmarks) => bookmarks,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to list bookmarks: {err:?}");
+                    Vec::new()
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.bookmarks = bookmarks;
+                    cx.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn select_repository(
+    fn refresh_git_remotes(
         &mut self,
-        repo_id: ProjectEntryId,
+        jj_store: &Entity<JjStore>,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        if self.selected_repo == Some(repo_id) {
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).git_remotes(repo_id, cx))
+        else {
+            self.git_remotes.clear();
             return;
-        }
-        self.selected_repo = Some(repo_id);
-        self.request_refresh(window, cx);
+        };
+        self._git_remotes_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let git_remotes = match task.await {
+                Ok(remotes) => remotes,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to list git remotes: {err:?}");
+                    Vec::new()
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.git_remotes = git_remotes;
+                    cx.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn close_context_menu(&mut self, cx: &mut Context<Self>) {
-        if self.context_menu.is_some() {
-            self.context_menu.take();
-            cx.notify();
-        }
+    fn refresh_git_head(
+        &mut self,
+        jj_store: &Entity<JjStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).git_head(repo_id, cx))
+        else {
+            self.git_head = None;
+            return;
+        };
+        self._git_head_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let git_head = match task.await {
+                Ok(git_head) => git_head,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to read git HEAD: {err:?}");
+                    None
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.git_head = git_head;
+                    cx.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn trigger_edit_change(
+    /// In a colocated repository git tools see the branch at `@-` as checked out. Asks before
+    /// a checkout that moves `@-` to `new_parent`, away from that branch, and otherwise runs
+    /// `checkout` right away.
+    fn confirm_leaving_git_branch(
         &mut self,
-        commit: &JjCommitSummary,
+        new_parent: Option<CommitId>,
         window: &mut Window,
         cx: &mut Context<Self>,
+        checkout: impl FnOnce(&mut Self, &mut Window, &mut Context<Self>) + 'static,
     ) {
-        self.close_context_menu(cx);
-        let Some(repo_id) = self.selected_repo else {
-            return;
+        let branches = match (&self.git_head, &new_parent) {
+            (Some(git_head), Some(new_parent))
+                if !git_head.branches.is_empty() && &git_head.commit_id != new_parent =>
+            {
+                git_head.branches.join(", ")
+            }
+            _ => return checkout(self, window, cx),
         };
-        let Some(store) = self.project.read(cx).jj_store().cloned() else {
-            self.error = Some("JJ support unavailable".into());
-            cx.notify();
-            return;
+        let detail = format!(
+            "Git tools see {branches} checked out in this colocated repository. Afterwards \
+             git's HEAD is detached at the new parent of the working copy, with no current \
+             branch until a bookmark points there."
+        );
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("Move git's HEAD off {branches}?"),
+            Some(&detail),
+            &["Continue", "Cancel"],
+            cx,
+        );
+        cx.spawn_in(window, async move |panel, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            panel
+                .update_in(cx, |panel, window, cx| checkout(panel, window, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn render_git_head(&self) -> Option<impl IntoElement> {
+        let git_head = self.git_head.as_ref()?;
+        let head = if git_head.branches.is_empty() {
+            format!("detached at {}", short_commit_hash(&git_head.commit_id))
+        } else {
+            git_head.branches.join(", ")
         };
-        let change_id = commit.change_id.clone();
-        if let Some(task) = store.update(cx, |store, cx| store.edit_change(repo_id, change_id, cx))
+        let (label, color) = if git_head.is_working_copy_parent {
+            (format!("git HEAD: {head}"), Color::Muted)
+        } else {
+            (
+                format!("git HEAD: {head}, not the working copy's parent"),
+                Color::Warning,
+            )
+        };
+        Some(Label::new(label).size(LabelSize::XSmall).color(color))
+    }
+
+    /// The remote to fetch from and push to: the one picked in the panel, else `origin` like
+    /// the jj CLI, else the repository's first remote.
+    fn git_remote(&self) -> SharedString {
+        let has_remote = |name: &str| self.git_remotes.iter().any(|remote| remote.name == name);
+        if let Some(selected) = &self.selected_remote
+            && has_remote(selected.as_ref())
         {
-            self.spawn_store_task("jj edit", task, true, window, cx);
+            return selected.clone();
         }
+        if !has_remote(DEFAULT_GIT_REMOTE)
+            && let Some(remote) = self.git_remotes.first()
+        {
+            return remote.name.clone().into();
+        }
+        DEFAULT_GIT_REMOTE.into()
     }
 
-    fn show_rename_modal(
+    fn refresh_untracked_paths(
         &mut self,
-        target: CommitMenuTarget,
+        jj_store: &Entity<JjStore>,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        self.close_context_menu(cx);
-        let Some(workspace) = self._workspace.upgrade() else {
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).untracked_paths(repo_id, cx))
+        else {
+            self.untracked_paths.clear();
             return;
         };
-        let project = self.project.clone();
-        let panel = cx.entity().downgrade();
-        let _ = workspace.update(cx, |workspace, cx| {
-            workspace.toggle_modal(window, cx, move |window, cx| {
-                RenameChangeModal::new(project.clone(), panel.clone(), target.clone(), window, cx)
-            });
-        });
+        self._untracked_paths_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let untracked_paths = match task.await {
+                Ok(paths) => paths,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to list untracked files: {err:?}");
+                    Vec::new()
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.untracked_paths = untracked_paths;
+                    cx.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn deploy_commit_context_menu(
+    fn refresh_working_copy_status(
         &mut self,
-        target: CommitMenuTarget,
-        position: Point<Pixels>,
+        jj_store: &Entity<JjStore>,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let panel = cx.entity().downgrade();
-        let menu = ContextMenu::build(window, cx, move |menu, _window, _cx| {
-            let rename_target = target.clone();
-            let rename_panel = panel.clone();
-            menu.entry("Rename change…", None, move |window, cx| {
-                if let Some(panel) = rename_panel.upgrade() {
-                    let _ = panel.update(cx, |panel, cx| {
-                        panel.show_rename_modal(rename_target.clone(), window, cx);
-                    });
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).status(repo_id, cx))
+        else {
+            self.working_copy_status.clear();
+            self.working_copy_state = None;
+            return;
+        };
+        let jj_store = jj_store.clone();
+        self._working_copy_status_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let working_copy_status = match task.await {
+                Ok(statuses) => statuses,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to load working copy status: {err:?}");
+                    Vec::new()
                 }
-            })
-        });
-        self.set_context_menu(menu, position, window, cx);
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.working_copy_status = working_copy_status;
+                    // Loading the status snapshots the working copy, which moves it to a new
+                    // operation whenever files changed.
+                    panel.refresh_working_copy_state(&jj_store, window, cx);
+                    cx.notify();
+                })
+                .ok();
+        }));
     }
 
-    fn set_context_menu(
+    fn refresh_hidden_commits(
         &mut self,
-        menu: Entity<ContextMenu>,
-        position: Point<Pixels>,
-        window: &Window,
+        jj_store: &Entity<JjStore>,
+        window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let subscription =
-            cx.subscribe_in(&menu, window, |this, _, _: &DismissEvent, window, cx| {
-                if this.context_menu.as_ref().is_some_and(|(open_menu, _, _)| {
-                    open_menu.focus_handle(cx).contains_focused(window, cx)
-                }) {
-                    window.focus(&this.focus_handle);
+        let Some(task) = self
+            .selected_repo
+            .filter(|_| self.show_hidden_commits)
+            .and_then(|repo_id| {
+                jj_store
+                    .read(cx)
+                    .hidden_heads(repo_id, HIDDEN_COMMITS_LIMIT, cx)
+            })
+        else {
+            self.hidden_commits.clear();
+            self._hidden_commits_task = None;
+            return;
+        };
+        self._hidden_commits_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let hidden_commits = match task.await {
+                Ok(commits) => commits,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to load abandoned commits: {err:?}");
+                    Vec::new()
                 }
-                this.context_menu.take();
-                cx.notify();
-            });
-        self.context_menu = Some((menu, position, subscription));
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.hidden_commits = hidden_commits;
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    fn toggle_hidden_commits(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.show_hidden_commits = !self.show_hidden_commits;
+        if let Some(jj_store) = self.project.read(cx).jj_store().cloned() {
+            self.refresh_hidden_commits(&jj_store, window, cx);
+        }
         cx.notify();
     }
 
-    fn spawn_store_task(
-        &self,
-        label: &'static str,
-        task: Task<Result<()>>,
-        refresh_on_success: bool,
+    fn restore_hidden_commit(
+        &mut self,
+        repo_id: ProjectEntryId,
+        commit_id: CommitId,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let panel = cx.entity().downgrade();
-        cx.spawn_in(window, async move |_, cx| match task.await {
-            Ok(_) => {
-                info!(target: "jj_ui", "{label} completed");
-                if refresh_on_success {
-                    if let Some(panel) = panel.upgrade() {
-                        let panel_clone = panel.clone();
-                        let _ = cx.update(|window, cx| {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.restore_hidden_commit(repo_id, commit_id, cx)
+        }) {
+            self.spawn_store_task("jj restore hidden commit", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn render_hidden_commits(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        if !self.show_hidden_commits {
+            return None;
+        }
+        let repo_id = self.selected_repo?;
+        let can_restore = !self.is_guest(cx) && !self.is_read_only(cx);
+        let title = match self.hidden_commits.len() {
+            0 => "No abandoned changes".to_string(),
+            1 => "1 abandoned change".to_string(),
+            count => format!("{count} abandoned changes"),
+        };
+
+        Some(
+            v_flex()
+                .gap(rems(0.25))
+                .p(px(4.0))
+                .rounded(px(4.0))
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().surface_background)
+                .child(Label::new(title).size(LabelSize::Small).color(Color::Muted))
+                .children(
+                    self.hidden_commits
+                        .iter()
+                        .enumerate()
+                        .map(|(index, commit)| {
+                            let commit_id = commit.commit_id.clone();
+                            CommitRow::new(commit.clone(), Self::format_timestamp(commit.timestamp))
+                                .disabled(true)
+                                .when(can_restore, |row| {
+                                    row.child(
+                                        Button::new(("jj-restore-hidden", index), "Restore")
+                                            .style(ButtonStyle::Subtle)
+                                            .label_size(LabelSize::XSmall)
+                                            .on_click(cx.listener(move |panel, _, window, cx| {
+                                                panel.restore_hidden_commit(
+                                                    repo_id,
+                                                    commit_id.clone(),
+                                                    window,
+                                                    cx,
+                                                );
+                                            })),
+                                    )
+                                })
+                        }),
+                )
+                .into_any(),
+        )
+    }
+
+    fn refresh_working_copy_state(
+        &mut self,
+        jj_store: &Entity<JjStore>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(task) = self
+            .selected_repo
+            .and_then(|repo_id| jj_store.read(cx).working_copy_state(repo_id, cx))
+        else {
+            self.working_copy_state = None;
+            return;
+        };
+        self._working_copy_state_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let working_copy_state = match task.await {
+                Ok(state) => Some(state),
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to check working copy state: {err:?}");
+                    None
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.working_copy_state = working_copy_state;
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    /// A dot next to the panel title telling whether the working copy is at the operation head,
+    /// with the update `jj workspace update-stale` would do when an operation left it stale.
+    fn render_working_copy_freshness(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let repo_id = self.selected_repo?;
+        let state = self.working_copy_state.as_ref()?;
+        let short_id =
+            |operation_id: &str| operation_id.get(..12).unwrap_or(operation_id).to_string();
+        let (color, tooltip) = if state.is_at_head() {
+            (
+                Color::Success,
+                format!(
+                    "Working copy is at the latest operation {}",
+                    short_id(&state.operation_id)
+                ),
+            )
+        } else if state.is_stale {
+            (
+                Color::Warning,
+                format!(
+                    "Working copy is stale at operation {}, since {} rewrote its change",
+                    short_id(&state.operation_id),
+                    short_id(&state.head_operation_id)
+                ),
+            )
+        } else {
+            (
+                Color::Muted,
+                format!(
+                    "Working copy is at operation {}, behind {}, with its files up to date",
+                    short_id(&state.operation_id),
+                    short_id(&state.head_operation_id)
+                ),
+            )
+        };
+        let can_update = state.is_stale && !self.is_read_only(cx) && !self.is_guest(cx);
+
+        Some(
+            h_flex()
+                .gap(rems(0.25))
+                .items_center()
+                .child(
+                    div()
+                        .id("jj-working-copy-freshness")
+                        .child(Indicator::dot().color(color))
+                        .tooltip(Tooltip::text(tooltip)),
+                )
+                .when(can_update, |row| {
+                    row.child(
+                        Button::new("jj-update-stale", "Update Working Copy")
+                            .style(ButtonStyle::Filled)
+                            .label_size(LabelSize::XSmall)
+                            .on_click(cx.listener(move |panel, _, window, cx| {
+                                panel.update_stale_working_copy(repo_id, window, cx);
+                            })),
+                    )
+                })
+                .into_any_element(),
+        )
+    }
+
+    fn update_stale_working_copy(
+        &mut self,
+        repo_id: ProjectEntryId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| store.recover_working_copy(repo_id, cx)) {
+            self.spawn_store_task("jj workspace update-stale", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn refresh_rendered_commits(&mut self, cx: &mut Context<Self>) {
+        let change_ids = self
+            .commits
+            .iter()
+            .map(|commit| commit.change_id.clone())
+            .collect();
+        let Some(task) = self.selected_repo.and_then(|repo_id| {
+            let store = self.project.read(cx).jj_store()?;
+            store.read(cx).rendered_commits(repo_id, change_ids, cx)
+        }) else {
+            self.rendered_commits.clear();
+            return;
+        };
+        self._rendered_commits_task = Some(cx.spawn(async move |panel, cx| {
+            let rendered = match task.await {
+                Ok(rendered) => rendered,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to render templates.log: {err:?}");
+                    HashMap::default()
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.rendered_commits = rendered
+                        .into_iter()
+                        .map(|(change_id, text)| (change_id, text.trim_end().to_string().into()))
+                        .collect();
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    /// Fetches statuses only for pushed commits, and keeps finished or missing ones cached so
+    /// that refreshes don't spend the hosting provider's rate limit on them again. Pending
+    /// statuses are asked about again with a growing backoff.
+    fn refresh_ci_statuses(&mut self, cx: &mut Context<Self>) {
+        if !ProjectSettings::get_global(cx).jj.show_ci_status {
+            self.ci_statuses.clear();
+            self._ci_status_task = None;
+            return;
+        }
+        let Some(remote) = self.hosting_remote(cx) else {
+            return;
+        };
+        let now = Instant::now();
+        let commit_ids = self
+            .commits
+            .iter()
+            .filter(|commit| commit.is_pushed)
+            .filter(|commit| {
+                self.ci_statuses
+                    .get(&commit.commit_id)
+                    .is_none_or(|check| check.needs_refresh(now))
+            })
+            .map(|commit| commit.commit_id.clone())
+            .collect::<Vec<_>>();
+        if commit_ids.is_empty() {
+            return;
+        }
+        let http_client = cx.http_client();
+        self._ci_status_task = Some(cx.spawn(async move |panel, cx| {
+            let mut statuses = Vec::new();
+            for commit_id in commit_ids {
+                match remote
+                    .commit_status(commit_id.to_string().into(), http_client.clone())
+                    .await
+                {
+                    Ok(status) => statuses.push((commit_id, status)),
+                    Err(err) => {
+                        warn!(target: "jj_ui", "failed to fetch CI status: {err:?}");
+                        break;
+                    }
+                }
+            }
+            panel
+                .update(cx, |panel, cx| {
+                    let checked_at = Instant::now();
+                    for (commit_id, status) in statuses {
+                        let pending_checks = if status == Some(CommitStatus::Pending) {
+                            panel
+                                .ci_statuses
+                                .get(&commit_id)
+                                .map_or(0, |check| check.pending_checks)
+                                + 1
+                        } else {
+                            0
+                        };
+                        panel.ci_statuses.insert(
+                            commit_id,
+                            CiStatusCheck {
+                                status,
+                                checked_at,
+                                pending_checks,
+                            },
+                        );
+                    }
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    /// The hosting provider remote of the git repository colocated with the selected jj
+    /// repository, if any.
+    fn hosting_remote(&self, cx: &App) -> Option<GitRemote> {
+        let repo_id = self.selected_repo?;
+        let project = self.project.read(cx);
+        let repo_root = project.jj_store()?.read(cx).repository_root(repo_id)?;
+        let remote_url = project
+            .git_store()
+            .read(cx)
+            .repositories()
+            .values()
+            .find_map(|repository| {
+                let repository = repository.read(cx);
+                if repository.work_directory_abs_path != repo_root {
+                    return None;
+                }
+                repository
+                    .remote_upstream_url
+                    .clone()
+                    .or_else(|| repository.remote_origin_url.clone())
+            })?;
+        let registry = GitHostingProviderRegistry::try_global(cx)?;
+        let (host, parsed_remote) = parse_git_remote_url(registry, &remote_url)?;
+        Some(GitRemote {
+            host,
+            owner: parsed_remote.owner.into(),
+            repo: parsed_remote.repo.into(),
+        })
+    }
+
+    fn trigger_restack(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        if let Some(task) =
+            store.update(cx, |store, cx| store.restack(repo_id, COMMIT_LOG_LIMIT, cx))
+        {
+            self.restack_candidates.clear();
+            self.spawn_store_task("jj restack", repo_id, task, true, window, cx);
+        }
+    }
+
+    /// Looks for changes whose diffs already landed in trunk and, after listing them for the
+    /// user to confirm, abandons them all in one operation.
+    fn clean_up_landed_changes(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        let Some(task) = store.read(cx).landed_changes(repo_id, COMMIT_LOG_LIMIT, cx) else {
+            return;
+        };
+        let workspace = self._workspace.clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            let landed = match task.await {
+                Ok(landed) => landed,
+                Err(err) => {
+                    panel
+                        .update(cx, |panel, cx| {
+                            panel.error =
+                                Some(format!("Failed to find landed changes: {err}").into());
+                            cx.notify();
+                        })
+                        .ok();
+                    return;
+                }
+            };
+            if landed.is_empty() {
+                workspace
+                    .update(cx, |workspace, cx| {
+                        workspace.show_toast(
+                            Toast::new(
+                                NotificationId::unique::<JjOperationToast>(),
+                                "No landed changes to clean up",
+                            )
+                            .autohide(),
+                            cx,
+                        );
+                    })
+                    .ok();
+                return;
+            }
+            let detail = landed
+                .iter()
+                .map(|commit| {
+                    let subject = commit.description.lines().next().unwrap_or_default().trim();
+                    let subject = if subject.is_empty() {
+                        "(no description set)"
+                    } else {
+                        subject
+                    };
+                    format!("{} {subject}", short_change_hash(&commit.change_id))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let message = match landed.len() {
+                1 => "Abandon 1 change already in trunk?".to_string(),
+                count => format!("Abandon {count} changes already in trunk?"),
+            };
+            let changes_with_diffs = landed
+                .iter()
+                .filter(|commit| !commit.is_empty)
+                .map(|commit| commit.change_id.clone())
+                .collect::<Vec<_>>();
+            let answers: &[&str] = if changes_with_diffs.is_empty() {
+                &["Abandon", "Cancel"]
+            } else {
+                &["Abandon", "Copy Diffs and Abandon", "Cancel"]
+            };
+            let Ok(answer) = panel.update_in(cx, |_, window, cx| {
+                window.prompt(PromptLevel::Warning, &message, Some(&detail), answers, cx)
+            }) else {
+                return;
+            };
+            match answer.await {
+                Ok(0) => {}
+                Ok(1) if !changes_with_diffs.is_empty() => {
+                    if !copy_diffs_before_abandoning(
+                        &store,
+                        repo_id,
+                        &changes_with_diffs,
+                        &panel,
+                        cx,
+                    )
+                    .await
+                    {
+                        return;
+                    }
+                }
+                _ => return,
+            }
+            let change_ids = landed
+                .into_iter()
+                .map(|commit| commit.change_id)
+                .collect::<Vec<_>>();
+            let Some(task) = store
+                .update(cx, |store, cx| {
+                    store.abandon_changes(repo_id, change_ids, cx)
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.spawn_store_task("jj abandon", repo_id, task, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn new_change(
+        &mut self,
+        repo_id: ProjectEntryId,
+        parent_change_id: Option<ChangeId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let new_parent = parent_change_id.as_ref().and_then(|change_id| {
+            self.commits
+                .iter()
+                .find(|commit| &commit.change_id == change_id)
+                .map(|commit| commit.commit_id.clone())
+        });
+        self.confirm_leaving_git_branch(new_parent, window, cx, move |panel, window, cx| {
+            if let Some(task) = store.update(cx, |store, cx| {
+                store.new_change(repo_id, parent_change_id, String::new(), cx)
+            }) {
+                panel.spawn_store_task("jj new", repo_id, task, true, window, cx);
+            }
+        });
+    }
+
+    fn profile_repository(
+        &mut self,
+        repo_id: ProjectEntryId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) = store
+            .read(cx)
+            .profile_repository(repo_id, COMMIT_LOG_LIMIT, cx)
+        else {
+            return;
+        };
+        self.open_text_in_editor("jj profile".to_string(), "Markdown", task, window, cx);
+    }
+
+    fn park_working_copy(
+        &mut self,
+        repo_id: ProjectEntryId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| store.park_working_copy(repo_id, cx)) {
+            self.spawn_store_task("jj park", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn unpark_working_copy(
+        &mut self,
+        repo_id: ProjectEntryId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| store.unpark(repo_id, cx)) {
+            self.spawn_store_task("jj unpark", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn keep_divergent_commit(
+        &mut self,
+        repo_id: ProjectEntryId,
+        change_id: ChangeId,
+        commit_id: CommitId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.keep_divergent_commit(repo_id, change_id, commit_id, cx)
+        }) {
+            self.divergent_change = None;
+            self.spawn_store_task("jj abandon", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn start_loading_indicator_timer(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.cancel_loading_indicator();
+        let panel = cx.entity().downgrade();
+        let timer = cx.background_executor().timer(Duration::from_millis(500));
+        self.loading_indicator_task = Some(cx.spawn_in(window, async move |_, cx| {
+            timer.await;
+            if let Some(panel) = panel.upgrade() {
+                let _ = panel.update(cx, |panel, cx| {
+                    if panel.is_loading {
+                        panel.show_loading_indicator = true;
+                        cx.notify();
+                    }
+                });
+            }
+        }));
+    }
+
+    fn cancel_loading_indicator(&mut self) {
+        if self.loading_indicator_task.take().is_some() {
+            self.show_loading_indicator = false;
+        }
+    }
+
+    fn ensure_store_subscription(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(store) = self.project.read(cx).jj_store().cloned() {
+            if self._store_subscription.is_none() {
+                let subscription = cx.observe_in(&store, window, |panel, _, window, cx| {
+                    panel.handle_store_updated(window, cx);
+                });
+                self._store_subscription = Some(subscription);
+            }
+        } else {
+            self._store_subscription.take();
+        }
+    }
+
+    fn handle_store_updated(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.is_active {
+            self.needs_refresh = true;
+            return;
+        }
+        self.refresh_rewritten_commits(window, cx);
+        self.request_refresh(window, cx);
+    }
+
+    /// Marks rows whose change has been rewritten as stale and reloads them one by one,
+    /// so they don't show outdated data until the full log reload completes.
+    fn refresh_rewritten_commits(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if self.commits.is_empty() {
+            return;
+        }
+        let displayed = self
+            .commits
+            .iter()
+            .map(|commit| (commit.commit_id.clone(), commit.change_id.clone()))
+            .collect();
+        let Some(task) = store.read(cx).rewritten_commits(repo_id, displayed, cx) else {
+            return;
+        };
+        self._freshness_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let rewritten = match task.await {
+                Ok(rewritten) => rewritten,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to check commit freshness: {err:?}");
+                    return;
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    for commit_id in rewritten {
+                        panel.refresh_commit_row(repo_id, &store, commit_id, cx);
+                    }
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    fn refresh_commit_row(
+        &mut self,
+        repo_id: ProjectEntryId,
+        store: &Entity<JjStore>,
+        commit_id: CommitId,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(change_id) = self
+            .commits
+            .iter()
+            .find(|commit| commit.commit_id == commit_id)
+            .map(|commit| commit.change_id.clone())
+        else {
+            return;
+        };
+        if !self.stale_commits.insert(commit_id.clone()) {
+            return;
+        }
+        let Some(task) = store.read(cx).commit_for_change(repo_id, change_id, cx) else {
+            return;
+        };
+        cx.spawn(async move |panel, cx| {
+            let latest = match task.await {
+                Ok(latest) => latest,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to reload rewritten commit: {err:?}");
+                    return;
+                }
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    // Abandoned changes stay marked as stale until the next full reload.
+                    let Some(latest) = latest else {
+                        return;
+                    };
+                    if let Some(row) = panel
+                        .commits
+                        .iter_mut()
+                        .find(|commit| commit.commit_id == commit_id)
+                    {
+                        *row = latest;
+                        panel.stale_commits.remove(&commit_id);
+                        cx.notify();
+                    }
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn focus_in(_this: &mut Self, _: &mut Window, cx: &mut Context<Self>) {
+        info!(target: "jj_ui", "JJ panel focused");
+        cx.emit(PanelEvent::Activate);
+    }
+
+    fn format_timestamp(timestamp: i64) -> String {
+        let nanos = (timestamp as i128) * 1_000_000;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .ok()
+            .and_then(|time| time.format(&Rfc3339).ok())
+            .unwrap_or_else(|| "unknown time".to_string())
+    }
+
+    fn refresh_action(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        info!(
+            target: "jj_ui",
+            "refresh pressed (selected_repo={:?})",
+            self.selected_repo
+        );
+        self.request_refresh(window, cx);
+    }
+
+    fn select_repository(
+        &mut self,
+        repo_id: ProjectEntryId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.selected_repo == Some(repo_id) {
+            return;
+        }
+        self.selected_repo = Some(repo_id);
+        self.merge_parents.clear();
+        self.request_refresh(window, cx);
+    }
+
+    fn select_repository_at(
+        &mut self,
+        work_directory: &Path,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let repo_id = self
+            .project
+            .read(cx)
+            .jj_store()
+            .and_then(|store| store.read(cx).repository_for_work_directory(work_directory));
+        if let Some(repo_id) = repo_id {
+            self.select_repository(repo_id, window, cx);
+        }
+    }
+
+    /// The git repository sharing the selected repository's working copy, if they're colocated.
+    fn colocated_git_repository(&self, cx: &App) -> Option<Entity<Repository>> {
+        let repo_id = self.selected_repo?;
+        let project = self.project.read(cx);
+        let root = project.jj_store()?.read(cx).repository_root(repo_id)?;
+        project
+            .git_store()
+            .read(cx)
+            .repositories()
+            .values()
+            .find(|repository| repository.read(cx).work_directory_abs_path == root)
+            .cloned()
+    }
+
+    fn render_view_as_git_button(&self, cx: &App) -> Option<impl IntoElement> {
+        let git_repository = self.colocated_git_repository(cx)?;
+        Some(
+            Button::new("jj-view-as-git", "View as git")
+                .style(ButtonStyle::Subtle)
+                .label_size(LabelSize::Small)
+                .tooltip(Tooltip::text("Switch to the Git Panel"))
+                .on_click(move |_, window, cx| {
+                    git_repository.update(cx, |repository, cx| {
+                        repository.set_as_active_repository(cx);
+                    });
+                    window.dispatch_action(zed_actions::jj::ViewAsGit.boxed_clone(), cx);
+                }),
+        )
+    }
+
+    /// Selects the repository containing `change_id` and highlights that change in the log.
+    pub fn reveal_change(
+        &mut self,
+        repo_id: ProjectEntryId,
+        change_id: ChangeId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.selected_change = Some(change_id);
+        if self.selected_repo == Some(repo_id) {
+            cx.notify();
+        } else {
+            self.selected_repo = Some(repo_id);
+            self.merge_parents.clear();
+            self.request_refresh(window, cx);
+        }
+    }
+
+    fn toggle_merge_parent(&mut self, change_id: &ChangeId, cx: &mut Context<Self>) {
+        if let Some(index) = self.merge_parents.iter().position(|id| id == change_id) {
+            self.merge_parents.remove(index);
+        } else {
+            self.merge_parents.push(change_id.clone());
+        }
+        cx.notify();
+    }
+
+    fn new_merge_change(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        if self.merge_parents.len() < 2 {
+            return;
+        }
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let parent_change_ids = mem::take(&mut self.merge_parents);
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.new_merge_change(repo_id, parent_change_ids, String::new(), cx)
+        }) {
+            self.spawn_store_task("jj new", repo_id, task, true, window, cx);
+        }
+    }
+
+    /// The change the keyboard actions apply to: the one highlighted in the log, else the
+    /// working-copy change.
+    fn selected_commit_target(&self, cx: &App) -> Option<CommitMenuTarget> {
+        if self.is_guest(cx) || self.is_read_only(cx) {
+            return None;
+        }
+        let repo_id = self.selected_repo?;
+        let commit = match &self.selected_change {
+            Some(change_id) => self
+                .commits
+                .iter()
+                .find(|commit| &commit.change_id == change_id),
+            None => self.commits.iter().find(|commit| commit.is_current),
+        }?;
+        Some(CommitMenuTarget {
+            repo_id,
+            commit: commit.clone(),
+        })
+    }
+
+    fn edit_selected_change(
+        &mut self,
+        _: &EditSelectedChange,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(target) = self.selected_commit_target(cx) {
+            self.trigger_edit_change(&target.commit, window, cx);
+        }
+    }
+
+    fn rename_selected_change(
+        &mut self,
+        _: &RenameSelectedChange,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(target) = self.selected_commit_target(cx) {
+            self.show_rename_modal(target, window, cx);
+        }
+    }
+
+    fn abandon_selected_change(
+        &mut self,
+        _: &AbandonSelectedChange,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(target) = self.selected_commit_target(cx) {
+            self.abandon_change(&target, window, cx);
+        }
+    }
+
+    fn squash_selected_change(
+        &mut self,
+        _: &SquashSelectedChange,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(target) = self.selected_commit_target(cx) {
+            self.squash_change(&target, None, window, cx);
+        }
+    }
+
+    fn toggle_zoom(
+        &mut self,
+        _: &workspace::ToggleZoom,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.is_zoomed {
+            cx.emit(PanelEvent::ZoomOut);
+        } else {
+            if !self.focus_handle.contains_focused(window, cx) {
+                window.focus(&self.focus_handle);
+            }
+            cx.emit(PanelEvent::ZoomIn);
+        }
+    }
+
+    fn toggle_key_legend(&mut self, _: &ToggleKeyLegend, _: &mut Window, cx: &mut Context<Self>) {
+        self.show_key_legend = !self.show_key_legend;
+        cx.notify();
+    }
+
+    /// Lists the keys bound to the selected-change actions in the panel, read from the keymap
+    /// so user rebindings show up. Actions without a binding are left out.
+    fn render_key_legend(&self, window: &Window, cx: &mut Context<Self>) -> Option<AnyElement> {
+        if !self.show_key_legend {
+            return None;
+        }
+        let target = self.selected_commit_target(cx)?;
+        let actions: [(Box<dyn Action>, &str); 4] = [
+            (Box::new(EditSelectedChange), "Edit"),
+            (Box::new(RenameSelectedChange), "Rename"),
+            (Box::new(AbandonSelectedChange), "Abandon"),
+            (Box::new(SquashSelectedChange), "Squash"),
+        ];
+        let entries = actions
+            .into_iter()
+            .filter_map(|(action, label)| {
+                let key_binding =
+                    ui::KeyBinding::for_action_in(action.as_ref(), &self.focus_handle, cx)
+                        .size(rems_from_px(12.));
+                key_binding.has_binding(window).then(|| {
+                    h_flex().gap(rems(0.25)).child(key_binding).child(
+                        Label::new(label)
+                            .size(LabelSize::XSmall)
+                            .color(Color::Muted),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return None;
+        }
+        Some(
+            h_flex()
+                .justify_between()
+                .pt(rems(0.25))
+                .border_t_1()
+                .border_color(cx.theme().colors().border_variant)
+                .child(
+                    h_flex()
+                        .flex_wrap()
+                        .gap(rems(0.75))
+                        .child(
+                            Label::new(short_change_hash(&target.commit.change_id))
+                                .size(LabelSize::XSmall)
+                                .color(Color::Accent),
+                        )
+                        .children(entries),
+                )
+                .child(
+                    IconButton::new("jj-hide-key-legend", IconName::Close)
+                        .icon_size(IconSize::XSmall)
+                        .tooltip(Tooltip::text("Hide Keys"))
+                        .on_click(cx.listener(|panel, _, window, cx| {
+                            panel.toggle_key_legend(&ToggleKeyLegend, window, cx);
+                        })),
+                )
+                .into_any_element(),
+        )
+    }
+
+    fn close_context_menu(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        // Dropping the menu's subscription means its `DismissEvent` never arrives.
+        if self.context_menu.take().is_some() {
+            self.pop_focus(window, cx);
+        }
+    }
+
+    fn pop_focus(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(change_id) = self.focus_stack.pop() else {
+            return;
+        };
+        cx.notify();
+        if !self.focus_stack.is_empty() {
+            return;
+        }
+        if let Some(change_id) = change_id
+            && self
+                .commits
+                .iter()
+                .any(|commit| commit.change_id == change_id)
+        {
+            self.selected_change = Some(change_id);
+        }
+        // Deferred so the modal layer has restored whatever it focused before, which is often the
+        // menu that opened it and no longer exists. Focus the user moved elsewhere is left alone.
+        cx.defer_in(window, |panel, window, cx| {
+            let focused_elsewhere = window
+                .focused(cx)
+                .is_some_and(|focused| !panel.focus_handle.contains(&focused, window));
+            if !focused_elsewhere {
+                window.focus(&panel.focus_handle);
+            }
+        });
+    }
+
+    /// Opens a modal through the workspace, returning focus and the selection to the panel once
+    /// it's dismissed.
+    fn open_modal<V: ModalView>(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        build: impl FnOnce(&mut Window, &mut Context<V>) -> V + 'static,
+    ) {
+        let Some(workspace) = self._workspace.upgrade() else {
+            return;
+        };
+        let modal = workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(window, cx, build);
+            workspace.active_modal::<V>(cx)
+        });
+        // A modal replacing one of ours is hidden without being dismissed.
+        if self._modal_subscription.take().is_some() {
+            self.focus_stack.pop();
+        }
+        let Some(modal) = modal else {
+            return;
+        };
+        self.focus_stack.push(self.selected_change.clone());
+        let subscription =
+            cx.subscribe_in(&modal, window, |panel, _, _: &DismissEvent, window, cx| {
+                panel._modal_subscription = None;
+                panel.pop_focus(window, cx);
+            });
+        self._modal_subscription = Some(subscription);
+    }
+
+    /// Escape backs out of the panel's state one step at a time: the selection, the merge
+    /// parents, then the log filters and the zoom, and only then collapses the panel.
+    fn cancel(&mut self, _: &menu::Cancel, window: &mut Window, cx: &mut Context<Self>) {
+        if self.past_operation.take().is_some() {
+            cx.notify();
+        } else if self.selected_change.take().is_some() {
+            cx.notify();
+        } else if !self.merge_parents.is_empty() {
+            self.merge_parents.clear();
+            cx.notify();
+        } else if self.revset_filter.is_some() {
+            self.set_revset_filter(None, window, cx);
+        } else if self.date_filter != LogDateFilter::default() {
+            self.set_date_filter(LogDateFilter::default(), window, cx);
+        } else if self.is_zoomed {
+            cx.emit(PanelEvent::ZoomOut);
+        } else if let Some(workspace) = self._workspace.upgrade() {
+            workspace.update(cx, |workspace, cx| {
+                workspace.close_panel::<Self>(window, cx)
+            });
+        }
+    }
+
+    fn trigger_edit_change(
+        &mut self,
+        commit: &JjCommitSummary,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        if self.is_guest(cx) || self.is_read_only(cx) {
+            return;
+        }
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        let change_id = commit.change_id.clone();
+        let new_parent = commit.parent_ids.first().cloned();
+        self.confirm_leaving_git_branch(new_parent, window, cx, move |panel, window, cx| {
+            if let Some(task) =
+                store.update(cx, |store, cx| store.edit_change(repo_id, change_id, cx))
+            {
+                panel.spawn_store_task("jj edit", repo_id, task, true, window, cx);
+            }
+        });
+    }
+
+    /// Whether this panel belongs to a guest of a shared project, who can browse the host's
+    /// repositories but only request operations from the host.
+    fn is_guest(&self, cx: &App) -> bool {
+        self.project
+            .read(cx)
+            .jj_store()
+            .is_some_and(|store| store.read(cx).is_remote())
+    }
+
+    fn is_read_only(&self, cx: &App) -> bool {
+        self.project
+            .read(cx)
+            .jj_store()
+            .is_some_and(|store| store.read(cx).is_read_only())
+    }
+
+    fn request_operation(
+        &mut self,
+        repo_id: ProjectEntryId,
+        request: JjOperationRequest,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let description = request.describe();
+        let Some(task) = store.read(cx).request_operation(repo_id, request, cx) else {
+            return;
+        };
+        let workspace = self._workspace.clone();
+        cx.spawn_in(window, async move |panel, cx| match task.await {
+            Ok(()) => {
+                workspace
+                    .update(cx, |workspace, cx| {
+                        workspace.show_toast(
+                            Toast::new(
+                                NotificationId::unique::<JjOperationToast>(),
+                                format!("Asked the host to {description}"),
+                            )
+                            .autohide(),
+                            cx,
+                        );
+                    })
+                    .ok();
+            }
+            Err(err) => {
+                warn!(target: "jj_ui", "failed to request operation: {err:?}");
+                panel
+                    .update(cx, |panel, cx| {
+                        panel.error = Some(format!("Failed to ask the host: {err}").into());
+                        cx.notify();
+                    })
+                    .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn deploy_guest_commit_context_menu(
+        &mut self,
+        target: CommitMenuTarget,
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let panel = cx.entity().downgrade();
+        let menu = ContextMenu::build(window, cx, move |menu, _window, _cx| {
+            let change_id = target.commit.change_id.clone();
+            let requests = [
+                (
+                    "Request new change on top",
+                    JjOperationRequest::NewChange {
+                        parent_change_id: change_id.clone(),
+                    },
+                ),
+                (
+                    "Request squash into parent",
+                    JjOperationRequest::SquashChange {
+                        change_id: change_id.clone(),
+                    },
+                ),
+                (
+                    "Request abandon",
+                    JjOperationRequest::AbandonChange {
+                        change_id: change_id.clone(),
+                    },
+                ),
+            ];
+            let rename_target = target.clone();
+            let rename_panel = panel.clone();
+            let menu = menu.header("Ask the host to…").entry(
+                "Request rename…",
+                None,
+                move |window, cx| {
+                    if let Some(panel) = rename_panel.upgrade() {
+                        panel.update(cx, |panel, cx| {
+                            panel.show_rename_modal(rename_target.clone(), window, cx);
+                        });
+                    }
+                },
+            );
+            requests
+                .into_iter()
+                .filter(|(_, request)| {
+                    !matches!(request, JjOperationRequest::SquashChange { .. })
+                        || target.commit.parent_ids.len() == 1
+                })
+                .fold(menu, |menu, (label, request)| {
+                    let panel = panel.clone();
+                    let repo_id = target.repo_id;
+                    menu.entry(label, None, move |window, cx| {
+                        if let Some(panel) = panel.upgrade() {
+                            panel.update(cx, |panel, cx| {
+                                panel.request_operation(repo_id, request.clone(), window, cx);
+                            });
+                        }
+                    })
+                })
+        });
+        self.set_context_menu(menu, position, window, cx);
+    }
+
+    /// Only the entries that leave the repository as it is.
+    fn deploy_read_only_commit_context_menu(
+        &mut self,
+        target: CommitMenuTarget,
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let panel = cx.entity().downgrade();
+        let menu = ContextMenu::build(window, cx, move |menu, _window, _cx| {
+            let copy_target = target.clone();
+            let copy_panel = panel.clone();
+            let export_target = target.clone();
+            let export_panel = panel.clone();
+            menu.entry("Copy diff", None, move |window, cx| {
+                if let Some(panel) = copy_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.copy_change_diff(&copy_target, window, cx);
+                    });
+                }
+            })
+            .entry("Export as patch…", None, move |window, cx| {
+                if let Some(panel) = export_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.export_change_patch(&export_target, window, cx);
+                    });
+                }
+            })
+        });
+        self.set_context_menu(menu, position, window, cx);
+    }
+
+    fn show_rename_modal(
+        &mut self,
+        target: CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let project = self.project.clone();
+        let panel = cx.entity().downgrade();
+        let repo_label = self.repository_label(target.repo_id);
+        self.open_modal(window, cx, move |window, cx| {
+            RenameChangeModal::new(project, panel, target, repo_label, window, cx)
+        });
+    }
+
+    fn show_set_bookmark_modal(
+        &mut self,
+        target: CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let project = self.project.clone();
+        let panel = cx.entity().downgrade();
+        let repo_label = self.repository_label(target.repo_id);
+        self.open_modal(window, cx, move |window, cx| {
+            SetBookmarkModal::new(project, panel, target, repo_label, window, cx)
+        });
+    }
+
+    fn duplicate_change(
+        &mut self,
+        target: &CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.duplicate_change(target.repo_id, target.commit.change_id.clone(), cx)
+        }) {
+            self.spawn_store_task("jj duplicate", target.repo_id, task, true, window, cx);
+        }
+    }
+
+    fn squash_change(
+        &mut self,
+        target: &CommitMenuTarget,
+        paths: Option<Vec<RepoPathBuf>>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let change_id = target.commit.change_id.clone();
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.squash_change(target.repo_id, change_id.clone(), paths, cx)
+        }) {
+            self.merge_parents.retain(|id| id != &change_id);
+            self.spawn_store_task("jj squash", target.repo_id, task, true, window, cx);
+        }
+    }
+
+    /// Sets `path` in the working copy back to how it is in the target change.
+    fn restore_from_change(
+        &mut self,
+        target: &CommitMenuTarget,
+        path: RepoPathBuf,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.restore_paths(
+                target.repo_id,
+                vec![path],
+                Some(target.commit.change_id.clone()),
+                cx,
+            )
+        }) {
+            self.spawn_store_task("jj restore", target.repo_id, task, true, window, cx);
+        }
+    }
+
+    /// Lists the change's files in a menu under `header`, running `on_select` for the chosen one,
+    /// so that single files can be acted on without touching the rest of the change.
+    fn deploy_changed_files_menu(
+        &mut self,
+        target: CommitMenuTarget,
+        header: &'static str,
+        on_select: fn(&mut Self, &CommitMenuTarget, RepoPathBuf, &mut Window, &mut Context<Self>),
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) =
+            store
+                .read(cx)
+                .changed_paths(target.repo_id, target.commit.change_id.clone(), cx)
+        else {
+            return;
+        };
+        cx.spawn_in(window, async move |panel, cx| {
+            let paths = match task.await {
+                Ok(paths) => paths,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to list changed files: {err:?}");
+                    return;
+                }
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    let panel_handle = cx.entity().downgrade();
+                    let menu = ContextMenu::build(window, cx, move |menu, _window, _cx| {
+                        let menu = menu.header(header);
+                        if paths.is_empty() {
+                            return menu.label("No changed files");
+                        }
+                        paths.into_iter().fold(menu, |menu, path| {
+                            let panel = panel_handle.clone();
+                            let target = target.clone();
+                            menu.entry(
+                                path.as_internal_file_string().to_string(),
+                                None,
+                                move |window, cx| {
+                                    if let Some(panel) = panel.upgrade() {
+                                        panel.update(cx, |panel, cx| {
+                                            on_select(panel, &target, path.clone(), window, cx);
+                                        });
+                                    }
+                                },
+                            )
+                        })
+                    });
+                    panel.set_context_menu(menu, position, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn abandon_change(
+        &mut self,
+        target: &CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let summary = target
+            .commit
+            .description
+            .lines()
+            .next()
+            .filter(|line| !line.trim().is_empty())
+            .unwrap_or("(no description set)");
+        let message = format!(
+            "Abandon change {} \"{summary}\"?",
+            short_change_hash(&target.commit.change_id)
+        );
+        let mut detail = "Its descendants will be rebased onto its parents.".to_string();
+        if let Some(repo_label) = self.repository_label(target.repo_id) {
+            detail.push_str(&format!("\nRepository: {repo_label}"));
+        }
+        let has_diff = !target.commit.is_empty;
+        let answers: &[&str] = if has_diff {
+            &["Abandon", "Copy Diff and Abandon", "Cancel"]
+        } else {
+            &["Abandon", "Cancel"]
+        };
+        let answer = window.prompt(PromptLevel::Warning, &message, Some(&detail), answers, cx);
+        let repo_id = target.repo_id;
+        let change_id = target.commit.change_id.clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            match answer.await {
+                Ok(0) => {}
+                Ok(1) if has_diff => {
+                    let change_ids = [change_id.clone()];
+                    if !copy_diffs_before_abandoning(&store, repo_id, &change_ids, &panel, cx).await
+                    {
+                        return;
+                    }
+                }
+                _ => return,
+            }
+            let Some(task) = store
+                .update(cx, |store, cx| {
+                    store.abandon_change(repo_id, change_id.clone(), cx)
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.merge_parents.retain(|id| id != &change_id);
+                    if panel.selected_change.as_ref() == Some(&change_id) {
+                        panel.selected_change = None;
+                    }
+                    panel.spawn_store_task("jj abandon", repo_id, task, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    /// Copies the change's diff so its contents can be recovered without the op log.
+    fn copy_change_diff(
+        &mut self,
+        target: &CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) =
+            store
+                .read(cx)
+                .change_diff(target.repo_id, target.commit.change_id.clone(), cx)
+        else {
+            return;
+        };
+        cx.spawn(async move |panel, cx| {
+            let result = task.await;
+            panel
+                .update(cx, |panel, cx| {
+                    match result {
+                        Ok(diff) => cx.write_to_clipboard(ClipboardItem::new_string(diff)),
+                        Err(err) => {
+                            warn!(target: "jj_ui", "failed to copy change diff: {err:?}");
+                            panel.error = Some(format!("Failed to copy diff: {err}").into());
+                        }
+                    }
+                    cx.notify();
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn open_parent_diff(
+        &mut self,
+        commit: &JjCommitSummary,
+        parent_id: CommitId,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let title = format!(
+            "{} against {}",
+            short_change_hash(&commit.change_id),
+            short_commit_hash(&parent_id)
+        );
+        let Some(task) =
+            store
+                .read(cx)
+                .diff_against_parent(repo_id, commit.change_id.clone(), parent_id, cx)
+        else {
+            return;
+        };
+        self.open_text_in_editor(title, "Diff", task, window, cx);
+    }
+
+    fn open_text_in_editor(
+        &mut self,
+        title: String,
+        language_name: &'static str,
+        task: Task<Result<String>>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let workspace = self._workspace.clone();
+        let languages = self.project.read(cx).languages().clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            let text = match task.await {
+                Ok(text) => text,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to load {title}: {err:?}");
+                    panel
+                        .update(cx, |panel, cx| {
+                            panel.error = Some(format!("Failed to load {title}: {err}").into());
+                            cx.notify();
+                        })
+                        .ok();
+                    return;
+                }
+            };
+            let language = languages.language_for_name(language_name).await.ok();
+            workspace
+                .update_in(cx, |workspace, window, cx| {
+                    let project = workspace.project().clone();
+                    let buffer = project.update(cx, |project, cx| {
+                        project.create_local_buffer(&text, language, false, cx)
+                    });
+                    let buffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx).with_title(title));
+                    let editor = cx.new(|cx| {
+                        let mut editor = Editor::for_multibuffer(buffer, Some(project), window, cx);
+                        editor.set_read_only(true);
+                        editor
+                    });
+                    workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn render_merge_parents(
+        &self,
+        commit: &JjCommitSummary,
+        cx: &mut Context<Self>,
+    ) -> Option<AnyElement> {
+        if commit.parent_ids.len() < 2 {
+            return None;
+        }
+        Some(
+            v_flex()
+                .gap(rems(0.1))
+                .children(
+                    commit
+                        .parent_ids
+                        .iter()
+                        .enumerate()
+                        .map(|(index, parent_id)| {
+                            let description = self
+                                .commits
+                                .iter()
+                                .find(|candidate| &candidate.commit_id == parent_id)
+                                .and_then(|parent| parent.description.lines().next())
+                                .filter(|line| !line.trim().is_empty())
+                                .unwrap_or("(no description set)")
+                                .to_string();
+                            let diff_commit = commit.clone();
+                            let diff_parent = parent_id.clone();
+                            h_flex()
+                                .gap(rems(0.25))
+                                .items_center()
+                                .child(
+                                    Label::new(format!(
+                                        "parent {}: {description}",
+                                        short_commit_hash(parent_id)
+                                    ))
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted),
+                                )
+                                .child(
+                                    Button::new(
+                                        SharedString::from(format!(
+                                            "jj-parent-diff-{}-{index}",
+                                            short_commit_hash(&commit.commit_id)
+                                        )),
+                                        "Diff",
+                                    )
+                                    .style(ButtonStyle::Subtle)
+                                    .label_size(LabelSize::XSmall)
+                                    .tooltip(Tooltip::text("Diff against this parent"))
+                                    .on_click(cx.listener(
+                                        move |panel, _, window, cx| {
+                                            cx.stop_propagation();
+                                            panel.open_parent_diff(
+                                                &diff_commit,
+                                                diff_parent.clone(),
+                                                window,
+                                                cx,
+                                            );
+                                        },
+                                    )),
+                                )
+                        }),
+                )
+                .into_any_element(),
+        )
+    }
+
+    fn export_change_patch(
+        &mut self,
+        target: &CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(directory) = store.read(cx).repository_root(target.repo_id) else {
+            return;
+        };
+        let suggested_name = format!("{}.patch", short_change_hash(&target.commit.change_id));
+        let destination = cx.prompt_for_new_path(&directory, Some(&suggested_name));
+        let repo_id = target.repo_id;
+        let change_id = target.commit.change_id.clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            let Ok(Ok(Some(destination))) = destination.await else {
+                return;
+            };
+            let Some(task) = store
+                .update(cx, |store, cx| {
+                    store.export_patch(repo_id, change_id, destination, cx)
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.spawn_store_task("jj export patch", repo_id, task, false, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn apply_patch_action(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Apply Patch".into()),
+        });
+        cx.spawn_in(window, async move |panel, cx| {
+            let Ok(Ok(Some(mut paths))) = paths.await else {
+                return;
+            };
+            let Some(source) = paths.pop() else {
+                return;
+            };
+            let Some(task) = store
+                .update(cx, |store, cx| store.import_patch(repo_id, source, cx))
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.spawn_store_task("jj apply patch", repo_id, task, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn deploy_commit_context_menu(
+        &mut self,
+        target: CommitMenuTarget,
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.is_guest(cx) {
+            self.deploy_guest_commit_context_menu(target, position, window, cx);
+            return;
+        }
+        if self.is_read_only(cx) {
+            self.deploy_read_only_commit_context_menu(target, position, window, cx);
+            return;
+        }
+        let panel = cx.entity().downgrade();
+        let push_label = format!("Push change to {}", self.git_remote());
+        let menu = ContextMenu::build(window, cx, move |menu, _window, _cx| {
+            let new_change_target = target.clone();
+            let new_change_panel = panel.clone();
+            let merge_parent_target = target.clone();
+            let merge_parent_panel = panel.clone();
+            let rename_target = target.clone();
+            let rename_panel = panel.clone();
+            let copy_target = target.clone();
+            let copy_panel = panel.clone();
+            let export_target = target.clone();
+            let export_panel = panel.clone();
+            let duplicate_target = target.clone();
+            let duplicate_panel = panel.clone();
+            let bookmark_target = target.clone();
+            let bookmark_panel = panel.clone();
+            let push_target = target.clone();
+            let push_panel = panel.clone();
+            let squash_target = target.clone();
+            let squash_panel = panel.clone();
+            let squash_files_target = target.clone();
+            let squash_files_panel = panel.clone();
+            let restore_target = target.clone();
+            let restore_panel = panel.clone();
+            let abandon_target = target.clone();
+            let abandon_panel = panel.clone();
+            let keep_target = target.clone();
+            let keep_panel = panel.clone();
+            menu.entry("New change on top", None, move |window, cx| {
+                if let Some(panel) = new_change_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.new_change(
+                            new_change_target.repo_id,
+                            Some(new_change_target.commit.change_id.clone()),
+                            window,
+                            cx,
+                        );
+                    });
+                }
+            })
+            .entry("Toggle merge parent", None, move |_window, cx| {
+                if let Some(panel) = merge_parent_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.toggle_merge_parent(&merge_parent_target.commit.change_id, cx);
+                    });
+                }
+            })
+            .entry("Rename change…", None, move |window, cx| {
+                if let Some(panel) = rename_panel.upgrade() {
+                    let _ = panel.update(cx, |panel, cx| {
+                        panel.show_rename_modal(rename_target.clone(), window, cx);
+                    });
+                }
+            })
+            .entry("Copy diff", None, move |window, cx| {
+                if let Some(panel) = copy_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.copy_change_diff(&copy_target, window, cx);
+                    });
+                }
+            })
+            .entry("Export as patch…", None, move |window, cx| {
+                if let Some(panel) = export_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.export_change_patch(&export_target, window, cx);
+                    });
+                }
+            })
+            .separator()
+            .entry("Duplicate change", None, move |window, cx| {
+                if let Some(panel) = duplicate_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.duplicate_change(&duplicate_target, window, cx);
+                    });
+                }
+            })
+            .entry("Set bookmark…", None, move |window, cx| {
+                if let Some(panel) = bookmark_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.show_set_bookmark_modal(bookmark_target.clone(), window, cx);
+                    });
+                }
+            })
+            .entry(push_label, None, move |window, cx| {
+                if let Some(panel) = push_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.push_change(&push_target, window, cx);
+                    });
+                }
+            })
+            .when(target.commit.parent_ids.len() == 1, |menu| {
+                menu.entry("Squash into parent", None, move |window, cx| {
+                    if let Some(panel) = squash_panel.upgrade() {
+                        panel.update(cx, |panel, cx| {
+                            panel.squash_change(&squash_target, None, window, cx);
+                        });
+                    }
+                })
+                .entry("Squash files into parent…", None, move |window, cx| {
+                    if let Some(panel) = squash_files_panel.upgrade() {
+                        panel.update(cx, |panel, cx| {
+                            panel.deploy_changed_files_menu(
+                                squash_files_target.clone(),
+                                "Squash into parent",
+                                |panel, target, path, window, cx| {
+                                    panel.squash_change(target, Some(vec![path]), window, cx);
+                                },
+                                position,
+                                window,
+                                cx,
+                            );
+                        });
+                    }
+                })
+            })
+            .entry(
+                "Restore files from this change…",
+                None,
+                move |window, cx| {
+                    if let Some(panel) = restore_panel.upgrade() {
+                        panel.update(cx, |panel, cx| {
+                            panel.deploy_changed_files_menu(
+                                restore_target.clone(),
+                                "Restore in working copy",
+                                Self::restore_from_change,
+                                position,
+                                window,
+                                cx,
+                            );
+                        });
+                    }
+                },
+            )
+            .entry("Abandon change…", None, move |window, cx| {
+                if let Some(panel) = abandon_panel.upgrade() {
+                    panel.update(cx, |panel, cx| {
+                        panel.abandon_change(&abandon_target, window, cx);
+                    });
+                }
+            })
+            .when(target.commit.is_divergent, |menu| {
+                menu.entry(
+                    "Keep this commit of the divergent change",
+                    None,
+                    move |window, cx| {
+                        if let Some(panel) = keep_panel.upgrade() {
+                            panel.update(cx, |panel, cx| {
+                                panel.keep_divergent_commit(
+                                    keep_target.repo_id,
+                                    keep_target.commit.change_id.clone(),
+                                    keep_target.commit.commit_id.clone(),
+                                    window,
+                                    cx,
+                                );
+                            });
+                        }
+                    },
+                )
+            })
+        });
+        self.set_context_menu(menu, position, window, cx);
+    }
+
+    fn set_context_menu(
+        &mut self,
+        menu: Entity<ContextMenu>,
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.close_context_menu(window, cx);
+        let subscription =
+            cx.subscribe_in(&menu, window, |this, _, _: &DismissEvent, window, cx| {
+                if this.context_menu.take().is_some() {
+                    this.pop_focus(window, cx);
+                }
+            });
+        window.focus(&menu.focus_handle(cx));
+        self.focus_stack.push(self.selected_change.clone());
+        self.context_menu = Some((menu, position, subscription));
+        cx.notify();
+    }
+
+    /// The repository's path when the panel lists several, so that messages about an operation
+    /// say where it ran.
+    fn repository_label(&self, repo_id: ProjectEntryId) -> Option<SharedString> {
+        if !self.shows_repository_selector() {
+            return None;
+        }
+        self.repositories
+            .iter()
+            .find(|repo| repo.id == repo_id)
+            .map(|repo| repo.relative_path.clone())
+    }
+
+    fn undo_last_operation(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| store.undo(repo_id, cx)) {
+            self.spawn_store_task("jj undo", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn git_fetch(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        let remote = self.git_remote();
+        let Some(task) = store.update(cx, |store, cx| {
+            store.git_fetch(repo_id, remote.to_string(), cx)
+        }) else {
+            return;
+        };
+        self._fetch_progress_task = Some(cx.spawn(async move |panel, cx| {
+            loop {
+                cx.background_executor()
+                    .timer(FETCH_PROGRESS_REDRAW_INTERVAL)
+                    .await;
+                if panel.update(cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        }));
+        let workspace = self._workspace.clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            let result = task.await;
+            panel
+                .update_in(cx, |panel, window, cx| match result {
+                    Ok(summary) => {
+                        panel._fetch_progress_task = None;
+                        panel.record_operation(repo_id, "jj git fetch", cx);
+                        workspace
+                            .update(cx, |workspace, cx| {
+                                workspace.show_toast(
+                                    Toast::new(
+                                        NotificationId::unique::<JjOperationToast>(),
+                                        format!("Fetched from {remote}: {}", summary.describe()),
+                                    )
+                                    .autohide(),
+                                    cx,
+                                );
+                            })
+                            .ok();
+                        panel.request_refresh(window, cx);
+                    }
+                    Err(err) => {
+                        panel._fetch_progress_task = None;
+                        warn!(target: "jj_ui", "jj git fetch failed: {err:?}");
+                        panel.error = Some(format!("jj git fetch failed: {err}").into());
+                        cx.notify();
+                    }
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn git_push(&mut self, bookmark: SharedString, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let remote = self.git_remote();
+        let Some(task) = store.update(cx, |store, cx| {
+            store.git_push(repo_id, bookmark.to_string(), remote.to_string(), cx)
+        }) else {
+            return;
+        };
+        self.spawn_push_task(repo_id, remote, task, window, cx);
+    }
+
+    /// `jj git push --change`: pushes the change under a generated `push-` bookmark, for review
+    /// tools that want one branch per change.
+    fn push_change(
+        &mut self,
+        target: &CommitMenuTarget,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let repo_id = target.repo_id;
+        let change_id = target.commit.change_id.clone();
+        let remote = self.git_remote();
+        let Some(task) = store.update(cx, |store, cx| {
+            store.git_push_change(repo_id, change_id, remote.to_string(), cx)
+        }) else {
+            return;
+        };
+        self.spawn_push_task(repo_id, remote, task, window, cx);
+    }
+
+    fn spawn_push_task(
+        &mut self,
+        repo_id: ProjectEntryId,
+        remote: SharedString,
+        task: Task<Result<PushSummary>>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let workspace = self._workspace.clone();
+        cx.spawn_in(window, async move |panel, cx| {
+            let result = task.await;
+            panel
+                .update_in(cx, |panel, window, cx| match result {
+                    Ok(summary) => {
+                        let bookmark = summary.bookmark;
+                        let message = if summary.pushed {
+                            panel.record_operation(repo_id, "jj git push", cx);
+                            panel.request_refresh(window, cx);
+                            format!("Pushed {bookmark} to {remote}")
+                        } else {
+                            format!("{bookmark} is already up to date on {remote}")
+                        };
+                        workspace
+                            .update(cx, |workspace, cx| {
+                                workspace.show_toast(
+                                    Toast::new(
+                                        NotificationId::unique::<JjOperationToast>(),
+                                        message,
+                                    )
+                                    .autohide(),
+                                    cx,
+                                );
+                            })
+                            .ok();
+                    }
+                    Err(err) => {
+                        warn!(target: "jj_ui", "jj git push failed: {err:?}");
+                        panel.error = Some(format!("jj git push failed: {err}").into());
+                        cx.notify();
+                    }
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn set_selected_remote(&mut self, remote: SharedString, cx: &mut Context<Self>) {
+        self.selected_remote = Some(remote);
+        cx.notify();
+    }
+
+    /// Picks the remote to fetch from and push to, for repositories with more than one.
+    fn render_remote_picker(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        if self.git_remotes.len() < 2 || self.is_guest(cx) || self.is_read_only(cx) {
+            return None;
+        }
+        let panel = cx.entity().downgrade();
+        let current = self.git_remote();
+        let names = self
+            .git_remotes
+            .iter()
+            .map(|remote| SharedString::from(remote.name.clone()))
+            .collect::<Vec<_>>();
+        Some(
+            PopoverMenu::new("jj-git-remote")
+                .trigger_with_tooltip(
+                    Button::new("jj-git-remote-button", current.clone())
+                        .style(ButtonStyle::Subtle)
+                        .label_size(LabelSize::Small)
+                        .icon(IconName::ChevronDown)
+                        .icon_position(IconPosition::End)
+                        .icon_size(IconSize::XSmall),
+                    Tooltip::text("Remote to Fetch From and Push To"),
+                )
+                .anchor(Corner::TopRight)
+                .menu(move |window, cx| {
+                    let panel = panel.clone();
+                    let names = names.clone();
+                    let current = current.clone();
+                    Some(ContextMenu::build(window, cx, move |menu, _, _| {
+                        names.into_iter().fold(menu, |menu, name| {
+                            let panel = panel.clone();
+                            menu.toggleable_entry(
+                                name.clone(),
+                                name == current,
+                                IconPosition::Start,
+                                None,
+                                move |_, cx| {
+                                    panel
+                                        .update(cx, |panel, cx| {
+                                            panel.set_selected_remote(name.clone(), cx);
+                                        })
+                                        .ok();
+                                },
+                            )
+                        })
+                    }))
+                }),
+        )
+    }
+
+    fn render_fetch_button(&self, cx: &mut Context<Self>) -> AnyElement {
+        let progress = self.selected_repo.and_then(|repo_id| {
+            let store = self.project.read(cx).jj_store()?;
+            store.read(cx).fetch_progress(repo_id)
+        });
+        if let Some(progress) = progress {
+            return Label::new(format!("Fetching… {}%", (progress * 100.).round() as u32))
+                .size(LabelSize::Small)
+                .color(Color::Muted)
+                .into_any_element();
+        }
+        IconButton::new("jj-git-fetch", IconName::CloudDownload)
+            .icon_size(IconSize::Small)
+            .disabled(self.selected_repo.is_none() || self.is_guest(cx) || self.is_read_only(cx))
+            .tooltip(Tooltip::text(format!("Fetch from {}", self.git_remote())))
+            .on_click(cx.listener(Self::git_fetch))
+            .into_any_element()
+    }
+
+    fn record_operation(
+        &self,
+        repo_id: ProjectEntryId,
+        description: &'static str,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(store) = self.project.read(cx).jj_store().cloned() {
+            store.update(cx, |store, cx| {
+                store.record_operation(repo_id, description, cx)
+            });
+        }
+    }
+
+    fn view_at_operation(
+        &mut self,
+        operation: JjSessionOperation,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) = store.read(cx).at_operation(
+            operation.repository_id,
+            operation.operation_id.clone(),
+            COMMIT_LOG_LIMIT,
+            cx,
+        ) else {
+            return;
+        };
+        self._past_operation_task = Some(cx.spawn_in(window, async move |panel, cx| {
+            let result = task.await;
+            panel
+                .update(cx, |panel, cx| {
+                    match result {
+                        Ok((commits, bookmarks)) => {
+                            panel.past_operation = Some(PastOperation {
+                                operation,
+                                commits,
+                                bookmarks,
+                            });
+                        }
+                        Err(err) => {
+                            warn!(target: "jj_ui", "failed to load operation: {err:?}");
+                            panel.error = Some(format!("Failed to load operation: {err}").into());
+                        }
+                    }
+                    cx.notify();
+                })
+                .ok();
+        }));
+    }
+
+    fn render_past_operation(&self, past: &PastOperation, cx: &mut Context<Self>) -> AnyElement {
+        let operation = &past.operation;
+        let short_id = &operation.operation_id[..12.min(operation.operation_id.len())];
+        let colors = cx.theme().colors();
+        v_flex()
+            .flex_1()
+            .min_h_0()
+            .gap(rems(0.5))
+            .child(
+                h_flex()
+                    .justify_between()
+                    .items_center()
+                    .p(px(4.0))
+                    .rounded(px(4.0))
+                    .border_1()
+                    .border_color(colors.border)
+                    .bg(colors.surface_background)
+                    .child(
+                        Label::new(format!("As of \"{}\" ({short_id})", operation.description))
+                            .size(LabelSize::Small),
+                    )
+                    .child(
+                        Button::new("jj-leave-past-operation", "Back to Present")
+                            .style(ButtonStyle::Filled)
+                            .on_click(cx.listener(|panel, _, _, cx| {
+                                panel.past_operation = None;
+                                cx.notify();
+                            })),
+                    ),
+            )
+            .when(!past.bookmarks.is_empty(), |view| {
+                view.child(
+                    h_flex()
+                        .flex_wrap()
+                        .gap(rems(0.5))
+                        .children(past.bookmarks.iter().map(|bookmark| {
+                            let name = match &bookmark.remote {
+                                Some(remote) => format!("{}@{remote}", bookmark.name),
+                                None => bookmark.name.clone(),
+                            };
+                            let label = match bookmark.target_ids.first() {
+                                Some(commit_id) => {
+                                    format!("{name} {}", short_commit_hash(commit_id))
+                                }
+                                None => name,
+                            };
+                            Label::new(label)
+                                .size(LabelSize::XSmall)
+                                .color(Color::Muted)
+                        })),
+                )
+            })
+            .child(
+                v_flex()
+                    .id("jj-past-operation-log")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .gap(rems(0.25))
+                    .children(past.commits.iter().map(|commit| {
+                        CommitRow::new(commit.clone(), Self::format_timestamp(commit.timestamp))
+                            .disabled(true)
+                    })),
+            )
+            .into_any_element()
+    }
+
+    fn undo_from_operation(
+        &mut self,
+        operation: JjSessionOperation,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let undone_count = store
+            .read(cx)
+            .session_operations()
+            .iter()
+            .skip_while(|candidate| **candidate != operation)
+            .filter(|candidate| candidate.repository_id == operation.repository_id)
+            .count();
+        let message = match undone_count {
+            1 => format!("Undo \"{}\"?", operation.description),
+            count => format!(
+                "Undo \"{}\" and the {} operations after it?",
+                operation.description,
+                count - 1
+            ),
+        };
+        let detail = self
+            .repository_label(operation.repository_id)
+            .map(|repo_label| format!("Repository: {repo_label}"));
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &message,
+            detail.as_deref(),
+            &["Undo", "Cancel"],
+            cx,
+        );
+        cx.spawn_in(window, async move |panel, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            let repo_id = operation.repository_id;
+            let Some(task) = store
+                .update(cx, |store, cx| {
+                    store.undo_from_operation(repo_id, operation.operation_id, cx)
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.spawn_store_task("jj op restore", repo_id, task, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn set_date_filter(
+        &mut self,
+        date_filter: LogDateFilter,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.date_filter != date_filter {
+            self.date_filter = date_filter;
+            self.request_refresh(window, cx);
+        }
+    }
+
+    fn set_revset_filter(
+        &mut self,
+        revset_filter: Option<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.revset_filter != revset_filter {
+            self.revset_filter = revset_filter;
+            self.request_refresh(window, cx);
+        }
+    }
+
+    fn show_revset_filter_modal(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let panel = cx.entity().downgrade();
+        let current = self.revset_filter.clone();
+        self.open_modal(window, cx, move |window, cx| {
+            RevsetFilterModal::new(panel, current, window, cx)
+        });
+    }
+
+    fn render_revset_filter(&self, revset: SharedString, cx: &mut Context<Self>) -> AnyElement {
+        h_flex()
+            .min_w_0()
+            .child(
+                Button::new("jj-revset-filter-button", revset)
+                    .style(ButtonStyle::Subtle)
+                    .label_size(LabelSize::Small)
+                    .tooltip(Tooltip::text("Edit Revset"))
+                    .on_click(cx.listener(|panel, _, window, cx| {
+                        panel.show_revset_filter_modal(window, cx);
+                    })),
+            )
+            .child(
+                IconButton::new("jj-clear-revset-filter", IconName::Close)
+                    .icon_size(IconSize::XSmall)
+                    .tooltip(Tooltip::text("Show Recent Commits"))
+                    .on_click(cx.listener(|panel, _, window, cx| {
+                        panel.set_revset_filter(None, window, cx);
+                    })),
+            )
+            .into_any_element()
+    }
+
+    fn show_custom_date_range_modal(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let panel = cx.entity().downgrade();
+        let current = self.date_filter;
+        self.open_modal(window, cx, move |window, cx| {
+            CustomDateRangeModal::new(panel, current, window, cx)
+        });
+    }
+
+    fn render_date_filter(&self, cx: &mut Context<Self>) -> AnyElement {
+        if let Some(revset) = self.revset_filter.clone() {
+            return self.render_revset_filter(revset, cx);
+        }
+        let panel = cx.entity().downgrade();
+        let current = self.date_filter;
+        PopoverMenu::new("jj-date-filter")
+            .trigger_with_tooltip(
+                Button::new("jj-date-filter-button", current.label())
+                    .style(ButtonStyle::Subtle)
+                    .label_size(LabelSize::Small)
+                    .icon(IconName::ChevronDown)
+                    .icon_position(IconPosition::End)
+                    .icon_size(IconSize::XSmall),
+                Tooltip::text("Filter Commits by Date"),
+            )
+            .anchor(Corner::TopLeft)
+            .menu(move |window, cx| {
+                let panel = panel.clone();
+                Some(ContextMenu::build(window, cx, move |menu, _, _| {
+                    let presets = [
+                        LogDateFilter::AllTime,
+                        LogDateFilter::LastDay,
+                        LogDateFilter::LastWeek,
+                    ];
+                    let menu = presets.into_iter().fold(menu, |menu, filter| {
+                        let panel = panel.clone();
+                        menu.toggleable_entry(
+                            filter.label(),
+                            current == filter,
+                            IconPosition::Start,
+                            None,
+                            move |window, cx| {
+                                panel
+                                    .update(cx, |panel, cx| {
+                                        panel.set_date_filter(filter, window, cx);
+                                    })
+                                    .ok();
+                            },
+                        )
+                    });
+                    let revset_panel = panel.clone();
+                    menu.separator()
+                        .toggleable_entry(
+                            "Custom Range…",
+                            matches!(current, LogDateFilter::Custom { .. }),
+                            IconPosition::Start,
+                            None,
+                            move |window, cx| {
+                                panel
+                                    .update(cx, |panel, cx| {
+                                        panel.show_custom_date_range_modal(window, cx);
+                                    })
+                                    .ok();
+                            },
+                        )
+                        .entry("Revset…", None, move |window, cx| {
+                            revset_panel
+                                .update(cx, |panel, cx| {
+                                    panel.show_revset_filter_modal(window, cx);
+                                })
+                                .ok();
+                        })
+                }))
+            })
+            .into_any_element()
+    }
+
+    /// Writes the commits currently shown in the log to the clipboard, or to a file the user
+    /// picks when `to_file` is set.
+    fn export_visible_log(
+        &mut self,
+        format: LogExportFormat,
+        to_file: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let contents = match export_log(&self.commits, &self.bookmarks, format) {
+            Ok(contents) => contents,
+            Err(err) => {
+                self.error = Some(format!("Failed to export log: {err}").into());
+                cx.notify();
+                return;
+            }
+        };
+        if !to_file {
+            cx.write_to_clipboard(ClipboardItem::new_string(contents));
+            return;
+        }
+        let Some(directory) = self.selected_repo.and_then(|repo_id| {
+            let store = self.project.read(cx).jj_store()?;
+            store.read(cx).repository_root(repo_id)
+        }) else {
+            return;
+        };
+        let suggested_name = format!("jj-log.{}", format.extension());
+        let destination = cx.prompt_for_new_path(&directory, Some(&suggested_name));
+        cx.spawn_in(window, async move |panel, cx| {
+            let Ok(Ok(Some(destination))) = destination.await else {
+                return;
+            };
+            let result = cx
+                .background_spawn(async move { std::fs::write(&destination, contents) })
+                .await;
+            if let Err(err) = result {
+                warn!(target: "jj_ui", "failed to export log: {err:?}");
+                panel
+                    .update(cx, |panel, cx| {
+                        panel.error = Some(format!("Failed to export log: {err}").into());
+                        cx.notify();
+                    })
+                    .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn render_export_menu(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let panel = cx.entity().downgrade();
+        let can_save = !self.is_guest(cx);
+        PopoverMenu::new("jj-export-log")
+            .trigger_with_tooltip(
+                IconButton::new("jj-export-log-button", IconName::FileTextOutlined)
+                    .icon_size(IconSize::Small)
+                    .disabled(self.commits.is_empty()),
+                Tooltip::text("Export Log…"),
+            )
+            .anchor(Corner::TopRight)
+            .menu(move |window, cx| {
+                let panel = panel.clone();
+                Some(ContextMenu::build(window, cx, move |menu, _, _| {
+                    let exports = [
+                        ("Copy as Text", LogExportFormat::Text, false),
+                        ("Copy as JSON", LogExportFormat::Json, false),
+                        ("Save as Text…", LogExportFormat::Text, true),
+                        ("Save as JSON…", LogExportFormat::Json, true),
+                    ];
+                    exports
+                        .into_iter()
+                        .filter(|(_, _, to_file)| can_save || !to_file)
+                        .fold(menu, |menu, (label, format, to_file)| {
+                            let panel = panel.clone();
+                            menu.entry(label, None, move |window, cx| {
+                                panel
+                                    .update(cx, |panel, cx| {
+                                        panel.export_visible_log(format, to_file, window, cx);
+                                    })
+                                    .ok();
+                            })
+                        })
+                }))
+            })
+    }
+
+    /// Asks whether to delete or forget the bookmark, offering deletion only while a local
+    /// bookmark of that name exists.
+    fn manage_bookmark(&mut self, name: SharedString, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let is_local = self
+            .bookmarks
+            .iter()
+            .any(|bookmark| bookmark.name == name.as_ref() && bookmark.remote.is_none());
+        let mut detail = if is_local {
+            "Deleting also deletes it from tracked remotes on the next push. Forgetting only \
+             removes it here and stops tracking it on remotes."
+                .to_string()
+        } else {
+            "Forgetting stops tracking it on remotes so that it no longer shows up here."
+                .to_string()
+        };
+        if let Some(repo_label) = self.repository_label(repo_id) {
+            detail.push_str(&format!("\nRepository: {repo_label}"));
+        }
+        let answers: &[&str] = if is_local {
+            &["Delete", "Forget", "Cancel"]
+        } else {
+            &["Forget", "Cancel"]
+        };
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("Remove bookmark \"{name}\"?"),
+            Some(&detail),
+            answers,
+            cx,
+        );
+        cx.spawn_in(window, async move |panel, cx| {
+            let Ok(answer) = answer.await else {
+                return;
+            };
+            let delete = match (is_local, answer) {
+                (true, 0) => true,
+                (true, 1) | (false, 0) => false,
+                _ => return,
+            };
+            let name = name.to_string();
+            let Some(task) = store
+                .update(cx, |store, cx| {
+                    if delete {
+                        store.delete_bookmark(repo_id, name, cx)
+                    } else {
+                        store.forget_bookmark(repo_id, name, cx)
+                    }
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            let label = if delete {
+                "jj bookmark delete"
+            } else {
+                "jj bookmark forget"
+            };
+            panel
+                .update_in(cx, |panel, window, cx| {
+                    panel.spawn_store_task(label, repo_id, task, true, window, cx);
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn set_remote_bookmark_tracked(
+        &mut self,
+        name: String,
+        remote: String,
+        track: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let Some(task) = store.update(cx, |store, cx| {
+            store.set_remote_bookmark_tracked(repo_id, name, remote, track, cx)
+        }) else {
+            return;
+        };
+        let label = if track {
+            "jj bookmark track"
+        } else {
+            "jj bookmark untrack"
+        };
+        self.spawn_store_task(label, repo_id, task, true, window, cx);
+    }
+
+    fn render_bookmarks_menu(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let panel = cx.entity().downgrade();
+        let mut names = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| SharedString::from(bookmark.name.clone()))
+            .collect::<Vec<_>>();
+        names.dedup();
+        let local_names = self
+            .bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.remote.is_none())
+            .map(|bookmark| SharedString::from(bookmark.name.clone()))
+            .collect::<Vec<_>>();
+        let remote_bookmarks = self
+            .bookmarks
+            .iter()
+            .filter_map(|bookmark| {
+                let remote = bookmark.remote.clone()?;
+                Some((bookmark.name.clone(), remote, bookmark.is_tracked))
+            })
+            .collect::<Vec<_>>();
+        let push_header = format!("Push to {}", self.git_remote());
+        PopoverMenu::new("jj-bookmarks")
+            .trigger_with_tooltip(
+                IconButton::new("jj-bookmarks-button", IconName::GitBranchAlt)
+                    .icon_size(IconSize::Small)
+                    .disabled(
+                        self.selected_repo.is_none() || self.is_guest(cx) || self.is_read_only(cx),
+                    ),
+                Tooltip::text("Manage Bookmarks"),
+            )
+            .anchor(Corner::TopRight)
+            .menu(move |window, cx| {
+                let panel = panel.clone();
+                let names = names.clone();
+                let local_names = local_names.clone();
+                let remote_bookmarks = remote_bookmarks.clone();
+                let push_header = push_header.clone();
+                Some(ContextMenu::build(window, cx, move |menu, _, _| {
+                    if names.is_empty() {
+                        return menu.label("No bookmarks");
+                    }
+                    let menu =
+                        names
+                            .into_iter()
+                            .fold(menu.header("Remove bookmark"), |menu, name| {
+                                let panel = panel.clone();
+                                menu.entry(name.clone(), None, move |window, cx| {
+                                    panel
+                                        .update(cx, |panel, cx| {
+                                            panel.manage_bookmark(name.clone(), window, cx);
+                                        })
+                                        .ok();
+                                })
+                            });
+                    let menu = if local_names.is_empty() {
+                        menu
+                    } else {
+                        menu.separator().header(push_header)
+                    };
+                    let menu = local_names.into_iter().fold(menu, |menu, name| {
+                        let panel = panel.clone();
+                        menu.entry(name.clone(), None, move |window, cx| {
+                            panel
+                                .update(cx, |panel, cx| {
+                                    panel.git_push(name.clone(), window, cx);
+                                })
+                                .ok();
+                        })
+                    });
+                    if remote_bookmarks.is_empty() {
+                        return menu;
+                    }
+                    remote_bookmarks.into_iter().fold(
+                        menu.separator().header("Track remote bookmark"),
+                        |menu, (name, remote, is_tracked)| {
+                            let panel = panel.clone();
+                            menu.toggleable_entry(
+                                format!("{name}@{remote}"),
+                                is_tracked,
+                                IconPosition::Start,
+                                None,
+                                move |window, cx| {
+                                    panel
+                                        .update(cx, |panel, cx| {
+                                            panel.set_remote_bookmark_tracked(
+                                                name.clone(),
+                                                remote.clone(),
+                                                !is_tracked,
+                                                window,
+                                                cx,
+                                            );
+                                        })
+                                        .ok();
+                                },
+                            )
+                        },
+                    )
+                }))
+            })
+    }
+
+    fn render_operation_history(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let panel = cx.entity().downgrade();
+        PopoverMenu::new("jj-operation-history")
+            .trigger_with_tooltip(
+                IconButton::new("jj-operation-history-button", IconName::HistoryRerun)
+                    .icon_size(IconSize::Small),
+                Tooltip::text("Operations This Session"),
+            )
+            .anchor(Corner::TopRight)
+            .menu(move |window, cx| {
+                let panel = panel.upgrade()?;
+                let operations = panel
+                    .read(cx)
+                    .project
+                    .read(cx)
+                    .jj_store()?
+                    .read(cx)
+                    .session_operations()
+                    .to_vec();
+                let entries = operations
+                    .into_iter()
+                    .rev()
+                    .map(|operation| {
+                        let repo_label = panel.read(cx).repository_label(operation.repository_id);
+                        let short_id =
+                            &operation.operation_id[..12.min(operation.operation_id.len())];
+                        let origin = format!(
+                            "{}@{}, {}",
+                            operation.username,
+                            operation.hostname,
+                            JjPanel::format_timestamp(operation.timestamp)
+                        );
+                        let label = match repo_label {
+                            Some(repo_label) => format!(
+                                "{} ({short_id}, {repo_label}, {origin})",
+                                operation.description
+                            ),
+                            None => format!("{} ({short_id}, {origin})", operation.description),
+                        };
+                        (operation, label)
+                    })
+                    .collect::<Vec<_>>();
+                Some(ContextMenu::build(window, cx, move |mut menu, _, _| {
+                    if entries.is_empty() {
+                        return menu.label("No operations performed yet");
+                    }
+                    menu = menu.header("Undo from here");
+                    for (operation, label) in entries.iter().cloned() {
+                        let panel = panel.downgrade();
+                        menu = menu.entry(label, None, move |window, cx| {
+                            panel
+                                .update(cx, |panel, cx| {
+                                    panel.undo_from_operation(operation.clone(), window, cx);
+                                })
+                                .ok();
+                        });
+                    }
+                    menu = menu.separator().header("View repository at");
+                    for (operation, label) in entries {
+                        let panel = panel.downgrade();
+                        menu = menu.entry(label, None, move |window, cx| {
+                            panel
+                                .update(cx, |panel, cx| {
+                                    panel.view_at_operation(operation.clone(), window, cx);
+                                })
+                                .ok();
+                        });
+                    }
+                    menu
+                }))
+            })
+    }
+
+    fn spawn_store_task(
+        &self,
+        label: &'static str,
+        repo_id: ProjectEntryId,
+        task: Task<Result<()>>,
+        refresh_on_success: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let panel = cx.entity().downgrade();
+        let workspace = self._workspace.clone();
+        let repo_label = self.repository_label(repo_id);
+        cx.spawn_in(window, async move |_, cx| match task.await {
+            Ok(_) => {
+                info!(target: "jj_ui", "{label} completed");
+                if refresh_on_success && let Some(panel) = panel.upgrade() {
+                    panel
+                        .update(cx, |panel, cx| panel.record_operation(repo_id, label, cx))
+                        .ok();
+                }
+                if let Some(repo_label) = &repo_label {
+                    workspace
+                        .update(cx, |workspace, cx| {
+                            workspace.show_toast(
+                                Toast::new(
+                                    NotificationId::unique::<JjOperationToast>(),
+                                    format!("{label} finished in {repo_label}"),
+                                )
+                                .autohide(),
+                                cx,
+                            );
+                        })
+                        .ok();
+                }
+                if refresh_on_success {
+                    if let Some(panel) = panel.upgrade() {
+                        let panel_clone = panel.clone();
+                        let _ = cx.update(|window, cx| {
                             panel_clone.update(cx, |panel, cx| {
                                 panel.request_refresh(window, cx);
                             })
@@ -397,9 +3748,17 @@ impl JjPanel {
             }
             Err(err) => {
                 warn!(target: "jj_ui", "{label} failed: {err:?}");
+                let message = match &repo_label {
+                    Some(repo_label) => format!("{label} failed in {repo_label}: {err}"),
+                    None => format!("{err}"),
+                };
+                let divergent_change = err.downcast_ref::<DivergentChangeError>().cloned();
                 if let Some(panel) = panel.upgrade() {
                     let _ = panel.update(cx, |panel, cx| {
-                        panel.error = Some(format!("{err}").into());
+                        panel.error = Some(message.into());
+                        if let Some(divergent_change) = divergent_change {
+                            panel.divergent_change = Some((repo_id, divergent_change));
+                        }
                         cx.notify();
                     });
                 }
@@ -408,124 +3767,441 @@ impl JjPanel {
         .detach();
     }
 
-    fn render_repository_selector(
-        &mut self,
-        _window: &mut Window,
-        cx: &mut Context<Self>,
-    ) -> Option<AnyElement> {
-        if self.repositories.len() <= 1 {
+    /// A repository that covers the whole project makes any nested ones irrelevant, so there is
+    /// nothing to choose between.
+    fn shows_repository_selector(&self) -> bool {
+        self.repositories.len() > 1
+            && !self
+                .repositories
+                .iter()
+                .any(|repo| repo.covers_entire_project)
+    }
+
+    fn render_repository_selector(&self, cx: &mut Context<Self>) -> Option<RepoSelector> {
+        if !self.shows_repository_selector() {
             return None;
         }
+        Some(
+            RepoSelector::new(self.repositories.clone(), self.selected_repo).on_select(
+                cx.listener(|panel, repo_id, window, cx| {
+                    panel.select_repository(*repo_id, window, cx);
+                }),
+            ),
+        )
+    }
 
-        let repos = self.repositories.clone();
+    fn render_parked_banner(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let repo_id = self.selected_repo?;
+        let store = self.project.read(cx).jj_store()?;
+        let parked_change = store.read(cx).parked_change(repo_id)?;
+        let label = format!("Change {} is parked", short_change_hash(parked_change));
 
         Some(
             h_flex()
+                .justify_between()
+                .items_center()
+                .p(px(4.0))
+                .rounded(px(4.0))
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().surface_background)
+                .child(Label::new(label).size(LabelSize::Small))
+                .when(!self.is_read_only(cx), |banner| {
+                    banner.child(
+                        Button::new("jj-unpark", "Unpark")
+                            .style(ButtonStyle::Filled)
+                            .on_click(cx.listener(move |panel, _, window, cx| {
+                                panel.unpark_working_copy(repo_id, window, cx);
+                            })),
+                    )
+                })
+                .into_any_element(),
+        )
+    }
+
+    fn render_divergent_banner(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let (repo_id, divergent_change) = self.divergent_change.as_ref()?;
+        if self.selected_repo != Some(*repo_id) {
+            return None;
+        }
+        let repo_id = *repo_id;
+        let title = format!(
+            "Change {} is divergent. Keep one of its commits to act on it",
+            short_change_hash(&divergent_change.change_id)
+        );
+        let can_keep = !self.is_read_only(cx);
+
+        Some(
+            v_flex()
                 .gap(rems(0.25))
-                .children(repos.into_iter().map(|repo| {
-                    let is_selected = self.selected_repo == Some(repo.id);
-                    let label = repo.path.clone();
-                    Button::new(("jj-repo", repo.id.to_proto()), label)
-                        .style(if is_selected {
-                            ButtonStyle::Filled
-                        } else {
-                            ButtonStyle::Outlined
-                        })
-                        .on_click(cx.listener(move |panel, _, window, cx| {
-                            panel.select_repository(repo.id, window, cx);
-                        }))
+                .p(px(4.0))
+                .rounded(px(4.0))
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().surface_background)
+                .child(
+                    h_flex()
+                        .justify_between()
+                        .items_center()
+                        .child(
+                            Label::new(title)
+                                .size(LabelSize::Small)
+                                .color(Color::Warning),
+                        )
+                        .child(
+                            IconButton::new("jj-dismiss-divergent", IconName::Close)
+                                .icon_size(IconSize::Small)
+                                .tooltip(Tooltip::text("Dismiss"))
+                                .on_click(cx.listener(|panel, _, _, cx| {
+                                    panel.divergent_change = None;
+                                    cx.notify();
+                                })),
+                        ),
+                )
+                .children(divergent_change.commit_ids.iter().enumerate().map(
+                    |(index, commit_id)| {
+                        let description = self
+                            .commits
+                            .iter()
+                            .find(|commit| &commit.commit_id == commit_id)
+                            .and_then(|commit| commit.description.lines().next())
+                            .unwrap_or_default()
+                            .to_string();
+                        let change_id = divergent_change.change_id.clone();
+                        let keep_commit_id = commit_id.clone();
+                        h_flex()
+                            .justify_between()
+                            .items_center()
+                            .child(
+                                Label::new(format!(
+                                    "{} {description}",
+                                    short_commit_hash(commit_id)
+                                ))
+                                .size(LabelSize::XSmall)
+                                .color(Color::Muted),
+                            )
+                            .when(can_keep, |row| {
+                                row.child(
+                                    Button::new(("jj-keep-divergent", index), "Keep")
+                                        .label_size(LabelSize::XSmall)
+                                        .on_click(cx.listener(move |panel, _, window, cx| {
+                                            panel.keep_divergent_commit(
+                                                repo_id,
+                                                change_id.clone(),
+                                                keep_commit_id.clone(),
+                                                window,
+                                                cx,
+                                            );
+                                        })),
+                                )
+                            })
+                    },
+                ))
+                .into_any(),
+        )
+    }
+
+    fn render_restack_banner(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        if self.restack_candidates.is_empty() {
+            return None;
+        }
+
+        let count = self.restack_candidates.len();
+        let title = if count == 1 {
+            "1 change is based on an outdated ancestor".to_string()
+        } else {
+            format!("{count} changes are based on outdated ancestors")
+        };
+
+        Some(
+            v_flex()
+                .gap(rems(0.25))
+                .p(px(4.0))
+                .rounded(px(4.0))
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().surface_background)
+                .child(
+                    h_flex()
+                        .justify_between()
+                        .items_center()
+                        .child(Label::new(title).size(LabelSize::Small))
+                        .when(!self.is_read_only(cx), |row| {
+                            row.child(
+                                Button::new("jj-restack", "Restack")
+                                    .style(ButtonStyle::Filled)
+                                    .on_click(cx.listener(|panel, _, window, cx| {
+                                        panel.trigger_restack(window, cx);
+                                    })),
+                            )
+                        }),
+                )
+                .children(self.restack_candidates.iter().map(|commit| {
+                    let description = commit
+                        .description
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                    Label::new(format!(
+                        "{} {description}",
+                        short_change_hash(&commit.change_id)
+                    ))
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted)
                 }))
                 .into_any(),
         )
     }
 
-    fn current_repository_label(&self) -> Option<SharedString> {
-        let selected = self.selected_repo?;
-        self.repositories
-            .iter()
-            .find(|repo| repo.id == selected)
-            .map(|repo| repo.path.clone())
+    fn track_paths(
+        &mut self,
+        paths: Vec<RepoPathBuf>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| store.track_paths(repo_id, paths, cx)) {
+            self.spawn_store_task("jj file track", repo_id, task, true, window, cx);
+        }
     }
 
-    fn render_commits(&mut self, cx: &mut Context<Self>) -> impl IntoElement + '_ {
-        v_flex()
-            .gap(rems(0.25))
-            .children(self.commits.iter().cloned().map(|commit| {
-                let timestamp = Self::format_timestamp(commit.timestamp);
-                let change_short = short_change_hash(&commit.change_id);
-                let commit_short = short_commit_hash(&commit.commit_id);
-                let description = commit.description.clone();
-                let author = commit.author.clone();
-                let click_commit = commit.clone();
-                let menu_commit = commit.clone();
+    fn render_untracked_paths(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        if self.untracked_paths.is_empty() || self.is_guest(cx) || self.is_read_only(cx) {
+            return None;
+        }
 
-                let mut title_row = h_flex().gap(rems(0.25)).items_center();
-                if commit.is_current {
-                    title_row = title_row
-                        .child(Label::new("•").color(Color::Accent).size(LabelSize::Small));
-                }
-                title_row = title_row.child(Label::new(description).size(LabelSize::Default));
+        let count = self.untracked_paths.len();
+        let title = if count == 1 {
+            "1 untracked file".to_string()
+        } else {
+            format!("{count} untracked files")
+        };
+        let hidden_count = count.saturating_sub(MAX_UNTRACKED_PATHS_SHOWN);
 
-                let body = v_flex()
-                    .gap(rems(0.1))
-                    .child(
-                        h_flex().justify_between().child(title_row).child(
-                            Label::new(timestamp)
-                                .color(Color::Muted)
-                                .size(LabelSize::XSmall),
+        Some(
+            v_flex()
+                .gap(rems(0.25))
+                .p(px(4.0))
+                .rounded(px(4.0))
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .bg(cx.theme().colors().surface_background)
+                .child(
+                    h_flex()
+                        .justify_between()
+                        .items_center()
+                        .child(Label::new(title).size(LabelSize::Small))
+                        .child(
+                            Button::new("jj-track-all", "Track All")
+                                .style(ButtonStyle::Filled)
+                                .on_click(cx.listener(|panel, _, window, cx| {
+                                    let paths = panel.untracked_paths.clone();
+                                    panel.track_paths(paths, window, cx);
+                                })),
                         ),
+                )
+                .children(
+                    self.untracked_paths
+                        .iter()
+                        .take(MAX_UNTRACKED_PATHS_SHOWN)
+                        .enumerate()
+                        .map(|(index, path)| {
+                            let track_path = path.clone();
+                            h_flex()
+                                .justify_between()
+                                .items_center()
+                                .gap(rems(0.5))
+                                .child(
+                                    Label::new(path.as_internal_file_string().to_string())
+                                        .size(LabelSize::XSmall)
+                                        .color(Color::Muted)
+                                        .truncate(),
+                                )
+                                .child(
+                                    Button::new(("jj-track-path", index), "Track")
+                                        .style(ButtonStyle::Subtle)
+                                        .label_size(LabelSize::XSmall)
+                                        .on_click(cx.listener(move |panel, _, window, cx| {
+                                            panel.track_paths(vec![track_path.clone()], window, cx);
+                                        })),
+                                )
+                        }),
+                )
+                .when(hidden_count > 0, |section| {
+                    section.child(
+                        Label::new(format!("and {hidden_count} more"))
+                            .size(LabelSize::XSmall)
+                            .color(Color::Muted),
                     )
-                    .child(
-                        h_flex()
-                            .gap(rems(0.5))
-                            .child(
-                                Label::new(format!("commit {commit_short}"))
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Muted),
-                            )
-                            .child(
-                                Label::new(format!("change {change_short}"))
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Muted),
-                            )
-                            .child(
-                                Label::new(author)
-                                    .size(LabelSize::XSmall)
-                                    .color(Color::Placeholder),
-                            ),
-                    );
+                })
+                .into_any(),
+        )
+    }
 
-                let interactive = self.selected_repo.is_some();
-                let mut wrapper = div().rounded(px(4.0)).p(px(4.0)).child(body);
+    fn render_working_copy_status(&self, cx: &mut Context<Self>) -> Option<WorkingCopySection> {
+        if self.working_copy_status.is_empty() || self.is_guest(cx) {
+            return None;
+        }
+        Some(
+            WorkingCopySection::new(self.working_copy_status.clone()).on_open(cx.listener(
+                |panel, path: &RepoPathBuf, window, cx| {
+                    panel.open_working_copy_file(path, window, cx);
+                },
+            )),
+        )
+    }
 
-                if commit.is_current {
-                    wrapper = wrapper
-                        .border_1()
-                        .border_color(cx.theme().colors().border_focused)
-                        .bg(cx.theme().colors().surface_background);
-                }
+    fn open_working_copy_file(
+        &mut self,
+        path: &RepoPathBuf,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_root) = self.selected_repo.and_then(|repo_id| {
+            let store = self.project.read(cx).jj_store()?;
+            store.read(cx).repository_root(repo_id)
+        }) else {
+            return;
+        };
+        let abs_path = repo_root.join(path.as_internal_file_string());
+        self._workspace
+            .update(cx, |workspace, cx| {
+                workspace
+                    .open_abs_path(abs_path, OpenOptions::default(), window, cx)
+                    .detach_and_log_err(cx);
+            })
+            .ok();
+    }
 
-                if interactive {
-                    wrapper = wrapper
-                        .cursor_pointer()
-                        .hover(|el| el.bg(cx.theme().colors().surface_background))
-                        .on_mouse_down(MouseButton::Left, |_, window, _| {
-                            window.prevent_default();
+    fn current_repository_label(&self) -> Option<SharedString> {
+        let selected = self.selected_repo?;
+        self.repositories
+            .iter()
+            .find(|repo| repo.id == selected)
+            .map(|repo| repo.path.clone())
+    }
+
+    /// Checks or unchecks one of the change's task-list items by rewriting its description.
+    fn toggle_checklist_item(
+        &mut self,
+        commit: &JjCommitSummary,
+        index: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repo_id) = self.selected_repo else {
+            return;
+        };
+        let Some(description) = toggle_checklist_item(&commit.description, index) else {
+            return;
+        };
+        let change_id = commit.change_id.clone();
+        if self.is_guest(cx) {
+            let request = JjOperationRequest::RenameChange {
+                change_id,
+                description,
+            };
+            self.request_operation(repo_id, request, window, cx);
+            return;
+        }
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        if let Some(task) = store.update(cx, |store, cx| {
+            store.rename_change(repo_id, change_id, description, cx)
+        }) {
+            self.spawn_store_task("jj describe", repo_id, task, true, window, cx);
+        }
+    }
+
+    fn render_checklist(&self, commit: &JjCommitSummary, cx: &mut Context<Self>) -> AnyElement {
+        let change_short = short_change_hash(&commit.change_id);
+        v_flex()
+            .pl(rems(0.5))
+            .gap(rems(0.1))
+            // Keep toggling an item from also checking out the change.
+            .on_mouse_up(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+            .children(
+                checklist_items(&commit.description)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        let commit = commit.clone();
+                        let panel = cx.entity().downgrade();
+                        Checkbox::new(
+                            SharedString::from(format!("jj-checklist-{change_short}-{index}")),
+                            item.is_checked.into(),
+                        )
+                        .label(item.text)
+                        .disabled(self.selected_repo.is_none() || self.is_read_only(cx))
+                        .on_click(move |_, window, cx| {
+                            panel
+                                .update(cx, |panel, cx| {
+                                    panel.toggle_checklist_item(&commit, index, window, cx);
+                                })
+                                .ok();
                         })
-                        .on_mouse_up(
-                            MouseButton::Left,
-                            cx.listener(move |panel, _, window, cx| {
-                                panel.trigger_edit_change(&click_commit, window, cx);
-                            }),
-                        );
-                } else {
-                    wrapper = wrapper.opacity(0.75);
-                }
+                    }),
+            )
+            .into_any_element()
+    }
+
+    fn render_commits(&mut self, cx: &mut Context<Self>) -> impl IntoElement + '_ {
+        let jj_settings = &ProjectSettings::get_global(cx).jj;
+        let color_by_author = jj_settings.color_by_author;
+        let mut day_headers = if jj_settings.group_by_day {
+            self.day_headers()
+        } else {
+            Vec::new()
+        }
+        .into_iter();
+        v_flex()
+            .id("jj-commit-log")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .track_scroll(&self.scroll_handle)
+            .gap(rems(0.25))
+            .children(self.commits.iter().cloned().map(|commit| {
+                let day_header = day_headers.next().flatten();
+                let click_commit = commit.clone();
+                let menu_commit = commit.clone();
+                let merge_parents = self.render_merge_parents(&commit, cx);
+                let shows_checklist = self
+                    .selected_change
+                    .as_ref()
+                    .map_or(commit.is_current, |change_id| {
+                        change_id == &commit.change_id
+                    });
+                let checklist = shows_checklist.then(|| self.render_checklist(&commit, cx));
 
-                if interactive {
-                    wrapper = wrapper.on_mouse_down(
-                        MouseButton::Right,
-                        cx.listener(move |panel, event: &MouseDownEvent, window, cx| {
+                let row = CommitRow::new(commit.clone(), Self::format_timestamp(commit.timestamp))
+                    .rendered(self.rendered_commits.get(&commit.change_id).cloned())
+                    .ci_status(
+                        self.ci_statuses
+                            .get(&commit.commit_id)
+                            .and_then(|check| check.status),
+                    )
+                    .stale(self.stale_commits.contains(&commit.commit_id))
+                    .color_by_author(color_by_author)
+                    .toggle_state(self.selected_change.as_ref() == Some(&commit.change_id))
+                    .merge_parent(self.merge_parents.contains(&commit.change_id))
+                    .disabled(self.selected_repo.is_none())
+                    .on_click(cx.listener(move |panel, event: &MouseUpEvent, window, cx| {
+                        if event.modifiers.secondary() {
+                            panel.toggle_merge_parent(&click_commit.change_id, cx);
+                        } else {
+                            panel.trigger_edit_change(&click_commit, window, cx);
+                        }
+                    }))
+                    .on_secondary_mouse_down(cx.listener(
+                        move |panel, event: &MouseDownEvent, window, cx| {
                             window.prevent_default();
                             let Some(repo_id) = panel.selected_repo else {
                                 return;
@@ -539,13 +4215,71 @@ impl JjPanel {
                                 window,
                                 cx,
                             );
-                        }),
-                    );
-                }
+                        },
+                    ))
+                    .children(merge_parents)
+                    .children(checklist);
 
-                wrapper
+                // The header shares its commit's child so the minimap's item indices still
+                // line up with `self.commits`.
+                match day_header {
+                    Some(label) => v_flex()
+                        .gap(rems(0.25))
+                        .child(Self::render_day_header(label, cx))
+                        .child(row)
+                        .into_any_element(),
+                    None => row.into_any_element(),
+                }
             }))
     }
+
+    /// The separator to show above each commit in the log: set for the first commit of each
+    /// day, `None` for the rest.
+    fn day_headers(&self) -> Vec<Option<SharedString>> {
+        let today = local_today();
+        let mut previous_day = None;
+        self.commits
+            .iter()
+            .map(|commit| {
+                let day = local_day(commit.timestamp)?;
+                if previous_day == Some(day) {
+                    return None;
+                }
+                previous_day = Some(day);
+                Some(day_header_label(day, today))
+            })
+            .collect()
+    }
+
+    fn render_day_header(label: SharedString, cx: &App) -> Div {
+        h_flex()
+            .px(rems(0.5))
+            .py(rems(0.125))
+            .bg(cx.theme().colors().panel_background)
+            .border_b_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(
+                Label::new(label)
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            )
+    }
+
+    /// Keeps the day of the topmost visible commit pinned above the log once its own header
+    /// has scrolled out of view.
+    fn render_sticky_day_header(&self, cx: &App) -> Option<Div> {
+        if !ProjectSettings::get_global(cx).jj.group_by_day
+            || self.scroll_handle.offset().y >= px(0.)
+        {
+            return None;
+        }
+        let commit = self.commits.get(self.scroll_handle.top_item())?;
+        let day = local_day(commit.timestamp)?;
+        Some(Self::render_day_header(
+            day_header_label(day, local_today()),
+            cx,
+        ))
+    }
 }
 
 impl Focusable for JjPanel {
@@ -575,6 +4309,14 @@ impl Panel for JjPanel {
 
     fn set_position(&mut self, _: DockPosition, _: &mut Window, _: &mut Context<Self>) {}
 
+    fn set_active(&mut self, active: bool, window: &mut Window, cx: &mut Context<Self>) {
+        self.is_active = active;
+        if active && self.needs_refresh {
+            self.needs_refresh = false;
+            self.request_refresh(window, cx);
+        }
+    }
+
     fn size(&self, _: &Window, _: &App) -> Pixels {
         px(320.0)
     }
@@ -600,51 +4342,269 @@ impl Panel for JjPanel {
     fn enabled(&self, cx: &App) -> bool {
         cx.has_flag::<JjUiFeatureFlag>()
     }
+
+    fn is_zoomed(&self, _: &Window, _: &App) -> bool {
+        self.is_zoomed
+    }
+
+    fn set_zoomed(&mut self, zoomed: bool, _: &mut Window, cx: &mut Context<Self>) {
+        self.is_zoomed = zoomed;
+        cx.notify();
+    }
 }
 
 impl Render for JjPanel {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let window = _window;
+        let is_guest = self.is_guest(cx);
+        let is_read_only = self.is_read_only(cx) || self.past_operation.is_some();
         let header = h_flex()
             .justify_between()
             .items_center()
             .p(px(4.0))
-            .child(Label::new("JJ History").size(LabelSize::Large))
             .child(
-                Button::new("refresh-jj", "Refresh")
-                    .style(ButtonStyle::Outlined)
-                    .on_click(cx.listener(Self::refresh_action)),
+                h_flex()
+                    .gap(rems(0.5))
+                    .items_center()
+                    .child(Label::new("JJ History").size(LabelSize::Large))
+                    .children(self.render_working_copy_freshness(cx)),
+            )
+            .child(
+                h_flex()
+                    .gap(rems(0.25))
+                    .children(self.render_view_as_git_button(cx))
+                    .when(!is_read_only, |row| {
+                        row.child(
+                            IconButton::new("jj-undo", IconName::Undo)
+                                .icon_size(IconSize::Small)
+                                .disabled(self.selected_repo.is_none() || is_guest)
+                                .tooltip(Tooltip::text("Undo Last Operation"))
+                                .on_click(cx.listener(Self::undo_last_operation)),
+                        )
+                    })
+                    .child(self.render_operation_history(cx))
+                    .child(self.render_bookmarks_menu(cx))
+                    .children(self.render_remote_picker(cx))
+                    .when(!is_read_only, |row| {
+                        row.child(self.render_fetch_button(cx)).child(
+                            IconButton::new("jj-clean-up-landed", IconName::Trash)
+                                .icon_size(IconSize::Small)
+                                .disabled(self.selected_repo.is_none() || is_guest)
+                                .tooltip(Tooltip::text("Clean Up Landed Changes"))
+                                .on_click(cx.listener(|panel, _, window, cx| {
+                                    panel.clean_up_landed_changes(window, cx);
+                                })),
+                        )
+                    })
+                    .child(
+                        IconButton::new("jj-review-current-change", IconName::Diff)
+                            .icon_size(IconSize::Small)
+                            .tooltip(Tooltip::text("Review Current Change"))
+                            .on_click(|_, window, cx| {
+                                window.dispatch_action(ReviewCurrentChange.boxed_clone(), cx);
+                            }),
+                    )
+                    .child({
+                        let is_zoomed = self.is_zoomed;
+                        IconButton::new("jj-toggle-zoom", IconName::Maximize)
+                            .icon_size(IconSize::Small)
+                            .toggle_state(is_zoomed)
+                            .selected_icon(IconName::Minimize)
+                            .tooltip(move |_window, cx| {
+                                Tooltip::for_action(
+                                    if is_zoomed { "Zoom Out" } else { "Zoom In" },
+                                    &workspace::ToggleZoom,
+                                    cx,
+                                )
+                            })
+                            .on_click(cx.listener(|panel, _, window, cx| {
+                                panel.toggle_zoom(&workspace::ToggleZoom, window, cx);
+                            }))
+                    })
+                    .when(!is_read_only, |row| {
+                        row.child(
+                            Button::new("new-change-jj", "New Change")
+                                .style(ButtonStyle::Outlined)
+                                .disabled(self.selected_repo.is_none() || is_guest)
+                                .on_click(cx.listener(|panel, _, window, cx| {
+                                    if let Some(repo_id) = panel.selected_repo {
+                                        panel.new_change(repo_id, None, window, cx);
+                                    }
+                                })),
+                        )
+                    })
+                    .when(
+                        !is_guest && !is_read_only && self.merge_parents.len() >= 2,
+                        |row| {
+                            row.child(
+                                Button::new(
+                                    "new-merge-change-jj",
+                                    format!("Merge {} Changes", self.merge_parents.len()),
+                                )
+                                .style(ButtonStyle::Outlined)
+                                .tooltip(Tooltip::text(
+                                    "Start a new change on all selected changes",
+                                ))
+                                .on_click(cx.listener(
+                                    |panel, _, window, cx| {
+                                        panel.new_merge_change(window, cx);
+                                    },
+                                )),
+                            )
+                        },
+                    )
+                    .when(!is_read_only, |row| {
+                        row.child(
+                            Button::new("apply-patch-jj", "Apply Patch…")
+                                .style(ButtonStyle::Outlined)
+                                .disabled(self.selected_repo.is_none() || is_guest)
+                                .on_click(cx.listener(Self::apply_patch_action)),
+                        )
+                    })
+                    .child(
+                        Button::new("refresh-jj", "Refresh")
+                            .style(ButtonStyle::Outlined)
+                            .on_click(cx.listener(Self::refresh_action)),
+                    ),
             );
 
-        let repo_selector = self.render_repository_selector(window, cx);
+        let repo_selector = self.render_repository_selector(cx);
         let repo_label = self.current_repository_label();
 
-        let content: AnyElement = if self.show_loading_indicator {
+        let is_opening_repository = self.selected_repo.is_some_and(|repo_id| {
+            self.project
+                .read(cx)
+                .jj_store()
+                .is_some_and(|store| store.read(cx).is_loading_repository(repo_id))
+        });
+        let content: AnyElement = if let Some(past) = &self.past_operation {
+            self.render_past_operation(past, cx)
+        } else if is_opening_repository {
+            h_flex()
+                .gap(rems(0.5))
+                .child(SpinnerLabel::new().color(Color::Muted))
+                .child(Label::new("Opening repository…").color(Color::Muted))
+                .into_any_element()
+        } else if self.show_loading_indicator {
             Label::new("Loading commits…").into_any_element()
         } else if let Some(error) = &self.error {
             Label::new(error.clone())
                 .color(Color::Error)
                 .into_any_element()
+        } else if self.commits.is_empty() && self.revset_filter.is_some() {
+            Label::new("No commits match this revset")
+                .color(Color::Muted)
+                .into_any_element()
+        } else if self.commits.is_empty() && self.date_filter != LogDateFilter::AllTime {
+            Label::new("No commits in this date range")
+                .color(Color::Muted)
+                .into_any_element()
         } else if self.commits.is_empty() {
             Label::new("No commits to show")
                 .color(Color::Muted)
                 .into_any_element()
         } else {
-            div().child(self.render_commits(cx)).into_any()
+            let log_overflows = self.scroll_handle.max_offset().height > px(0.);
+            let minimap = log_overflows
+                .then(|| CommitMinimap::new(&self.commits, self.scroll_handle.clone()));
+            let sticky_day_header = self.render_sticky_day_header(cx);
+            h_flex()
+                .flex_1()
+                .min_h_0()
+                .items_start()
+                .gap(rems(0.25))
+                .child(
+                    v_flex()
+                        .relative()
+                        .flex_1()
+                        .h_full()
+                        .child(self.render_commits(cx))
+                        .children(
+                            sticky_day_header
+                                .map(|header| header.absolute().top_0().left_0().right_0()),
+                        ),
+                )
+                .children(minimap)
+                .into_any()
         };
 
-        let mut layout = v_flex().gap(rems(0.5)).p(rems(0.5)).child(header);
+        let mut layout = v_flex()
+            .size_full()
+            .gap(rems(0.5))
+            .p(rems(0.5))
+            .child(header);
 
         if let Some(label) = repo_label {
             layout = layout.child(Label::new(label).size(LabelSize::Small).color(Color::Muted));
         }
 
+        if let Some(git_head) = self.render_git_head() {
+            layout = layout.child(git_head);
+        }
+
         if let Some(selector) = repo_selector {
             layout = layout.child(selector);
         }
 
+        layout = layout.child(
+            h_flex()
+                .justify_between()
+                .child(self.render_date_filter(cx))
+                .child(
+                    h_flex()
+                        .gap(rems(0.25))
+                        .child(
+                            IconButton::new("jj-toggle-hidden-commits", IconName::Eye)
+                                .icon_size(IconSize::Small)
+                                .toggle_state(self.show_hidden_commits)
+                                .disabled(self.selected_repo.is_none())
+                                .tooltip(Tooltip::text(if self.show_hidden_commits {
+                                    "Hide Abandoned Changes"
+                                } else {
+                                    "Show Abandoned Changes"
+                                }))
+                                .on_click(cx.listener(|panel, _, window, cx| {
+                                    panel.toggle_hidden_commits(window, cx);
+                                })),
+                        )
+                        .child(self.render_export_menu(cx)),
+                ),
+        );
+
+        // These describe the repository as it is now, which would be confusing next to a past
+        // operation's log.
+        if self.past_operation.is_none() {
+            if let Some(banner) = self.render_parked_banner(cx) {
+                layout = layout.child(banner);
+            }
+
+            if let Some(banner) = self.render_divergent_banner(cx) {
+                layout = layout.child(banner);
+            }
+
+            if let Some(banner) = self.render_restack_banner(cx) {
+                layout = layout.child(banner);
+            }
+
+            if let Some(section) = self.render_working_copy_status(cx) {
+                layout = layout.child(section);
+            }
+
+            if let Some(section) = self.render_untracked_paths(cx) {
+                layout = layout.child(section);
+            }
+
+            if let Some(section) = self.render_hidden_commits(cx) {
+                layout = layout.child(section);
+            }
+        }
+
         layout = layout.child(content);
 
+        if let Some(legend) = self.render_key_legend(window, cx) {
+            layout = layout.child(legend);
+        }
+
         if let Some((menu, position, _)) = &self.context_menu {
             layout = layout.child(
                 deferred(
@@ -658,11 +4618,360 @@ impl Render for JjPanel {
         }
 
         layout
+            .key_context("JjPanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::edit_selected_change))
+            .on_action(cx.listener(Self::rename_selected_change))
+            .on_action(cx.listener(Self::abandon_selected_change))
+            .on_action(cx.listener(Self::squash_selected_change))
+            .on_action(cx.listener(Self::toggle_key_legend))
+            .on_action(cx.listener(Self::cancel))
+            .on_action(cx.listener(Self::toggle_zoom))
+    }
+}
+
+/// Opening every file of a large conflicted rebase at once would bury the workspace.
+const MAX_CONFLICTED_FILES_TO_OPEN: usize = 10;
+
+/// A directory that should have been ignored can hold thousands of untracked files.
+const MAX_UNTRACKED_PATHS_SHOWN: usize = 20;
+
+/// Runs a panel action against the repository of the active buffer. When that doesn't pick
+/// one out and the project has several repositories, asks which one to use rather than
+/// falling back to whichever is selected in the panel.
+fn with_target_repository(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+    action: impl FnOnce(&mut JjPanel, ProjectEntryId, &mut Window, &mut Context<JjPanel>) + 'static,
+) {
+    let Some(panel) = workspace.panel::<JjPanel>(cx) else {
+        return;
+    };
+    let Some(store) = workspace.project().read(cx).jj_store().cloned() else {
+        return;
+    };
+    let buffer_repo_id = workspace
+        .active_item_as::<Editor>(cx)
+        .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton())
+        .and_then(|buffer| store.read(cx).repository_id_for_buffer(&buffer, cx));
+    let repositories = store.read(cx).repositories();
+    let repo_id = buffer_repo_id.or_else(|| match repositories.as_slice() {
+        [repo] => Some(repo.id),
+        _ => None,
+    });
+    if let Some(repo_id) = repo_id {
+        panel.update(cx, |panel, cx| action(panel, repo_id, window, cx));
+        return;
+    }
+    if repositories.is_empty() {
+        return;
+    }
+
+    let mut answers = repositories
+        .iter()
+        .map(|repo| repo.relative_path.as_ref())
+        .collect::<Vec<_>>();
+    answers.push("Cancel");
+    let answer = window.prompt(
+        PromptLevel::Info,
+        "Which repository?",
+        Some("The active file doesn't belong to a Jujutsu repository."),
+        &answers,
+        cx,
+    );
+    cx.spawn_in(window, async move |_, cx| {
+        let Ok(index) = answer.await else {
+            return;
+        };
+        let Some(repo) = repositories.get(index) else {
+            return;
+        };
+        let repo_id = repo.id;
+        panel
+            .update_in(cx, |panel, window, cx| action(panel, repo_id, window, cx))
+            .ok();
+    })
+    .detach();
+}
+
+fn show_conflict_notification(
+    workspace: &mut Workspace,
+    repository_id: ProjectEntryId,
+    change_id: &ChangeId,
+    repo_root: Option<Arc<Path>>,
+    paths: &[RepoPathBuf],
+    cx: &mut Context<Workspace>,
+) {
+    struct JjConflictNotification;
+
+    let message = if paths.len() == 1 {
+        format!(
+            "The current change {} has a conflict in {}",
+            short_change_hash(change_id),
+            paths[0].as_internal_file_string()
+        )
+    } else {
+        format!(
+            "The current change {} has conflicts in {} files",
+            short_change_hash(change_id),
+            paths.len()
+        )
+    };
+    let conflicted_files = repo_root
+        .map(|root| {
+            paths
+                .iter()
+                .take(MAX_CONFLICTED_FILES_TO_OPEN)
+                .map(|path| root.join(path.as_internal_file_string()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let workspace_handle = workspace.weak_handle();
+    workspace.show_notification(
+        NotificationId::composite::<JjConflictNotification>(repository_id.to_proto() as usize),
+        cx,
+        move |cx| {
+            let conflicted_files = conflicted_files.clone();
+            let workspace_handle = workspace_handle.clone();
+            cx.new(move |cx| {
+                MessageNotification::new(message.clone(), cx)
+                    .primary_message("Show Conflicts")
+                    .primary_icon(IconName::Warning)
+                    .primary_icon_color(Color::Warning)
+                    .primary_on_click(move |window, cx| {
+                        workspace_handle
+                            .update(cx, |workspace, cx| {
+                                for path in &conflicted_files {
+                                    workspace
+                                        .open_abs_path(
+                                            path.clone(),
+                                            OpenOptions::default(),
+                                            window,
+                                            cx,
+                                        )
+                                        .detach_and_log_err(cx);
+                                }
+                            })
+                            .ok();
+                    })
+            })
+        },
+    );
+}
+
+fn show_recovery_notification(
+    workspace: &mut Workspace,
+    store: Entity<JjStore>,
+    repository_id: ProjectEntryId,
+    repo_root: Option<Arc<Path>>,
+    problems: &[WorkingCopyProblem],
+    cx: &mut Context<Workspace>,
+) {
+    struct JjRecoveryNotification;
+
+    let repo_name = repo_root
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "this project".to_string());
+    let has_stale_lock = problems
+        .iter()
+        .any(|problem| matches!(problem, WorkingCopyProblem::StaleLock(_)));
+    let message = if has_stale_lock {
+        format!(
+            "The jj working copy in {repo_name} is locked by a process that seems to have crashed"
+        )
+    } else {
+        format!("The jj working copy in {repo_name} was left stale by an interrupted operation")
+    };
+    let workspace_handle = workspace.weak_handle();
+    workspace.show_notification(
+        NotificationId::composite::<JjRecoveryNotification>(repository_id.to_proto() as usize),
+        cx,
+        move |cx| {
+            let store = store.clone();
+            let workspace_handle = workspace_handle.clone();
+            cx.new(move |cx| {
+                MessageNotification::new(message.clone(), cx)
+                    .primary_message(if has_stale_lock {
+                        "Remove Lock and Update"
+                    } else {
+                        "Update Working Copy"
+                    })
+                    .primary_icon(IconName::Warning)
+                    .primary_icon_color(Color::Warning)
+                    .primary_on_click(move |_, cx| {
+                        let Some(task) = store.update(cx, |store, cx| {
+                            store.recover_working_copy(repository_id, cx)
+                        }) else {
+                            return;
+                        };
+                        let workspace_handle = workspace_handle.clone();
+                        cx.spawn(async move |_, cx| {
+                            if let Err(err) = task.await {
+                                warn!(target: "jj_ui", "working copy recovery failed: {err:?}");
+                                workspace_handle
+                                    .update(cx, |workspace, cx| {
+                                        workspace.show_toast(
+                                            Toast::new(
+                                                NotificationId::unique::<JjRecoveryNotification>(),
+                                                format!("Failed to recover working copy: {err}"),
+                                            ),
+                                            cx,
+                                        );
+                                    })
+                                    .ok();
+                            }
+                        })
+                        .detach();
+                    })
+            })
+        },
+    );
+}
+
+fn show_unsupported_format_notification(
+    workspace: &mut Workspace,
+    repository_id: ProjectEntryId,
+    repo_root: Option<Arc<Path>>,
+    error: &UnsupportedFormatError,
+    cx: &mut Context<Workspace>,
+) {
+    struct JjUnsupportedFormatNotification;
+
+    let repo_name = repo_root
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "this project".to_string());
+    workspace.show_toast(
+        Toast::new(
+            NotificationId::composite::<JjUnsupportedFormatNotification>(
+                repository_id.to_proto() as usize
+            ),
+            format!("JJ support is off for {repo_name}: the {error}"),
+        ),
+        cx,
+    );
+}
+
+fn show_operation_request_notification(
+    workspace: &mut Workspace,
+    store: Entity<JjStore>,
+    repository_id: ProjectEntryId,
+    requester_name: String,
+    request: JjOperationRequest,
+    cx: &mut Context<Workspace>,
+) {
+    struct JjOperationRequestNotification;
+
+    let repo_name = store
+        .read(cx)
+        .repository_root(repository_id)
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "this project".to_string());
+    let message = format!(
+        "{requester_name} asks to {} in {repo_name}",
+        request.describe()
+    );
+    let workspace_handle = workspace.weak_handle();
+    workspace.show_notification(
+        NotificationId::unique::<JjOperationRequestNotification>(),
+        cx,
+        move |cx| {
+            let store = store.clone();
+            let request = request.clone();
+            let workspace_handle = workspace_handle.clone();
+            cx.new(move |cx| {
+                MessageNotification::new(message.clone(), cx)
+                    .primary_message("Run")
+                    .primary_on_click(move |_, cx| {
+                        let command = request.command();
+                        let Some(task) = store.update(cx, |store, cx| {
+                            store.run_requested_operation(repository_id, request.clone(), cx)
+                        }) else {
+                            return;
+                        };
+                        let store = store.clone();
+                        let workspace_handle = workspace_handle.clone();
+                        cx.spawn(async move |_, cx| match task.await {
+                            Ok(()) => {
+                                store
+                                    .update(cx, |store, cx| {
+                                        store.record_operation(repository_id, command, cx);
+                                    })
+                                    .ok();
+                            }
+                            Err(err) => {
+                                warn!(target: "jj_ui", "requested {command} failed: {err:?}");
+                                workspace_handle
+                                    .update(cx, |workspace, cx| {
+                                        workspace.show_toast(
+                                            Toast::new(
+                                                NotificationId::unique::<
+                                                    JjOperationRequestNotification,
+                                                >(),
+                                                format!("{command} failed: {err}"),
+                                            ),
+                                            cx,
+                                        );
+                                    })
+                                    .ok();
+                            }
+                        })
+                        .detach();
+                    })
+                    .secondary_message("Decline")
+                    .secondary_on_click(|_, _| {})
+            })
+        },
+    );
+}
+
+/// Copies the diffs of changes about to be abandoned, returning whether abandoning should go
+/// ahead. A failure leaves the changes alone, since the user asked to keep their contents.
+async fn copy_diffs_before_abandoning(
+    store: &Entity<JjStore>,
+    repo_id: ProjectEntryId,
+    change_ids: &[ChangeId],
+    panel: &WeakEntity<JjPanel>,
+    cx: &mut AsyncWindowContext,
+) -> bool {
+    let mut diffs = Vec::with_capacity(change_ids.len());
+    for change_id in change_ids {
+        let Some(task) = store
+            .read_with(cx, |store, cx| {
+                store.change_diff(repo_id, change_id.clone(), cx)
+            })
+            .ok()
+            .flatten()
+        else {
+            return false;
+        };
+        match task.await {
+            Ok(diff) => diffs.push(diff),
+            Err(err) => {
+                warn!(target: "jj_ui", "failed to copy change diff before abandoning: {err:?}");
+                panel
+                    .update(cx, |panel, cx| {
+                        panel.error = Some(format!("Failed to copy diff: {err}").into());
+                        cx.notify();
+                    })
+                    .ok();
+                return false;
+            }
+        }
     }
+    cx.update(|_, cx| cx.write_to_clipboard(ClipboardItem::new_string(diffs.join("\n"))))
+        .is_ok()
 }
 
 fn open_unstaged_diff_for_active_editor(
     workspace: &mut Workspace,
+    log_all_hunks: bool,
     window: &mut Window,
     cx: &mut Context<Workspace>,
 ) -> Result<()> {
@@ -677,47 +4986,68 @@ fn open_unstaged_diff_for_active_editor(
             .context("active editor has no single buffer")?
     };
     let project = workspace.project().clone();
-    let buffer_for_log = buffer_entity.clone();
+    let buffer = buffer_entity.clone();
     let task = project.update(cx, |project, cx| {
         project.open_unstaged_diff(buffer_entity.clone(), cx)
     });
-    cx.spawn_in(window, async move |_, cx| match task.await {
+    cx.spawn_in(window, async move |workspace, cx| match task.await {
         Ok(diff_entity) => {
             info!(target: "jj_ui", "open_unstaged_diff completed; collecting diff details");
-            match cx.update(|_, app| {
-                let working_snapshot = buffer_for_log.read(app).snapshot();
-                let working_text = working_snapshot.text.text();
+            let result = cx.update(|_, app| {
+                let working_snapshot = buffer.read(app).snapshot();
                 let diff_read = diff_entity.read(app);
-                let base_text = diff_read.base_text().text.text();
-                let hunks: Vec<_> = diff_read.hunks(&working_snapshot.text, app).collect();
-                (working_text, base_text, hunks)
-            }) {
-                Ok((working_text, base_text, hunks)) => {
-                    info!(
-                        target: "jj_ui",
-                        "open_unstaged_diff base_preview={} working_preview={}",
-                        summarize_text_for_log(&base_text),
-                        summarize_text_for_log(&working_text)
-                    );
-                    if hunks.is_empty() {
-                        info!(target: "jj_ui", "open_unstaged_diff hunks: none");
-                    } else {
-                        info!(
+                info!(
+                    target: "jj_ui",
+                    "open_unstaged_diff base_preview={} working_preview={}",
+                    summarize_text_for_log(&diff_read.base_text().text.text()),
+                    summarize_text_for_log(&working_snapshot.text.text())
+                );
+                let (logged_hunks, hunk_log_level) = if log_all_hunks {
+                    (usize::MAX, Level::Info)
+                } else {
+                    (MAX_LOGGED_HUNKS, Level::Debug)
+                };
+                let mut counts = HashMap::<DiffHunkStatusKind, usize>::default();
+                let mut total = 0;
+                for hunk in diff_read.hunks(&working_snapshot.text, app) {
+                    if total < logged_hunks {
+                        log!(
                             target: "jj_ui",
-                            "open_unstaged_diff hunks total={}",
-                            hunks.len()
+                            hunk_log_level,
+                            "open_unstaged_diff hunk {total}: {hunk:?}"
                         );
-                        for (index, hunk) in hunks.iter().enumerate() {
-                            info!(target: "jj_ui", "open_unstaged_diff hunk {index}: {hunk:?}");
-                        }
                     }
+                    *counts.entry(hunk.status().kind).or_default() += 1;
+                    total += 1;
                 }
-                Err(err) => {
-                    info!(
+                let count = |kind| counts.get(&kind).copied().unwrap_or(0);
+                info!(
+                    target: "jj_ui",
+                    "open_unstaged_diff hunks total={total} added={} modified={} deleted={}",
+                    count(DiffHunkStatusKind::Added),
+                    count(DiffHunkStatusKind::Modified),
+                    count(DiffHunkStatusKind::Deleted)
+                );
+                if total > logged_hunks {
+                    debug!(
                         target: "jj_ui",
-                        "open_unstaged_diff succeeded but logging failed: {err:?}"
+                        "open_unstaged_diff omitted {} hunks; run `jj_ui: dump diff hunks` to log all of them",
+                        total - logged_hunks
                     );
                 }
+            });
+            if let Err(err) = result {
+                info!(
+                    target: "jj_ui",
+                    "open_unstaged_diff succeeded but logging failed: {err:?}"
+                );
+            }
+            if !log_all_hunks {
+                workspace
+                    .update_in(cx, |workspace, window, cx| {
+                        open_diff_review_editor(workspace, buffer, diff_entity, window, cx);
+                    })
+                    .ok();
             }
         }
         Err(err) => info!(target: "jj_ui", "open_unstaged_diff failed: {err:?}"),
@@ -726,6 +5056,102 @@ fn open_unstaged_diff_for_active_editor(
     Ok(())
 }
 
+/// Opens `buffer` in a new editor with `diff` attached and every hunk expanded, so the changes
+/// read like a review rather than requiring the gutter to be toggled hunk by hunk.
+fn open_diff_review_editor(
+    workspace: &mut Workspace,
+    buffer: Entity<Buffer>,
+    diff: Entity<BufferDiff>,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) {
+    let title = buffer
+        .read(cx)
+        .file()
+        .map(|file| format!("{} (jj diff)", file.file_name(cx)))
+        .unwrap_or_else(|| "jj diff".to_string());
+    let multibuffer = cx.new(|cx| {
+        let mut multibuffer = MultiBuffer::singleton(buffer, cx).with_title(title);
+        multibuffer.add_diff(diff, cx);
+        multibuffer
+    });
+    let project = workspace.project().clone();
+    let editor = cx.new(|cx| {
+        let mut editor = Editor::for_multibuffer(multibuffer, Some(project), window, cx);
+        editor.set_expand_all_diff_hunks(cx);
+        editor
+    });
+    workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+}
+
+fn discard_active_file_changes(
+    workspace: &mut Workspace,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Result<()> {
+    let editor = workspace
+        .active_item_as::<Editor>(cx)
+        .context("no active editor")?;
+    let buffer = editor
+        .read(cx)
+        .buffer()
+        .read(cx)
+        .as_singleton()
+        .context("active editor has no single buffer")?;
+    let project = workspace.project().clone();
+    let store = project
+        .read(cx)
+        .jj_store()
+        .cloned()
+        .context("project has no jj store")?;
+    let file_name = buffer
+        .read(cx)
+        .file()
+        .map(|file| file.file_name(cx).to_string())
+        .context("buffer has no file")?;
+    let answer = window.prompt(
+        PromptLevel::Warning,
+        &format!("Discard changes to {file_name}?"),
+        Some("The file will be restored from the parent revision, including unsaved edits."),
+        &["Discard", "Cancel"],
+        cx,
+    );
+    cx.spawn_in(window, async move |workspace, cx| {
+        if answer.await != Ok(0) {
+            return;
+        }
+        let result = async {
+            let task = store
+                .update(cx, |store, cx| store.restore_file(&buffer, cx))?
+                .context("file is not in a jj repository")?;
+            task.await?;
+            project
+                .update(cx, |project, cx| {
+                    project.reload_buffers([buffer.clone()].into_iter().collect(), false, cx)
+                })?
+                .await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(err) = result {
+            warn!(target: "jj_ui", "discarding changes to {file_name} failed: {err:?}");
+            workspace
+                .update(cx, |workspace, cx| {
+                    workspace.show_toast(
+                        Toast::new(
+                            NotificationId::unique::<JjOperationToast>(),
+                            format!("Failed to discard changes to {file_name}: {err}"),
+                        ),
+                        cx,
+                    );
+                })
+                .ok();
+        }
+    })
+    .detach();
+    Ok(())
+}
+
 fn summarize_text_for_log(text: &str) -> String {
     const MAX_PREVIEW_CHARS: usize = 120;
     if text.is_empty() {
@@ -743,14 +5169,27 @@ fn summarize_text_for_log(text: &str) -> String {
     }
 }
 
+const DESCRIPTION_EDITOR_MIN_LINES: usize = 3;
+const DESCRIPTION_EDITOR_MAX_LINES: usize = 12;
+
 struct RenameChangeModal {
     focus_handle: FocusHandle,
-    input: Entity<InputField>,
+    editor: Entity<Editor>,
+    /// The rendered description, present while the preview pane is shown.
+    preview: Option<Entity<Markdown>>,
     project: Entity<Project>,
     panel: WeakEntity<JjPanel>,
     target: CommitMenuTarget,
+    repo_label: Option<SharedString>,
+    suggested_description: Option<String>,
+    /// Descriptions recently given to changes in this repository, most recent first.
+    history: Vec<String>,
+    history_index: Option<usize>,
+    /// What was typed before browsing the history, restored when browsing past the newest entry.
+    draft: String,
     is_submitting: bool,
     error: Option<SharedString>,
+    _editor_subscription: Subscription,
 }
 
 impl RenameChangeModal {
@@ -758,40 +5197,122 @@ impl RenameChangeModal {
         project: Entity<Project>,
         panel: WeakEntity<JjPanel>,
         target: CommitMenuTarget,
+        repo_label: Option<SharedString>,
         window: &mut Window,
         cx: &mut Context<Self>,
     ) -> Self {
-        let input = cx.new(|cx| {
-            InputField::new(window, cx, "New change description")
-                .label("Description")
-                .label_size(LabelSize::Small)
+        let language_registry = project.read(cx).languages().clone();
+        // Going through the project registers the buffer, so language servers and spellchecking
+        // attach to it as they do to files.
+        let buffer = project.update(cx, |project, cx| {
+            project.create_local_buffer(&target.commit.description, None, false, cx)
         });
-        input.update(cx, |field, cx| {
-            field.set_text(target.commit.description.clone(), window, cx);
+        // Descriptions often end up as pull request bodies, so they're edited as markdown.
+        let markdown_language = language_registry.language_for_name("Markdown");
+        let markdown_buffer = buffer.clone();
+        cx.spawn(async move |_, cx| {
+            let language = markdown_language.await?;
+            markdown_buffer.update(cx, |buffer, cx| buffer.set_language(Some(language), cx))
+        })
+        .detach_and_log_err(cx);
+        let editor = cx.new(|cx| {
+            let multibuffer = cx.new(|cx| MultiBuffer::singleton(buffer, cx));
+            let mut editor = Editor::new(
+                EditorMode::AutoHeight {
+                    min_lines: DESCRIPTION_EDITOR_MIN_LINES,
+                    max_lines: Some(DESCRIPTION_EDITOR_MAX_LINES),
+                },
+                multibuffer,
+                Some(project.clone()),
+                window,
+                cx,
+            );
+            editor.set_use_autoclose(false);
+            editor.set_show_gutter(false, cx);
+            editor.set_show_wrap_guides(false, cx);
+            editor.set_show_indent_guides(false, cx);
+            editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
+            editor.set_placeholder_text("New change description", window, cx);
+            editor
         });
-        input.update(cx, |field, cx| {
-            let editor = field.editor().clone();
-            editor.update(cx, |editor, cx| {
-                let focus = editor.focus_handle(cx);
-                window.focus(&focus);
+        window.focus(&editor.focus_handle(cx));
+        let editor_subscription =
+            cx.subscribe(&editor, |modal: &mut Self, _, event: &EditorEvent, cx| {
+                if let EditorEvent::BufferEdited = event {
+                    modal.update_preview(cx);
+                }
             });
-        });
-        Self {
+        let history = project
+            .read(cx)
+            .jj_store()
+            .and_then(|store| store.read(cx).repository_root(target.repo_id))
+            .map(|repo_root| description_history::load_descriptions(&repo_root))
+            .unwrap_or_default();
+        let mut modal = Self {
             focus_handle: cx.focus_handle(),
-            input,
+            editor,
+            preview: None,
             project,
             panel,
             target,
+            repo_label,
+            suggested_description: None,
+            history,
+            history_index: None,
+            draft: String::new(),
             is_submitting: false,
             error: None,
+            _editor_subscription: editor_subscription,
+        };
+        if modal.target.commit.description.trim().is_empty()
+            && ProjectSettings::get_global(cx).jj.auto_description
+        {
+            modal.load_suggested_description(window, cx);
         }
+        modal
+    }
+
+    fn load_suggested_description(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            return;
+        };
+        let repo_id = self.target.repo_id;
+        let change_id = self.target.commit.change_id.clone();
+        let Some(task) = store.read(cx).suggested_description(repo_id, change_id, cx) else {
+            return;
+        };
+        cx.spawn_in(window, async move |modal, cx| {
+            let suggestion = match task.await {
+                Ok(Some(suggestion)) => suggestion,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!(target: "jj_ui", "failed to suggest description: {err:?}");
+                    return;
+                }
+            };
+            modal
+                .update_in(cx, |modal, window, cx| {
+                    modal.editor.update(cx, |editor, cx| {
+                        editor.set_placeholder_text(&suggestion, window, cx);
+                    });
+                    modal.suggested_description = Some(suggestion);
+                    cx.notify();
+                })
+                .ok();
+        })
+        .detach();
     }
 
     fn submit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if self.is_submitting {
             return;
         }
-        let description = self.input.read(cx).text(cx).trim().to_string();
+        let mut description = self.editor.read(cx).text(cx).trim().to_string();
+        if description.is_empty()
+            && let Some(suggestion) = &self.suggested_description
+        {
+            description = suggestion.clone();
+        }
         if description.is_empty() {
             self.error = Some("Description cannot be empty".into());
             cx.notify();
@@ -805,6 +5326,20 @@ impl RenameChangeModal {
         let change_id = self.target.commit.change_id.clone();
         let repo_id = self.target.repo_id;
         let panel = self.panel.clone();
+        let repo_root = store.read(cx).repository_root(repo_id);
+        if store.read(cx).is_remote() {
+            let request = JjOperationRequest::RenameChange {
+                change_id,
+                description,
+            };
+            panel
+                .update(cx, |panel, cx| {
+                    panel.request_operation(repo_id, request, window, cx);
+                })
+                .ok();
+            cx.emit(DismissEvent);
+            return;
+        }
         if let Some(task) = store.update(cx, |store, cx| {
             store.rename_change(repo_id, change_id.clone(), description.clone(), cx)
         }) {
@@ -812,7 +5347,18 @@ impl RenameChangeModal {
             let modal = cx.entity().downgrade();
             cx.spawn_in(window, async move |_, cx| match task.await {
                 Ok(_) => {
+                    if let Some(repo_root) = repo_root {
+                        cx.update(|_, cx| {
+                            description_history::record_description(&repo_root, description, cx);
+                        })
+                        .ok();
+                    }
                     if let Some(panel) = panel.upgrade() {
+                        panel
+                            .update(cx, |panel, cx| {
+                                panel.record_operation(repo_id, "jj describe", cx);
+                            })
+                            .ok();
                         let panel_clone = panel.clone();
                         let _ = cx.update(|window, cx| {
                             panel_clone.update(cx, |panel, cx| {
@@ -845,13 +5391,83 @@ impl RenameChangeModal {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        if event.keystroke.key.eq_ignore_ascii_case("enter")
-            && event.keystroke.modifiers == Modifiers::default()
-        {
+        if event.keystroke.modifiers == Modifiers::default() && event.keystroke.key == "enter" {
             window.prevent_default();
             self.submit(window, cx);
         }
     }
+
+    fn toggle_preview(&mut self, cx: &mut Context<Self>) {
+        if self.preview.take().is_none() {
+            let text = self.editor.read(cx).text(cx);
+            let language_registry = self.project.read(cx).languages().clone();
+            self.preview =
+                Some(cx.new(|cx| Markdown::new(text.into(), Some(language_registry), None, cx)));
+        }
+        cx.notify();
+    }
+
+    fn update_preview(&mut self, cx: &mut Context<Self>) {
+        if let Some(preview) = &self.preview {
+            let text = self.editor.read(cx).text(cx);
+            preview.update(cx, |preview, cx| preview.replace(text, cx));
+        }
+    }
+
+    /// Up and down only browse the history while the description is a single line, so that they
+    /// still move the cursor through longer descriptions.
+    fn capture_history_step(&mut self, older: bool, window: &mut Window, cx: &mut Context<Self>) {
+        if self.editor.read(cx).text(cx).contains('\n') {
+            return;
+        }
+        if self.step_history(older, window, cx) {
+            cx.stop_propagation();
+        }
+    }
+
+    /// Replaces the input with an older or newer description from the history, like a shell.
+    fn step_history(&mut self, older: bool, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        if self.history.is_empty() {
+            return false;
+        }
+        let next_index = match (self.history_index, older) {
+            (None, false) => return false,
+            (None, true) => {
+                self.draft = self.editor.read(cx).text(cx);
+                Some(0)
+            }
+            (Some(index), true) => Some((index + 1).min(self.history.len() - 1)),
+            (Some(0), false) => None,
+            (Some(index), false) => Some(index - 1),
+        };
+        self.history_index = next_index;
+        let text = match next_index {
+            Some(index) => self.history[index].clone(),
+            None => mem::take(&mut self.draft),
+        };
+        self.editor
+            .update(cx, |editor, cx| editor.set_text(text, window, cx));
+        true
+    }
+
+    /// Completes the input to the most recent description that starts with it.
+    fn complete_from_history(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let text = self.editor.read(cx).text(cx);
+        if text.is_empty() {
+            return false;
+        }
+        let Some(completion) = self
+            .history
+            .iter()
+            .find(|description| description.len() > text.len() && description.starts_with(&text))
+            .cloned()
+        else {
+            return false;
+        };
+        self.editor
+            .update(cx, |editor, cx| editor.set_text(completion, window, cx));
+        true
+    }
 }
 
 impl ModalView for RenameChangeModal {}
@@ -867,9 +5483,60 @@ impl Focusable for RenameChangeModal {
 impl Render for RenameChangeModal {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let change_short = short_change_hash(&self.target.commit.change_id);
-        let header = ModalHeader::new().headline(format!("Rename change {change_short}"));
+        let headline = match &self.repo_label {
+            Some(repo_label) => format!("Rename change {change_short} in {repo_label}"),
+            None => format!("Rename change {change_short}"),
+        };
+        let header = ModalHeader::new().headline(headline);
 
-        let mut body = v_flex().gap(rems(0.5)).child(self.input.clone());
+        let colors = cx.theme().colors();
+        let mut body = v_flex()
+            .gap(rems(0.5))
+            .child(
+                h_flex()
+                    .justify_between()
+                    .child(Label::new("Description").size(LabelSize::Small))
+                    .child(
+                        IconButton::new("rename-toggle-preview", IconName::Eye)
+                            .icon_size(IconSize::Small)
+                            .toggle_state(self.preview.is_some())
+                            .tooltip(Tooltip::text("Toggle Markdown Preview"))
+                            .on_click(cx.listener(|modal, _, _, cx| modal.toggle_preview(cx))),
+                    ),
+            )
+            .child(
+                div()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(colors.border_variant)
+                    .bg(colors.editor_background)
+                    .child(self.editor.clone()),
+            );
+        if let Some(preview) = &self.preview {
+            body = body.child(
+                div()
+                    .id("rename-change-preview")
+                    .max_h(rems(16.))
+                    .overflow_y_scroll()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(colors.border_variant)
+                    .child(MarkdownElement::new(
+                        preview.clone(),
+                        hover_markdown_style(window, cx),
+                    )),
+            );
+        }
+        let hint = if self.history.is_empty() {
+            "Shift-Enter for a new line"
+        } else {
+            "Shift-Enter for a new line, ↑ ↓ for recent descriptions, Tab to complete"
+        };
+        body = body.child(Label::new(hint).size(LabelSize::XSmall).color(Color::Muted));
 
         if let Some(error) = &self.error {
             body = body.child(Label::new(error.clone()).color(Color::Error));
@@ -902,7 +5569,6 @@ impl Render for RenameChangeModal {
             .section(section)
             .footer(footer);
 
-        let colors = cx.theme().colors();
         div()
             .id("rename-change-modal")
             .w(rems(32.))
@@ -913,6 +5579,21 @@ impl Render for RenameChangeModal {
             .on_key_down(cx.listener(|modal, event, window, cx| {
                 modal.handle_key_down(event, window, cx);
             }))
+            .capture_action(
+                cx.listener(|modal, _: &editor::actions::MoveUp, window, cx| {
+                    modal.capture_history_step(true, window, cx);
+                }),
+            )
+            .capture_action(
+                cx.listener(|modal, _: &editor::actions::MoveDown, window, cx| {
+                    modal.capture_history_step(false, window, cx);
+                }),
+            )
+            .capture_action(cx.listener(|modal, _: &editor::actions::Tab, window, cx| {
+                if modal.complete_from_history(window, cx) {
+                    cx.stop_propagation();
+                }
+            }))
             .child(modal)
     }
 }
@@ -0,0 +1,256 @@
+use crate::JjPanel;
+use gpui::{
+    App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, KeyDownEvent, Modifiers,
+    WeakEntity,
+};
+use jj::CommitDateRange;
+use time::{Date, Duration, OffsetDateTime, Time, UtcOffset, macros::format_description};
+use ui::{ButtonStyle, Modal, ModalFooter, ModalHeader, Section, prelude::*};
+use ui_input::InputField;
+use workspace::ModalView;
+
+/// Which commits the panel's log shows, by when they were committed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum LogDateFilter {
+    #[default]
+    AllTime,
+    LastDay,
+    LastWeek,
+    /// Between two calendar days in the local time zone, both included.
+    Custom {
+        from: Option<Date>,
+        to: Option<Date>,
+    },
+}
+
+impl LogDateFilter {
+    /// The committer timestamps to show, relative to `now` so that a refresh moves the window.
+    pub(crate) fn date_range(self, now: OffsetDateTime) -> CommitDateRange {
+        let millis = |time: OffsetDateTime| (time.unix_timestamp_nanos() / 1_000_000) as i64;
+        match self {
+            Self::AllTime => CommitDateRange::default(),
+            Self::LastDay => CommitDateRange {
+                after: Some(millis(now - Duration::DAY)),
+                before: None,
+            },
+            Self::LastWeek => CommitDateRange {
+                after: Some(millis(now - Duration::WEEK)),
+                before: None,
+            },
+            Self::Custom { from, to } => {
+                let offset = local_offset();
+                let start_of_day =
+                    |date: Date| date.with_time(Time::MIDNIGHT).assume_offset(offset);
+                CommitDateRange {
+                    after: from.map(|from| millis(start_of_day(from))),
+                    before: to
+                        .and_then(|to| to.next_day())
+                        .map(|day_after| millis(start_of_day(day_after))),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn label(self) -> SharedString {
+        match self {
+            Self::AllTime => "All Time".into(),
+            Self::LastDay => "Last Day".into(),
+            Self::LastWeek => "Last Week".into(),
+            Self::Custom { from, to } => match (from, to) {
+                (Some(from), Some(to)) => format!("{from} – {to}").into(),
+                (Some(from), None) => format!("Since {from}").into(),
+                (None, Some(to)) => format!("Until {to}").into(),
+                (None, None) => Self::AllTime.label(),
+            },
+        }
+    }
+}
+
+fn local_offset() -> UtcOffset {
+    UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
+}
+
+pub(crate) fn local_today() -> Date {
+    OffsetDateTime::now_utc().to_offset(local_offset()).date()
+}
+
+/// The calendar day in the local time zone of a timestamp in milliseconds since the epoch.
+pub(crate) fn local_day(timestamp: i64) -> Option<Date> {
+    OffsetDateTime::from_unix_timestamp_nanos(timestamp as i128 * 1_000_000)
+        .ok()
+        .map(|time| time.to_offset(local_offset()).date())
+}
+
+/// The separator shown above the first commit of `day` when the log is grouped by day.
+pub(crate) fn day_header_label(day: Date, today: Date) -> SharedString {
+    if day == today {
+        return "Today".into();
+    }
+    if today.previous_day() == Some(day) {
+        return "Yesterday".into();
+    }
+    let formatted = if day.year() == today.year() {
+        day.format(format_description!("[month repr:short] [day padding:none]"))
+    } else {
+        day.format(format_description!(
+            "[month repr:short] [day padding:none], [year]"
+        ))
+    };
+    formatted.unwrap_or_else(|_| day.to_string()).into()
+}
+
+fn parse_date(text: &str) -> Result<Option<Date>, SharedString> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Date::parse(text, format_description!("[year]-[month]-[day]"))
+        .map(Some)
+        .map_err(|_| format!("\"{text}\" is not a date like 2024-02-03").into())
+}
+
+/// Asks for the first and last day of a custom log date range.
+pub(crate) struct CustomDateRangeModal {
+    focus_handle: FocusHandle,
+    from_input: Entity<InputField>,
+    to_input: Entity<InputField>,
+    panel: WeakEntity<JjPanel>,
+    error: Option<SharedString>,
+}
+
+impl CustomDateRangeModal {
+    pub(crate) fn new(
+        panel: WeakEntity<JjPanel>,
+        current: LogDateFilter,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let (from, to) = match current {
+            LogDateFilter::Custom { from, to } => (from, to),
+            _ => (None, None),
+        };
+        let today = local_today();
+        let from_input = cx.new(|cx| {
+            InputField::new(window, cx, (today - Duration::WEEK).to_string())
+                .label("From")
+                .label_size(LabelSize::Small)
+        });
+        let to_input = cx.new(|cx| {
+            InputField::new(window, cx, today.to_string())
+                .label("To")
+                .label_size(LabelSize::Small)
+        });
+        for (input, date) in [(&from_input, from), (&to_input, to)] {
+            if let Some(date) = date {
+                input.update(cx, |field, cx| field.set_text(date.to_string(), window, cx));
+            }
+        }
+        let editor = from_input.read(cx).editor().clone();
+        window.focus(&editor.focus_handle(cx));
+        Self {
+            focus_handle: cx.focus_handle(),
+            from_input,
+            to_input,
+            panel,
+            error: None,
+        }
+    }
+
+    fn submit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let dates = parse_date(&self.from_input.read(cx).text(cx))
+            .and_then(|from| Ok((from, parse_date(&self.to_input.read(cx).text(cx))?)));
+        let (from, to) = match dates {
+            Ok(dates) => dates,
+            Err(error) => {
+                self.error = Some(error);
+                cx.notify();
+                return;
+            }
+        };
+        if let (Some(from), Some(to)) = (from, to)
+            && from > to
+        {
+            self.error = Some("The range ends before it starts".into());
+            cx.notify();
+            return;
+        }
+        let filter = if from.is_none() && to.is_none() {
+            LogDateFilter::AllTime
+        } else {
+            LogDateFilter::Custom { from, to }
+        };
+        self.panel
+            .update(cx, |panel, cx| panel.set_date_filter(filter, window, cx))
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.modifiers == Modifiers::default() && event.keystroke.key == "enter" {
+            window.prevent_default();
+            self.submit(window, cx);
+        }
+    }
+}
+
+impl ModalView for CustomDateRangeModal {}
+
+impl EventEmitter<DismissEvent> for CustomDateRangeModal {}
+
+impl Focusable for CustomDateRangeModal {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for CustomDateRangeModal {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let mut body = v_flex()
+            .gap(rems(0.5))
+            .child(self.from_input.clone())
+            .child(self.to_input.clone())
+            .child(
+                Label::new("Days are YYYY-MM-DD and both included. Leave one empty for no bound.")
+                    .size(LabelSize::XSmall)
+                    .color(Color::Muted),
+            );
+        if let Some(error) = &self.error {
+            body = body.child(Label::new(error.clone()).color(Color::Error));
+        }
+
+        let footer_actions = h_flex()
+            .gap(rems(0.5))
+            .child(
+                Button::new("date-range-cancel", "Cancel")
+                    .style(ButtonStyle::Transparent)
+                    .on_click(cx.listener(|_, _, _, cx| cx.emit(DismissEvent))),
+            )
+            .child(
+                Button::new("date-range-submit", "Apply")
+                    .style(ButtonStyle::Filled)
+                    .on_click(cx.listener(|modal, _, window, cx| modal.submit(window, cx))),
+            );
+
+        let modal = Modal::new("custom-date-range", None)
+            .header(ModalHeader::new().headline("Show Commits Committed Between"))
+            .section(Section::new().child(body))
+            .footer(ModalFooter::new().end_slot(footer_actions));
+
+        let colors = cx.theme().colors();
+        div()
+            .id("custom-date-range-modal")
+            .w(rems(24.))
+            .elevation_3(cx)
+            .rounded_lg()
+            .bg(colors.elevated_surface_background)
+            .on_key_down(cx.listener(|modal, event, window, cx| {
+                modal.handle_key_down(event, window, cx);
+            }))
+            .child(modal)
+    }
+}
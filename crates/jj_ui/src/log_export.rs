@@ -0,0 +1,121 @@
+use anyhow::Result;
+use jj::{BookmarkSummary, CommitId, short_change_hash, short_commit_hash};
+use project::JjCommitSummary;
+use serde_json::json;
+use time::{
+    OffsetDateTime, UtcOffset, format_description::well_known::Rfc3339, macros::format_description,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogExportFormat {
+    /// Lines shaped like `jj log` output, for pasting into status updates.
+    Text,
+    Json,
+}
+
+impl LogExportFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Renders `commits` in the given format, listing the bookmarks from `bookmarks` that point at
+/// each of them.
+pub(crate) fn export_log(
+    commits: &[JjCommitSummary],
+    bookmarks: &[BookmarkSummary],
+    format: LogExportFormat,
+) -> Result<String> {
+    match format {
+        LogExportFormat::Text => Ok(export_text(commits, bookmarks)),
+        LogExportFormat::Json => export_json(commits, bookmarks),
+    }
+}
+
+fn export_text(commits: &[JjCommitSummary], bookmarks: &[BookmarkSummary]) -> String {
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let mut output = String::new();
+    for commit in commits {
+        let marker = if commit.is_current { "@" } else { "○" };
+        let mut line = format!(
+            "{marker}  {} {}",
+            short_change_hash(&commit.change_id),
+            commit.author_email
+        );
+        if let Some(time) = commit_time(commit.timestamp) {
+            let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+            if let Ok(time) = time.to_offset(offset).format(format) {
+                line.push(' ');
+                line.push_str(&time);
+            }
+        }
+        for bookmark in bookmark_names(&commit.commit_id, bookmarks) {
+            line.push(' ');
+            line.push_str(&bookmark);
+        }
+        line.push(' ');
+        line.push_str(&short_commit_hash(&commit.commit_id));
+        output.push_str(&line);
+        output.push('\n');
+
+        let subject = commit.description.lines().next().unwrap_or_default().trim();
+        if subject.is_empty() {
+            output.push_str("│  (no description set)\n");
+        } else {
+            output.push_str(&format!("│  {subject}\n"));
+        }
+    }
+    output
+}
+
+fn export_json(commits: &[JjCommitSummary], bookmarks: &[BookmarkSummary]) -> Result<String> {
+    let commits = commits
+        .iter()
+        .map(|commit| {
+            json!({
+                "change_id": commit.change_id.to_string(),
+                "commit_id": commit.commit_id.to_string(),
+                "parent_ids": commit
+                    .parent_ids
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                "description": commit.description.as_ref(),
+                "author": {
+                    "name": commit.author.as_ref(),
+                    "email": commit.author_email.as_ref(),
+                    "timestamp": commit_time(commit.author_timestamp)
+                        .and_then(|time| time.format(&Rfc3339).ok()),
+                },
+                "committer": {
+                    "name": commit.committer.as_ref(),
+                    "email": commit.committer_email.as_ref(),
+                },
+                "timestamp": commit_time(commit.timestamp)
+                    .and_then(|time| time.format(&Rfc3339).ok()),
+                "bookmarks": bookmark_names(&commit.commit_id, bookmarks),
+                "is_current": commit.is_current,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&commits)?)
+}
+
+fn commit_time(timestamp: i64) -> Option<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(timestamp as i128 * 1_000_000).ok()
+}
+
+/// Bookmarks pointing at the commit, written `name` or `name@remote` like `jj log` does.
+fn bookmark_names(commit_id: &CommitId, bookmarks: &[BookmarkSummary]) -> Vec<String> {
+    bookmarks
+        .iter()
+        .filter(|bookmark| bookmark.target_ids.contains(commit_id))
+        .map(|bookmark| match &bookmark.remote {
+            Some(remote) => format!("{}@{remote}", bookmark.name),
+            None => bookmark.name.clone(),
+        })
+        .collect()
+}
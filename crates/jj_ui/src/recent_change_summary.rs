@@ -0,0 +1,53 @@
+use db::kvp::KEY_VALUE_STORE;
+use gpui::{App, SharedString};
+use jj::short_change_hash;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use util::ResultExt as _;
+use workspace::Workspace;
+
+const KEY_PREFIX: &str = "jj_recent_change";
+
+/// Records the working-copy change of each jj repository in the workspace's project, so that
+/// the recent projects picker can show what was being worked on when it was closed.
+pub(crate) fn record_change_summaries(workspace: &Workspace, cx: &App) {
+    let Some(store) = workspace.project().read(cx).jj_store() else {
+        return;
+    };
+    let summaries = store.read(cx).working_copy_summaries(cx);
+    cx.background_spawn(async move {
+        for (repo_root, commit) in summaries.await {
+            let description = commit
+                .description
+                .lines()
+                .next()
+                .filter(|line| !line.trim().is_empty())
+                .unwrap_or("(no description set)");
+            let summary = format!("{description} ({})", short_change_hash(&commit.change_id));
+            KEY_VALUE_STORE
+                .write_kvp(summary_key(&repo_root), summary)
+                .await
+                .log_err();
+        }
+    })
+    .detach();
+}
+
+/// Reads the change summaries recorded for the given paths, skipping paths without one.
+pub fn load_change_summaries(
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> HashMap<PathBuf, SharedString> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let summary = KEY_VALUE_STORE.read_kvp(&summary_key(&path)).log_err()??;
+            Some((path, SharedString::from(summary)))
+        })
+        .collect()
+}
+
+fn summary_key(repo_root: &Path) -> String {
+    format!("{KEY_PREFIX}:{}", repo_root.display())
+}
@@ -0,0 +1,88 @@
+use project::{JjRepositorySummary, ProjectEntryId, WorktreeId};
+use std::rc::Rc;
+use ui::{Tooltip, prelude::*};
+
+/// A button per jj repository in the project, for projects with more than one.
+#[derive(IntoElement, RegisterComponent)]
+pub struct RepoSelector {
+    repositories: Vec<JjRepositorySummary>,
+    selected: Option<ProjectEntryId>,
+    on_select: Option<Rc<dyn Fn(&ProjectEntryId, &mut Window, &mut App) + 'static>>,
+}
+
+impl RepoSelector {
+    pub fn new(repositories: Vec<JjRepositorySummary>, selected: Option<ProjectEntryId>) -> Self {
+        Self {
+            repositories,
+            selected,
+            on_select: None,
+        }
+    }
+
+    pub fn on_select(
+        mut self,
+        handler: impl Fn(&ProjectEntryId, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_select = Some(Rc::new(handler));
+        self
+    }
+}
+
+impl RenderOnce for RepoSelector {
+    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let selected = self.selected;
+        let on_select = self.on_select;
+        h_flex()
+            .gap(rems(0.25))
+            .children(self.repositories.into_iter().map(|repo| {
+                let repo_id = repo.id;
+                Button::new(("jj-repo", repo_id.to_proto()), repo.relative_path)
+                    .tooltip(Tooltip::text(repo.path))
+                    .style(if selected == Some(repo_id) {
+                        ButtonStyle::Filled
+                    } else {
+                        ButtonStyle::Outlined
+                    })
+                    .when_some(on_select.clone(), |button, on_select| {
+                        button.on_click(move |_, window, cx| on_select(&repo_id, window, cx))
+                    })
+            }))
+    }
+}
+
+// View this component preview using `workspace: open component-preview`
+impl Component for RepoSelector {
+    fn scope() -> ComponentScope {
+        ComponentScope::VersionControl
+    }
+
+    fn preview(_window: &mut Window, _cx: &mut App) -> Option<AnyElement> {
+        let repositories = ["app", "vendor/protocol"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, relative_path)| JjRepositorySummary {
+                id: ProjectEntryId::from_usize(index + 1),
+                worktree_id: WorktreeId::from_usize(1),
+                path: format!("/home/jane/project/{relative_path}").into(),
+                relative_path: relative_path.into(),
+                covers_entire_project: false,
+            })
+            .collect::<Vec<_>>();
+        Some(
+            v_flex()
+                .gap_6()
+                .child(example_group(vec![
+                    single_example(
+                        "First selected",
+                        RepoSelector::new(repositories.clone(), Some(repositories[0].id))
+                            .into_any_element(),
+                    ),
+                    single_example(
+                        "None selected",
+                        RepoSelector::new(repositories, None).into_any_element(),
+                    ),
+                ]))
+                .into_any_element(),
+        )
+    }
+}
@@ -0,0 +1,111 @@
+use crate::JjPanel;
+use gpui::{
+    App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, KeyDownEvent, Modifiers,
+    WeakEntity,
+};
+use ui::{ButtonStyle, Modal, ModalFooter, ModalHeader, Section, prelude::*};
+use ui_input::InputField;
+use workspace::ModalView;
+
+/// Asks for a revset to show in the log instead of the most recent commits.
+pub(crate) struct RevsetFilterModal {
+    focus_handle: FocusHandle,
+    input: Entity<InputField>,
+    panel: WeakEntity<JjPanel>,
+}
+
+impl RevsetFilterModal {
+    pub(crate) fn new(
+        panel: WeakEntity<JjPanel>,
+        current: Option<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let input = cx.new(|cx| {
+            InputField::new(window, cx, "mine() & ::@")
+                .label("Revset")
+                .label_size(LabelSize::Small)
+        });
+        if let Some(current) = current {
+            input.update(cx, |field, cx| field.set_text(current, window, cx));
+        }
+        let editor = input.read(cx).editor().clone();
+        window.focus(&editor.focus_handle(cx));
+        Self {
+            focus_handle: cx.focus_handle(),
+            input,
+            panel,
+        }
+    }
+
+    fn submit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let revset = self.input.read(cx).text(cx).trim().to_string();
+        let filter = (!revset.is_empty()).then(|| SharedString::from(revset));
+        self.panel
+            .update(cx, |panel, cx| panel.set_revset_filter(filter, window, cx))
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.modifiers == Modifiers::default() && event.keystroke.key == "enter" {
+            window.prevent_default();
+            self.submit(window, cx);
+        }
+    }
+}
+
+impl ModalView for RevsetFilterModal {}
+
+impl EventEmitter<DismissEvent> for RevsetFilterModal {}
+
+impl Focusable for RevsetFilterModal {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for RevsetFilterModal {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let body = v_flex().gap(rems(0.5)).child(self.input.clone()).child(
+            Label::new("Written as for jj log -r. Leave empty to show recent commits.")
+                .size(LabelSize::XSmall)
+                .color(Color::Muted),
+        );
+
+        let footer_actions = h_flex()
+            .gap(rems(0.5))
+            .child(
+                Button::new("revset-filter-cancel", "Cancel")
+                    .style(ButtonStyle::Transparent)
+                    .on_click(cx.listener(|_, _, _, cx| cx.emit(DismissEvent))),
+            )
+            .child(
+                Button::new("revset-filter-submit", "Apply")
+                    .style(ButtonStyle::Filled)
+                    .on_click(cx.listener(|modal, _, window, cx| modal.submit(window, cx))),
+            );
+
+        let modal = Modal::new("revset-filter", None)
+            .header(ModalHeader::new().headline("Show Commits Matching a Revset"))
+            .section(Section::new().child(body))
+            .footer(ModalFooter::new().end_slot(footer_actions));
+
+        let colors = cx.theme().colors();
+        div()
+            .id("revset-filter-modal")
+            .w(rems(28.))
+            .elevation_3(cx)
+            .rounded_lg()
+            .bg(colors.elevated_surface_background)
+            .on_key_down(cx.listener(|modal, event, window, cx| {
+                modal.handle_key_down(event, window, cx);
+            }))
+            .child(modal)
+    }
+}
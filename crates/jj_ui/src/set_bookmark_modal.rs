@@ -0,0 +1,155 @@
+use crate::{CommitMenuTarget, JjPanel};
+use gpui::{
+    App, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, KeyDownEvent, Modifiers,
+    WeakEntity,
+};
+use jj::short_change_hash;
+use project::Project;
+use ui::{ButtonStyle, Modal, ModalFooter, ModalHeader, Section, prelude::*};
+use ui_input::InputField;
+use workspace::ModalView;
+
+/// Asks for a bookmark name and points that bookmark at the target change, creating it if
+/// needed.
+pub(crate) struct SetBookmarkModal {
+    focus_handle: FocusHandle,
+    input: Entity<InputField>,
+    project: Entity<Project>,
+    panel: WeakEntity<JjPanel>,
+    target: CommitMenuTarget,
+    repo_label: Option<SharedString>,
+    error: Option<SharedString>,
+}
+
+impl SetBookmarkModal {
+    pub(crate) fn new(
+        project: Entity<Project>,
+        panel: WeakEntity<JjPanel>,
+        target: CommitMenuTarget,
+        repo_label: Option<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let input = cx.new(|cx| {
+            InputField::new(window, cx, "main")
+                .label("Bookmark name")
+                .label_size(LabelSize::Small)
+        });
+        let editor = input.read(cx).editor().clone();
+        window.focus(&editor.focus_handle(cx));
+        Self {
+            focus_handle: cx.focus_handle(),
+            input,
+            project,
+            panel,
+            target,
+            repo_label,
+            error: None,
+        }
+    }
+
+    fn submit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = self.input.read(cx).text(cx).trim().to_string();
+        if name.is_empty() {
+            self.error = Some("Bookmark name cannot be empty".into());
+            cx.notify();
+            return;
+        }
+        if name.contains(char::is_whitespace) {
+            self.error = Some("Bookmark names cannot contain spaces".into());
+            cx.notify();
+            return;
+        }
+        let Some(store) = self.project.read(cx).jj_store().cloned() else {
+            self.error = Some("JJ support unavailable".into());
+            cx.notify();
+            return;
+        };
+        let repo_id = self.target.repo_id;
+        let change_id = self.target.commit.change_id.clone();
+        let Some(task) = store.update(cx, |store, cx| {
+            store.set_bookmark(repo_id, name, change_id, cx)
+        }) else {
+            self.error = Some("Repository is no longer available".into());
+            cx.notify();
+            return;
+        };
+        self.panel
+            .update(cx, |panel, cx| {
+                panel.spawn_store_task("jj bookmark set", repo_id, task, true, window, cx);
+            })
+            .ok();
+        cx.emit(DismissEvent);
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if event.keystroke.modifiers == Modifiers::default() && event.keystroke.key == "enter" {
+            window.prevent_default();
+            self.submit(window, cx);
+        }
+    }
+}
+
+impl ModalView for SetBookmarkModal {}
+
+impl EventEmitter<DismissEvent> for SetBookmarkModal {}
+
+impl Focusable for SetBookmarkModal {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for SetBookmarkModal {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let change_short = short_change_hash(&self.target.commit.change_id);
+        let headline = match &self.repo_label {
+            Some(repo_label) => format!("Set bookmark on {change_short} in {repo_label}"),
+            None => format!("Set bookmark on {change_short}"),
+        };
+
+        let mut body = v_flex().gap(rems(0.5)).child(self.input.clone()).child(
+            Label::new("An existing bookmark with this name is moved here.")
+                .size(LabelSize::XSmall)
+                .color(Color::Muted),
+        );
+        if let Some(error) = &self.error {
+            body = body.child(Label::new(error.clone()).color(Color::Error));
+        }
+
+        let footer_actions = h_flex()
+            .gap(rems(0.5))
+            .child(
+                Button::new("set-bookmark-cancel", "Cancel")
+                    .style(ButtonStyle::Transparent)
+                    .on_click(cx.listener(|_, _, _, cx| cx.emit(DismissEvent))),
+            )
+            .child(
+                Button::new("set-bookmark-submit", "Set Bookmark")
+                    .style(ButtonStyle::Filled)
+                    .on_click(cx.listener(|modal, _, window, cx| modal.submit(window, cx))),
+            );
+
+        let modal = Modal::new("set-bookmark", None)
+            .header(ModalHeader::new().headline(headline))
+            .section(Section::new().child(body))
+            .footer(ModalFooter::new().end_slot(footer_actions));
+
+        let colors = cx.theme().colors();
+        div()
+            .id("set-bookmark-modal")
+            .w(rems(28.))
+            .elevation_3(cx)
+            .rounded_lg()
+            .bg(colors.elevated_surface_background)
+            .on_key_down(cx.listener(|modal, event, window, cx| {
+                modal.handle_key_down(event, window, cx);
+            }))
+            .child(modal)
+    }
+}
@@ -0,0 +1,167 @@
+use jj::{FileStatus, FileStatusKind, RepoPathBuf};
+use std::rc::Rc;
+use ui::prelude::*;
+
+/// Status lists longer than this are cut short, like the untracked files are.
+const MAX_FILE_STATUSES_SHOWN: usize = 20;
+
+/// The files the working-copy change adds, modifies, removes or leaves conflicted, like
+/// `jj status`. Conflicted files come first, so they're never among the ones cut short.
+#[derive(IntoElement, RegisterComponent)]
+pub struct WorkingCopySection {
+    statuses: Vec<FileStatus>,
+    on_open: Option<Rc<dyn Fn(&RepoPathBuf, &mut Window, &mut App) + 'static>>,
+}
+
+impl WorkingCopySection {
+    pub fn new(statuses: Vec<FileStatus>) -> Self {
+        Self {
+            statuses,
+            on_open: None,
+        }
+    }
+
+    pub fn on_open(
+        mut self,
+        handler: impl Fn(&RepoPathBuf, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_open = Some(Rc::new(handler));
+        self
+    }
+}
+
+fn status_icon(kind: FileStatusKind, cx: &App) -> Icon {
+    let colors = cx.theme().colors();
+    let (icon_name, color) = match kind {
+        FileStatusKind::Added => (IconName::SquarePlus, colors.version_control_added),
+        FileStatusKind::Modified => (IconName::SquareDot, colors.version_control_modified),
+        FileStatusKind::Removed => (IconName::SquareMinus, colors.version_control_deleted),
+        FileStatusKind::Conflicted => (IconName::Warning, colors.version_control_conflict),
+    };
+    Icon::new(icon_name)
+        .size(IconSize::Small)
+        .color(Color::Custom(color))
+}
+
+impl RenderOnce for WorkingCopySection {
+    fn render(mut self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        self.statuses
+            .sort_by_key(|status| status.kind != FileStatusKind::Conflicted);
+        let count = self.statuses.len();
+        let conflicted_count = self
+            .statuses
+            .iter()
+            .take_while(|status| status.kind == FileStatusKind::Conflicted)
+            .count();
+        let mut title = if count == 1 {
+            "1 file changed".to_string()
+        } else {
+            format!("{count} files changed")
+        };
+        if conflicted_count > 0 {
+            title.push_str(&format!(", {conflicted_count} with conflicts"));
+        }
+        let first_conflict = self
+            .statuses
+            .first()
+            .filter(|status| status.kind == FileStatusKind::Conflicted)
+            .map(|status| status.path.clone());
+        let hidden_count = count.saturating_sub(MAX_FILE_STATUSES_SHOWN);
+        let on_open = self.on_open;
+        let hover_background = cx.theme().colors().element_hover;
+
+        v_flex()
+            .gap(rems(0.25))
+            .p(px(4.0))
+            .rounded(px(4.0))
+            .border_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().surface_background)
+            .child(
+                h_flex()
+                    .justify_between()
+                    .items_center()
+                    .child(Label::new(title).size(LabelSize::Small))
+                    .when_some(
+                        first_conflict.zip(on_open.clone()),
+                        |header, (path, on_open)| {
+                            header.child(
+                                Button::new(
+                                    "jj-resolve-conflicts-first",
+                                    "Resolve Conflicts First",
+                                )
+                                .style(ButtonStyle::Filled)
+                                .label_size(LabelSize::XSmall)
+                                .on_click(move |_, window, cx| on_open(&path, window, cx)),
+                            )
+                        },
+                    ),
+            )
+            .children(
+                self.statuses
+                    .into_iter()
+                    .take(MAX_FILE_STATUSES_SHOWN)
+                    .enumerate()
+                    .map(|(index, status)| {
+                        let label = status.path.as_internal_file_string().to_string();
+                        h_flex()
+                            .id(("jj-working-copy-file", index))
+                            .gap(rems(0.5))
+                            .items_center()
+                            .child(status_icon(status.kind, cx))
+                            .child(
+                                Label::new(label)
+                                    .size(LabelSize::XSmall)
+                                    .color(Color::Muted)
+                                    .truncate(),
+                            )
+                            .when_some(on_open.clone(), |row, on_open| {
+                                let path = status.path;
+                                row.cursor_pointer()
+                                    .hover(|row| row.bg(hover_background))
+                                    .on_click(move |_, window, cx| on_open(&path, window, cx))
+                            })
+                    }),
+            )
+            .when(hidden_count > 0, |section| {
+                section.child(
+                    Label::new(format!("and {hidden_count} more"))
+                        .size(LabelSize::XSmall)
+                        .color(Color::Muted),
+                )
+            })
+    }
+}
+
+// View this component preview using `workspace: open component-preview`
+impl Component for WorkingCopySection {
+    fn scope() -> ComponentScope {
+        ComponentScope::VersionControl
+    }
+
+    fn preview(_window: &mut Window, _cx: &mut App) -> Option<AnyElement> {
+        let statuses = [
+            ("src/lib.rs", FileStatusKind::Modified),
+            ("src/status.rs", FileStatusKind::Added),
+            ("src/legacy.rs", FileStatusKind::Removed),
+            ("Cargo.lock", FileStatusKind::Conflicted),
+        ]
+        .into_iter()
+        .filter_map(|(path, kind)| {
+            Some(FileStatus {
+                path: RepoPathBuf::from_internal_string(path).ok()?,
+                kind,
+            })
+        })
+        .collect::<Vec<_>>();
+        Some(
+            v_flex()
+                .gap_6()
+                .child(example_group(vec![single_example(
+                    "Mixed statuses",
+                    WorkingCopySection::new(statuses).into_any_element(),
+                )]))
+                .into_any_element(),
+        )
+    }
+}
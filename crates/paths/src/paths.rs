@@ -378,6 +378,14 @@ pub fn external_agents_dir() -> &'static PathBuf {
     EXTERNAL_AGENTS_DIR.get_or_init(|| data_dir().join("external_agents"))
 }
 
+/// Returns the path to the Jujutsu cache directory.
+///
+/// This is where per-repository caches used by the Jujutsu integration are stored.
+pub fn jj_cache_dir() -> &'static PathBuf {
+    static JJ_CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+    JJ_CACHE_DIR.get_or_init(|| data_dir().join("jj"))
+}
+
 /// Returns the path to the Copilot directory.
 pub fn copilot_dir() -> &'static PathBuf {
     static COPILOT_DIR: OnceLock<PathBuf> = OnceLock::new();
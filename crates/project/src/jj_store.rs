@@ -1,34 +1,396 @@
+use crate::project_settings::ProjectSettings;
 use crate::worktree_store::{WorktreeStore, WorktreeStoreEvent};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use buffer_diff::{BufferDiff, DiffReviewMode};
 #[cfg(feature = "jj-ui")]
+use futures::channel::mpsc;
+use futures::{StreamExt as _, stream};
+use git::{
+    GITIGNORE,
+    status::{FileStatus, StatusCode},
+};
+#[cfg(feature = "jj-ui")]
 use gpui::SharedString;
-use gpui::{AppContext as _, AsyncApp, Context, Entity, Subscription, Task, WeakEntity};
-use jj::{ChangeId, CommitId, JjWorkspace, RepoPathBuf, short_change_hash};
+use gpui::{
+    App, AppContext as _, AsyncApp, BackgroundExecutor, Context, Entity, EventEmitter, Global,
+    Subscription, Task, WeakEntity,
+};
+use jj::{
+    AnnotatedLine, BookmarkSummary, ChangeId, CommitDateRange, CommitField, CommitId, CommitRecord,
+    CommitRef, ConflictSides, DiffStats, FetchSummary, FileStatus, GitHead, GitRemote, GraphEdge,
+    GraphEdgeKind, JjBackends, JjWorkspace, ObjectId as _, ParentTreeCache, PushSummary,
+    RebaseMode, RepoPathBuf, UnsupportedFormatError, WorkingCopyProblem, WorkingCopyState,
+    short_change_hash, short_commit_hash,
+};
 use language::{Buffer, LocalFile};
 use log::{debug, info, warn};
 use parking_lot::Mutex;
-use std::{collections::HashMap, path::Path, sync::Arc};
+use rpc::{AnyProtoClient, TypedEnvelope, proto};
+use settings::{Settings as _, SettingsStore};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 use text::BufferId;
-use worktree::{JjRepoEntryForWorktree, ProjectEntryId, Worktree, WorktreeId};
+use util::{ResultExt as _, rel_path::RelPath};
+use worktree::{
+    JjRepoEntryForWorktree, ProjectEntryId, UpdatedEntriesSet, UpdatedGitRepositoriesSet, Worktree,
+    WorktreeId,
+};
+
+/// How many parent-tree materializations may run at once when recalculating diffs, e.g. after
+/// switching to a different change with many buffers open.
+const MAX_CONCURRENT_MATERIALIZATIONS: usize = 4;
+
+/// Loading the operation head reads from disk, so don't let polling run too frequently.
+const MIN_POLL_INTERVAL_MS: u64 = 500;
+
+const PENDING_OPERATION_POLL_INTERVAL_MS: u64 = 50;
 
 pub struct JjStore {
+    /// Lets query methods that only have an `&App` update the store once a remote reply arrives.
+    this: WeakEntity<Self>,
     worktree_store: Entity<WorktreeStore>,
     repositories_by_worktree: HashMap<WorktreeId, Vec<Arc<JjRepositoryState>>>,
     repositories_by_id: HashMap<ProjectEntryId, Arc<JjRepositoryState>>,
     diffs_by_buffer: HashMap<BufferId, JjDiffState>,
+    last_seen_operations: HashMap<ProjectEntryId, String>,
+    /// Changes moved aside by "park working copy", so they can be unparked later.
+    parked_changes: HashMap<ProjectEntryId, ChangeId>,
+    /// The conflicted working-copy change of each repository that was last reported.
+    conflicted_changes: HashMap<ProjectEntryId, ChangeId>,
+    /// Operations performed through the UI since the project was opened, oldest first.
+    session_operations: Vec<JjSessionOperation>,
+    /// How many repository-mutating operations are currently running.
+    pending_operations: usize,
+    /// How far along the running `jj git fetch` of each repository is, from 0 to 1.
+    fetch_progress: HashMap<ProjectEntryId, f32>,
+    /// Repositories whose workspace is being loaded for the first time. See
+    /// [`Self::load_repository`].
+    loading_repositories: HashSet<ProjectEntryId>,
+    poll_interval_ms: Option<u64>,
+    /// Whether repositories are opened with every write refused, from `jj.read_only`.
+    read_only: bool,
+    /// The host's client and project id when this store belongs to a guest of a shared project.
+    upstream: Option<(AnyProtoClient, u64)>,
+    /// The host's repositories, as last reported to a guest.
+    remote_repositories: Vec<JjRepositorySummary>,
+    refresh_task: Option<Task<()>>,
+    _poll_task: Option<Task<()>>,
+    _conflict_check_task: Option<Task<()>>,
     _subscriptions: Vec<Subscription>,
 }
 
+#[derive(Clone, Debug)]
+pub enum JjStoreEvent {
+    /// The working-copy change of a repository became conflicted.
+    WorkingCopyConflicted {
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        paths: Vec<RepoPathBuf>,
+    },
+    /// A repository was opened with leftovers of an interrupted jj process.
+    RecoveryNeeded {
+        repository_id: ProjectEntryId,
+        problems: Vec<WorkingCopyProblem>,
+    },
+    /// A repository turned out to be in a format the bundled jj-lib can't read, so the store
+    /// stopped using it.
+    UnsupportedFormat {
+        repository_id: ProjectEntryId,
+        error: UnsupportedFormatError,
+    },
+    /// A guest of the shared project asked the host to run an operation.
+    OperationRequested {
+        repository_id: ProjectEntryId,
+        requester: Option<proto::PeerId>,
+        request: JjOperationRequest,
+    },
+}
+
+/// An operation a guest asks the host to run, since guests can't touch the host's repositories.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JjOperationRequest {
+    NewChange {
+        parent_change_id: ChangeId,
+    },
+    RenameChange {
+        change_id: ChangeId,
+        description: String,
+    },
+    SquashChange {
+        change_id: ChangeId,
+    },
+    AbandonChange {
+        change_id: ChangeId,
+    },
+}
+
+impl JjOperationRequest {
+    /// Describes the request for the host's approval prompt.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NewChange { parent_change_id } => format!(
+                "start a new change on {}",
+                short_change_hash(parent_change_id)
+            ),
+            Self::RenameChange {
+                change_id,
+                description,
+            } => format!(
+                "rename change {} to \"{}\"",
+                short_change_hash(change_id),
+                description.lines().next().unwrap_or_default()
+            ),
+            Self::SquashChange { change_id } => format!(
+                "squash change {} into its parent",
+                short_change_hash(change_id)
+            ),
+            Self::AbandonChange { change_id } => {
+                format!("abandon change {}", short_change_hash(change_id))
+            }
+        }
+    }
+
+    /// The command the operation corresponds to, as recorded in the session's history.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::NewChange { .. } => "jj new",
+            Self::RenameChange { .. } => "jj describe",
+            Self::SquashChange { .. } => "jj squash",
+            Self::AbandonChange { .. } => "jj abandon",
+        }
+    }
+
+    fn to_proto(&self) -> proto::jj_request_operation::Operation {
+        use proto::jj_request_operation::Operation;
+        match self {
+            Self::NewChange { parent_change_id } => Operation::NewChange(proto::JjNewChange {
+                parent_change_id: parent_change_id.hex(),
+            }),
+            Self::RenameChange {
+                change_id,
+                description,
+            } => Operation::RenameChange(proto::JjRenameChange {
+                change_id: change_id.hex(),
+                description: description.clone(),
+            }),
+            Self::SquashChange { change_id } => Operation::SquashChange(proto::JjSquashChange {
+                change_id: change_id.hex(),
+            }),
+            Self::AbandonChange { change_id } => Operation::AbandonChange(proto::JjAbandonChange {
+                change_id: change_id.hex(),
+            }),
+        }
+    }
+
+    fn from_proto(operation: proto::jj_request_operation::Operation) -> Result<Self> {
+        use proto::jj_request_operation::Operation;
+        Ok(match operation {
+            Operation::NewChange(new_change) => Self::NewChange {
+                parent_change_id: change_id_from_proto(&new_change.parent_change_id)?,
+            },
+            Operation::RenameChange(rename) => Self::RenameChange {
+                change_id: change_id_from_proto(&rename.change_id)?,
+                description: rename.description,
+            },
+            Operation::SquashChange(squash) => Self::SquashChange {
+                change_id: change_id_from_proto(&squash.change_id)?,
+            },
+            Operation::AbandonChange(abandon) => Self::AbandonChange {
+                change_id: change_id_from_proto(&abandon.change_id)?,
+            },
+        })
+    }
+}
+
+fn change_id_from_proto(hex: &str) -> Result<ChangeId> {
+    ChangeId::try_from_hex(hex).ok_or_else(|| anyhow!("invalid change id {hex:?}"))
+}
+
+fn commit_id_from_proto(hex: &str) -> Result<CommitId> {
+    CommitId::try_from_hex(hex).ok_or_else(|| anyhow!("invalid commit id {hex:?}"))
+}
+
+impl EventEmitter<JjStoreEvent> for JjStore {}
+
+/// An operation performed through the UI during this session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JjSessionOperation {
+    pub repository_id: ProjectEntryId,
+    pub operation_id: String,
+    pub description: String,
+    /// Who recorded the operation and where. Usually Zed's own user and machine, unless
+    /// another process on a shared machine or a synced copy of the repository got in first.
+    pub username: String,
+    pub hostname: String,
+    /// When the operation finished, in milliseconds since the epoch.
+    pub timestamp: i64,
+}
+
 #[cfg(feature = "jj-ui")]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct JjCommitSummary {
     pub commit_id: CommitId,
+    pub parent_ids: Vec<CommitId>,
     pub change_id: ChangeId,
     pub description: SharedString,
     pub author: SharedString,
+    pub author_email: SharedString,
+    pub author_timestamp: i64,
+    pub committer: SharedString,
+    pub committer_email: SharedString,
     pub timestamp: i64,
+    pub is_signed: bool,
+    pub is_pushed: bool,
+    pub nearest_ref: Option<SharedString>,
     pub is_current: bool,
+    pub graph_edges: Vec<GraphEdge>,
+    pub is_divergent: bool,
+    pub is_hidden: bool,
+    pub is_empty: bool,
+    pub has_conflict: bool,
+    pub refs: Vec<CommitRef>,
+}
+
+#[cfg(feature = "jj-ui")]
+impl JjCommitSummary {
+    fn new(summary: jj::CommitSummary, is_current: bool) -> Self {
+        Self {
+            commit_id: summary.commit_id,
+            parent_ids: summary.parent_ids,
+            change_id: summary.change_id,
+            description: SharedString::from(summary.description),
+            author: SharedString::from(summary.author),
+            author_email: SharedString::from(summary.author_email),
+            author_timestamp: summary.author_timestamp,
+            committer: SharedString::from(summary.committer),
+            committer_email: SharedString::from(summary.committer_email),
+            timestamp: summary.timestamp,
+            is_signed: summary.is_signed,
+            is_pushed: summary.is_pushed,
+            nearest_ref: summary.nearest_ref.map(SharedString::from),
+            is_current,
+            graph_edges: summary.graph_edges,
+            is_divergent: summary.is_divergent,
+            is_hidden: summary.is_hidden,
+            is_empty: summary.is_empty,
+            has_conflict: summary.has_conflict,
+            refs: summary.refs,
+        }
+    }
+
+    fn to_proto(&self) -> proto::JjCommit {
+        proto::JjCommit {
+            commit_id: self.commit_id.hex(),
+            parent_ids: self.parent_ids.iter().map(CommitId::hex).collect(),
+            change_id: self.change_id.hex(),
+            description: self.description.to_string(),
+            author: self.author.to_string(),
+            author_email: self.author_email.to_string(),
+            author_timestamp: self.author_timestamp,
+            committer: self.committer.to_string(),
+            committer_email: self.committer_email.to_string(),
+            timestamp: self.timestamp,
+            is_signed: self.is_signed,
+            is_pushed: self.is_pushed,
+            nearest_ref: self.nearest_ref.as_ref().map(ToString::to_string),
+            is_current: self.is_current,
+            graph_edges: self
+                .graph_edges
+                .iter()
+                .map(|edge| proto::JjGraphEdge {
+                    target: edge.target.hex(),
+                    kind: match edge.kind {
+                        GraphEdgeKind::Direct => proto::jj_graph_edge::Kind::Direct,
+                        GraphEdgeKind::Indirect => proto::jj_graph_edge::Kind::Indirect,
+                        GraphEdgeKind::Missing => proto::jj_graph_edge::Kind::Missing,
+                    } as i32,
+                })
+                .collect(),
+            is_divergent: self.is_divergent,
+            is_hidden: self.is_hidden,
+            is_empty: self.is_empty,
+            has_conflict: self.has_conflict,
+            refs: self
+                .refs
+                .iter()
+                .map(|commit_ref| proto::JjCommitRef {
+                    name: commit_ref.name.clone(),
+                    remote: commit_ref.remote.clone(),
+                    is_tag: commit_ref.is_tag,
+                    is_conflicted: commit_ref.is_conflicted,
+                })
+                .collect(),
+        }
+    }
+
+    fn from_proto(commit: proto::JjCommit) -> Result<Self> {
+        Ok(Self {
+            commit_id: commit_id_from_proto(&commit.commit_id)?,
+            parent_ids: commit
+                .parent_ids
+                .iter()
+                .map(|parent_id| commit_id_from_proto(parent_id))
+                .collect::<Result<_>>()?,
+            change_id: change_id_from_proto(&commit.change_id)?,
+            description: commit.description.into(),
+            author: commit.author.into(),
+            author_email: commit.author_email.into(),
+            author_timestamp: commit.author_timestamp,
+            committer: commit.committer.into(),
+            committer_email: commit.committer_email.into(),
+            timestamp: commit.timestamp,
+            is_signed: commit.is_signed,
+            is_pushed: commit.is_pushed,
+            nearest_ref: commit.nearest_ref.map(SharedString::from),
+            is_current: commit.is_current,
+            graph_edges: commit
+                .graph_edges
+                .iter()
+                .map(|edge| {
+                    let kind = match edge.kind() {
+                        proto::jj_graph_edge::Kind::Direct => GraphEdgeKind::Direct,
+                        proto::jj_graph_edge::Kind::Indirect => GraphEdgeKind::Indirect,
+                        proto::jj_graph_edge::Kind::Missing => GraphEdgeKind::Missing,
+                    };
+                    Ok(GraphEdge {
+                        target: commit_id_from_proto(&edge.target)?,
+                        kind,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            is_divergent: commit.is_divergent,
+            is_hidden: commit.is_hidden,
+            is_empty: commit.is_empty,
+            has_conflict: commit.has_conflict,
+            refs: commit
+                .refs
+                .into_iter()
+                .map(|commit_ref| CommitRef {
+                    name: commit_ref.name,
+                    remote: commit_ref.remote,
+                    is_tag: commit_ref.is_tag,
+                    is_conflicted: commit_ref.is_conflicted,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// The change whose parent tree backs the diff of a buffer.
+#[cfg(feature = "jj-ui")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JjBufferChange {
+    pub repository_id: ProjectEntryId,
+    pub commit: JjCommitSummary,
 }
 
 #[cfg(feature = "jj-ui")]
@@ -37,44 +399,283 @@ pub struct JjRepositorySummary {
     pub id: ProjectEntryId,
     pub worktree_id: WorktreeId,
     pub path: SharedString,
+    /// The path relative to the worktree, or the directory name for a repository at or above
+    /// the worktree root.
+    pub relative_path: SharedString,
+    /// Whether the repository contains the whole project rather than a directory inside it.
+    pub covers_entire_project: bool,
 }
 
+/// The backends jj repositories are loaded with, for builds that link in custom ones.
+struct GlobalJjBackends(Arc<JjBackends>);
+
+impl Global for GlobalJjBackends {}
+
 impl JjStore {
+    /// Registers the backends repositories are loaded with from now on, in addition to jj's
+    /// built-in ones. Repositories that were already tracked keep the backends they were
+    /// loaded with.
+    pub fn set_backends(backends: JjBackends, cx: &mut App) {
+        cx.set_global(GlobalJjBackends(Arc::new(backends)));
+    }
+
     pub fn new(worktree_store: Entity<WorktreeStore>, cx: &mut Context<Self>) -> Self {
         let mut this = Self {
+            this: cx.weak_entity(),
             worktree_store: worktree_store.clone(),
             repositories_by_worktree: HashMap::new(),
             repositories_by_id: HashMap::new(),
             diffs_by_buffer: HashMap::new(),
+            last_seen_operations: HashMap::new(),
+            parked_changes: HashMap::new(),
+            conflicted_changes: HashMap::new(),
+            session_operations: Vec::new(),
+            pending_operations: 0,
+            fetch_progress: HashMap::new(),
+            loading_repositories: HashSet::new(),
+            poll_interval_ms: None,
+            read_only: ProjectSettings::get_global(cx).jj.read_only,
+            upstream: None,
+            remote_repositories: Vec::new(),
+            refresh_task: None,
+            _poll_task: None,
+            _conflict_check_task: None,
             _subscriptions: Vec::new(),
         };
 
         this.refresh_existing_worktrees(cx);
         this._subscriptions
             .push(cx.subscribe(&worktree_store, Self::on_worktree_store_event));
+        this._subscriptions
+            .push(cx.observe_global::<SettingsStore>(Self::restart_polling));
+        this._subscriptions
+            .push(cx.observe_global::<SettingsStore>(Self::update_read_only));
+        this.restart_polling(cx);
         this
     }
 
+    /// Creates the store of a guest in a shared project. It has no repositories of its own; the
+    /// log is read from the host, and operations can only be requested from it.
+    pub fn remote(
+        worktree_store: Entity<WorktreeStore>,
+        project_id: u64,
+        client: AnyProtoClient,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut this = Self::new(worktree_store, cx);
+        this.upstream = Some((client.clone(), project_id));
+        this.request_remote_log(client, project_id, None, 0, CommitDateRange::default(), cx)
+            .detach_and_log_err(cx);
+        this
+    }
+
+    pub fn init(client: &AnyProtoClient) {
+        client.add_entity_request_handler(Self::handle_get_log);
+        client.add_entity_request_handler(Self::handle_request_operation);
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.upstream.is_some()
+    }
+
+    /// Notifies observers once `.jj` has been quiet for the configured debounce period.
+    fn schedule_refresh(&mut self, cx: &mut Context<Self>) {
+        let debounce_ms = ProjectSettings::get_global(cx).jj.refresh_debounce_ms;
+        if debounce_ms == 0 {
+            self.refresh_task = None;
+            self.refresh(cx);
+            return;
+        }
+        let timer = cx
+            .background_executor()
+            .timer(Duration::from_millis(debounce_ms));
+        self.refresh_task = Some(cx.spawn(async move |store, cx| {
+            timer.await;
+            store
+                .update(cx, |store, cx| {
+                    store.refresh_task = None;
+                    store.refresh(cx);
+                })
+                .ok();
+        }));
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        cx.notify();
+        self.check_working_copy_conflicts(cx);
+    }
+
+    /// Emits [`JjStoreEvent::WorkingCopyConflicted`] when a repository's working-copy change
+    /// becomes conflicted, whichever process made it so.
+    fn check_working_copy_conflicts(&mut self, cx: &mut Context<Self>) {
+        let repositories = self
+            .repositories_by_id
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        let conflicts = cx.background_spawn(async move {
+            repositories
+                .into_iter()
+                .filter_map(|repository| {
+                    let workspace = repository.loaded_workspace()?;
+                    let conflict = match workspace.working_copy_conflicts() {
+                        Ok(conflict) => Ok(conflict),
+                        // A newer jj may upgrade the repository while it's open.
+                        Err(error) => match repository.disable_if_unsupported(&error) {
+                            Some(unsupported) => Err(unsupported),
+                            None => {
+                                warn!(
+                                    target: "project::jj_store",
+                                    "conflict check failed: {error:?}"
+                                );
+                                return None;
+                            }
+                        },
+                    };
+                    Some((repository.work_directory_id, conflict))
+                })
+                .collect::<Vec<_>>()
+        });
+        self._conflict_check_task = Some(cx.spawn(async move |store, cx| {
+            let conflicts = conflicts.await;
+            store
+                .update(cx, |store, cx| {
+                    for (repository_id, conflict) in conflicts {
+                        let conflict = match conflict {
+                            Ok(conflict) => conflict,
+                            Err(error) => {
+                                store.conflicted_changes.remove(&repository_id);
+                                store.report_unsupported_format(repository_id, error, cx);
+                                continue;
+                            }
+                        };
+                        let Some((change_id, paths)) = conflict else {
+                            store.conflicted_changes.remove(&repository_id);
+                            continue;
+                        };
+                        if store.conflicted_changes.get(&repository_id) == Some(&change_id) {
+                            continue;
+                        }
+                        store
+                            .conflicted_changes
+                            .insert(repository_id, change_id.clone());
+                        cx.emit(JjStoreEvent::WorkingCopyConflicted {
+                            repository_id,
+                            change_id,
+                            paths,
+                        });
+                    }
+                })
+                .ok();
+        }));
+    }
+
+    /// Whether the repositories are opened read-only, so the UI should offer no way to change
+    /// them.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn update_read_only(&mut self, cx: &mut Context<Self>) {
+        let read_only = ProjectSettings::get_global(cx).jj.read_only;
+        if read_only == self.read_only {
+            return;
+        }
+        self.read_only = read_only;
+        let mut reload = Vec::new();
+        for repository in self.repositories_by_id.values() {
+            if repository.loaded_workspace().is_some() {
+                reload.push(repository.work_directory_id);
+            }
+            repository.set_read_only(read_only);
+        }
+        for repository_id in reload {
+            self.load_repository(repository_id, cx);
+        }
+        cx.notify();
+    }
+
+    fn restart_polling(&mut self, cx: &mut Context<Self>) {
+        let poll_interval_ms = ProjectSettings::get_global(cx).jj.poll_interval_ms;
+        if poll_interval_ms == self.poll_interval_ms {
+            return;
+        }
+        self.poll_interval_ms = poll_interval_ms;
+        self._poll_task = poll_interval_ms.map(|interval_ms| {
+            let interval = Duration::from_millis(interval_ms.max(MIN_POLL_INTERVAL_MS));
+            cx.spawn(async move |store, cx| {
+                loop {
+                    cx.background_executor().timer(interval).await;
+                    let Ok(repositories) = store.read_with(cx, |store, _| {
+                        store
+                            .repositories_by_id
+                            .values()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    }) else {
+                        break;
+                    };
+                    let operations = cx
+                        .background_spawn(async move {
+                            repositories
+                                .iter()
+                                .filter_map(|repository| {
+                                    let operation_id = repository
+                                        .loaded_workspace()?
+                                        .head_operation_id()
+                                        .log_err()?;
+                                    Some((repository.work_directory_id, operation_id))
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .await;
+                    let Ok(()) = store.update(cx, |store, cx| {
+                        store.record_polled_operations(operations, cx)
+                    }) else {
+                        break;
+                    };
+                }
+            })
+        });
+    }
+
+    fn record_polled_operations(
+        &mut self,
+        operations: Vec<(ProjectEntryId, String)>,
+        cx: &mut Context<Self>,
+    ) {
+        let mut changed = false;
+        for (repository_id, operation_id) in operations {
+            let previous = self
+                .last_seen_operations
+                .insert(repository_id, operation_id.clone());
+            if previous.is_some_and(|previous| previous != operation_id) {
+                changed = true;
+            }
+        }
+        if changed {
+            debug!(target: "project::jj_store", "operation head changed while polling");
+            self.schedule_refresh(cx);
+        }
+    }
+
     pub fn open_unstaged_diff(
         &mut self,
         buffer: Entity<Buffer>,
         cx: &mut Context<Self>,
     ) -> Option<Task<Result<Entity<BufferDiff>>>> {
         let (repository, repo_path) = self.repository_and_path_for_buffer(&buffer, cx)?;
-        let workspace = match repository.workspace() {
-            Ok(workspace) => workspace,
-            Err(err) => {
-                warn!(
-                    target: "jj::diff",
-                    "failed to load jj workspace for {}: {err:?}",
-                    repository
-                        .work_directory_path()
-                        .display()
-                        .to_string()
-                );
-                return Some(Task::ready(Err(err)));
-            }
-        };
+        // Editors on the same buffer must share one diff: hunk expansion acts on the diff held by
+        // each multibuffer, and only the tracked diff is recalculated when the parent changes.
+        if let Some(diff) = self
+            .diffs_by_buffer
+            .get(&buffer.read(cx).remote_id())
+            .and_then(|state| state.diff.upgrade())
+        {
+            return Some(Task::ready(Ok(diff)));
+        }
+        // The first buffer opened in a repository loads it, off the main thread.
+        self.load_repository(repository.work_directory_id, cx);
         let repo_root = repository.work_directory_path();
         let repo_root_display = repo_root.display().to_string();
         let repo_path_string = repo_path.as_internal_file_string().to_owned();
@@ -102,6 +703,19 @@ impl JjStore {
         let store = cx.entity().downgrade();
         let repository_for_task = repository.clone();
         let task = cx.spawn(async move |_, cx| {
+            let workspace = cx
+                .background_spawn({
+                    let repository = repository_for_task.clone();
+                    async move { repository.workspace() }
+                })
+                .await
+                .inspect_err(|err| {
+                    warn!(
+                        target: "jj::diff",
+                        "failed to load jj workspace for {}: {err:?}",
+                        repo_root_display_for_task
+                    );
+                })?;
             debug!(
                 target: "jj::diff",
                 "materializing parent tree text: repo_root={} path={}",
@@ -133,6 +747,7 @@ impl JjStore {
                     return Err(err);
                 }
             };
+            let base_text_hash = hash_base_text(base_text.as_deref());
             let base_text = base_text.map(Arc::new);
             let rx = diff.update(cx, |diff, cx| {
                 diff.set_base_text(
@@ -155,6 +770,7 @@ impl JjStore {
                             diff.downgrade(),
                             repository_for_task.clone(),
                             repo_path_for_task.clone(),
+                            base_text_hash,
                         );
                     })
                     .ok();
@@ -194,11 +810,34 @@ impl JjStore {
         }
         let store = cx.entity().downgrade();
         Some(cx.spawn(async move |_, cx| {
-            for (buffer, buffer_id, state) in jobs {
-                if let Err(err) =
-                    Self::recalculate_diff_for_job(&store, buffer.clone(), buffer_id, state, cx)
+            let background = cx.background_executor().clone();
+            let mut materializations =
+                stream::iter(jobs.into_iter().map(|(buffer, buffer_id, state)| {
+                    let background = background.clone();
+                    async move {
+                        let base_text = Self::materialize_base_text(&state, &background).await;
+                        (buffer, buffer_id, state, base_text)
+                    }
+                }))
+                .buffer_unordered(MAX_CONCURRENT_MATERIALIZATIONS);
+
+            while let Some((buffer, buffer_id, state, base_text)) = materializations.next().await {
+                let result = match base_text {
+                    Ok((base_text, base_text_hash)) => {
+                        Self::apply_base_text(
+                            &store,
+                            buffer,
+                            buffer_id,
+                            state,
+                            base_text,
+                            base_text_hash,
+                            cx,
+                        )
                         .await
-                {
+                    }
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
                     warn!(
                         target: "jj::diff",
                         "failed to recalc diff for buffer {buffer_id:?}: {err:?}"
@@ -211,7 +850,7 @@ impl JjStore {
     fn repository_and_path_for_buffer(
         &self,
         buffer: &Entity<Buffer>,
-        cx: &Context<Self>,
+        cx: &App,
     ) -> Option<(Arc<JjRepositoryState>, RepoPathBuf)> {
         let (worktree_id, abs_path) = {
             let buffer = buffer.read(cx);
@@ -230,20 +869,20 @@ impl JjStore {
     }
 
     fn refresh_existing_worktrees(&mut self, cx: &mut Context<Self>) {
-        let store = self.worktree_store.read(cx);
-        for worktree in store.worktrees() {
+        let worktrees = self.worktree_store.read(cx).worktrees().collect::<Vec<_>>();
+        for worktree in worktrees {
             self.add_worktree_repositories(&worktree, cx);
         }
     }
 
-    fn add_worktree_repositories(&mut self, worktree: &Entity<Worktree>, cx: &Context<Self>) {
+    fn add_worktree_repositories(&mut self, worktree: &Entity<Worktree>, cx: &mut Context<Self>) {
         let (worktree_id, entries) = {
             let guard = worktree.read(cx);
             (guard.id(), guard.jj_repository_entries())
         };
         if let Some(entries) = entries {
             for entry in entries {
-                self.track_repository(worktree_id, entry);
+                self.track_repository(worktree_id, entry, cx);
             }
         }
     }
@@ -275,31 +914,226 @@ impl JjStore {
                                 .read(cx)
                                 .jj_repository_entry(change.work_directory_id)
                             {
-                                self.track_repository(*worktree_id, entry);
+                                self.track_repository(*worktree_id, entry, cx);
                             }
                         }
                     } else {
                         self.remove_repository(change.work_directory_id);
                     }
                 }
+                self.schedule_refresh(cx);
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                self.handle_ignore_file_changes(*worktree_id, changes, cx)
+            }
+            WorktreeStoreEvent::WorktreeUpdatedGitRepositories(worktree_id, changes) => {
+                self.handle_git_repository_changes(*worktree_id, changes, cx)
             }
             _ => {}
         }
     }
 
-    fn track_repository(&mut self, worktree_id: WorktreeId, entry: JjRepoEntryForWorktree) {
-        let state = Arc::new(JjRepositoryState::from_entry(worktree_id, entry));
-        self.repositories_by_id
+    /// Ignore files decide which new files snapshots track, so editing one changes what the
+    /// working-copy change should contain, but nothing in `.jj` changes until the next jj
+    /// command. Snapshotting right away records a new operation, which then refreshes the log
+    /// and statuses like any other. Edits to the repository's config, such as
+    /// `snapshot.auto-track`, already arrive as repository updates and reload the workspace.
+    fn handle_ignore_file_changes(
+        &mut self,
+        worktree_id: WorktreeId,
+        changes: &UpdatedEntriesSet,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repositories) = self.repositories_by_worktree.get(&worktree_id) else {
+            return;
+        };
+        let affected = repositories
+            .iter()
+            .filter(|repository| {
+                changes.iter().any(|(path, _, _)| {
+                    path.file_name() == Some(GITIGNORE)
+                        && (repository.covers_entire_project
+                            || path.starts_with(repository.work_directory_rel_path.as_ref()))
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if affected.is_empty() {
+            return;
+        }
+        cx.background_spawn(async move {
+            for repository in affected {
+                if let Some(workspace) = repository.loaded_workspace() {
+                    workspace.snapshot().log_err();
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Git tools working in a colocated repository move its refs behind jj's back. Importing
+    /// them records an operation when anything changed, which refreshes the log like any other.
+    /// Changes jj makes itself are exported as they're made, so importing them finds nothing.
+    fn handle_git_repository_changes(
+        &mut self,
+        worktree_id: WorktreeId,
+        changes: &UpdatedGitRepositoriesSet,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repositories) = self.repositories_by_worktree.get(&worktree_id) else {
+            return;
+        };
+        let affected = repositories
+            .iter()
+            .filter(|repository| {
+                changes.iter().any(|change| {
+                    change.new_work_directory_abs_path.as_ref()
+                        == Some(&repository.work_directory_abs_path)
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if affected.is_empty() {
+            return;
+        }
+        cx.background_spawn(async move {
+            for repository in affected {
+                let Some(workspace) = repository.loaded_workspace() else {
+                    continue;
+                };
+                if workspace.is_colocated() && !workspace.is_read_only() {
+                    workspace.import_git_refs().log_err();
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn track_repository(
+        &mut self,
+        worktree_id: WorktreeId,
+        entry: JjRepoEntryForWorktree,
+        cx: &mut Context<Self>,
+    ) {
+        let backends = cx
+            .try_global::<GlobalJjBackends>()
+            .map(|backends| backends.0.clone())
+            .unwrap_or_default();
+        let state = Arc::new(JjRepositoryState::from_entry(
+            worktree_id,
+            entry,
+            backends,
+            self.read_only,
+        ));
+        let previous = self
+            .repositories_by_id
             .insert(state.work_directory_id, state.clone());
+        let was_loaded = previous.is_some_and(|previous| previous.loaded_workspace().is_some());
         let repos = self
             .repositories_by_worktree
             .entry(worktree_id)
             .or_default();
+        repos.retain(|repo| repo.work_directory_id != state.work_directory_id);
+        let repository_id = state.work_directory_id;
         repos.push(state);
         repos.sort_by(|a, b| b.path_depth.cmp(&a.path_depth));
+        if was_loaded {
+            self.load_repository(repository_id, cx);
+        }
+    }
+
+    /// Loads a repository's workspace in the background, unless it's loaded or loading
+    /// already. Tracked repositories stay unloaded until the panel or a buffer inside them
+    /// needs them, so projects containing jj repositories open as fast as any other, and
+    /// polling and other background checks skip repositories that aren't loaded.
+    ///
+    /// Once loaded, emits [`JjStoreEvent::RecoveryNeeded`] if a crashed jj process left the
+    /// working copy locked or stale.
+    pub fn load_repository(&mut self, repository_id: ProjectEntryId, cx: &mut Context<Self>) {
+        let Some(repository) = self.repositories_by_id.get(&repository_id).cloned() else {
+            return;
+        };
+        if repository.loaded_workspace().is_some()
+            || repository.is_unsupported()
+            || !self.loading_repositories.insert(repository_id)
+        {
+            return;
+        }
+        // Recovering would write to the working copy, so read-only repositories aren't checked.
+        // Otherwise the working copy state is still read, to catch metadata jj-lib can't decode.
+        let check_for_problems = !self.read_only;
+        let problems = cx.background_spawn({
+            let repository = repository.clone();
+            async move {
+                let workspace = repository.workspace()?;
+                if check_for_problems {
+                    workspace.working_copy_problems()
+                } else {
+                    workspace.working_copy_state().map(|_| Vec::new())
+                }
+            }
+        });
+        cx.notify();
+        cx.spawn(async move |store, cx| {
+            let problems = problems.await;
+            store.update(cx, |store, cx| {
+                store.loading_repositories.remove(&repository_id);
+                store.schedule_refresh(cx);
+                let problems = match problems {
+                    Ok(problems) => problems,
+                    Err(error) => {
+                        let Some(unsupported) = repository.disable_if_unsupported(&error) else {
+                            return Err(error);
+                        };
+                        store.report_unsupported_format(repository_id, unsupported, cx);
+                        return Ok(());
+                    }
+                };
+                if !problems.is_empty() {
+                    warn!(
+                        target: "project::jj_store",
+                        "repo {:?} needs recovery: {problems:?}",
+                        repository_id
+                    );
+                    cx.emit(JjStoreEvent::RecoveryNeeded {
+                        repository_id,
+                        problems,
+                    });
+                }
+                anyhow::Ok(())
+            })?
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn report_unsupported_format(
+        &mut self,
+        repository_id: ProjectEntryId,
+        error: UnsupportedFormatError,
+        cx: &mut Context<Self>,
+    ) {
+        warn!(
+            target: "project::jj_store",
+            "disabling repo {:?}: {error}",
+            repository_id
+        );
+        cx.emit(JjStoreEvent::UnsupportedFormat {
+            repository_id,
+            error,
+        });
+        cx.notify();
+    }
+
+    /// Whether [`Self::load_repository`] is still loading the repository's workspace.
+    pub fn is_loading_repository(&self, repository_id: ProjectEntryId) -> bool {
+        self.loading_repositories.contains(&repository_id)
     }
 
     fn remove_repository(&mut self, work_directory_id: ProjectEntryId) {
+        self.last_seen_operations.remove(&work_directory_id);
+        self.loading_repositories.remove(&work_directory_id);
+        self.parked_changes.remove(&work_directory_id);
+        self.conflicted_changes.remove(&work_directory_id);
         if let Some(state) = self.repositories_by_id.remove(&work_directory_id) {
             if let Some(repos) = self.repositories_by_worktree.get_mut(&state.worktree_id) {
                 repos.retain(|repo| repo.work_directory_id != work_directory_id);
@@ -316,6 +1150,7 @@ impl JjStore {
         diff: WeakEntity<BufferDiff>,
         repository: Arc<JjRepositoryState>,
         repo_path: RepoPathBuf,
+        base_text_hash: u64,
     ) {
         self.diffs_by_buffer.insert(
             buffer_id,
@@ -323,15 +1158,40 @@ impl JjStore {
                 diff,
                 repository,
                 repo_path,
+                base_text_hash,
             },
         );
     }
 
-    async fn recalculate_diff_for_job(
+    async fn materialize_base_text(
+        state: &JjDiffState,
+        background: &BackgroundExecutor,
+    ) -> Result<(Option<String>, u64)> {
+        let workspace = state.repository.workspace()?;
+        let repo_path = state.repo_path.clone();
+        let repo_root = state.repository.work_directory_path();
+        debug!(
+            target: "jj::diff",
+            "recalculating diff base: repo_root={} path={}",
+            repo_root.display(),
+            repo_path.as_internal_file_string()
+        );
+        background
+            .spawn(async move {
+                let base_text = workspace.parent_tree_text(repo_path.as_ref()).await?;
+                let base_text_hash = hash_base_text(base_text.as_deref());
+                Ok((base_text, base_text_hash))
+            })
+            .await
+    }
+
+    async fn apply_base_text(
         store: &WeakEntity<Self>,
         buffer: Entity<Buffer>,
         buffer_id: BufferId,
         state: JjDiffState,
+        base_text: Option<String>,
+        base_text_hash: u64,
         cx: &mut AsyncApp,
     ) -> Result<()> {
         let Some(diff_entity) = state.diff.upgrade() else {
@@ -345,19 +1205,6 @@ impl JjStore {
             return Ok(());
         };
 
-        let workspace = state.repository.workspace()?;
-        let repo_path = state.repo_path.clone();
-        let repo_root = state.repository.work_directory_path();
-        let repo_root_display = repo_root.display().to_string();
-        let path_string = repo_path.as_internal_file_string().to_owned();
-        debug!(
-            target: "jj::diff",
-            "recalculating diff base: repo_root={} path={}",
-            repo_root_display,
-            path_string
-        );
-
-        let base_text = workspace.parent_tree_text(repo_path.as_ref()).await?;
         let base_text = base_text.map(Arc::new);
         let (language, language_registry, text_snapshot) = buffer.read_with(cx, |buffer, _| {
             (
@@ -367,6 +1214,29 @@ impl JjStore {
             )
         })?;
 
+        if base_text_hash == state.base_text_hash {
+            debug!(
+                target: "jj::diff",
+                "diff base unchanged, rediffing buffer only: path={}",
+                state.repo_path.as_internal_file_string()
+            );
+            let snapshot = BufferDiff::update_diff(
+                diff_entity.clone(),
+                text_snapshot.clone(),
+                base_text,
+                false,
+                false,
+                language,
+                language_registry,
+                cx,
+            )
+            .await?;
+            diff_entity.update(cx, |diff, cx| {
+                diff.set_snapshot(snapshot, &text_snapshot, cx);
+            })?;
+            return Ok(());
+        }
+
         let rx = diff_entity.update(cx, |diff, cx| {
             diff.set_base_text(
                 base_text.clone(),
@@ -377,6 +1247,15 @@ impl JjStore {
             )
         })?;
         rx.await?;
+        if let Some(store) = store.upgrade() {
+            store
+                .update(cx, |store, _| {
+                    if let Some(state) = store.diffs_by_buffer.get_mut(&buffer_id) {
+                        state.base_text_hash = base_text_hash;
+                    }
+                })
+                .ok();
+        }
         Ok(())
     }
 
@@ -393,25 +1272,67 @@ impl JjStore {
         !self.repositories_by_id.is_empty()
     }
 
+    /// Loads the working-copy change of every loaded repository, keyed by the repository root.
+    /// Repositories nothing opened during the session have nothing new to record.
+    #[cfg(feature = "jj-ui")]
+    pub fn working_copy_summaries(&self, cx: &App) -> Task<Vec<(Arc<Path>, JjCommitSummary)>> {
+        let repositories = self
+            .repositories_by_id
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        cx.background_spawn(async move {
+            repositories
+                .into_iter()
+                .filter_map(|repository| {
+                    let commit = repository
+                        .loaded_workspace()?
+                        .working_copy_commit()
+                        .log_err()??;
+                    Some((
+                        repository.work_directory_abs_path.clone(),
+                        JjCommitSummary::new(commit, true),
+                    ))
+                })
+                .collect()
+        })
+    }
+
     #[cfg(feature = "jj-ui")]
     pub fn repositories(&self) -> Vec<JjRepositorySummary> {
+        if self.is_remote() {
+            return self.remote_repositories.clone();
+        }
         self.repositories_by_id
             .values()
             .map(|repo| JjRepositorySummary {
                 id: repo.work_directory_id,
                 worktree_id: repo.worktree_id,
                 path: SharedString::from(repo.display_name()),
+                relative_path: SharedString::from(repo.relative_display_name()),
+                covers_entire_project: repo.covers_entire_project,
             })
             .collect()
     }
 
     #[cfg(feature = "jj-ui")]
     pub fn recent_commits(
-        &mut self,
+        &self,
         repository_id: Option<ProjectEntryId>,
         limit: usize,
-        cx: &mut Context<Self>,
+        date_range: CommitDateRange,
+        cx: &App,
     ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        if let Some((client, project_id)) = self.upstream.clone() {
+            return Some(self.request_remote_log(
+                client,
+                project_id,
+                repository_id,
+                limit,
+                date_range,
+                cx,
+            ));
+        }
         let repo = match repository_id {
             Some(id) => self.repositories_by_id.get(&id)?.clone(),
             None => self.repositories_by_id.values().next()?.clone(),
@@ -419,21 +1340,14 @@ impl JjStore {
         let task = cx.background_spawn(async move {
             let workspace = repo.workspace()?;
             let current_change = workspace.current_change_id()?;
-            let commits = workspace.recent_commits(limit)?;
+            let commits = workspace.recent_commits_in_date_range(limit, date_range)?;
             let summaries = commits
                 .into_iter()
                 .map(|summary| {
                     let is_current = current_change
                         .as_ref()
                         .is_some_and(|id| id == &summary.change_id);
-                    JjCommitSummary {
-                        commit_id: summary.commit_id,
-                        change_id: summary.change_id,
-                        description: SharedString::from(summary.description),
-                        author: SharedString::from(summary.author),
-                        timestamp: summary.timestamp,
-                        is_current,
-                    }
+                    JjCommitSummary::new(summary, is_current)
                 })
                 .collect();
             Ok(summaries)
@@ -441,24 +1355,1393 @@ impl JjStore {
         Some(task)
     }
 
+    /// Lists up to `limit` commits that modified the buffer's file, newest first.
     #[cfg(feature = "jj-ui")]
-    pub fn edit_change(
-        &mut self,
+    pub fn file_history(
+        &self,
+        buffer: &Entity<Buffer>,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        let (repository, path) = self.repository_and_path_for_buffer(buffer, cx)?;
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let current_change = workspace.current_change_id()?;
+            let commits = workspace.file_history(&path, limit)?;
+            Ok(commits
+                .into_iter()
+                .map(|summary| {
+                    let is_current = current_change
+                        .as_ref()
+                        .is_some_and(|id| id == &summary.change_id);
+                    JjCommitSummary::new(summary, is_current)
+                })
+                .collect())
+        }))
+    }
+
+    /// Lists up to `limit` commits matching `revset`, for filtering the log with it. Only
+    /// available on the host, like the other `jj-ui` queries.
+    #[cfg(feature = "jj-ui")]
+    pub fn revset_commits(
+        &self,
         repository_id: ProjectEntryId,
-        change_id: ChangeId,
-        cx: &mut Context<Self>,
-    ) -> Option<Task<Result<()>>> {
+        revset: String,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
         let repository = self.repositories_by_id.get(&repository_id)?.clone();
-        Some(cx.spawn(async move |_, _| {
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let current_change = workspace.current_change_id()?;
+            let commits = workspace.evaluate_revset(&revset, limit)?;
+            Ok(commits
+                .into_iter()
+                .map(|summary| {
+                    let is_current = current_change
+                        .as_ref()
+                        .is_some_and(|id| id == &summary.change_id);
+                    JjCommitSummary::new(summary, is_current)
+                })
+                .collect())
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn rewritten_commits(
+        &self,
+        repository_id: ProjectEntryId,
+        commits: Vec<(CommitId, ChangeId)>,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<CommitId>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(
+            cx.background_spawn(async move { repository.workspace()?.rewritten_commits(&commits) }),
+        )
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn commit_for_change(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<Option<JjCommitSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let current_change = workspace.current_change_id()?;
+            let is_current = current_change.as_ref() == Some(&change_id);
+            Ok(workspace
+                .commit_for_change(&change_id)?
+                .map(|summary| JjCommitSummary::new(summary, is_current)))
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn change_diff(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<String>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(
+            cx.background_spawn(async move { repository.workspace()?.diff_text(&change_id).await }),
+        )
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn diff_against_parent(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        parent_id: CommitId,
+        cx: &App,
+    ) -> Option<Task<Result<String>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .diff_against_parent(&change_id, &parent_id)
+                .await
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn repository_root(&self, repository_id: ProjectEntryId) -> Option<Arc<Path>> {
+        self.repositories_by_id
+            .get(&repository_id)
+            .map(|repository| repository.work_directory_abs_path.clone())
+    }
+
+    /// The repository whose working copy is `work_directory`, such as the jj side of a
+    /// colocated git repository.
+    #[cfg(feature = "jj-ui")]
+    pub fn repository_for_work_directory(&self, work_directory: &Path) -> Option<ProjectEntryId> {
+        self.repositories_by_id
+            .values()
+            .find(|repository| &*repository.work_directory_abs_path == work_directory)
+            .map(|repository| repository.work_directory_id)
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn export_patch(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        destination: PathBuf,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let patch = repository.workspace()?.export_patch(&change_id).await?;
+            std::fs::write(&destination, patch)?;
+            info!(
+                target: "project::jj_store",
+                "exported change {} to {}",
+                short_change_hash(&change_id),
+                destination.display()
+            );
+            Ok(())
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn import_patch(
+        &mut self,
+        repository_id: ProjectEntryId,
+        source: PathBuf,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let patch = std::fs::read_to_string(&source)?;
+            let change_id = repository.workspace()?.import_patch(&patch).await?;
+            info!(
+                target: "project::jj_store",
+                "applied {} as change {} in repo {:?}",
+                source.display(),
+                short_change_hash(&change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn parked_change(&self, repository_id: ProjectEntryId) -> Option<&ChangeId> {
+        self.parked_changes.get(&repository_id)
+    }
+
+    /// Starts a new empty change on top of `parent_change_id`, or on top of the working-copy
+    /// change when it's `None`.
+    #[cfg(feature = "jj-ui")]
+    pub fn new_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        parent_change_id: Option<ChangeId>,
+        description: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let parent_change_id = match parent_change_id {
+                Some(change_id) => change_id,
+                None => workspace
+                    .current_change_id()?
+                    .ok_or_else(|| anyhow!("the workspace has no working-copy change"))?,
+            };
+            let change_id = workspace.new_change(&parent_change_id, &description)?;
+            info!(
+                target: "project::jj_store",
+                "created change {} on {} in repo {:?}",
+                short_change_hash(&change_id),
+                short_change_hash(&parent_change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Starts a new empty merge change on top of all of `parent_change_ids`.
+    #[cfg(feature = "jj-ui")]
+    pub fn new_merge_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        parent_change_ids: Vec<ChangeId>,
+        description: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let change_id = workspace.new_change_with_parents(&parent_change_ids, &description)?;
+            info!(
+                target: "project::jj_store",
+                "created merge change {} of {} parents in repo {:?}",
+                short_change_hash(&change_id),
+                parent_change_ids.len(),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn park_working_copy(
+        &mut self,
+        repository_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.spawn(async move |store, cx| {
+            let change_id = repository.workspace()?.park_working_copy()?;
+            info!(
+                target: "project::jj_store",
+                "parked change {} in repo {:?}",
+                short_change_hash(&change_id),
+                repository_id
+            );
+            store.update(cx, |store, cx| {
+                store.parked_changes.insert(repository_id, change_id);
+                cx.notify();
+            })
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn unpark(
+        &mut self,
+        repository_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let change_id = self.parked_changes.get(&repository_id)?.clone();
+        let task = cx.spawn(async move |store, cx| {
+            repository.workspace()?.unpark(&change_id)?;
+            info!(
+                target: "project::jj_store",
+                "unparked change {} in repo {:?}",
+                short_change_hash(&change_id),
+                repository_id
+            );
+            store.update(cx, |store, cx| {
+                store.parked_changes.remove(&repository_id);
+                cx.notify();
+            })
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Counts `task` as in flight until it finishes, so that closing the project can wait for
+    /// it rather than leave the working copy between a snapshot and its checkout.
+    #[cfg(feature = "jj-ui")]
+    fn track_operation<T: 'static>(
+        &mut self,
+        task: Task<Result<T>>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<T>> {
+        self.pending_operations += 1;
+        cx.spawn(async move |store, cx| {
+            let result = task.await;
+            store
+                .update(cx, |store, _| {
+                    store.pending_operations = store.pending_operations.saturating_sub(1);
+                })
+                .ok();
+            result
+        })
+    }
+
+    /// Waits up to `timeout` for in-flight operations that mutate a repository, resolving to
+    /// whether they all finished.
+    pub fn wait_for_pending_operations(
+        store: &Entity<Self>,
+        timeout: Duration,
+        cx: &App,
+    ) -> Task<bool> {
+        let store = store.downgrade();
+        let executor = cx.background_executor().clone();
+        cx.spawn(async move |cx| {
+            let deadline = Instant::now() + timeout;
+            loop {
+                let has_pending = store
+                    .read_with(cx, |store, _| store.pending_operations > 0)
+                    .unwrap_or(false);
+                if !has_pending {
+                    return true;
+                }
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                executor
+                    .timer(Duration::from_millis(PENDING_OPERATION_POLL_INTERVAL_MS))
+                    .await;
+            }
+        })
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn session_operations(&self) -> &[JjSessionOperation] {
+        &self.session_operations
+    }
+
+    /// Adds the repository's current operation to the session history under `description`.
+    /// Called once an operation started from the UI has finished.
+    #[cfg(feature = "jj-ui")]
+    pub fn record_operation(
+        &mut self,
+        repository_id: ProjectEntryId,
+        description: impl Into<String>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(repository) = self.repositories_by_id.get(&repository_id).cloned() else {
+            return;
+        };
+        let description = description.into();
+        cx.spawn(async move |store, cx| {
+            let operation = repository.workspace()?.head_operation()?;
+            store.update(cx, |store, cx| {
+                store.session_operations.push(JjSessionOperation {
+                    repository_id,
+                    operation_id: operation.id,
+                    description,
+                    username: operation.username,
+                    hostname: operation.hostname,
+                    timestamp: operation.timestamp,
+                });
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn recover_working_copy(
+        &mut self,
+        repository_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.recover_working_copy()?;
+            info!(
+                target: "project::jj_store",
+                "recovered working copy in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn undo(
+        &mut self,
+        repository_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.undo()?;
+            info!(
+                target: "project::jj_store",
+                "undid last operation in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Restores the repository to its state before `operation_id`, dropping that operation and
+    /// the later ones in the same repository from the session history.
+    #[cfg(feature = "jj-ui")]
+    pub fn undo_from_operation(
+        &mut self,
+        repository_id: ProjectEntryId,
+        operation_id: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.spawn(async move |store, cx| {
+            repository
+                .workspace()?
+                .restore_before_operation(&operation_id)?;
+            info!(
+                target: "project::jj_store",
+                "undid operations from {operation_id} in repo {:?}",
+                repository_id
+            );
+            store.update(cx, |store, cx| {
+                let undone_index = store.session_operations.iter().position(|operation| {
+                    operation.repository_id == repository_id
+                        && operation.operation_id == operation_id
+                });
+                if let Some(undone_index) = undone_index {
+                    let mut index = 0;
+                    store.session_operations.retain(|operation| {
+                        let keep = index < undone_index || operation.repository_id != repository_id;
+                        index += 1;
+                        keep
+                    });
+                }
+                cx.notify();
+            })
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Profiles the repository's common operations and renders a Markdown report.
+    #[cfg(feature = "jj-ui")]
+    pub fn profile_repository(
+        &self,
+        repository_id: ProjectEntryId,
+        log_limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<String>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let profile = repository.workspace()?.profile(log_limit).await?;
+            info!(target: "project::jj_store", "profiled repo: {profile:?}");
+            Ok(profile.report())
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn restack_candidates(
+        &self,
+        repository_id: ProjectEntryId,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let current_change = workspace.current_change_id()?;
+            Ok(workspace
+                .restack_candidates(limit)?
+                .into_iter()
+                .map(|summary| {
+                    let is_current = current_change.as_ref() == Some(&summary.change_id);
+                    JjCommitSummary::new(summary, is_current)
+                })
+                .collect())
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn restack(
+        &mut self,
+        repository_id: ProjectEntryId,
+        limit: usize,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let rebased = repository.workspace()?.restack(limit)?;
+            info!(
+                target: "project::jj_store",
+                "restacked {rebased} commits in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Renders each of `change_ids` with the user's `templates.log`, for the panel to show in
+    /// place of its own commit details. Empty when the user hasn't customized the template, or
+    /// customized it beyond what [`JjWorkspace::render_commit`] supports.
+    #[cfg(feature = "jj-ui")]
+    pub fn rendered_commits(
+        &self,
+        repository_id: ProjectEntryId,
+        change_ids: Vec<ChangeId>,
+        cx: &App,
+    ) -> Option<Task<Result<HashMap<ChangeId, String>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let Some(template) = workspace.log_template()? else {
+                return Ok(HashMap::new());
+            };
+            let mut rendered = HashMap::new();
+            for change_id in change_ids {
+                match workspace.render_commit(&change_id, &template) {
+                    Ok(text) => {
+                        rendered.insert(change_id, text);
+                    }
+                    Err(err) => {
+                        warn!(
+                            target: "project::jj_store",
+                            "can't render templates.log, showing the default log: {err:?}"
+                        );
+                        return Ok(HashMap::new());
+                    }
+                }
+            }
+            Ok(rendered)
+        }))
+    }
+
+    /// Lists the changes whose diffs trunk already contains, for the panel to preview before
+    /// [`Self::abandon_changes`] cleans them up.
+    #[cfg(feature = "jj-ui")]
+    pub fn landed_changes(
+        &self,
+        repository_id: ProjectEntryId,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let current_change = workspace.current_change_id()?;
+            Ok(workspace
+                .landed_changes(limit)?
+                .into_iter()
+                .map(|summary| {
+                    let is_current = current_change.as_ref() == Some(&summary.change_id);
+                    JjCommitSummary::new(summary, is_current)
+                })
+                .collect())
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn abandon_changes(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_ids: Vec<ChangeId>,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let abandoned = repository.workspace()?.abandon_changes(&change_ids)?;
+            info!(
+                target: "project::jj_store",
+                "abandoned {abandoned} changes in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Derives the buffer's status from its diff against the parent tree, so it includes unsaved
+    /// edits and is only known once that diff has been opened.
+    #[cfg(feature = "jj-ui")]
+    pub fn status_for_buffer_id(&self, buffer_id: BufferId, cx: &App) -> Option<FileStatus> {
+        let diff = self.diffs_by_buffer.get(&buffer_id)?.diff.upgrade()?;
+        let diff = diff.read(cx);
+        let status = if !diff.base_text_exists() {
+            FileStatus::worktree(StatusCode::Added)
+        } else if diff.snapshot(cx).is_empty() {
+            FileStatus::worktree(StatusCode::Unmodified)
+        } else {
+            FileStatus::worktree(StatusCode::Modified)
+        };
+        Some(status)
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn is_buffer_tracked(&self, buffer: &Entity<Buffer>, cx: &App) -> bool {
+        self.repository_and_path_for_buffer(buffer, cx).is_some()
+    }
+
+    /// Discards the working-copy change's modifications to the buffer's file, restoring it from
+    /// the parent revision on disk. The buffer itself still has to be reloaded.
+    #[cfg(feature = "jj-ui")]
+    pub fn restore_file(
+        &mut self,
+        buffer: &Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let (repository, repo_path) = self.repository_and_path_for_buffer(buffer, cx)?;
+        self.restore_paths(repository.work_directory_id, vec![repo_path], None, cx)
+    }
+
+    /// Sets `paths` in the working copy to how they are in `source_change_id`, or in the
+    /// working-copy change's parent without a source.
+    #[cfg(feature = "jj-ui")]
+    pub fn restore_paths(
+        &mut self,
+        repository_id: ProjectEntryId,
+        paths: Vec<RepoPathBuf>,
+        source_change_id: Option<ChangeId>,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .restore_paths(&paths, source_change_id.as_ref())?;
+            info!(
+                target: "project::jj_store",
+                "restored {} paths from {} in repo {:?}",
+                paths.len(),
+                source_change_id
+                    .as_ref()
+                    .map_or_else(|| "the parent".to_string(), short_change_hash),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Resolves a change id the user typed, accepting any unique prefix.
+    #[cfg(feature = "jj-ui")]
+    pub fn resolve_change_id(
+        &self,
+        repository_id: ProjectEntryId,
+        text: String,
+        cx: &App,
+    ) -> Option<Task<Result<ChangeId>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(
+            async move { repository.workspace()?.resolve_change_id_prefix(&text) },
+        ))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn repository_id_for_buffer(
+        &self,
+        buffer: &Entity<Buffer>,
+        cx: &App,
+    ) -> Option<ProjectEntryId> {
+        let (repository, _) = self.repository_and_path_for_buffer(buffer, cx)?;
+        Some(repository.work_directory_id)
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn change_for_buffer(
+        &self,
+        buffer: &Entity<Buffer>,
+        cx: &App,
+    ) -> Option<Task<Result<Option<JjBufferChange>>>> {
+        let (repository, _) = self.repository_and_path_for_buffer(buffer, cx)?;
+        Some(cx.background_spawn(async move {
+            let commit = repository.workspace()?.working_copy_commit()?;
+            Ok(commit.map(|commit| JjBufferChange {
+                repository_id: repository.work_directory_id,
+                commit: JjCommitSummary::new(commit, true),
+            }))
+        }))
+    }
+
+    /// Lists the files `change_id` modifies relative to its parents.
+    #[cfg(feature = "jj-ui")]
+    pub fn changed_paths(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<RepoPathBuf>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(
+            async move { repository.workspace()?.changed_paths(&change_id).await },
+        ))
+    }
+
+    /// Counts the files and lines `change_id` changes relative to its parents.
+    #[cfg(feature = "jj-ui")]
+    pub fn diff_stats(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<DiffStats>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(
+            cx.background_spawn(
+                async move { repository.workspace()?.diff_stats(&change_id).await },
+            ),
+        )
+    }
+
+    /// Finds the change that last touched each line of the buffer's file as saved, for blame.
+    #[cfg(feature = "jj-ui")]
+    pub fn annotate_buffer(
+        &self,
+        buffer: &Entity<Buffer>,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<AnnotatedLine>>>> {
+        let (repository, path) = self.repository_and_path_for_buffer(buffer, cx)?;
+        Some(cx.background_spawn(async move { repository.workspace()?.annotate(&path) }))
+    }
+
+    /// Lists the repository's local and remote bookmarks with the commits they point at.
+    #[cfg(feature = "jj-ui")]
+    pub fn bookmarks(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<BookmarkSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.bookmarks() }))
+    }
+
+    /// Loads the log and bookmarks as they were right after `operation_id`, without changing
+    /// the repository.
+    #[cfg(feature = "jj-ui")]
+    pub fn at_operation(
+        &self,
+        repository_id: ProjectEntryId,
+        operation_id: String,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<(Vec<JjCommitSummary>, Vec<BookmarkSummary>)>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let past = repository.workspace()?.at_operation(&operation_id, limit)?;
+            let commits = past
+                .commits
+                .into_iter()
+                .map(|summary| {
+                    let is_current = past.current_change_id.as_ref() == Some(&summary.change_id);
+                    JjCommitSummary::new(summary, is_current)
+                })
+                .collect();
+            Ok((commits, past.bookmarks))
+        }))
+    }
+
+    /// Reports `fields` of up to `limit` commits matching `revset`, as plain serializable records
+    /// for the agent and scripts rather than the panel's display types. No `fields` means all
+    /// of them.
+    #[cfg(feature = "jj-ui")]
+    pub fn query_json(
+        &self,
+        repository_id: ProjectEntryId,
+        revset: String,
+        fields: Vec<CommitField>,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<CommitRecord>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let fields = if fields.is_empty() {
+                CommitField::ALL.to_vec()
+            } else {
+                fields
+            };
+            repository
+                .workspace()?
+                .query_commits(&revset, &fields, limit)
+        }))
+    }
+
+    /// Lists the files the working-copy change modifies, like `jj status`.
+    #[cfg(feature = "jj-ui")]
+    pub fn working_copy_status(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<RepoPathBuf>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            let Some(change_id) = workspace.current_change_id()? else {
+                return Ok(Vec::new());
+            };
+            workspace.changed_paths(&change_id).await
+        }))
+    }
+
+    /// Lists the files on disk that the working-copy change doesn't track although no ignore
+    /// file hides them.
+    #[cfg(feature = "jj-ui")]
+    pub fn untracked_paths(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<RepoPathBuf>>>> {
+        // Finding them takes a snapshot, which read-only repositories never do.
+        if self.read_only {
+            return None;
+        }
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.untracked_paths() }))
+    }
+
+    /// Starts tracking `paths` in the working-copy change, like `jj file track`.
+    #[cfg(feature = "jj-ui")]
+    pub fn track_paths(
+        &mut self,
+        repository_id: ProjectEntryId,
+        paths: Vec<RepoPathBuf>,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.track_paths(&paths)?;
+            info!(
+                target: "project::jj_store",
+                "tracked {} paths in repo {:?}",
+                paths.len(),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Records `resolved_contents` as the resolution of the conflict at `path` in the
+    /// working-copy change.
+    #[cfg(feature = "jj-ui")]
+    pub fn resolve_conflict(
+        &mut self,
+        repository_id: ProjectEntryId,
+        path: RepoPathBuf,
+        resolved_contents: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .resolve_conflict(&path, &resolved_contents)
+                .await?;
+            info!(
+                target: "project::jj_store",
+                "resolved conflict in {} in repo {:?}",
+                path.as_internal_file_string(),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Resolves the divergent change `change_id` by abandoning its visible commits other than
+    /// `commit_id`.
+    pub fn keep_divergent_commit(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        commit_id: CommitId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .keep_divergent_commit(&change_id, &commit_id)?;
+            info!(
+                target: "project::jj_store",
+                "kept commit {} of divergent change {} in repo {:?}",
+                short_commit_hash(&commit_id),
+                short_change_hash(&change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Compares the operation the working copy was last updated at with the operation head.
+    #[cfg(feature = "jj-ui")]
+    pub fn working_copy_state(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<WorkingCopyState>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.working_copy_state() }))
+    }
+
+    /// Lists the paths the working-copy change adds, modifies, removes or leaves conflicted,
+    /// after snapshotting the files on disk.
+    #[cfg(feature = "jj-ui")]
+    pub fn status(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<FileStatus>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.status().await }))
+    }
+
+    /// Lists up to `limit` versions of `change_id`, newest first, as it was rewritten.
+    #[cfg(feature = "jj-ui")]
+    pub fn evolution_log(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let versions = repository.workspace()?.evolution_log(&change_id, limit)?;
+            // Every version shares the change id, so none is marked as the working copy.
+            Ok(versions
+                .into_iter()
+                .map(|version| JjCommitSummary::new(version, false))
+                .collect())
+        }))
+    }
+
+    /// Lists up to `limit` abandoned commits that can be brought back, most recently hidden
+    /// first.
+    #[cfg(feature = "jj-ui")]
+    pub fn hidden_heads(
+        &self,
+        repository_id: ProjectEntryId,
+        limit: usize,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<JjCommitSummary>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            Ok(repository
+                .workspace()?
+                .hidden_heads(limit)?
+                .into_iter()
+                .map(|commit| JjCommitSummary::new(commit, false))
+                .collect())
+        }))
+    }
+
+    pub fn restore_hidden_commit(
+        &mut self,
+        repository_id: ProjectEntryId,
+        commit_id: CommitId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.restore_hidden_commit(&commit_id)?;
+            info!(
+                target: "project::jj_store",
+                "restored hidden commit {} in repo {:?}",
+                short_commit_hash(&commit_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Reads the left side, base and right side of the conflict in the buffer's file, for
+    /// showing them next to each other rather than as markers in the file.
+    #[cfg(feature = "jj-ui")]
+    pub fn conflict_sides(
+        &self,
+        buffer: &Entity<Buffer>,
+        cx: &App,
+    ) -> Option<Task<Result<ConflictSides>>> {
+        let (repository, repo_path) = self.repository_and_path_for_buffer(buffer, cx)?;
+        Some(cx.background_spawn(async move {
+            repository.workspace()?.conflict_sides(&repo_path).await
+        }))
+    }
+
+    /// Lists the paths with unresolved conflicts in `change_id`.
+    #[cfg(feature = "jj-ui")]
+    pub fn conflicted_paths(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<RepoPathBuf>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(
+            cx.background_spawn(
+                async move { repository.workspace()?.conflicted_paths(&change_id) },
+            ),
+        )
+    }
+
+    /// Returns the working-copy change and its conflicted paths, or `None` if it has no
+    /// conflicts.
+    #[cfg(feature = "jj-ui")]
+    pub fn working_copy_conflicts(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Option<(ChangeId, Vec<RepoPathBuf>)>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.working_copy_conflicts() }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn suggested_description(
+        &self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &App,
+    ) -> Option<Task<Result<Option<String>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move {
+            let paths = repository.workspace()?.changed_paths(&change_id).await?;
+            Ok(jj::describe_changed_paths(
+                paths.iter().map(|path| path.as_internal_file_string()),
+            ))
+        }))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn edit_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.spawn(async move |_, _| {
             repository.workspace()?.edit_change(&change_id)?;
             info!(
                 target: "project::jj_store",
-                "switched workspace {:?} to change {}",
+                "switched workspace {:?} to change {}",
+                repository_id,
+                short_change_hash(&change_id)
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn abandon_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.abandon_change(&change_id)?;
+            info!(
+                target: "project::jj_store",
+                "abandoned change {} in repo {:?}",
+                short_change_hash(&change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Moves `change_id` onto `destination_change_id`, with `mode` picking which commits move
+    /// along with it.
+    #[cfg(feature = "jj-ui")]
+    pub fn rebase_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        destination_change_id: ChangeId,
+        mode: RebaseMode,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .rebase(&change_id, &destination_change_id, mode)?;
+            info!(
+                target: "project::jj_store",
+                "rebased change {} onto {} ({mode:?}) in repo {:?}",
+                short_change_hash(&change_id),
+                short_change_hash(&destination_change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn duplicate_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let duplicate = repository.workspace()?.duplicate_change(&change_id)?;
+            info!(
+                target: "project::jj_store",
+                "duplicated change {} as {} in repo {:?}",
+                short_change_hash(&change_id),
+                short_change_hash(&duplicate),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn set_bookmark(
+        &mut self,
+        repository_id: ProjectEntryId,
+        name: String,
+        change_id: ChangeId,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.set_bookmark(&name, &change_id)?;
+            info!(
+                target: "project::jj_store",
+                "set bookmark {name} to change {} in repo {:?}",
+                short_change_hash(&change_id),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn delete_bookmark(
+        &mut self,
+        repository_id: ProjectEntryId,
+        name: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.delete_bookmark(&name)?;
+            info!(
+                target: "project::jj_store",
+                "deleted bookmark {name} in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn forget_bookmark(
+        &mut self,
+        repository_id: ProjectEntryId,
+        name: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.forget_bookmark(&name)?;
+            info!(
+                target: "project::jj_store",
+                "forgot bookmark {name} in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Reads git's HEAD if the repository is colocated with git.
+    #[cfg(feature = "jj-ui")]
+    pub fn git_head(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Option<GitHead>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.git_head() }))
+    }
+
+    /// Lists the remotes of the repository's backing git repo.
+    #[cfg(feature = "jj-ui")]
+    pub fn git_remotes(
+        &self,
+        repository_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<Task<Result<Vec<GitRemote>>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        Some(cx.background_spawn(async move { repository.workspace()?.git_remotes() }))
+    }
+
+    /// Adds the git remote `name` fetching from and pushing to `url`.
+    #[cfg(feature = "jj-ui")]
+    pub fn add_git_remote(
+        &mut self,
+        repository_id: ProjectEntryId,
+        name: String,
+        url: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository.workspace()?.add_git_remote(&name, &url)?;
+            info!(
+                target: "project::jj_store",
+                "added git remote {name} ({url}) in repo {:?}",
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Fetches from the git remote `remote` in the background. Until it finishes, its progress
+    /// is available from [`Self::fetch_progress`]. Observers aren't notified as it advances,
+    /// since they reload the log when notified.
+    #[cfg(feature = "jj-ui")]
+    pub fn git_fetch(
+        &mut self,
+        repository_id: ProjectEntryId,
+        remote: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<FetchSummary>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let (progress_tx, mut progress_rx) = mpsc::unbounded();
+        let fetch = cx.background_spawn(async move {
+            let summary = repository
+                .workspace()?
+                .git_fetch(&remote, &mut |progress| {
+                    progress_tx.unbounded_send(progress).ok();
+                })?;
+            info!(
+                target: "project::jj_store",
+                "fetched from {remote} in repo {:?}: {}",
                 repository_id,
-                short_change_hash(&change_id)
+                summary.describe()
+            );
+            Ok(summary)
+        });
+
+        self.fetch_progress.insert(repository_id, 0.);
+        cx.spawn(async move |store, cx| {
+            while let Some(progress) = progress_rx.next().await {
+                store
+                    .update(cx, |store, _| {
+                        store.fetch_progress.insert(repository_id, progress);
+                    })
+                    .ok();
+            }
+            store
+                .update(cx, |store, _| {
+                    store.fetch_progress.remove(&repository_id);
+                })
+                .ok();
+        })
+        .detach();
+        Some(self.track_operation(fetch, cx))
+    }
+
+    /// Pushes the local bookmark `bookmark` to the git remote `remote`.
+    #[cfg(feature = "jj-ui")]
+    pub fn git_push(
+        &mut self,
+        repository_id: ProjectEntryId,
+        bookmark: String,
+        remote: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<PushSummary>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let summary = repository.workspace()?.git_push(&bookmark, &remote)?;
+            info!(
+                target: "project::jj_store",
+                "pushed {bookmark} to {remote} in repo {:?} (changed: {})",
+                repository_id,
+                summary.pushed
+            );
+            Ok(summary)
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// Pushes `change_id` to the git remote `remote` under a generated `push-` bookmark.
+    #[cfg(feature = "jj-ui")]
+    pub fn git_push_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        remote: String,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<PushSummary>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let summary = repository
+                .workspace()?
+                .git_push_change(&change_id, &remote)?;
+            info!(
+                target: "project::jj_store",
+                "pushed change {} to {remote} as {} in repo {:?} (changed: {})",
+                short_change_hash(&change_id),
+                summary.bookmark,
+                repository_id,
+                summary.pushed
+            );
+            Ok(summary)
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    /// How far along the repository's running fetch is, from 0 to 1, or `None` when it isn't
+    /// fetching.
+    #[cfg(feature = "jj-ui")]
+    pub fn fetch_progress(&self, repository_id: ProjectEntryId) -> Option<f32> {
+        self.fetch_progress.get(&repository_id).copied()
+    }
+
+    /// Starts or stops tracking the bookmark `name` on `remote`, which decides whether fetches
+    /// move the local bookmark and whether the remote bookmark shows up in the log.
+    #[cfg(feature = "jj-ui")]
+    pub fn set_remote_bookmark_tracked(
+        &mut self,
+        repository_id: ProjectEntryId,
+        name: String,
+        remote: String,
+        track: bool,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            let workspace = repository.workspace()?;
+            if track {
+                workspace.track_remote_bookmark(&name, &remote)?;
+            } else {
+                workspace.untrack_remote_bookmark(&name, &remote)?;
+            }
+            info!(
+                target: "project::jj_store",
+                "{} {name}@{remote} in repo {:?}",
+                if track { "tracked" } else { "untracked" },
+                repository_id
             );
             Ok(())
-        }))
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    #[cfg(feature = "jj-ui")]
+    pub fn squash_change(
+        &mut self,
+        repository_id: ProjectEntryId,
+        change_id: ChangeId,
+        paths: Option<Vec<RepoPathBuf>>,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let repository = self.repositories_by_id.get(&repository_id)?.clone();
+        let task = cx.background_spawn(async move {
+            repository
+                .workspace()?
+                .squash_change(&change_id, paths.as_deref())?;
+            info!(
+                target: "project::jj_store",
+                "squashed change {} ({}) into its parent in repo {:?}",
+                short_change_hash(&change_id),
+                paths.map_or("all files".to_string(), |paths| format!("{} files", paths.len())),
+                repository_id
+            );
+            Ok(())
+        });
+        Some(self.track_operation(task, cx))
     }
 
     #[cfg(feature = "jj-ui")]
@@ -470,7 +2753,7 @@ impl JjStore {
         cx: &mut Context<Self>,
     ) -> Option<Task<Result<()>>> {
         let repository = self.repositories_by_id.get(&repository_id)?.clone();
-        Some(cx.spawn(async move |_, _| {
+        let task = cx.spawn(async move |_, _| {
             repository
                 .workspace()?
                 .rename_change(&change_id, &new_description)?;
@@ -481,8 +2764,174 @@ impl JjStore {
                 repository_id
             );
             Ok(())
+        });
+        Some(self.track_operation(task, cx))
+    }
+
+    fn request_remote_log(
+        &self,
+        client: AnyProtoClient,
+        project_id: u64,
+        repository_id: Option<ProjectEntryId>,
+        limit: usize,
+        date_range: CommitDateRange,
+        cx: &App,
+    ) -> Task<Result<Vec<JjCommitSummary>>> {
+        let store = self.this.clone();
+        cx.spawn(async move |cx| {
+            let response = client
+                .request(proto::JjGetLog {
+                    project_id,
+                    repository_id: repository_id.map(ProjectEntryId::to_proto),
+                    limit: limit as u64,
+                    committed_after: date_range.after,
+                    committed_before: date_range.before,
+                })
+                .await?;
+            let repositories = response
+                .repositories
+                .into_iter()
+                .map(|repository| JjRepositorySummary {
+                    id: ProjectEntryId::from_proto(repository.id),
+                    worktree_id: WorktreeId::from_proto(repository.worktree_id),
+                    path: repository.path.into(),
+                    relative_path: repository.relative_path.into(),
+                    covers_entire_project: repository.covers_entire_project,
+                })
+                .collect::<Vec<_>>();
+            store.update(cx, |store, cx| {
+                if store.remote_repositories != repositories {
+                    store.remote_repositories = repositories;
+                    cx.notify();
+                }
+            })?;
+            response
+                .commits
+                .into_iter()
+                .map(JjCommitSummary::from_proto)
+                .collect()
+        })
+    }
+
+    /// Asks the host to run `request`. Only guests can request operations, and the request
+    /// completes once the host has been asked, not once it has run.
+    pub fn request_operation(
+        &self,
+        repository_id: ProjectEntryId,
+        request: JjOperationRequest,
+        cx: &App,
+    ) -> Option<Task<Result<()>>> {
+        let (client, project_id) = self.upstream.clone()?;
+        let operation = request.to_proto();
+        Some(cx.background_spawn(async move {
+            client
+                .request(proto::JjRequestOperation {
+                    project_id,
+                    repository_id: repository_id.to_proto(),
+                    operation: Some(operation),
+                })
+                .await?;
+            Ok(())
         }))
     }
+
+    /// Runs an operation a guest requested, once the host has approved it.
+    pub fn run_requested_operation(
+        &mut self,
+        repository_id: ProjectEntryId,
+        request: JjOperationRequest,
+        cx: &mut Context<Self>,
+    ) -> Option<Task<Result<()>>> {
+        match request {
+            JjOperationRequest::NewChange { parent_change_id } => {
+                self.new_change(repository_id, Some(parent_change_id), String::new(), cx)
+            }
+            JjOperationRequest::RenameChange {
+                change_id,
+                description,
+            } => self.rename_change(repository_id, change_id, description, cx),
+            JjOperationRequest::SquashChange { change_id } => {
+                self.squash_change(repository_id, change_id, None, cx)
+            }
+            JjOperationRequest::AbandonChange { change_id } => {
+                self.abandon_change(repository_id, change_id, cx)
+            }
+        }
+    }
+
+    async fn handle_get_log(
+        this: Entity<Self>,
+        envelope: TypedEnvelope<proto::JjGetLog>,
+        mut cx: AsyncApp,
+    ) -> Result<proto::JjGetLogResponse> {
+        let repository_id = envelope
+            .payload
+            .repository_id
+            .map(ProjectEntryId::from_proto);
+        let limit = envelope.payload.limit as usize;
+        let date_range = CommitDateRange {
+            after: envelope.payload.committed_after,
+            before: envelope.payload.committed_before,
+        };
+        let (repositories, commits) = this.read_with(&mut cx, |store, cx| {
+            let repositories = store
+                .repositories()
+                .into_iter()
+                .map(|repository| proto::JjRepository {
+                    id: repository.id.to_proto(),
+                    worktree_id: repository.worktree_id.to_proto(),
+                    path: repository.path.to_string(),
+                    relative_path: repository.relative_path.to_string(),
+                    covers_entire_project: repository.covers_entire_project,
+                })
+                .collect::<Vec<_>>();
+            let commits = if limit == 0 {
+                None
+            } else {
+                store.recent_commits(repository_id, limit, date_range, cx)
+            };
+            (repositories, commits)
+        })?;
+        let commits = match commits {
+            Some(task) => task.await?,
+            None => Vec::new(),
+        };
+        Ok(proto::JjGetLogResponse {
+            repositories,
+            commits: commits.iter().map(JjCommitSummary::to_proto).collect(),
+        })
+    }
+
+    async fn handle_request_operation(
+        this: Entity<Self>,
+        envelope: TypedEnvelope<proto::JjRequestOperation>,
+        mut cx: AsyncApp,
+    ) -> Result<proto::Ack> {
+        let repository_id = ProjectEntryId::from_proto(envelope.payload.repository_id);
+        let operation = envelope
+            .payload
+            .operation
+            .ok_or_else(|| anyhow!("missing jj operation"))?;
+        let request = JjOperationRequest::from_proto(operation)?;
+        let requester = envelope.original_sender_id;
+        this.update(&mut cx, |store, cx| {
+            if !store.repositories_by_id.contains_key(&repository_id) {
+                return Err(anyhow!("no jj repository with id {repository_id:?}"));
+            }
+            info!(
+                target: "project::jj_store",
+                "guest {requester:?} requested to {} in repo {repository_id:?}",
+                request.describe()
+            );
+            cx.emit(JjStoreEvent::OperationRequested {
+                repository_id,
+                requester,
+                request,
+            });
+            Ok(())
+        })??;
+        Ok(proto::Ack {})
+    }
 }
 
 #[derive(Clone)]
@@ -490,38 +2939,108 @@ struct JjDiffState {
     diff: WeakEntity<BufferDiff>,
     repository: Arc<JjRepositoryState>,
     repo_path: RepoPathBuf,
+    /// Hash of the base text last handed to the diff, used to skip re-parsing the base
+    /// when the parent tree didn't change for this path.
+    base_text_hash: u64,
+}
+
+fn hash_base_text(base_text: Option<&str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    base_text.hash(&mut hasher);
+    hasher.finish()
 }
 
 struct JjRepositoryState {
     worktree_id: WorktreeId,
     work_directory_id: ProjectEntryId,
     work_directory_abs_path: Arc<Path>,
+    work_directory_rel_path: Arc<RelPath>,
+    covers_entire_project: bool,
     path_depth: usize,
+    backends: Arc<JjBackends>,
+    read_only: AtomicBool,
     workspace: Mutex<Option<Arc<JjWorkspace>>>,
+    unsupported_format: Mutex<Option<UnsupportedFormatError>>,
 }
 
 impl JjRepositoryState {
-    fn from_entry(worktree_id: WorktreeId, entry: JjRepoEntryForWorktree) -> Self {
+    fn from_entry(
+        worktree_id: WorktreeId,
+        entry: JjRepoEntryForWorktree,
+        backends: Arc<JjBackends>,
+        read_only: bool,
+    ) -> Self {
         let path_depth = entry.work_directory_abs_path.components().count();
         Self {
             worktree_id,
             work_directory_id: entry.work_directory_id,
             work_directory_abs_path: entry.work_directory_abs_path.clone(),
+            work_directory_rel_path: entry.work_directory_rel_path.clone(),
+            covers_entire_project: entry.covers_entire_project,
             path_depth,
+            backends,
+            read_only: AtomicBool::new(read_only),
             workspace: Mutex::new(None),
+            unsupported_format: Mutex::new(None),
         }
     }
 
+    /// The workspace, if something loaded it already.
+    fn loaded_workspace(&self) -> Option<Arc<JjWorkspace>> {
+        self.workspace.lock().clone()
+    }
+
     fn workspace(&self) -> Result<Arc<JjWorkspace>> {
+        if let Some(unsupported) = self.unsupported_format.lock().clone() {
+            return Err(unsupported.into());
+        }
         let mut cached = self.workspace.lock();
         if let Some(workspace) = cached.as_ref() {
             return Ok(workspace.clone());
         }
-        let workspace = Arc::new(JjWorkspace::load(self.work_directory_abs_path.as_ref())?);
+        let workspace = Arc::new(
+            JjWorkspace::load_with_backends(
+                self.work_directory_abs_path.as_ref(),
+                self.backends.clone(),
+            )?
+            .with_parent_tree_cache(Arc::new(ParentTreeCache::load(self.cache_path())))
+            .with_read_only(self.read_only.load(Ordering::Relaxed)),
+        );
         *cached = Some(workspace.clone());
         Ok(workspace)
     }
 
+    /// Stops using the repository when `error` shows jj-lib can't read it, so that background
+    /// work skips it and every operation fails with the same explanation. Returns the error only
+    /// the first time, for the caller to report.
+    fn disable_if_unsupported(&self, error: &anyhow::Error) -> Option<UnsupportedFormatError> {
+        let unsupported = UnsupportedFormatError::detect(error)?;
+        let mut disabled = self.unsupported_format.lock();
+        if disabled.is_some() {
+            return None;
+        }
+        *disabled = Some(unsupported.clone());
+        drop(disabled);
+        self.workspace.lock().take();
+        Some(unsupported)
+    }
+
+    fn is_unsupported(&self) -> bool {
+        self.unsupported_format.lock().is_some()
+    }
+
+    /// Drops the loaded workspace, so that it's loaded again with the new mode.
+    fn set_read_only(&self, read_only: bool) {
+        let mut cached = self.workspace.lock();
+        self.read_only.store(read_only, Ordering::Relaxed);
+        cached.take();
+    }
+
+    fn cache_path(&self) -> PathBuf {
+        let digest = Sha256::digest(self.work_directory_abs_path.as_os_str().as_encoded_bytes());
+        paths::jj_cache_dir().join(format!("parent_trees-{digest:x}.json"))
+    }
+
     fn relative_repo_path(&self, file_abs_path: &Path) -> Option<RepoPathBuf> {
         let relative = file_abs_path
             .strip_prefix(self.work_directory_abs_path.as_ref())
@@ -537,4 +3056,15 @@ impl JjRepositoryState {
     fn display_name(&self) -> String {
         self.work_directory_abs_path.display().to_string()
     }
+
+    #[cfg(feature = "jj-ui")]
+    fn relative_display_name(&self) -> String {
+        if self.work_directory_rel_path.is_empty() {
+            return self.work_directory_abs_path.file_name().map_or_else(
+                || self.display_name(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+        }
+        self.work_directory_rel_path.as_unix_str().to_string()
+    }
 }
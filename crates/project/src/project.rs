@@ -26,6 +26,8 @@ pub mod worktree_store;
 
 #[cfg(test)]
 mod project_tests;
+#[cfg(test)]
+mod vcs_tests;
 
 mod environment;
 use buffer_diff::BufferDiff;
@@ -34,7 +36,10 @@ pub use environment::ProjectEnvironmentEvent;
 use git::repository::get_git_committer;
 use git_store::{Repository, RepositoryId};
 #[cfg(feature = "jj-ui")]
-pub use jj_store::{JjCommitSummary, JjRepositorySummary};
+pub use jj_store::{
+    JjBufferChange, JjCommitSummary, JjOperationRequest, JjRepositorySummary, JjSessionOperation,
+    JjStore, JjStoreEvent,
+};
 pub mod search_history;
 mod yarn;
 
@@ -1044,6 +1049,8 @@ impl Project {
         BufferStore::init(&client);
         LspStore::init(&client);
         GitStore::init(&client);
+        #[cfg(feature = "jj-ui")]
+        jj_store::JjStore::init(&client);
         SettingsObserver::init(&client);
         TaskStore::init(Some(&client));
         ToolchainStore::init(&client);
@@ -1617,6 +1624,10 @@ impl Project {
             )
         })?;
         #[cfg(feature = "jj-ui")]
+        let jj_store = cx.new(|cx| {
+            jj_store::JjStore::remote(worktree_store.clone(), remote_id, client.clone().into(), cx)
+        })?;
+        #[cfg(feature = "jj-ui")]
         let vcs_backend: Arc<dyn VcsBackend> =
             Arc::new(ProjectVcsBackend::new(git_store.clone(), None));
         #[cfg(not(feature = "jj-ui"))]
@@ -1690,7 +1701,7 @@ impl Project {
                 dap_store: dap_store.clone(),
                 git_store: git_store.clone(),
                 #[cfg(feature = "jj-ui")]
-                _jj_store: None,
+                _jj_store: Some(jj_store),
                 vcs_backend: vcs_backend.clone(),
                 agent_server_store,
                 buffers_needing_diff: Default::default(),
@@ -2385,6 +2396,14 @@ impl Project {
                 .subscribe_to_entity(project_id)?
                 .set_entity(&self.git_store, &cx.to_async()),
         ]);
+        #[cfg(feature = "jj-ui")]
+        if let Some(jj_store) = &self._jj_store {
+            self.client_subscriptions.push(
+                self.collab_client
+                    .subscribe_to_entity(project_id)?
+                    .set_entity(jj_store, &cx.to_async()),
+            );
+        }
 
         self.buffer_store.update(cx, |buffer_store, cx| {
             buffer_store.shared(project_id, self.collab_client.clone().into(), cx)
@@ -5373,6 +5392,17 @@ impl Project {
         self._jj_store.as_ref()
     }
 
+    /// Waits up to `timeout` for in-flight jj operations that mutate a repository, resolving to
+    /// whether they all finished.
+    #[cfg_attr(not(feature = "jj-ui"), allow(unused_variables))]
+    pub fn wait_for_jj_operations(&self, timeout: Duration, cx: &App) -> Task<bool> {
+        #[cfg(feature = "jj-ui")]
+        if let Some(jj_store) = &self._jj_store {
+            return jj_store::JjStore::wait_for_pending_operations(jj_store, timeout, cx);
+        }
+        Task::ready(true)
+    }
+
     pub fn set_agent_location(
         &mut self,
         new_location: Option<AgentLocation>,
@@ -76,6 +76,10 @@ pub struct ProjectSettings {
     #[cfg(feature = "jj-ui")]
     /// Preferred version control integration when multiple are available.
     pub preferred_vcs: PreferredVcs,
+
+    #[cfg(feature = "jj-ui")]
+    /// Configuration for Jujutsu-related features
+    pub jj: JjSettings,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -103,6 +107,84 @@ impl Default for PreferredVcs {
     }
 }
 
+#[cfg(feature = "jj-ui")]
+#[derive(Clone, Copy, Debug)]
+pub struct JjSettings {
+    /// Whether to show the change owning the active buffer's diff base in the editor toolbar.
+    ///
+    /// Default: true
+    pub show_change_indicator: bool,
+    /// Whether to suggest a description derived from the modified paths
+    /// when describing a change that has none.
+    ///
+    /// Default: true
+    pub auto_description: bool,
+    /// How long to wait after a change in `.jj` before refreshing Jujutsu state, in milliseconds.
+    ///
+    /// Default: 100
+    pub refresh_debounce_ms: u64,
+    /// If set, poll the repository's operation head at this interval, in milliseconds.
+    ///
+    /// Default: None
+    pub poll_interval_ms: Option<u64>,
+    /// Whether to show the CI status of pushed changes in colocated repositories.
+    ///
+    /// Default: false
+    pub show_ci_status: bool,
+    /// Whether to tint each change in the Jujutsu panel with a color derived from its author.
+    ///
+    /// Default: false
+    pub color_by_author: bool,
+    /// Whether to separate the changes in the Jujutsu panel by the day they were committed.
+    ///
+    /// Default: false
+    pub group_by_day: bool,
+    /// Whether to open repositories without ever writing to them: no snapshots, no operations
+    /// and no UI for changing them.
+    ///
+    /// Default: false
+    pub read_only: bool,
+}
+
+#[cfg(feature = "jj-ui")]
+impl Default for JjSettings {
+    fn default() -> Self {
+        Self {
+            show_change_indicator: true,
+            auto_description: true,
+            refresh_debounce_ms: 100,
+            poll_interval_ms: None,
+            show_ci_status: false,
+            color_by_author: false,
+            group_by_day: false,
+            read_only: false,
+        }
+    }
+}
+
+#[cfg(feature = "jj-ui")]
+impl From<&settings::JjSettingsContent> for JjSettings {
+    fn from(content: &settings::JjSettingsContent) -> Self {
+        let defaults = Self::default();
+        Self {
+            show_change_indicator: content
+                .show_change_indicator
+                .unwrap_or(defaults.show_change_indicator),
+            auto_description: content
+                .auto_description
+                .unwrap_or(defaults.auto_description),
+            refresh_debounce_ms: content
+                .refresh_debounce_ms
+                .unwrap_or(defaults.refresh_debounce_ms),
+            poll_interval_ms: content.poll_interval_ms.or(defaults.poll_interval_ms),
+            show_ci_status: content.show_ci_status.unwrap_or(defaults.show_ci_status),
+            color_by_author: content.color_by_author.unwrap_or(defaults.color_by_author),
+            group_by_day: content.group_by_day.unwrap_or(defaults.group_by_day),
+            read_only: content.read_only.unwrap_or(defaults.read_only),
+        }
+    }
+}
+
 #[cfg(feature = "jj-ui")]
 impl From<settings::VcsPreferenceContent> for PreferredVcs {
     fn from(value: settings::VcsPreferenceContent) -> Self {
@@ -477,6 +559,13 @@ impl Settings for ProjectSettings {
             .as_ref()
             .map(|settings| settings.default.into())
             .unwrap_or_default();
+        #[cfg(feature = "jj-ui")]
+        let jj = project
+            .vcs
+            .as_ref()
+            .and_then(|settings| settings.jj.as_ref())
+            .map(JjSettings::from)
+            .unwrap_or_default();
 
         let git = content.git.as_ref().unwrap();
         let git_settings = GitSettings {
@@ -556,6 +645,8 @@ impl Settings for ProjectSettings {
             },
             #[cfg(feature = "jj-ui")]
             preferred_vcs,
+            #[cfg(feature = "jj-ui")]
+            jj,
         }
     }
 }
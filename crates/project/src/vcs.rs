@@ -139,6 +139,14 @@ impl VcsBackend for ProjectVcsBackend {
     }
 
     fn status_for_buffer_id(&self, buffer_id: BufferId, cx: &App) -> Option<FileStatus> {
+        #[cfg(feature = "jj-ui")]
+        {
+            if let Some(jj) = self.preferred_jj_backend(cx)
+                && let Some(status) = jj.status_for_buffer_id(buffer_id, cx)
+            {
+                return Some(status);
+            }
+        }
         self.git.status_for_buffer_id(buffer_id, cx)
     }
 
@@ -197,10 +205,14 @@ impl JjVcsBackend {
         })
     }
 
-    fn has_repositories(&self, cx: &mut Context<Project>) -> bool {
+    fn has_repositories(&self, cx: &App) -> bool {
         self.jj_store.read(cx).has_repositories()
     }
 
+    fn status_for_buffer_id(&self, buffer_id: BufferId, cx: &App) -> Option<FileStatus> {
+        self.jj_store.read(cx).status_for_buffer_id(buffer_id, cx)
+    }
+
     fn recalculate_buffer_diffs(
         &self,
         buffers: Vec<Entity<Buffer>>,
@@ -218,7 +230,7 @@ impl JjVcsBackend {
 
 #[cfg(feature = "jj-ui")]
 impl ProjectVcsBackend {
-    fn preferred_jj_backend<'a>(&'a self, cx: &mut Context<Project>) -> Option<&'a JjVcsBackend> {
+    fn preferred_jj_backend<'a>(&'a self, cx: &App) -> Option<&'a JjVcsBackend> {
         let jj = self.jj.as_ref()?;
         if !cx.has_flag::<JjUiFeatureFlag>() {
             return None;
@@ -0,0 +1,124 @@
+//! Checks that every [`VcsBackend`] reports diff bases, statuses and recalculated diffs the same
+//! way, so that a trait method can't be implemented for git alone.
+
+use crate::{Project, project_tests::init_test, vcs::VcsBackend};
+use fs::RealFs;
+use git::status::{FileStatus, StatusCode};
+use gpui::{Entity, TestAppContext};
+use language::Buffer;
+use serde_json::json;
+use std::{path::Path, sync::Arc};
+use util::test::TempTree;
+
+const BASE_TEXT: &str = "one\ntwo\nthree\n";
+const MODIFIED_TEXT: &str = "one\nTWO\nthree\n";
+
+#[gpui::test]
+async fn test_git_backend_conformance(cx: &mut TestAppContext) {
+    check_backend_conformance(cx, |work_directory| {
+        let repository = git2::Repository::init(work_directory).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@zed.dev").unwrap();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, "base", &tree, &[])
+            .unwrap();
+    })
+    .await;
+}
+
+#[cfg(feature = "jj-ui")]
+#[gpui::test]
+async fn test_jj_backend_conformance(cx: &mut TestAppContext) {
+    check_backend_conformance(cx, |work_directory| {
+        let workspace = jj::JjWorkspace::init(work_directory).unwrap();
+        let change_id = workspace.current_change_id().unwrap().unwrap();
+        workspace.new_change(&change_id, "").unwrap();
+    })
+    .await;
+}
+
+/// Commits `file.txt` with [`BASE_TEXT`] using `commit_base`, modifies it on disk, and checks
+/// what the project's backend reports for it.
+async fn check_backend_conformance(cx: &mut TestAppContext, commit_base: impl FnOnce(&Path)) {
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let root = TempTree::new(json!({
+        "project": {
+            "file.txt": BASE_TEXT,
+        },
+    }));
+    let work_directory = root.path().join("project");
+    commit_base(&work_directory);
+    std::fs::write(work_directory.join("file.txt"), MODIFIED_TEXT).unwrap();
+
+    let project = Project::test(
+        Arc::new(RealFs::new(None, cx.executor())),
+        [work_directory.as_path()],
+        cx,
+    )
+    .await;
+    let tree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
+    tree.flush_fs_events(cx).await;
+    project
+        .update(cx, |project, cx| project.git_scans_complete(cx))
+        .await;
+    cx.executor().run_until_parked();
+
+    let vcs_backend = project.read_with(cx, |project, _| project.vcs_backend.clone());
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(work_directory.join("file.txt"), cx)
+        })
+        .await
+        .unwrap();
+
+    let unstaged_diff = project
+        .update(cx, |_, cx| {
+            vcs_backend.open_unstaged_diff(buffer.clone(), cx)
+        })
+        .await
+        .unwrap();
+    let uncommitted_diff = project
+        .update(cx, |_, cx| {
+            vcs_backend.open_uncommitted_diff(buffer.clone(), cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    for diff in [&unstaged_diff, &uncommitted_diff] {
+        diff.read_with(cx, |diff, cx| {
+            assert_eq!(diff.base_text_string().as_deref(), Some(BASE_TEXT));
+            assert_eq!(hunk_count(diff, &buffer, cx), 1);
+        });
+    }
+
+    let buffer_id = buffer.read_with(cx, |buffer, _| buffer.remote_id());
+    cx.update(|cx| {
+        assert_eq!(
+            vcs_backend.status_for_buffer_id(buffer_id, cx),
+            Some(FileStatus::worktree(StatusCode::Modified))
+        );
+    });
+
+    buffer.update(cx, |buffer, cx| buffer.set_text(BASE_TEXT, cx));
+    project
+        .update(cx, |_, cx| {
+            vcs_backend.recalculate_buffer_diffs(vec![buffer.clone()], cx)
+        })
+        .await;
+    cx.executor().run_until_parked();
+    for diff in [&unstaged_diff, &uncommitted_diff] {
+        diff.read_with(cx, |diff, cx| {
+            assert_eq!(hunk_count(diff, &buffer, cx), 0);
+        });
+    }
+}
+
+fn hunk_count(diff: &buffer_diff::BufferDiff, buffer: &Entity<Buffer>, cx: &gpui::App) -> usize {
+    let snapshot = buffer.read(cx).snapshot();
+    diff.hunks(&snapshot, cx).count()
+}
@@ -336,7 +336,10 @@ messages!(
     (RemoteStarted, Background),
     (GitGetWorktrees, Background),
     (GitWorktreesResponse, Background),
-    (GitCreateWorktree, Background)
+    (GitCreateWorktree, Background),
+    (JjGetLog, Background),
+    (JjGetLogResponse, Background),
+    (JjRequestOperation, Background)
 );
 
 request_messages!(
@@ -517,7 +520,9 @@ request_messages!(
     (GetAgentServerCommand, AgentServerCommand),
     (RemoteStarted, Ack),
     (GitGetWorktrees, GitWorktreesResponse),
-    (GitCreateWorktree, Ack)
+    (GitCreateWorktree, Ack),
+    (JjGetLog, JjGetLogResponse),
+    (JjRequestOperation, Ack)
 );
 
 lsp_messages!(
@@ -684,7 +689,9 @@ entity_messages!(
     ExternalAgentLoadingStatusUpdated,
     NewExternalAgentVersionAvailable,
     GitGetWorktrees,
-    GitCreateWorktree
+    GitCreateWorktree,
+    JjGetLog,
+    JjRequestOperation
 );
 
 entity_messages!(
@@ -23,7 +23,11 @@ use picker::{
 pub use remote_connections::SshSettings;
 pub use remote_servers::RemoteServerProjects;
 use settings::Settings;
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use ui::{KeyBinding, ListItem, ListItemSpacing, Tooltip, prelude::*, tooltip_container};
 use util::{ResultExt, paths::PathExt};
 use workspace::{
@@ -226,8 +230,23 @@ impl RecentProjects {
                 .await
                 .log_err()
                 .unwrap_or_default();
+            #[cfg(feature = "jj-ui")]
+            let change_summaries = {
+                let local_paths = workspaces
+                    .iter()
+                    .filter(|(_, location, _)| {
+                        matches!(location, SerializedWorkspaceLocation::Local)
+                    })
+                    .flat_map(|(_, _, paths)| paths.paths().iter().cloned())
+                    .collect::<Vec<_>>();
+                cx.background_spawn(async move { jj_ui::load_change_summaries(local_paths) })
+                    .await
+            };
+            #[cfg(not(feature = "jj-ui"))]
+            let change_summaries = HashMap::default();
             this.update_in(cx, move |this, window, cx| {
                 this.picker.update(cx, move |picker, cx| {
+                    picker.delegate.change_summaries = change_summaries;
                     picker.delegate.set_workspaces(workspaces);
                     picker.update_matches(picker.query(cx), window, cx)
                 })
@@ -282,6 +301,8 @@ impl Render for RecentProjects {
 pub struct RecentProjectsDelegate {
     workspace: WeakEntity<Workspace>,
     workspaces: Vec<(WorkspaceId, SerializedWorkspaceLocation, PathList)>,
+    /// The jj change each local project was on when it was last closed, keyed by path.
+    change_summaries: HashMap<PathBuf, SharedString>,
     selected_match_index: usize,
     matches: Vec<StringMatch>,
     render_paths: bool,
@@ -296,6 +317,7 @@ impl RecentProjectsDelegate {
         Self {
             workspace,
             workspaces: Vec::new(),
+            change_summaries: HashMap::default(),
             selected_match_index: 0,
             matches: Default::default(),
             create_new_window,
@@ -517,6 +539,9 @@ impl PickerDelegate for RecentProjectsDelegate {
         let hit = self.matches.get(ix)?;
 
         let (_, location, paths) = self.workspaces.get(hit.candidate_id)?;
+        let change_summary = paths
+            .ordered_paths()
+            .find_map(|path| self.change_summaries.get(path));
 
         let mut path_start_offset = 0;
 
@@ -538,9 +563,16 @@ impl PickerDelegate for RecentProjectsDelegate {
             _ => None,
         };
 
+        let mut match_label = HighlightedMatch::join(match_labels.into_iter().flatten(), ", ");
+        if matches!(location, SerializedWorkspaceLocation::Local)
+            && let Some(change_summary) = change_summary
+        {
+            match_label.text.push_str(" — ");
+            match_label.text.push_str(change_summary);
+        }
         let highlighted_match = HighlightedMatchWithPaths {
             prefix,
-            match_label: HighlightedMatch::join(match_labels.into_iter().flatten(), ", "),
+            match_label,
             paths,
         };
 
@@ -113,6 +113,63 @@ pub struct WorktreeSettingsContent {
 pub struct VcsSettingsContent {
     #[serde(default = "default_vcs_preference")]
     pub default: VcsPreferenceContent,
+
+    /// Settings specific to the Jujutsu integration.
+    pub jj: Option<JjSettingsContent>,
+}
+
+#[cfg(feature = "jj-ui")]
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema, MergeFrom)]
+pub struct JjSettingsContent {
+    /// Whether to show the change owning the active buffer's diff base in the editor toolbar.
+    ///
+    /// Default: true
+    pub show_change_indicator: Option<bool>,
+
+    /// Whether to suggest a description derived from the modified paths
+    /// (e.g. "update editor/display_map") when describing a change that has none.
+    ///
+    /// Default: true
+    pub auto_description: Option<bool>,
+
+    /// How long to wait after a change in `.jj` before refreshing Jujutsu state, in milliseconds.
+    ///
+    /// Default: 100
+    pub refresh_debounce_ms: Option<u64>,
+
+    /// If set, poll the repository's operation head at this interval (in milliseconds) in
+    /// addition to watching `.jj`, for file systems where change notifications are unreliable
+    /// (e.g. network mounts).
+    ///
+    /// Default: null
+    pub poll_interval_ms: Option<u64>,
+
+    /// Whether to show the CI status of pushed changes in colocated repositories, queried
+    /// from the repository's hosting provider.
+    ///
+    /// Default: false
+    pub show_ci_status: Option<bool>,
+
+    /// Whether to tint each change in the Jujutsu panel with a color derived from its author,
+    /// so changes by different people are easy to tell apart.
+    ///
+    /// Default: false
+    pub color_by_author: Option<bool>,
+
+    /// Whether to separate the changes in the Jujutsu panel by the day they were committed,
+    /// with headers such as "Today", "Yesterday" or "Feb 3".
+    ///
+    /// Default: false
+    pub group_by_day: Option<bool>,
+
+    /// Whether to open repositories without ever writing to them, for checkouts owned by
+    /// automation where Zed must not record operations. Nothing is snapshotted, so the panel
+    /// shows the working copy as jj last recorded it, and actions that would change the
+    /// repository are hidden.
+    ///
+    /// Default: false
+    pub read_only: Option<bool>,
 }
 
 #[cfg(feature = "jj-ui")]
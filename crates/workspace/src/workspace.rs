@@ -134,6 +134,9 @@ use crate::{item::ItemBufferKind, notifications::NotificationId};
 
 pub const SERIALIZATION_THROTTLE_TIME: Duration = Duration::from_millis(200);
 
+/// How long closing a workspace waits for in-flight jj operations before asking the user.
+const JJ_OPERATION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 static ZED_WINDOW_SIZE: LazyLock<Option<Size<Pixels>>> = LazyLock::new(|| {
     env::var("ZED_WINDOW_SIZE")
         .ok()
@@ -2404,6 +2407,28 @@ impl Workspace {
                 }
             }
 
+            let jj_operations_finished = this
+                .update(cx, |this, cx| {
+                    this.project
+                        .read(cx)
+                        .wait_for_jj_operations(JJ_OPERATION_CLOSE_TIMEOUT, cx)
+                })?
+                .await;
+            if !jj_operations_finished {
+                let answer = cx.update(|window, cx| {
+                    window.prompt(
+                        PromptLevel::Warning,
+                        "A Jujutsu operation is still running",
+                        Some("Closing now may leave the working copy stale."),
+                        &["Close Anyway", "Cancel"],
+                        cx,
+                    )
+                })?;
+                if answer.await.log_err() != Some(0) {
+                    return anyhow::Ok(false);
+                }
+            }
+
             let save_result = this
                 .update_in(cx, |this, window, cx| {
                     this.save_all_internal(SaveIntent::Close, window, cx)
@@ -37,7 +37,7 @@ use gpui::{
 };
 use image_viewer::ImageInfo;
 #[cfg(feature = "jj-ui")]
-use jj_ui::JjPanel;
+use jj_ui::{JjChangeIndicator, JjPanel};
 use language::Capability;
 use language_onboarding::BasedPyrightBanner;
 use language_tools::lsp_button::{self, LspButton};
@@ -1150,6 +1150,11 @@ fn initialize_pane(
             toolbar.add_item(project_diff_toolbar, window, cx);
             let commit_view_toolbar = cx.new(|cx| CommitViewToolbar::new(workspace, cx));
             toolbar.add_item(commit_view_toolbar, window, cx);
+            #[cfg(feature = "jj-ui")]
+            {
+                let jj_change_indicator = cx.new(|cx| JjChangeIndicator::new(workspace, cx));
+                toolbar.add_item(jj_change_indicator, window, cx);
+            }
             let agent_diff_toolbar = cx.new(AgentDiffToolbar::new);
             toolbar.add_item(agent_diff_toolbar, window, cx);
             let basedpyright_banner = cx.new(|cx| BasedPyrightBanner::new(workspace, cx));
@@ -1707,6 +1712,15 @@ pub fn load_default_keymap(cx: &mut App) {
     cx.bind_keys(
         KeymapFile::load_asset(DEFAULT_KEYMAP_PATH, Some(KeybindSource::Default), cx).unwrap(),
     );
+    #[cfg(feature = "jj-ui")]
+    cx.bind_keys(
+        jj_ui::default_key_bindings()
+            .into_iter()
+            .map(|mut key_binding| {
+                key_binding.set_meta(KeybindSource::Default.meta());
+                key_binding
+            }),
+    );
 
     if let Some(asset_path) = base_keymap.asset_path() {
         cx.bind_keys(KeymapFile::load_asset(asset_path, Some(KeybindSource::Base), cx).unwrap());
@@ -221,6 +221,22 @@ pub mod git {
     );
 }
 
+pub mod jj {
+    use gpui::actions;
+
+    actions!(
+        jj,
+        [
+            /// Switches from the jj panel to the git panel in a repository colocated with git,
+            /// keeping the repository selected.
+            ViewAsGit,
+            /// Switches from the git panel to the jj panel in a repository colocated with jj,
+            /// keeping the repository selected.
+            ViewAsJj
+        ]
+    );
+}
+
 pub mod toast {
     use gpui::actions;
 